@@ -0,0 +1,56 @@
+//! Playlist construction and navigation over a synthetic 10k-episode feed, once with short
+//! and once with long episode strings. Both should take the same time: the playlist shares
+//! the list and moves indices, so the size of the episodes doesn't matter.
+//! `cargo bench --bench playlist`
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use mfp::feed::Episode;
+use mfp::playlist::Playlist;
+use std::sync::Arc;
+
+const EPISODES: usize = 10_000;
+
+fn feed(text_len: usize) -> Arc<[Episode]> {
+    let text = "x".repeat(text_len);
+    (1..=EPISODES)
+        .map(|n| Episode {
+            title: format!("Episode {}: {}", n, text),
+            audio_url: format!("https://example.com/{}/{}.mp3", n, text),
+            duration: "01:00:00".into(),
+            pub_date: String::new(),
+            description: text.clone(),
+            page_url: Some(format!("https://example.com/{}/{}", n, text)),
+            image_url: None,
+        })
+        .collect()
+}
+
+fn playlist(c: &mut Criterion) {
+    let mut group = c.benchmark_group("playlist");
+
+    for text_len in [16, 4096] {
+        let episodes = feed(text_len);
+
+        group.bench_with_input(
+            BenchmarkId::new("new", text_len),
+            &episodes,
+            |b, episodes| b.iter(|| Playlist::new(Arc::clone(episodes))),
+        );
+
+        let mut shuffled = Playlist::new(Arc::clone(&episodes));
+        shuffled.enable_shuffle();
+        group.bench_with_input(BenchmarkId::new("jump_to", text_len), &(), |b, _| {
+            b.iter(|| shuffled.jump_to(black_box(EPISODES / 2)).is_some())
+        });
+
+        let mut playlist = Playlist::new(Arc::clone(&episodes));
+        group.bench_with_input(BenchmarkId::new("next", text_len), &(), |b, _| {
+            b.iter(|| playlist.next().is_some())
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, playlist);
+criterion_main!(benches);
@@ -0,0 +1,81 @@
+//! A-B loop inside the play loop (`a` / `loop`)
+//! The first `a` marks where the loop starts, the second where it ends, and from then on
+//! playback jumps back to the start whenever it reaches the end. A third `a` clears it.
+
+/// Loop over a section of the episode playing, in seconds from its start
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum AbLoop {
+    #[default]
+    Off,
+    /// Start marked, waiting for the end
+    Start(u64),
+    Looping {
+        start: u64,
+        end: u64,
+    },
+}
+
+impl AbLoop {
+    /// What marking `position` makes of the loop. An end before the start swaps the two; an
+    /// end on the start is refused, leaving the loop as it was.
+    pub fn mark(self, position: u64) -> Self {
+        match self {
+            AbLoop::Off => AbLoop::Start(position),
+            AbLoop::Start(start) if start == position => self,
+            AbLoop::Start(start) => AbLoop::Looping {
+                start: start.min(position),
+                end: start.max(position),
+            },
+            AbLoop::Looping { .. } => AbLoop::Off,
+        }
+    }
+
+    /// Start and, once marked, end, for drawing on the progress line
+    pub fn bounds(self) -> Option<(u64, Option<u64>)> {
+        match self {
+            AbLoop::Off => None,
+            AbLoop::Start(start) => Some((start, None)),
+            AbLoop::Looping { start, end } => Some((start, Some(end))),
+        }
+    }
+
+    /// Where to seek to once playback at `position` has reached the end of the loop
+    pub fn jump_back(self, position: u64) -> Option<u64> {
+        match self {
+            AbLoop::Looping { start, end } if position >= end => Some(start),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn marks_start_then_end_then_clears() {
+        let start = AbLoop::Off.mark(60);
+        assert_eq!(start, AbLoop::Start(60));
+        let looping = start.mark(90);
+        assert_eq!(looping, AbLoop::Looping { start: 60, end: 90 });
+        assert_eq!(looping.mark(75), AbLoop::Off);
+    }
+
+    #[test]
+    fn an_end_before_the_start_swaps_them() {
+        assert_eq!(
+            AbLoop::Start(90).mark(60),
+            AbLoop::Looping { start: 60, end: 90 }
+        );
+        assert_eq!(AbLoop::Start(60).mark(60), AbLoop::Start(60));
+    }
+
+    #[test]
+    fn jumps_back_only_once_past_the_end() {
+        let looping = AbLoop::Looping { start: 60, end: 90 };
+        assert_eq!(looping.jump_back(89), None);
+        assert_eq!(looping.jump_back(90), Some(60));
+        assert_eq!(looping.jump_back(120), Some(60));
+        assert_eq!(AbLoop::Start(60).jump_back(120), None);
+    }
+}
@@ -0,0 +1,924 @@
+//! The subcommands behind the `mfp` binary, apart from `play`
+//! Each takes its arguments already parsed and prints what it has to say; errors go back to
+//! `main`, which prints them and picks the exit code.
+
+#[cfg(feature = "cast")]
+use crate::cast;
+#[cfg(feature = "scrobble")]
+use crate::scrobbler;
+use crate::{
+    backup, clipboard, config, downloader, errors, favorites, feed, history, i18n, journal, menu,
+    now_playing, output, picker, player, playlists, state, station, style, wizard,
+};
+
+use crate::play::{continued, last_unfinished, play_radio, PlayOptions};
+use crate::terminal::read_terminal_line;
+use anyhow::{Context, Result};
+use config::Config;
+use downloader::Downloader;
+use errors::NotFound;
+use favorites::Favorites;
+use feed::{extract_episode_number, Episode, Feed};
+use history::History;
+use i18n::{t, tf, Msg};
+use journal::Journal;
+use now_playing::NowPlaying;
+use playlists::{Edit, Playlists};
+use state::State;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use style::Role;
+
+/// One `mfp list --json` entry
+#[derive(serde::Serialize)]
+struct ListedEpisode<'a> {
+    number: Option<usize>,
+    #[serde(flatten)]
+    episode: &'a Episode,
+    favorite: bool,
+}
+
+pub fn list_episodes(json: bool) -> Result<()> {
+    output::status(t(Msg::FetchingEpisodes));
+    let feed = Feed::fetch()?;
+    let favorites = Favorites::load()?;
+
+    if json {
+        let listed: Vec<ListedEpisode> = feed
+            .episodes()
+            .iter()
+            .enumerate()
+            .map(|(i, episode)| ListedEpisode {
+                number: feed.number(i),
+                episode,
+                favorite: favorites.is_favorite(&episode.title),
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&listed)?);
+        return Ok(());
+    }
+
+    for (i, episode) in feed.episodes().iter().enumerate() {
+        let fav_marker = if favorites.is_favorite(&episode.title) {
+            style::paint(Role::Favorite, "*")
+        } else {
+            " ".to_string()
+        };
+        println!(
+            "{} {:3}. {} {}",
+            fav_marker,
+            feed.number(i).unwrap_or(i + 1),
+            episode.title,
+            style::paint(Role::Dim, &format!("[{}]", episode.duration))
+        );
+    }
+
+    Ok(())
+}
+
+/// Fuzzy picker over the feed, then straight into the play loop on the chosen episode
+pub fn pick_episode() -> Result<()> {
+    let feed = match Feed::load_cached() {
+        Some(feed) => feed,
+        None => {
+            output::status(t(Msg::FetchingEpisodes));
+            Feed::fetch()?
+        }
+    };
+    let mut favorites = Favorites::load()?;
+    let downloader = Downloader::new()?;
+
+    let markers = picker::Markers {
+        downloaded: feed
+            .episodes()
+            .iter()
+            .filter(|ep| downloader.is_downloaded(&ep.title))
+            .map(|ep| ep.title.clone())
+            .collect(),
+        played: History::load()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|entry| entry.title)
+            .collect(),
+    };
+
+    let picked = picker::run(feed.episodes(), &mut favorites, &markers)?;
+
+    for i in picked.downloads {
+        let ep = &feed.episodes()[i];
+        // Through anyhow so `{:#}` prints the cause chain
+        if let Err(e) = downloader
+            .download_episode(&ep.title, &ep.audio_url)
+            .map_err(anyhow::Error::from)
+        {
+            eprintln!(
+                "{} {:#}",
+                style::paint_err(Role::Error, t(Msg::ErrorLabel)),
+                e
+            );
+        }
+    }
+
+    if let Some(i) = picked.play {
+        let number = feed.number(i);
+        play_radio(number, None, PlayOptions::default())?;
+    }
+
+    Ok(())
+}
+
+/// Plain `mfp` in a terminal: the start menu, then the command behind the choice
+pub fn start_menu() -> Result<()> {
+    let context = menu::Context {
+        resume: last_unfinished(),
+        queued: State::load().map_or(0, |state| state.queue().len()),
+        latest: Feed::load_cached()
+            .and_then(|feed| feed.episodes().first().map(|ep| ep.title.clone())),
+        favorites: Favorites::load().map_or(0, |favorites| favorites.list().len()),
+        downloads: Downloader::new()
+            .ok()
+            .and_then(|downloader| downloader.list_downloaded().ok())
+            .map_or(0, |files| files.len()),
+    };
+
+    match menu::run(&context)? {
+        menu::Choice::Continue => play_radio(None, None, continued(PlayOptions::default())),
+        menu::Choice::Latest => play_radio(None, None, PlayOptions::default()),
+        menu::Choice::Browse => pick_episode(),
+        menu::Choice::Favorites => play_radio(
+            None,
+            None,
+            PlayOptions {
+                shuffle: true,
+                favorites: true,
+                ..PlayOptions::default()
+            },
+        ),
+        menu::Choice::Downloads => manage_downloads(None, false, true, None, false),
+        menu::Choice::Quit => Ok(()),
+    }
+}
+
+/// Completion helper: never hits the network and prints nothing without a cache
+pub fn complete_episodes() {
+    let Some(feed) = Feed::load_cached() else {
+        return;
+    };
+
+    let mut out = io::stdout().lock();
+    for (i, episode) in feed.episodes().iter().enumerate() {
+        if let Some(num) = feed.number(i) {
+            let title = episode
+                .title
+                .split_once(':')
+                .map(|(_, name)| name.trim())
+                .unwrap_or(&episode.title);
+            let _ = writeln!(out, "{}\t{}", num, title);
+        }
+    }
+}
+
+/// Completion helper: prints one favorite title per line
+pub fn complete_favorites() {
+    let Ok(favorites) = Favorites::load() else {
+        return;
+    };
+
+    let mut out = io::stdout().lock();
+    for title in favorites.list() {
+        let _ = writeln!(out, "{}", title);
+    }
+}
+
+#[cfg(feature = "scrobble")]
+pub fn manage_scrobbling(test: bool) -> Result<()> {
+    let config = Config::load()?;
+    let Some(scrobbler) = scrobbler::Scrobbler::new(&config.scrobble) else {
+        println!("{}", t(Msg::ScrobbleNotConfigured));
+        println!(
+            "{}",
+            tf(
+                Msg::ScrobbleAddSection,
+                &[&Config::config_path()?.display()]
+            )
+        );
+        return Ok(());
+    };
+
+    if test {
+        let user = scrobbler.test()?;
+        println!("{}", tf(Msg::ScrobbleCredentialsOk, &[&user]));
+
+        let sent = scrobbler.flush_queue()?;
+        if sent > 0 {
+            println!("{}", tf(Msg::ScrobbleQueueSent, &[&sent]));
+        }
+        return Ok(());
+    }
+
+    println!("{}", t(Msg::ScrobbleUsage));
+
+    Ok(())
+}
+
+pub fn show_history(limit: usize, json: bool, play: Option<usize>) -> Result<()> {
+    let entries = History::recent(limit.max(play.unwrap_or(0)))?;
+
+    if let Some(n) = play {
+        let entry = match n.checked_sub(1).and_then(|i| entries.get(i)) {
+            Some(entry) => entry,
+            None => {
+                println!("{}", tf(Msg::NoHistoryEntry, &[&n]));
+                return Ok(());
+            }
+        };
+        return match extract_episode_number(&entry.title) {
+            Some(num) => play_radio(Some(num), None, PlayOptions::default()),
+            None => {
+                println!("{}", tf(Msg::CannotReplay, &[&entry.title]));
+                Ok(())
+            }
+        };
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&entries)?);
+        return Ok(());
+    }
+
+    if entries.is_empty() {
+        println!("{}", t(Msg::NoHistory));
+        return Ok(());
+    }
+
+    println!(
+        "{:>4}  {:<16} {:>9}  {:<4}  {}",
+        "#",
+        t(Msg::HistoryWhen),
+        t(Msg::HistoryListened),
+        t(Msg::HistoryDone),
+        t(Msg::HistoryEpisode)
+    );
+    for (i, entry) in entries.iter().enumerate() {
+        println!(
+            "{:>4}  {:<16} {:>9}  {:<4}  {}",
+            i + 1,
+            history::format_relative(entry.started_at),
+            player::format_duration(entry.listened_secs),
+            if entry.completed { t(Msg::Yes) } else { "" },
+            entry.title
+        );
+    }
+
+    Ok(())
+}
+
+pub fn show_journal(episode: Option<usize>, export: Option<PathBuf>) -> Result<()> {
+    let mut entries = Journal::load()?;
+    if let Some(n) = episode {
+        entries.retain(|entry| extract_episode_number(&entry.episode) == Some(n));
+    }
+
+    if let Some(path) = export {
+        let count = entries.len();
+        std::fs::write(&path, journal::to_markdown(entries))
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        output::status(tf(Msg::JournalExported, &[&count, &path.display()]));
+        return Ok(());
+    }
+
+    if entries.is_empty() {
+        match episode {
+            Some(n) => println!("{}", tf(Msg::NoJournalForEpisode, &[&n])),
+            None => println!("{}", t(Msg::NoJournal)),
+        }
+        return Ok(());
+    }
+
+    for (episode, notes) in journal::group_by_episode(entries) {
+        println!("\n{}", style::paint(Role::Title, &episode));
+        for note in notes {
+            println!(
+                "  {} {}  {}",
+                style::paint(Role::Accent, &format!("{:<10}", note.position())),
+                note.text,
+                style::paint(Role::Dim, &journal::format_date(note.written_at))
+            );
+        }
+    }
+
+    Ok(())
+}
+
+pub fn export_state(path: &Path) -> Result<()> {
+    let files = backup::export(path)?;
+    output::status(tf(
+        Msg::BackupExported,
+        &[&files.join(", "), &path.display()],
+    ));
+    Ok(())
+}
+
+pub fn import_state(path: &Path, replace: bool, yes: bool) -> Result<()> {
+    let mode = if replace {
+        if !yes && !confirm_replace()? {
+            println!("{}", t(Msg::BackupReplaceCancelled));
+            return Ok(());
+        }
+        backup::ImportMode::Replace
+    } else {
+        backup::ImportMode::Merge
+    };
+
+    let summary = backup::import(path, mode)?;
+    if summary.files.is_empty() {
+        output::status(t(Msg::BackupEmpty));
+    } else if replace {
+        output::status(tf(
+            Msg::BackupRestored,
+            &[&summary.files.join(", "), &path.display()],
+        ));
+    } else {
+        output::status(tf(
+            Msg::BackupMerged,
+            &[
+                &path.display(),
+                &summary.favorites,
+                &summary.playlists,
+                &summary.history,
+                &summary.journal,
+                &summary.settings,
+            ],
+        ));
+    }
+
+    if !summary.missing_downloads.is_empty() {
+        output::status(tf(
+            Msg::BackupMissingDownloads,
+            &[&summary.missing_downloads.len()],
+        ));
+        for name in &summary.missing_downloads {
+            output::status(format!("  {}", name));
+        }
+    }
+
+    Ok(())
+}
+
+/// Ask before `import-state --replace` overwrites anything; refuses without a terminal
+fn confirm_replace() -> Result<bool> {
+    if !io::stdin().is_terminal() {
+        anyhow::bail!(t(Msg::BackupReplaceNeedsYes));
+    }
+
+    print!("{}", t(Msg::BackupReplaceConfirm));
+    io::stdout().flush()?;
+    let answer = read_terminal_line()?;
+    Ok(matches!(
+        answer.trim().to_lowercase().as_str(),
+        "y" | "yes" | "s" | "si" | "sí"
+    ))
+}
+
+pub fn manage_favorites(
+    add: Option<String>,
+    remove: Option<String>,
+    list: bool,
+    undo: bool,
+    redo: bool,
+) -> Result<()> {
+    let mut favorites = Favorites::load()?;
+
+    if undo {
+        output::status(undo_favorite(&mut favorites, false)?);
+    }
+    if redo {
+        output::status(undo_favorite(&mut favorites, true)?);
+    }
+
+    if let Some(title) = add {
+        if favorites.add(title.clone()) {
+            output::status(format!(
+                "{} {}",
+                style::paint(Role::Favorite, "*"),
+                tf(Msg::FavAdded, &[&title])
+            ));
+        } else {
+            output::status(tf(Msg::FavAlreadyAdded, &[&title]));
+        }
+    }
+
+    if let Some(title) = remove {
+        if favorites.remove(&title) {
+            output::status(tf(Msg::FavRemoved, &[&title]));
+        } else {
+            return Err(NotFound(tf(Msg::FavNotFound, &[&title])).into());
+        }
+    }
+
+    if list {
+        let fav_list = favorites.list();
+        if fav_list.is_empty() {
+            println!("{}", t(Msg::NoFavorites));
+        } else {
+            println!("{}", t(Msg::FavoritesHeader));
+            for title in fav_list {
+                println!("  {} {}", style::paint(Role::Favorite, "*"), title);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Undo (or with `redo`, redo) the last favorites change; returns what happened
+pub(crate) fn undo_favorite(
+    favorites: &mut Favorites,
+    redo: bool,
+) -> Result<String, errors::StorageError> {
+    let change = if redo {
+        favorites.redo()?
+    } else {
+        favorites.undo()?
+    };
+    let Some(change) = change else {
+        return Ok(t(if redo {
+            Msg::NothingToRedo
+        } else {
+            Msg::NothingToUndo
+        })
+        .to_string());
+    };
+
+    let mut what = if change.added == redo {
+        tf(Msg::FavIsFavorite, &[&change.title])
+    } else {
+        tf(Msg::FavIsNotFavorite, &[&change.title])
+    };
+    if change.station != station::active() {
+        what = format!("{} ({})", what, change.station);
+    }
+    Ok(tf(
+        if redo { Msg::FavRedone } else { Msg::FavUndone },
+        &[&what],
+    ))
+}
+
+pub fn manage_downloads(
+    episode: Option<usize>,
+    latest: bool,
+    list: bool,
+    delete: Option<String>,
+    size: bool,
+) -> Result<()> {
+    let downloader = Downloader::new()?;
+
+    if size {
+        let total_bytes = downloader.get_total_size()?;
+        let total_mb = total_bytes as f64 / 1_048_576.0;
+        println!("{}", tf(Msg::DiskUsage, &[&format!("{:.2}", total_mb)]));
+        println!(
+            "{}",
+            tf(Msg::Location, &[&downloader.download_dir().display()])
+        );
+        return Ok(());
+    }
+
+    if list {
+        let downloaded = downloader.list_downloaded()?;
+        if downloaded.is_empty() {
+            println!("{}", t(Msg::NoDownloads));
+        } else {
+            println!("{}", tf(Msg::DownloadedHeader, &[&downloaded.len()]));
+            for path in downloaded {
+                if let Some(filename) = path.file_name() {
+                    println!("  - {}", filename.to_string_lossy());
+                }
+            }
+        }
+        return Ok(());
+    }
+
+    if let Some(title) = delete {
+        downloader.delete_episode(&title)?;
+        return Ok(());
+    }
+
+    if let Some(ep_num) = episode {
+        output::status(t(Msg::FetchingEpisode));
+        let feed = Feed::fetch()?;
+
+        if let Some(ep) = feed.find_by_number(ep_num) {
+            downloader.download_episode(&ep.title, &ep.audio_url)?;
+        } else {
+            return Err(NotFound(tf(Msg::EpisodeNotFound, &[&ep_num])).into());
+        }
+        return Ok(());
+    }
+
+    if latest {
+        output::status(t(Msg::FetchingEpisode));
+        let feed = Feed::fetch()?;
+
+        match feed.episodes().first() {
+            Some(ep) => {
+                downloader.download_episode(&ep.title, &ep.audio_url)?;
+            }
+            None => return Err(NotFound(t(Msg::NoEpisodesAvailable).to_string()).into()),
+        }
+        return Ok(());
+    }
+
+    println!("{}", t(Msg::DownloadsHelp));
+
+    Ok(())
+}
+
+#[cfg(feature = "cast")]
+pub fn manage_cast(list: bool) -> Result<()> {
+    if !list {
+        println!("{}", t(Msg::CastHint));
+        return Ok(());
+    }
+
+    output::status(t(Msg::CastSearchingAll));
+    let devices = cast::discover(cast::DISCOVERY_TIME)?;
+    if devices.is_empty() {
+        println!("{}", t(Msg::CastNoDevices));
+    }
+    for device in devices {
+        println!("{:<24} {}:{}", device.name, device.host, device.port);
+    }
+
+    Ok(())
+}
+
+/// Make `name` the station of this run; an unknown name is an error before anything else runs
+pub fn select_station(name: Option<&str>) -> Result<()> {
+    let Some(name) = name else {
+        return Ok(());
+    };
+    if station::feed_url(&Config::load()?, name).is_none() {
+        return Err(NotFound(tf(Msg::UnknownStation, &[&name])).into());
+    }
+
+    station::select(name);
+    Ok(())
+}
+
+pub fn create_playlist(name: &str) -> Result<()> {
+    if !station::is_valid_name(name) {
+        anyhow::bail!(tf(Msg::InvalidPlaylistName, &[&name]));
+    }
+    if !Playlists::load()?.create(name)? {
+        anyhow::bail!(tf(Msg::PlaylistExists, &[&name]));
+    }
+    output::status(tf(Msg::PlaylistCreated, &[&name]));
+
+    Ok(())
+}
+
+pub fn add_to_playlist(name: &str, episodes: &[String]) -> Result<()> {
+    let mut playlists = Playlists::load()?;
+    if playlists.get(name).is_none() {
+        return Err(unknown_playlist(name));
+    }
+
+    output::status(t(Msg::FetchingEpisodes));
+    let feed = Feed::fetch()?;
+    for episode in episodes {
+        let title = episode_title(&feed, episode)?;
+        match playlists.add(name, &title)? {
+            Edit::Done => output::status(tf(Msg::PlaylistAdded, &[&title, &name])),
+            Edit::Unchanged => output::status(tf(Msg::PlaylistAlreadyHas, &[&name, &title])),
+            Edit::NoPlaylist => return Err(unknown_playlist(name)),
+        }
+    }
+
+    Ok(())
+}
+
+pub fn remove_from_playlist(name: &str, episodes: &[String]) -> Result<()> {
+    let mut playlists = Playlists::load()?;
+    let Some(titles) = playlists.get(name).map(<[String]>::to_vec) else {
+        return Err(unknown_playlist(name));
+    };
+
+    // Titles are taken as they are, so episodes gone from the feed can still go;
+    // only numbers need the feed
+    let feed = if episodes.iter().any(|episode| !titles.contains(episode)) {
+        output::status(t(Msg::FetchingEpisodes));
+        Some(Feed::fetch()?)
+    } else {
+        None
+    };
+    for episode in episodes {
+        let title = match &feed {
+            Some(feed) if !titles.contains(episode) => episode_title(feed, episode)?,
+            _ => episode.clone(),
+        };
+        match playlists.remove(name, &title)? {
+            Edit::Done => output::status(tf(Msg::PlaylistRemoved, &[&title, &name])),
+            Edit::Unchanged => {
+                return Err(NotFound(tf(Msg::NotInPlaylist, &[&title, &name])).into())
+            }
+            Edit::NoPlaylist => return Err(unknown_playlist(name)),
+        }
+    }
+
+    Ok(())
+}
+
+pub fn delete_playlist(name: &str) -> Result<()> {
+    if !Playlists::load()?.delete(name)? {
+        return Err(unknown_playlist(name));
+    }
+    output::status(tf(Msg::PlaylistDeleted, &[&name]));
+
+    Ok(())
+}
+
+pub fn list_playlists() -> Result<()> {
+    let mut any = false;
+    for (name, titles) in Playlists::load()?.list() {
+        any = true;
+        println!(
+            "{:<16} {}",
+            name,
+            style::paint(Role::Dim, &tf(Msg::PlaylistSize, &[&titles.len()]))
+        );
+    }
+    if !any {
+        println!("{}", t(Msg::NoPlaylists));
+    }
+
+    Ok(())
+}
+
+pub fn show_playlist(name: &str) -> Result<()> {
+    let playlists = Playlists::load()?;
+    let Some(titles) = playlists.get(name) else {
+        return Err(unknown_playlist(name));
+    };
+    if titles.is_empty() {
+        println!("{}", tf(Msg::PlaylistEmpty, &[&name, &name]));
+        return Ok(());
+    }
+
+    output::status(t(Msg::FetchingEpisodes));
+    let feed = Feed::fetch()?;
+    println!("{}", style::paint(Role::Title, name));
+    for title in titles {
+        match feed.episodes().iter().position(|e| e.title == *title) {
+            Some(i) => println!(
+                "  {:>4}  {}",
+                feed.number(i).map_or(String::new(), |n| n.to_string()),
+                title
+            ),
+            None => println!(
+                "  {:>4}  {} {}",
+                "-",
+                title,
+                style::paint(Role::Dim, t(Msg::PlaylistMissingTag))
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+pub(crate) fn unknown_playlist(name: &str) -> anyhow::Error {
+    NotFound(tf(Msg::UnknownPlaylist, &[&name])).into()
+}
+
+/// Title of the episode an argument names: its number, or its exact title
+pub(crate) fn episode_title(feed: &Feed, episode: &str) -> Result<String> {
+    let found = match episode.parse::<usize>() {
+        Ok(number) => find_episode(feed, number)?,
+        Err(_) => feed
+            .find_by_title(episode)
+            .ok_or_else(|| NotFound(tf(Msg::EpisodeTitleNotFound, &[&episode])))?,
+    };
+
+    Ok(found.title.clone())
+}
+
+pub fn add_station(name: &str, url: &str) -> Result<()> {
+    if !station::is_valid_name(name) {
+        anyhow::bail!(tf(Msg::InvalidStationName, &[&name]));
+    }
+    if station::feed_url(&Config::load()?, name).is_some() {
+        anyhow::bail!(tf(Msg::StationExists, &[&name]));
+    }
+
+    output::status(t(Msg::CheckingFeed));
+    let episodes = Feed::fetch_from(url)?.episodes().len();
+    if episodes == 0 {
+        anyhow::bail!(tf(Msg::StationFeedEmpty, &[&url]));
+    }
+
+    edit_stations(|stations| {
+        stations.insert(name.to_string(), url.into());
+    })?;
+    println!("{}", tf(Msg::StationAdded, &[&name, &episodes]));
+
+    Ok(())
+}
+
+pub fn list_stations() -> Result<()> {
+    let config = Config::load()?;
+    let default_url = station::feed_url(&config, station::DEFAULT).unwrap_or_default();
+    println!(
+        "{:<12} {} {}",
+        station::DEFAULT,
+        default_url,
+        style::paint(Role::Dim, t(Msg::StationDefault))
+    );
+    for (name, url) in &config.stations {
+        println!("{:<12} {}", name, url);
+    }
+
+    Ok(())
+}
+
+pub fn remove_station(name: &str) -> Result<()> {
+    if !Config::load()?.stations.contains_key(name) {
+        return Err(NotFound(tf(Msg::UnknownStation, &[&name])).into());
+    }
+
+    edit_stations(|stations| {
+        stations.remove(name);
+    })?;
+    println!("{}", tf(Msg::StationRemoved, &[&name]));
+
+    Ok(())
+}
+
+/// Change the `[stations]` table of `config.toml`, keeping every other setting
+fn edit_stations(edit: impl FnOnce(&mut toml::Table)) -> Result<()> {
+    let path = Config::config_path()?;
+    let mut table: toml::Table = match std::fs::read_to_string(&path) {
+        Ok(content) => content.parse().context("Failed to parse config file")?,
+        Err(_) => toml::Table::new(),
+    };
+
+    let stations = table
+        .entry("stations")
+        .or_insert_with(|| toml::Table::new().into());
+    let Some(stations) = stations.as_table_mut() else {
+        anyhow::bail!("[stations] in {} is not a table", path.display());
+    };
+    edit(stations);
+    if stations.is_empty() {
+        table.remove("stations");
+    }
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).context("Failed to create config directory")?;
+    }
+    std::fs::write(&path, toml::to_string(&table)?).context("Failed to write config file")
+}
+
+pub fn manage_config(init: bool) -> Result<()> {
+    if init {
+        return wizard::run();
+    }
+
+    let path = Config::config_path()?;
+    println!("{}", tf(Msg::ConfigFile, &[&path.display()]));
+    if !path.exists() {
+        println!("{}", t(Msg::ConfigMissing));
+    }
+
+    Ok(())
+}
+
+pub fn show_now_playing(format: Option<String>, no_newline: bool) -> Result<()> {
+    let template = format
+        .or(Config::load()?.now_playing_format)
+        .unwrap_or_else(|| now_playing::DEFAULT_FORMAT.to_string());
+    now_playing::check_template(&template)?;
+    let state = NowPlaying::load().ok_or_else(|| NotFound(t(Msg::NothingPlaying).to_string()))?;
+
+    if no_newline {
+        print!("{}", state.render(&template));
+        io::stdout().flush()?;
+    } else {
+        println!("{}", state.render(&template));
+    }
+    Ok(())
+}
+
+/// Status bar output; a stopped player is a normal state, not an error
+pub fn show_status(
+    waybar: bool,
+    follow: bool,
+    format: Option<String>,
+    max_length: Option<usize>,
+) -> Result<()> {
+    let config = Config::load()?;
+    let template = format
+        .or(config.status_format)
+        .unwrap_or_else(|| now_playing::DEFAULT_STATUS_FORMAT.to_string());
+    now_playing::check_template(&template)?;
+    let max_length = max_length
+        .or(config.status_max_length)
+        .unwrap_or(now_playing::DEFAULT_STATUS_MAX_LENGTH);
+
+    let render = || {
+        let state = NowPlaying::load();
+        if waybar {
+            now_playing::waybar_json(state.as_ref(), &template, max_length)
+        } else {
+            state
+                .map(|state| now_playing::truncate(&state.render(&template), max_length))
+                .unwrap_or_default()
+        }
+    };
+
+    if !follow {
+        println!("{}", render());
+        return Ok(());
+    }
+
+    let mut out = io::stdout();
+    let mut last = None;
+    loop {
+        let line = render();
+        if last.as_ref() != Some(&line) {
+            // The bar closing our pipe is the normal way to stop following
+            if writeln!(out, "{}", line).and_then(|_| out.flush()).is_err() {
+                return Ok(());
+            }
+            last = Some(line);
+        }
+        std::thread::sleep(Duration::from_secs(1));
+    }
+}
+
+fn find_episode(feed: &Feed, ep_num: usize) -> Result<&Episode> {
+    let episode = feed
+        .find_by_number(ep_num)
+        .ok_or_else(|| NotFound(tf(Msg::EpisodeNotFound, &[&ep_num])))?;
+
+    Ok(episode)
+}
+
+pub fn show_info(ep_num: usize, copy_url: bool) -> Result<()> {
+    output::status(t(Msg::FetchingEpisode));
+    let feed = Feed::fetch()?;
+    let episode = find_episode(&feed, ep_num)?;
+
+    println!(
+        "{}",
+        tf(
+            Msg::InfoEpisode,
+            &[&style::paint(Role::Title, &episode.title)]
+        )
+    );
+    println!("{}", tf(Msg::InfoDuration, &[&episode.duration]));
+    println!("{}", tf(Msg::InfoPublished, &[&episode.pub_date]));
+    if let Some(url) = &episode.page_url {
+        println!("{}", tf(Msg::InfoPage, &[url]));
+    }
+    println!("{}", tf(Msg::InfoAudioUrl, &[&episode.audio_url]));
+
+    if copy_url {
+        clipboard::copy(&episode.audio_url);
+        clipboard::release();
+    }
+
+    Ok(())
+}
+
+pub fn open_episode(ep_num: usize) -> Result<()> {
+    output::status(t(Msg::FetchingEpisode));
+    let feed = Feed::fetch()?;
+    let episode = find_episode(&feed, ep_num)?;
+
+    match &episode.page_url {
+        Some(url) => open_in_browser(url),
+        None => return Err(NotFound(t(Msg::NoPageUrl).to_string()).into()),
+    }
+
+    Ok(())
+}
+
+/// Open a link in the default browser, or print it when there is none (e.g. over SSH)
+pub(crate) fn open_in_browser(url: &str) {
+    match webbrowser::open(url) {
+        Ok(()) => println!("{}", tf(Msg::OpeningPage, &[&url])),
+        Err(e) => {
+            tracing::debug!("Failed to launch browser: {}", e);
+            println!("{}", tf(Msg::NoBrowser, &[&url]));
+        }
+    }
+}
+
+pub fn interactive_mode() -> Result<()> {
+    println!("Music For Programming - Radio Player");
+    println!("\n{}", t(Msg::AvailableCommands));
+
+    Ok(())
+}
@@ -0,0 +1,571 @@
+//! What the play loop needs from an audio backend
+//! [`Player`] plays through the output device; [`NullPlayer`] plays nothing and only keeps
+//! track of what it was asked, so playback logic can be run without a sound card.
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[cfg(feature = "audio")]
+use crate::player::Player;
+use crate::player::{BufferAhead, PlayerError, Starting, StreamInfo};
+
+/// Where a track's audio comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlaybackSource<'a> {
+    /// Streamed from a URL
+    Stream(&'a str),
+    /// A downloaded copy, played from the given second on
+    File(&'a Path, u64),
+}
+
+/// An audio backend. Beyond playing, pausing, volume and position, the calls are about
+/// streaming (buffering, reconnects, the output device) and have defaults for a backend
+/// that doesn't stream.
+pub trait AudioPlayer {
+    /// Stop whatever is playing and start `source`
+    fn play(&self, source: PlaybackSource) -> Result<(), PlayerError>;
+    /// `play`, giving a stream up while it still buffers once `interrupted` says so and
+    /// telling `starting` how far it got
+    fn play_interruptible(
+        &self,
+        source: PlaybackSource,
+        _interrupted: &dyn Fn() -> bool,
+        _starting: &dyn Fn(Starting),
+    ) -> Result<(), PlayerError> {
+        self.play(source)
+    }
+    fn stop(&self);
+    fn pause(&self);
+    fn resume(&self);
+    fn is_paused(&self) -> bool;
+    fn volume(&self) -> f32;
+    fn set_volume(&self, volume: f32);
+    /// Mute, or go back to the volume from before; true when now muted
+    fn toggle_mute(&self) -> bool;
+    fn is_muted(&self) -> bool;
+    /// Volume the next track starts at
+    fn set_start_volume(&self, volume: f32);
+    /// Jump to `secs`; false while the track can't seek yet
+    fn seek(&self, secs: u64) -> bool;
+    /// Whether the track has played to its end, or stopped
+    fn is_finished(&self) -> bool;
+    /// Position in the track
+    fn elapsed_seconds(&self) -> u64;
+
+    /// Audio actually heard, None until playback has started
+    fn played_seconds(&self) -> Option<u64> {
+        Some(self.elapsed_seconds())
+    }
+
+    fn set_balance(&self, _balance: f32) {}
+
+    fn balance(&self) -> f32 {
+        0.0
+    }
+
+    /// RMS level of what plays lately, 0.0 to 1.0; None when the backend can't measure it
+    fn level(&self) -> Option<f32> {
+        None
+    }
+
+    /// Seconds of leading silence skipped from the track playing
+    fn trimmed_secs(&self) -> Option<f32> {
+        None
+    }
+
+    fn stream_info(&self) -> Option<StreamInfo> {
+        None
+    }
+
+    fn set_spool_dir(&self, _dir: Option<PathBuf>) {}
+
+    fn buffered_seconds(&self, _duration_secs: u64) -> Option<u64> {
+        None
+    }
+
+    fn buffered_ahead_secs(&self) -> Option<BufferAhead> {
+        None
+    }
+
+    fn stalled_for(&self) -> Option<Duration> {
+        None
+    }
+
+    fn poll_underrun(&self) -> Option<u64> {
+        None
+    }
+
+    fn set_initial_buffer_kb(&self, _kb: u64) {}
+
+    fn set_rebuffer_secs(&self, _secs: u64) {}
+
+    /// Why the track stopped playing, once
+    fn take_error(&self) -> Option<PlayerError> {
+        None
+    }
+
+    fn reconnecting(&self) -> Option<(u64, u64)> {
+        None
+    }
+
+    fn reconnects(&self) -> u64 {
+        0
+    }
+
+    fn set_max_reconnects(&self, _max: u64) {}
+
+    fn set_max_readahead_mb(&self, _mb: Option<u64>) {}
+
+    fn reconnect(&self) {}
+
+    fn set_fade_pauses(&self, _fade: bool) {}
+
+    fn set_trim_silence(&self, _below_db: Option<f32>) {}
+
+    fn prefetch(&self, _url: &str) {}
+
+    fn cancel_prefetch(&self) {}
+
+    /// Save the stream playing to `path` as well; false when there is none to save
+    fn record(&self, _path: &Path) -> Result<bool, PlayerError> {
+        Ok(false)
+    }
+
+    /// Write what has arrived of the stream playing to `path`, for a download to carry on
+    /// from; the bytes there, 0 when there is no stream
+    fn save_head(&self, _path: &Path) -> Result<u64, PlayerError> {
+        Ok(0)
+    }
+
+    fn output_lost(&self) -> bool {
+        false
+    }
+
+    fn reopen_output(&mut self) -> Result<(), PlayerError> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "audio")]
+impl AudioPlayer for Player {
+    fn play(&self, source: PlaybackSource) -> Result<(), PlayerError> {
+        match source {
+            PlaybackSource::Stream(url) => Player::play(self, url),
+            PlaybackSource::File(path, from_secs) => self.play_file(path, from_secs),
+        }
+    }
+
+    fn play_interruptible(
+        &self,
+        source: PlaybackSource,
+        interrupted: &dyn Fn() -> bool,
+        starting: &dyn Fn(Starting),
+    ) -> Result<(), PlayerError> {
+        match source {
+            PlaybackSource::Stream(url) => {
+                Player::play_interruptible(self, url, interrupted, starting)
+            }
+            PlaybackSource::File(path, from_secs) => self.play_file(path, from_secs),
+        }
+    }
+
+    fn stop(&self) {
+        Player::stop(self)
+    }
+
+    fn pause(&self) {
+        Player::pause(self)
+    }
+
+    fn resume(&self) {
+        Player::resume(self)
+    }
+
+    fn is_paused(&self) -> bool {
+        Player::is_paused(self)
+    }
+
+    fn volume(&self) -> f32 {
+        Player::volume(self)
+    }
+
+    fn set_volume(&self, volume: f32) {
+        Player::set_volume(self, volume)
+    }
+
+    fn toggle_mute(&self) -> bool {
+        Player::toggle_mute(self)
+    }
+
+    fn is_muted(&self) -> bool {
+        Player::is_muted(self)
+    }
+
+    fn set_start_volume(&self, volume: f32) {
+        Player::set_start_volume(self, volume)
+    }
+
+    fn seek(&self, secs: u64) -> bool {
+        self.seek_to(secs)
+    }
+
+    fn is_finished(&self) -> bool {
+        Player::is_finished(self)
+    }
+
+    fn elapsed_seconds(&self) -> u64 {
+        Player::elapsed_seconds(self)
+    }
+
+    fn played_seconds(&self) -> Option<u64> {
+        Player::played_seconds(self)
+    }
+
+    fn set_balance(&self, balance: f32) {
+        Player::set_balance(self, balance)
+    }
+
+    fn balance(&self) -> f32 {
+        Player::balance(self)
+    }
+
+    fn level(&self) -> Option<f32> {
+        Some(Player::level(self))
+    }
+
+    fn trimmed_secs(&self) -> Option<f32> {
+        Player::trimmed_secs(self)
+    }
+
+    fn stream_info(&self) -> Option<StreamInfo> {
+        Some(Player::stream_info(self))
+    }
+
+    fn set_spool_dir(&self, dir: Option<PathBuf>) {
+        Player::set_spool_dir(self, dir)
+    }
+
+    fn buffered_seconds(&self, duration_secs: u64) -> Option<u64> {
+        Player::buffered_seconds(self, duration_secs)
+    }
+
+    fn buffered_ahead_secs(&self) -> Option<BufferAhead> {
+        Player::buffered_ahead_secs(self)
+    }
+
+    fn stalled_for(&self) -> Option<Duration> {
+        Player::stalled_for(self)
+    }
+
+    fn poll_underrun(&self) -> Option<u64> {
+        Player::poll_underrun(self)
+    }
+
+    fn set_initial_buffer_kb(&self, kb: u64) {
+        Player::set_initial_buffer_kb(self, kb)
+    }
+
+    fn set_rebuffer_secs(&self, secs: u64) {
+        Player::set_rebuffer_secs(self, secs)
+    }
+
+    fn take_error(&self) -> Option<PlayerError> {
+        Player::take_error(self)
+    }
+
+    fn reconnecting(&self) -> Option<(u64, u64)> {
+        Player::reconnecting(self)
+    }
+
+    fn reconnects(&self) -> u64 {
+        Player::reconnects(self)
+    }
+
+    fn set_max_reconnects(&self, max: u64) {
+        Player::set_max_reconnects(self, max)
+    }
+
+    fn set_max_readahead_mb(&self, mb: Option<u64>) {
+        Player::set_max_readahead_mb(self, mb)
+    }
+
+    fn reconnect(&self) {
+        Player::reconnect(self)
+    }
+
+    fn set_fade_pauses(&self, fade: bool) {
+        Player::set_fade_pauses(self, fade)
+    }
+
+    fn set_trim_silence(&self, below_db: Option<f32>) {
+        Player::set_trim_silence(self, below_db)
+    }
+
+    fn prefetch(&self, url: &str) {
+        Player::prefetch(self, url)
+    }
+
+    fn cancel_prefetch(&self) {
+        Player::cancel_prefetch(self)
+    }
+
+    fn record(&self, path: &Path) -> Result<bool, PlayerError> {
+        Player::record(self, path)
+    }
+
+    fn save_head(&self, path: &Path) -> Result<u64, PlayerError> {
+        Player::save_head(self, path)
+    }
+
+    fn output_lost(&self) -> bool {
+        Player::output_lost(self)
+    }
+
+    fn reopen_output(&mut self) -> Result<(), PlayerError> {
+        Player::reopen_output(self)
+    }
+}
+
+/// A call made to a [`NullPlayer`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Call {
+    PlayStream(String),
+    PlayFile(PathBuf, u64),
+    Stop,
+    Pause,
+    Resume,
+    SetVolume(f32),
+    ToggleMute,
+    Seek(u64),
+}
+
+#[derive(Debug)]
+struct NullState {
+    calls: Vec<Call>,
+    playing: bool,
+    paused: bool,
+    elapsed: u64,
+    /// Length of every track, after which it reads as finished; None plays forever
+    track_secs: Option<u64>,
+    volume: f32,
+    start_volume: f32,
+    unmuted_volume: Option<f32>,
+}
+
+/// A backend without audio. It records the calls made and, while a track plays and isn't
+/// paused, moves the position on by what [`advance`](Self::advance) is given. Clones share
+/// their state, so a test can hand one over and keep another to look at.
+#[derive(Debug, Clone)]
+pub struct NullPlayer {
+    state: Arc<Mutex<NullState>>,
+}
+
+impl Default for NullPlayer {
+    fn default() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(NullState {
+                calls: Vec::new(),
+                playing: false,
+                paused: false,
+                elapsed: 0,
+                track_secs: None,
+                volume: 1.0,
+                start_volume: 1.0,
+                unmuted_volume: None,
+            })),
+        }
+    }
+}
+
+impl NullPlayer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tracks end after `secs` instead of playing forever
+    pub fn with_track_secs(self, secs: u64) -> Self {
+        self.state.lock().unwrap().track_secs = Some(secs);
+        self
+    }
+
+    /// Let `secs` of the track play, stopping at its end
+    pub fn advance(&self, secs: u64) {
+        let mut state = self.state.lock().unwrap();
+        if state.playing && !state.paused {
+            let elapsed = state.elapsed.saturating_add(secs);
+            state.elapsed = state.track_secs.map_or(elapsed, |end| elapsed.min(end));
+        }
+    }
+
+    /// Every call so far, oldest first
+    pub fn calls(&self) -> Vec<Call> {
+        self.state.lock().unwrap().calls.clone()
+    }
+
+    fn record(&self, call: Call) -> std::sync::MutexGuard<'_, NullState> {
+        let mut state = self.state.lock().unwrap();
+        state.calls.push(call);
+        state
+    }
+}
+
+impl AudioPlayer for NullPlayer {
+    fn play(&self, source: PlaybackSource) -> Result<(), PlayerError> {
+        let (call, from_secs) = match source {
+            PlaybackSource::Stream(url) => (Call::PlayStream(url.to_string()), 0),
+            PlaybackSource::File(path, from_secs) => {
+                (Call::PlayFile(path.to_path_buf(), from_secs), from_secs)
+            }
+        };
+        let mut state = self.record(call);
+        state.playing = true;
+        state.paused = false;
+        state.elapsed = from_secs;
+        state.volume = if state.unmuted_volume.is_some() {
+            0.0
+        } else {
+            state.start_volume
+        };
+        Ok(())
+    }
+
+    fn stop(&self) {
+        let mut state = self.record(Call::Stop);
+        state.playing = false;
+        state.paused = false;
+        state.elapsed = 0;
+    }
+
+    fn pause(&self) {
+        let mut state = self.record(Call::Pause);
+        state.paused = state.playing;
+    }
+
+    fn resume(&self) {
+        self.record(Call::Resume).paused = false;
+    }
+
+    fn is_paused(&self) -> bool {
+        self.state.lock().unwrap().paused
+    }
+
+    fn volume(&self) -> f32 {
+        self.state.lock().unwrap().volume
+    }
+
+    fn set_volume(&self, volume: f32) {
+        let mut state = self.record(Call::SetVolume(volume));
+        state.unmuted_volume = None;
+        state.volume = volume.clamp(0.0, 2.0);
+    }
+
+    fn toggle_mute(&self) -> bool {
+        let mut state = self.record(Call::ToggleMute);
+        match state.unmuted_volume.take() {
+            Some(volume) => {
+                state.volume = volume;
+                false
+            }
+            None => {
+                state.unmuted_volume = Some(state.volume);
+                state.volume = 0.0;
+                true
+            }
+        }
+    }
+
+    fn is_muted(&self) -> bool {
+        self.state.lock().unwrap().unmuted_volume.is_some()
+    }
+
+    fn set_start_volume(&self, volume: f32) {
+        self.state.lock().unwrap().start_volume = volume.clamp(0.0, 2.0);
+    }
+
+    fn seek(&self, secs: u64) -> bool {
+        let mut state = self.record(Call::Seek(secs));
+        if !state.playing {
+            return false;
+        }
+        state.elapsed = state.track_secs.map_or(secs, |end| secs.min(end));
+        true
+    }
+
+    fn is_finished(&self) -> bool {
+        let state = self.state.lock().unwrap();
+        !state.playing || state.track_secs.is_some_and(|end| state.elapsed >= end)
+    }
+
+    fn elapsed_seconds(&self) -> u64 {
+        self.state.lock().unwrap().elapsed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_calls_and_keeps_time() {
+        let player = NullPlayer::new().with_track_secs(3600);
+        let backend: Box<dyn AudioPlayer> = Box::new(player.clone());
+
+        assert!(!backend.seek(10), "nothing to seek in yet");
+        backend
+            .play(PlaybackSource::Stream("https://example.com/1.mp3"))
+            .unwrap();
+        player.advance(30);
+        backend.pause();
+        player.advance(30);
+        assert_eq!(backend.elapsed_seconds(), 30);
+
+        backend.resume();
+        assert!(backend.seek(3590));
+        player.advance(30);
+        assert_eq!(backend.elapsed_seconds(), 3600);
+        assert!(backend.is_finished());
+
+        assert_eq!(
+            player.calls(),
+            vec![
+                Call::Seek(10),
+                Call::PlayStream("https://example.com/1.mp3".to_string()),
+                Call::Pause,
+                Call::Resume,
+                Call::Seek(3590),
+            ]
+        );
+    }
+
+    #[test]
+    fn files_start_where_asked() {
+        let player = NullPlayer::new();
+        player
+            .play(PlaybackSource::File(Path::new("/tmp/1.mp3"), 90))
+            .unwrap();
+        assert_eq!(player.elapsed_seconds(), 90);
+        assert!(!player.is_finished());
+
+        player.stop();
+        assert_eq!(player.elapsed_seconds(), 0);
+        assert!(player.is_finished());
+    }
+
+    #[test]
+    fn mute_comes_back_to_the_volume_before() {
+        let player = NullPlayer::new();
+        player.set_volume(0.6);
+        assert!(player.toggle_mute());
+        assert_eq!(player.volume(), 0.0);
+        assert!(!player.toggle_mute());
+        assert_eq!(player.volume(), 0.6);
+
+        // A new track stays muted
+        player.set_start_volume(0.8);
+        player.toggle_mute();
+        player.play(PlaybackSource::Stream("u")).unwrap();
+        assert_eq!(player.volume(), 0.0);
+        assert!(player.is_muted());
+    }
+}
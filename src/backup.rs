@@ -0,0 +1,335 @@
+//! Backup and restore of mfp's state
+//! `export` bundles the settings, favorites, playlists, player state, history and listening
+//! journal into a `.tar.gz`
+//! along with an index of the downloaded episodes (never the audio itself); `import` restores
+//! an archive by merging into the current state or replacing it.
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use crate::downloader::{DownloadError, Downloader};
+use crate::errors::{StorageError, EXIT_FAILURE};
+use crate::favorites::Favorites;
+use crate::history::{self, History, HistoryEntry};
+use crate::i18n::{tf, Msg};
+use crate::journal::{Journal, JournalEntry};
+use crate::paths;
+use crate::playlists::Playlists;
+use crate::state::State;
+
+/// Archive layout version written by `export`; `import` reads this one and older
+pub const FORMAT_VERSION: u32 = 1;
+
+const MANIFEST: &str = "manifest.json";
+const DOWNLOADS: &str = "downloads.json";
+const CONFIG: &str = "config.toml";
+const FAVORITES: &str = "favorites.json";
+const PLAYLISTS: &str = "playlists.json";
+const STATE: &str = "state.json";
+const HISTORY: &str = "history.jsonl";
+const JOURNAL: &str = "journal.jsonl";
+
+#[derive(Debug, thiserror::Error)]
+pub enum BackupError {
+    #[error(transparent)]
+    Storage(#[from] StorageError),
+    #[error(transparent)]
+    Download(#[from] DownloadError),
+    /// Not a gzipped tarball, or one without a manifest
+    #[error("{}", tf(Msg::BackupNotAnArchive, &[&path.display()]))]
+    NotAnArchive {
+        path: PathBuf,
+        #[source]
+        source: Option<io::Error>,
+    },
+    #[error("{}", tf(Msg::BackupUnsupportedVersion, &[version, &FORMAT_VERSION]))]
+    UnsupportedVersion { version: u32 },
+    /// An entry mfp should be able to read is not what `export` writes
+    #[error("{}", tf(Msg::BackupDamaged, &[name]))]
+    Damaged {
+        name: &'static str,
+        #[source]
+        source: Box<dyn Error + Send + Sync>,
+    },
+}
+
+impl BackupError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            BackupError::Storage(e) => e.exit_code(),
+            BackupError::Download(e) => e.exit_code(),
+            _ => EXIT_FAILURE,
+        }
+    }
+
+    fn damaged<E: Into<Box<dyn Error + Send + Sync>>>(
+        name: &'static str,
+    ) -> impl FnOnce(E) -> Self {
+        move |source| BackupError::Damaged {
+            name,
+            source: source.into(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Manifest {
+    version: u32,
+    /// mfp version that wrote the archive
+    mfp_version: String,
+    /// Unix timestamp (seconds) of the export
+    created_at: u64,
+}
+
+/// What `import` should do with state that is already there
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportMode {
+    /// Keep the current state and add what the backup has on top
+    Merge,
+    /// Make every state file exactly what the backup holds
+    Replace,
+}
+
+/// Result of an import
+#[derive(Debug, Default)]
+pub struct ImportSummary {
+    /// State files the backup held, by entry name
+    pub files: Vec<&'static str>,
+    /// New favorites, when merging
+    pub favorites: usize,
+    /// Titles added to playlists, when merging
+    pub playlists: usize,
+    /// New history entries, when merging
+    pub history: usize,
+    /// New journal notes, when merging
+    pub journal: usize,
+    /// Settings keys taken from the backup, when merging
+    pub settings: usize,
+    /// Episode files downloaded on the backed-up machine that aren't downloaded here
+    pub missing_downloads: Vec<String>,
+}
+
+/// Entry names in an archive and the files they come from
+fn state_files() -> Result<[(&'static str, PathBuf); 6], StorageError> {
+    Ok([
+        (CONFIG, paths::config_file()?),
+        (FAVORITES, paths::favorites_file()?),
+        (PLAYLISTS, paths::playlists_file()?),
+        (STATE, paths::state_file()?),
+        (HISTORY, paths::history_file()?),
+        (JOURNAL, paths::journal_file()?),
+    ])
+}
+
+/// File names of the downloaded episodes
+fn downloaded() -> Result<Vec<String>, BackupError> {
+    Ok(Downloader::new()?
+        .list_downloaded()?
+        .iter()
+        .filter_map(|path| path.file_name())
+        .map(|name| name.to_string_lossy().into_owned())
+        .collect())
+}
+
+/// Write every state file that exists into a new archive at `path`; returns the entries
+/// written besides the manifest and downloads index
+pub fn export(path: &Path) -> Result<Vec<&'static str>, BackupError> {
+    let created_at = history::unix_now();
+    let manifest = Manifest {
+        version: FORMAT_VERSION,
+        mfp_version: env!("CARGO_PKG_VERSION").to_string(),
+        created_at,
+    };
+
+    let mut entries = vec![(
+        MANIFEST,
+        serde_json::to_vec_pretty(&manifest).map_err(StorageError::write(path))?,
+    )];
+    let mut included = Vec::new();
+    for (name, file) in state_files()? {
+        if file.exists() {
+            entries.push((name, fs::read(&file).map_err(StorageError::read(&file))?));
+            included.push(name);
+        }
+    }
+    entries.push((
+        DOWNLOADS,
+        serde_json::to_vec_pretty(&downloaded()?).map_err(StorageError::write(path))?,
+    ));
+
+    let file = File::create(path).map_err(StorageError::write(path))?;
+    let mut archive = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+    for (name, data) in entries {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(created_at);
+        header.set_cksum();
+        archive
+            .append_data(&mut header, name, data.as_slice())
+            .map_err(StorageError::write(path))?;
+    }
+    archive
+        .into_inner()
+        .and_then(|encoder| encoder.finish())
+        .map_err(StorageError::write(path))?;
+
+    Ok(included)
+}
+
+/// Restore the archive at `path`. Entries the format doesn't know are ignored, so nothing
+/// in the archive decides where files are written.
+pub fn import(path: &Path, mode: ImportMode) -> Result<ImportSummary, BackupError> {
+    let mut contents = read_archive(path)?;
+
+    let manifest: Manifest = match contents.remove(MANIFEST) {
+        Some(data) => serde_json::from_slice(&data).map_err(BackupError::damaged(MANIFEST))?,
+        None => {
+            return Err(BackupError::NotAnArchive {
+                path: path.to_path_buf(),
+                source: None,
+            })
+        }
+    };
+    if manifest.version == 0 || manifest.version > FORMAT_VERSION {
+        return Err(BackupError::UnsupportedVersion {
+            version: manifest.version,
+        });
+    }
+
+    let mut summary = ImportSummary::default();
+    for (name, file) in state_files()? {
+        let data = contents.remove(name);
+        if data.is_some() {
+            summary.files.push(name);
+        }
+
+        match (mode, data) {
+            (ImportMode::Replace, Some(data)) => {
+                write(&file, &data)?;
+                // The backup's undo history would take back changes made before it was taken
+                if name == FAVORITES {
+                    Favorites::load()?.forget_changes()?;
+                }
+            }
+            (ImportMode::Replace, None) if file.exists() => {
+                fs::remove_file(&file).map_err(StorageError::write(&file))?
+            }
+            (ImportMode::Merge, Some(data)) => merge(name, &file, &data, &mut summary)?,
+            _ => {}
+        }
+    }
+
+    if let Some(data) = contents.remove(DOWNLOADS) {
+        let backed_up: Vec<String> =
+            serde_json::from_slice(&data).map_err(BackupError::damaged(DOWNLOADS))?;
+        let here = downloaded()?;
+        summary.missing_downloads = backed_up
+            .into_iter()
+            .filter(|name| !here.contains(name))
+            .collect();
+    }
+
+    Ok(summary)
+}
+
+/// Every entry of the archive by name
+fn read_archive(path: &Path) -> Result<HashMap<String, Vec<u8>>, BackupError> {
+    let file = File::open(path).map_err(StorageError::read(path))?;
+    let not_an_archive = |source| BackupError::NotAnArchive {
+        path: path.to_path_buf(),
+        source: Some(source),
+    };
+
+    let mut archive = tar::Archive::new(GzDecoder::new(file));
+    let mut contents = HashMap::new();
+    for entry in archive.entries().map_err(not_an_archive)? {
+        let mut entry = entry.map_err(not_an_archive)?;
+        let name = entry
+            .path()
+            .map_err(not_an_archive)?
+            .to_string_lossy()
+            .into_owned();
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data).map_err(not_an_archive)?;
+        contents.insert(name, data);
+    }
+
+    Ok(contents)
+}
+
+fn write(file: &Path, data: &[u8]) -> Result<(), StorageError> {
+    if let Some(parent) = file.parent() {
+        fs::create_dir_all(parent).map_err(StorageError::write(parent))?;
+    }
+    fs::write(file, data).map_err(StorageError::write(file))
+}
+
+/// Fold one backed-up state file into the current one
+fn merge(
+    name: &'static str,
+    file: &Path,
+    data: &[u8],
+    summary: &mut ImportSummary,
+) -> Result<(), BackupError> {
+    match name {
+        FAVORITES => {
+            let backed_up: Favorites =
+                serde_json::from_slice(data).map_err(BackupError::damaged(name))?;
+            summary.favorites = Favorites::load()?.merge(backed_up)?;
+        }
+        PLAYLISTS => {
+            let backed_up: Playlists =
+                serde_json::from_slice(data).map_err(BackupError::damaged(name))?;
+            summary.playlists = Playlists::load()?.merge(backed_up)?;
+        }
+        // The queue, volumes and played episodes of this machine win
+        STATE => {
+            let backed_up: State =
+                serde_json::from_slice(data).map_err(BackupError::damaged(name))?;
+            State::load()?.merge(backed_up)?;
+        }
+        HISTORY => summary.history = History::merge(lines::<HistoryEntry>(data))?,
+        JOURNAL => summary.journal = Journal::merge(lines::<JournalEntry>(data))?,
+        CONFIG => {
+            let text = std::str::from_utf8(data).map_err(BackupError::damaged(name))?;
+            let backed_up: toml::Table = text.parse().map_err(BackupError::damaged(name))?;
+            if !file.exists() {
+                summary.settings = backed_up.len();
+                write(file, data)?;
+                return Ok(());
+            }
+
+            // Settings made here win; keys only the backup has are added
+            let content = fs::read_to_string(file).map_err(StorageError::read(file))?;
+            let mut table: toml::Table = content.parse().map_err(StorageError::parse(file))?;
+            for (key, value) in backed_up {
+                if !table.contains_key(&key) {
+                    table.insert(key, value);
+                    summary.settings += 1;
+                }
+            }
+            if summary.settings > 0 {
+                let content = toml::to_string(&table).map_err(BackupError::damaged(name))?;
+                write(file, content.as_bytes())?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Entries of a JSON-lines file; malformed lines are skipped like the loaders do
+fn lines<T: serde::de::DeserializeOwned>(data: &[u8]) -> Vec<T> {
+    String::from_utf8_lossy(data)
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
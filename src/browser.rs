@@ -0,0 +1,179 @@
+//! Episode browser inside the play loop (`l` / `list`)
+//! Pages through the playlist in play order, one screenful of lines at a time. It reads whole
+//! lines, so the play loop leaves its raw-mode progress display while it is open.
+
+use anyhow::Result;
+use std::collections::HashSet;
+use std::io::Write;
+
+use crate::favorites::Favorites;
+use crate::i18n::{t, tf, Msg};
+use crate::playlist::Playlist;
+use crate::style::{self, Role};
+
+/// Rows per page
+pub const PAGE_SIZE: usize = 20;
+
+/// What the browser marks next to the titles
+pub struct Markers<'a> {
+    pub favorites: &'a Favorites,
+    pub downloaded: &'a HashSet<String>,
+}
+
+/// Show the playlist until the user picks an episode (its position in play order, for
+/// [`Playlist::jump_to_position`]) or leaves with `q` or an empty line (None)
+pub fn run(
+    playlist: &Playlist,
+    markers: &Markers,
+    mut read_line: impl FnMut() -> Result<String>,
+    out: &mut impl Write,
+) -> Result<Option<usize>> {
+    let total = playlist.len();
+    let pages = total.div_ceil(PAGE_SIZE).max(1);
+    let mut page = playlist.position() / PAGE_SIZE;
+
+    loop {
+        render(playlist, markers, page, pages, out)?;
+
+        let line = read_line()?;
+        match line.trim() {
+            "" | "q" => return Ok(None),
+            "n" => page = (page + 1).min(pages - 1),
+            "p" => page = page.saturating_sub(1),
+            input => match input.parse::<usize>() {
+                Ok(number) if (1..=total).contains(&number) => return Ok(Some(number - 1)),
+                _ => writeln!(out, "{}", tf(Msg::BrowserInvalid, &[&input]))?,
+            },
+        }
+    }
+}
+
+fn render(
+    playlist: &Playlist,
+    markers: &Markers,
+    page: usize,
+    pages: usize,
+    out: &mut impl Write,
+) -> Result<()> {
+    let marker = |on: bool, role: Role, symbol: &str| {
+        if on {
+            style::paint(role, symbol)
+        } else {
+            " ".to_string()
+        }
+    };
+
+    writeln!(
+        out,
+        "\n{}",
+        style::paint(Role::Title, &tf(Msg::BrowserPage, &[&(page + 1), &pages]))
+    )?;
+    for (position, episode) in playlist
+        .in_play_order()
+        .enumerate()
+        .skip(page * PAGE_SIZE)
+        .take(PAGE_SIZE)
+    {
+        let current = position == playlist.position();
+        let title = if current {
+            style::paint(Role::Title, &episode.title)
+        } else {
+            episode.title.clone()
+        };
+        writeln!(
+            out,
+            "{}{}{} {:3}. {} {}",
+            marker(current, Role::Accent, ">"),
+            marker(
+                markers.favorites.is_favorite(&episode.title),
+                Role::Favorite,
+                "*"
+            ),
+            marker(
+                markers.downloaded.contains(&episode.title),
+                Role::Success,
+                "↓"
+            ),
+            position + 1,
+            title,
+            style::paint(Role::Dim, &format!("[{}]", episode.duration))
+        )?;
+    }
+    write!(out, "{}", t(Msg::BrowserHint))?;
+    out.flush()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::feed::Episode;
+
+    fn playlist(count: usize) -> Playlist {
+        Playlist::new(
+            (1..=count)
+                .map(|n| Episode {
+                    title: format!("Episode {}: Test", n),
+                    audio_url: String::new(),
+                    duration: "01:00:00".into(),
+                    pub_date: String::new(),
+                    description: String::new(),
+                    page_url: None,
+                    image_url: None,
+                })
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    /// Run the browser on scripted input lines; returns the choice and everything printed
+    fn browse(playlist: &Playlist, input: &[&str]) -> (Option<usize>, String) {
+        let favorites = Favorites::default();
+        let downloaded = HashSet::new();
+        let markers = Markers {
+            favorites: &favorites,
+            downloaded: &downloaded,
+        };
+        let mut lines = input.iter().map(|line| format!("{}\n", line));
+        let mut out = Vec::new();
+
+        let choice = run(
+            playlist,
+            &markers,
+            || Ok(lines.next().unwrap_or_default()),
+            &mut out,
+        )
+        .unwrap();
+        (choice, String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn a_number_picks_that_row() {
+        let (choice, _) = browse(&playlist(30), &["25"]);
+        assert_eq!(choice, Some(24));
+    }
+
+    #[test]
+    fn pages_hold_twenty_rows_and_stop_at_the_ends() {
+        let (choice, out) = browse(&playlist(30), &["p", "n", "n", "q"]);
+        assert_eq!(choice, None);
+        assert!(out.contains(" 20. Episode 20: Test"));
+        assert!(out.contains(" 30. Episode 30: Test"));
+        assert!(!out.contains(" 31."));
+    }
+
+    #[test]
+    fn opens_on_the_page_of_the_current_episode() {
+        let mut list = playlist(45);
+        list.jump_to_position(41);
+        let (_, out) = browse(&list, &[""]);
+        assert!(out.contains(">    42. Episode 42: Test"), "{}", out);
+        assert!(!out.contains("Episode 40: Test"));
+    }
+
+    #[test]
+    fn out_of_range_numbers_are_refused() {
+        let (choice, _) = browse(&playlist(5), &["9", "0", "x", "3"]);
+        assert_eq!(choice, Some(2));
+    }
+}
@@ -0,0 +1,411 @@
+//! Chromecast output (`cast` feature)
+//! The device fetches and decodes the episode itself; mfp only sends it the audio URL and
+//! transport commands. A worker thread owns the connection (rust-cast's is not `Send`), polls
+//! the playback position once a second and reports when the device stops answering.
+
+use anyhow::{anyhow, Context, Result};
+use mdns_sd::{ServiceDaemon, ServiceEvent};
+use rust_cast::channels::media::{IdleReason, Media, PlayerState, StreamType};
+use rust_cast::channels::receiver::CastDeviceApp;
+use rust_cast::CastDevice;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+use crate::errors::NotFound;
+use crate::i18n::{tf, Msg};
+
+const SERVICE: &str = "_googlecast._tcp.local.";
+/// How long `discover` listens for answers
+pub const DISCOVERY_TIME: Duration = Duration::from_secs(3);
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A Chromecast found on the local network
+#[derive(Debug, Clone)]
+pub struct Device {
+    /// Name given to the device in the Google Home app
+    pub name: String,
+    pub host: String,
+    pub port: u16,
+}
+
+/// Devices answering on the local network within `wait`, sorted by name
+pub fn discover(wait: Duration) -> Result<Vec<Device>> {
+    let daemon = ServiceDaemon::new().context("Failed to start mDNS discovery")?;
+    let events = daemon
+        .browse(SERVICE)
+        .context("Failed to start mDNS discovery")?;
+
+    let mut devices: Vec<Device> = Vec::new();
+    let deadline = Instant::now() + wait;
+    while let Some(left) = deadline.checked_duration_since(Instant::now()) {
+        let Ok(event) = events.recv_timeout(left) else {
+            break;
+        };
+        let ServiceEvent::ServiceResolved(info) = event else {
+            continue;
+        };
+        let Some(address) = info.get_addresses().iter().next() else {
+            continue;
+        };
+
+        let name = info
+            .get_property_val_str("fn")
+            .unwrap_or(info.get_fullname())
+            .to_string();
+        if devices.iter().all(|device| device.name != name) {
+            devices.push(Device {
+                name,
+                host: address.to_string(),
+                port: info.get_port(),
+            });
+        }
+    }
+    let _ = daemon.shutdown();
+
+    devices.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(devices)
+}
+
+/// The device called `name` (ignoring case), or the only one whose name contains it
+pub fn find(name: &str) -> Result<Device> {
+    let devices = discover(DISCOVERY_TIME)?;
+    pick(devices, name).ok_or_else(|| NotFound(tf(Msg::CastDeviceNotFound, &[&name])).into())
+}
+
+fn pick(devices: Vec<Device>, name: &str) -> Option<Device> {
+    let wanted = name.to_lowercase();
+    if let Some(device) = devices
+        .iter()
+        .find(|device| device.name.to_lowercase() == wanted)
+    {
+        return Some(device.clone());
+    }
+
+    let mut partial = devices
+        .into_iter()
+        .filter(|device| device.name.to_lowercase().contains(&wanted));
+    match (partial.next(), partial.next()) {
+        (Some(device), None) => Some(device),
+        _ => None,
+    }
+}
+
+enum Command {
+    Load(String),
+    Play,
+    Pause,
+    Seek(f32),
+    Volume(f32),
+    Stop,
+}
+
+/// What the device last reported
+#[derive(Default)]
+struct Status {
+    position_secs: f32,
+    paused: bool,
+    finished: bool,
+    /// The connection failed; the worker has exited
+    lost: bool,
+}
+
+/// Remote control for one device; dropping it closes the app on the device
+pub struct Remote {
+    pub device: Device,
+    commands: Sender<Command>,
+    status: Arc<Mutex<Status>>,
+    volume: Mutex<f32>,
+    start_volume: Mutex<f32>,
+    /// While muted, the volume unmuting goes back to
+    unmuted_volume: Mutex<Option<f32>>,
+}
+
+impl Remote {
+    /// Connect and start the default media receiver app on `device`
+    pub fn connect(device: Device) -> Result<Self> {
+        let (commands, rx) = mpsc::channel();
+        let (ready_tx, ready_rx) = mpsc::channel();
+        let status = Arc::new(Mutex::new(Status::default()));
+
+        let worker_device = device.clone();
+        let worker_status = Arc::clone(&status);
+        thread::spawn(move || {
+            let mut session = match Session::open(&worker_device) {
+                Ok(session) => {
+                    let _ = ready_tx.send(Ok(()));
+                    session
+                }
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e));
+                    return;
+                }
+            };
+
+            loop {
+                let result = match rx.recv_timeout(POLL_INTERVAL) {
+                    Ok(command) => session.apply(command, &worker_status),
+                    Err(RecvTimeoutError::Timeout) => Ok(()),
+                    Err(RecvTimeoutError::Disconnected) => {
+                        session.close();
+                        return;
+                    }
+                };
+                if let Err(e) = result.and_then(|()| session.poll(&worker_status)) {
+                    warn!("Cast device lost: {:#}", e);
+                    worker_status.lock().unwrap().lost = true;
+                    return;
+                }
+            }
+        });
+
+        ready_rx
+            .recv()
+            .map_err(|_| anyhow!("cast worker exited"))?
+            .with_context(|| tf(Msg::CastConnectFailed, &[&device.name]))?;
+
+        Ok(Remote {
+            device,
+            commands,
+            status,
+            volume: Mutex::new(1.0),
+            start_volume: Mutex::new(1.0),
+            unmuted_volume: Mutex::new(None),
+        })
+    }
+
+    fn send(&self, command: Command) {
+        if self.commands.send(command).is_err() {
+            self.status.lock().unwrap().lost = true;
+        }
+    }
+
+    /// Have the device play `url` from the start
+    pub fn play(&self, url: &str) {
+        let lost = self.is_lost();
+        *self.status.lock().unwrap() = Status {
+            lost,
+            ..Status::default()
+        };
+        if self.is_muted() {
+            self.send_volume(0.0);
+        } else {
+            self.send_volume(*self.start_volume.lock().unwrap());
+        }
+        self.send(Command::Load(url.to_string()));
+    }
+
+    pub fn stop(&self) {
+        self.send(Command::Stop);
+    }
+
+    pub fn pause(&self) {
+        self.status.lock().unwrap().paused = true;
+        self.send(Command::Pause);
+    }
+
+    pub fn resume(&self) {
+        self.status.lock().unwrap().paused = false;
+        self.send(Command::Play);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.status.lock().unwrap().paused
+    }
+
+    pub fn volume(&self) -> f32 {
+        *self.volume.lock().unwrap()
+    }
+
+    /// The device tops out at its own full volume, so anything above 1.0 plays as 1.0
+    pub fn set_volume(&self, volume: f32) {
+        *self.unmuted_volume.lock().unwrap() = None;
+        self.send_volume(volume);
+    }
+
+    fn send_volume(&self, volume: f32) {
+        let volume = volume.clamp(0.0, 2.0);
+        *self.volume.lock().unwrap() = volume;
+        self.send(Command::Volume(volume.min(1.0)));
+    }
+
+    /// Mute, or go back to the volume from before; true when now muted
+    pub fn toggle_mute(&self) -> bool {
+        let mut unmuted = self.unmuted_volume.lock().unwrap();
+        match unmuted.take() {
+            Some(volume) => {
+                self.send_volume(volume);
+                false
+            }
+            None => {
+                *unmuted = Some(self.volume());
+                self.send_volume(0.0);
+                true
+            }
+        }
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.unmuted_volume.lock().unwrap().is_some()
+    }
+
+    pub fn set_start_volume(&self, volume: f32) {
+        *self.start_volume.lock().unwrap() = volume.clamp(0.0, 2.0);
+    }
+
+    pub fn seek(&self, secs: u64) {
+        self.status.lock().unwrap().position_secs = secs as f32;
+        self.send(Command::Seek(secs as f32));
+    }
+
+    /// Position the device reported last
+    pub fn position_seconds(&self) -> u64 {
+        self.status.lock().unwrap().position_secs as u64
+    }
+
+    pub fn is_finished(&self) -> bool {
+        let status = self.status.lock().unwrap();
+        status.finished || status.lost
+    }
+
+    /// The device stopped answering
+    pub fn is_lost(&self) -> bool {
+        self.status.lock().unwrap().lost
+    }
+}
+
+/// The connection as owned by the worker thread
+struct Session {
+    device: CastDevice<'static>,
+    transport_id: String,
+    session_id: String,
+    media_session_id: Option<i32>,
+}
+
+impl Session {
+    fn open(device: &Device) -> Result<Self> {
+        // Chromecasts present self-signed certificates
+        let cast = CastDevice::connect_without_host_verification(device.host.clone(), device.port)?;
+        cast.connection.connect("receiver-0")?;
+        cast.heartbeat.ping()?;
+
+        let app = cast
+            .receiver
+            .launch_app(&CastDeviceApp::DefaultMediaReceiver)?;
+        cast.connection.connect(app.transport_id.clone())?;
+        debug!(device = %device.name, "Cast session started");
+
+        Ok(Session {
+            device: cast,
+            transport_id: app.transport_id,
+            session_id: app.session_id,
+            media_session_id: None,
+        })
+    }
+
+    fn apply(&mut self, command: Command, status: &Mutex<Status>) -> Result<()> {
+        let transport = self.transport_id.clone();
+        match (command, self.media_session_id) {
+            (Command::Load(url), _) => {
+                let loaded = self.device.media.load(
+                    transport,
+                    self.session_id.clone(),
+                    &Media {
+                        content_id: url,
+                        stream_type: StreamType::Buffered,
+                        content_type: "audio/mpeg".to_string(),
+                        metadata: None,
+                        duration: None,
+                    },
+                )?;
+                self.media_session_id = loaded.entries.first().map(|e| e.media_session_id);
+                status.lock().unwrap().finished = false;
+            }
+            (Command::Play, Some(id)) => {
+                self.device.media.play(transport, id)?;
+            }
+            (Command::Pause, Some(id)) => {
+                self.device.media.pause(transport, id)?;
+            }
+            (Command::Seek(secs), Some(id)) => {
+                self.device.media.seek(transport, id, Some(secs), None)?;
+            }
+            (Command::Stop, Some(id)) => {
+                self.device.media.stop(transport, id)?;
+                self.media_session_id = None;
+            }
+            (Command::Volume(level), _) => {
+                self.device.receiver.set_volume(level)?;
+            }
+            // Nothing loaded yet
+            (Command::Play | Command::Pause | Command::Seek(_) | Command::Stop, None) => {}
+        }
+        Ok(())
+    }
+
+    /// Keep the connection alive and pick up the position and state
+    fn poll(&mut self, status: &Mutex<Status>) -> Result<()> {
+        self.device.heartbeat.ping()?;
+
+        let Some(id) = self.media_session_id else {
+            return Ok(());
+        };
+        let reported = self
+            .device
+            .media
+            .get_status(self.transport_id.clone(), Some(id))?;
+
+        if let Some(entry) = reported.entries.first() {
+            let mut status = status.lock().unwrap();
+            if let Some(position) = entry.current_time {
+                status.position_secs = position;
+            }
+            status.paused = matches!(entry.player_state, PlayerState::Paused);
+            status.finished = matches!(entry.player_state, PlayerState::Idle)
+                && matches!(entry.idle_reason, Some(IdleReason::Finished));
+        }
+        Ok(())
+    }
+
+    fn close(&mut self) {
+        let _ = self.device.receiver.stop_app(self.session_id.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn devices(names: &[&str]) -> Vec<Device> {
+        names
+            .iter()
+            .map(|name| Device {
+                name: name.to_string(),
+                host: "192.0.2.1".into(),
+                port: 8009,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn exact_names_win_over_partial_ones() {
+        let found = pick(
+            devices(&["Living Room", "Living Room speaker"]),
+            "living room",
+        );
+        assert_eq!(found.map(|d| d.name).as_deref(), Some("Living Room"));
+    }
+
+    #[test]
+    fn a_partial_name_must_be_unambiguous() {
+        let all = devices(&["Kitchen", "Living Room speaker", "Bedroom speaker"]);
+        assert_eq!(
+            pick(all.clone(), "kitch").map(|d| d.name).as_deref(),
+            Some("Kitchen")
+        );
+        assert!(pick(all.clone(), "speaker").is_none());
+        assert!(pick(all, "garage").is_none());
+    }
+}
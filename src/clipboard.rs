@@ -0,0 +1,54 @@
+//! Copying episode links to the system clipboard
+//! Without a clipboard (SSH, headless, or a build without the `clipboard` feature)
+//! the link is printed instead, so yanking never fails.
+
+use crate::i18n::{tf, Msg};
+use crate::style::{self, Role};
+
+/// Put `text` on the clipboard and say so, or print it for copying by hand
+pub fn copy(text: &str) {
+    match set_text(text) {
+        Ok(()) => println!("{}", tf(Msg::Copied, &[&text])),
+        Err(reason) => {
+            tracing::debug!("Clipboard unavailable: {}", reason);
+            println!(
+                "{}",
+                tf(Msg::NoClipboard, &[&style::paint(Role::Accent, text)])
+            );
+        }
+    }
+}
+
+#[cfg(feature = "clipboard")]
+thread_local! {
+    // On X11 and Wayland the text is only served while the owner is alive,
+    // so the play loop keeps one for the whole session
+    static CLIPBOARD: std::cell::RefCell<Option<arboard::Clipboard>> =
+        const { std::cell::RefCell::new(None) };
+}
+
+#[cfg(feature = "clipboard")]
+fn set_text(text: &str) -> Result<(), String> {
+    CLIPBOARD.with(|cell| {
+        let mut clipboard = cell.borrow_mut();
+        if clipboard.is_none() {
+            *clipboard = Some(arboard::Clipboard::new().map_err(|e| e.to_string())?);
+        }
+        clipboard
+            .as_mut()
+            .map_or(Ok(()), |clipboard| clipboard.set_text(text))
+            .map_err(|e| e.to_string())
+    })
+}
+
+#[cfg(not(feature = "clipboard"))]
+fn set_text(_text: &str) -> Result<(), String> {
+    Err("built without the clipboard feature".to_string())
+}
+
+/// Give up clipboard ownership before a one-shot command exits, which hands the text
+/// to the desktop's clipboard manager where there is one
+pub fn release() {
+    #[cfg(feature = "clipboard")]
+    CLIPBOARD.with(|cell| drop(cell.borrow_mut().take()));
+}
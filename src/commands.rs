@@ -0,0 +1,843 @@
+//! Player commands
+//! One table drives both command parsing in the play loop and the help overlay,
+//! so the help can never list a command that doesn't exist (or miss one that does).
+
+use anyhow::{bail, Result};
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::i18n::{t, Msg};
+use crate::keys::Keymap;
+use crate::playlist::RepeatMode;
+use crate::schedule;
+use crate::style::{self, Role};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Next,
+    Previous,
+    Pause,
+    Full,
+    Restart,
+    Seek,
+    Goto,
+    Episode,
+    SeekForward,
+    SeekBack,
+    SeekForwardLong,
+    SeekBackLong,
+    Loop,
+    VolumeUp,
+    VolumeDown,
+    Volume,
+    Mute,
+    BalanceRight,
+    BalanceLeft,
+    BalanceCenter,
+    VolumeSave,
+    Shuffle,
+    Repeat,
+    Queue,
+    List,
+    Favorite,
+    AddToPlaylist,
+    Undo,
+    Redo,
+    Download,
+    Record,
+    Info,
+    Open,
+    Yank,
+    YankPage,
+    Note,
+    Sleep,
+    StopAfter,
+    Device,
+    Help,
+    Quit,
+}
+
+impl Action {
+    const ALL: [Action; 41] = [
+        Action::Next,
+        Action::Previous,
+        Action::Pause,
+        Action::Full,
+        Action::Restart,
+        Action::Seek,
+        Action::Goto,
+        Action::Episode,
+        Action::SeekForward,
+        Action::SeekBack,
+        Action::SeekForwardLong,
+        Action::SeekBackLong,
+        Action::Loop,
+        Action::VolumeUp,
+        Action::VolumeDown,
+        Action::Volume,
+        Action::Mute,
+        Action::BalanceRight,
+        Action::BalanceLeft,
+        Action::BalanceCenter,
+        Action::VolumeSave,
+        Action::Shuffle,
+        Action::Repeat,
+        Action::Queue,
+        Action::List,
+        Action::Favorite,
+        Action::AddToPlaylist,
+        Action::Undo,
+        Action::Redo,
+        Action::Download,
+        Action::Record,
+        Action::Info,
+        Action::Open,
+        Action::Yank,
+        Action::YankPage,
+        Action::Note,
+        Action::Sleep,
+        Action::StopAfter,
+        Action::Device,
+        Action::Help,
+        Action::Quit,
+    ];
+
+    /// Name used for the action in the `[keys]` config section
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Next => "next",
+            Action::Previous => "previous",
+            Action::Pause => "pause",
+            Action::Full => "full",
+            Action::Restart => "restart",
+            Action::Seek => "seek",
+            Action::Goto => "goto",
+            Action::Episode => "episode",
+            Action::SeekForward => "seek_forward",
+            Action::SeekBack => "seek_back",
+            Action::SeekForwardLong => "seek_forward_long",
+            Action::SeekBackLong => "seek_back_long",
+            Action::Loop => "loop",
+            Action::VolumeUp => "volume_up",
+            Action::VolumeDown => "volume_down",
+            Action::Volume => "volume",
+            Action::Mute => "mute",
+            Action::BalanceRight => "balance_right",
+            Action::BalanceLeft => "balance_left",
+            Action::BalanceCenter => "balance_center",
+            Action::VolumeSave => "volume_save",
+            Action::Shuffle => "shuffle",
+            Action::Repeat => "repeat",
+            Action::Queue => "queue",
+            Action::List => "list",
+            Action::Favorite => "favorite",
+            Action::AddToPlaylist => "add_to_playlist",
+            Action::Undo => "undo",
+            Action::Redo => "redo",
+            Action::Download => "download",
+            Action::Record => "record",
+            Action::Info => "info",
+            Action::Open => "open",
+            Action::Yank => "yank",
+            Action::YankPage => "yank_page",
+            Action::Note => "note",
+            Action::Sleep => "sleep",
+            Action::StopAfter => "stop_after",
+            Action::Device => "device",
+            Action::Help => "help",
+            Action::Quit => "quit",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.name() == name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    Transport,
+    Volume,
+    Playlist,
+    Library,
+    Session,
+}
+
+impl Category {
+    const ALL: [Category; 5] = [
+        Category::Transport,
+        Category::Volume,
+        Category::Playlist,
+        Category::Library,
+        Category::Session,
+    ];
+
+    fn title(self) -> &'static str {
+        match self {
+            Category::Transport => t(Msg::HelpTransport),
+            Category::Volume => t(Msg::HelpVolume),
+            Category::Playlist => t(Msg::HelpPlaylist),
+            Category::Library => t(Msg::HelpLibrary),
+            Category::Session => t(Msg::HelpSession),
+        }
+    }
+}
+
+pub struct CommandSpec {
+    pub action: Action,
+    /// Accepted spellings; the first one is shown as the shortcut
+    pub names: &'static [&'static str],
+    pub category: Category,
+    pub help: Msg,
+}
+
+pub const COMMANDS: &[CommandSpec] = &[
+    CommandSpec {
+        action: Action::Pause,
+        names: &["p", "pause", "play"],
+        category: Category::Transport,
+        help: Msg::HelpPause,
+    },
+    CommandSpec {
+        action: Action::Next,
+        names: &["n", "next"],
+        category: Category::Transport,
+        help: Msg::HelpNext,
+    },
+    CommandSpec {
+        action: Action::Previous,
+        names: &["b", "back", "prev", "previous"],
+        category: Category::Transport,
+        help: Msg::HelpPrevious,
+    },
+    CommandSpec {
+        action: Action::Full,
+        names: &["full"],
+        category: Category::Transport,
+        help: Msg::HelpFull,
+    },
+    CommandSpec {
+        action: Action::Restart,
+        names: &["r", "restart"],
+        category: Category::Transport,
+        help: Msg::HelpRestart,
+    },
+    CommandSpec {
+        action: Action::Seek,
+        names: &["seek"],
+        category: Category::Transport,
+        help: Msg::HelpSeek,
+    },
+    CommandSpec {
+        action: Action::Goto,
+        names: &["goto"],
+        category: Category::Transport,
+        help: Msg::HelpGoto,
+    },
+    CommandSpec {
+        action: Action::Episode,
+        names: &["g", "ep"],
+        category: Category::Transport,
+        help: Msg::HelpEpisode,
+    },
+    CommandSpec {
+        action: Action::SeekForward,
+        names: &[">"],
+        category: Category::Transport,
+        help: Msg::HelpSeekForward,
+    },
+    CommandSpec {
+        action: Action::SeekBack,
+        names: &["<"],
+        category: Category::Transport,
+        help: Msg::HelpSeekBack,
+    },
+    CommandSpec {
+        action: Action::SeekForwardLong,
+        names: &[">>"],
+        category: Category::Transport,
+        help: Msg::HelpSeekForwardLong,
+    },
+    CommandSpec {
+        action: Action::SeekBackLong,
+        names: &["<<"],
+        category: Category::Transport,
+        help: Msg::HelpSeekBackLong,
+    },
+    CommandSpec {
+        action: Action::Loop,
+        names: &["a", "loop"],
+        category: Category::Transport,
+        help: Msg::HelpLoop,
+    },
+    CommandSpec {
+        action: Action::VolumeUp,
+        names: &["+", "up"],
+        category: Category::Volume,
+        help: Msg::HelpVolumeUp,
+    },
+    CommandSpec {
+        action: Action::VolumeDown,
+        names: &["-", "down"],
+        category: Category::Volume,
+        help: Msg::HelpVolumeDown,
+    },
+    CommandSpec {
+        action: Action::Volume,
+        names: &["vol", "volume"],
+        category: Category::Volume,
+        help: Msg::HelpSetVolume,
+    },
+    CommandSpec {
+        action: Action::Mute,
+        names: &["m", "mute"],
+        category: Category::Volume,
+        help: Msg::HelpMute,
+    },
+    CommandSpec {
+        action: Action::BalanceRight,
+        names: &["bal+"],
+        category: Category::Volume,
+        help: Msg::HelpBalanceRight,
+    },
+    CommandSpec {
+        action: Action::BalanceLeft,
+        names: &["bal-"],
+        category: Category::Volume,
+        help: Msg::HelpBalanceLeft,
+    },
+    CommandSpec {
+        action: Action::BalanceCenter,
+        names: &["bal0"],
+        category: Category::Volume,
+        help: Msg::HelpBalanceCenter,
+    },
+    CommandSpec {
+        action: Action::VolumeSave,
+        names: &["volsave"],
+        category: Category::Volume,
+        help: Msg::HelpVolumeSave,
+    },
+    CommandSpec {
+        action: Action::Shuffle,
+        names: &["s", "shuffle"],
+        category: Category::Playlist,
+        help: Msg::HelpShuffle,
+    },
+    CommandSpec {
+        action: Action::Repeat,
+        names: &["repeat"],
+        category: Category::Playlist,
+        help: Msg::HelpRepeat,
+    },
+    CommandSpec {
+        action: Action::Queue,
+        names: &["queue"],
+        category: Category::Playlist,
+        help: Msg::HelpQueue,
+    },
+    CommandSpec {
+        action: Action::List,
+        names: &["l", "list"],
+        category: Category::Playlist,
+        help: Msg::HelpList,
+    },
+    CommandSpec {
+        action: Action::Favorite,
+        names: &["f", "fav", "favorite"],
+        category: Category::Library,
+        help: Msg::HelpFavorite,
+    },
+    CommandSpec {
+        action: Action::AddToPlaylist,
+        names: &["add"],
+        category: Category::Library,
+        help: Msg::HelpAddToPlaylist,
+    },
+    CommandSpec {
+        action: Action::Undo,
+        names: &["u", "undo"],
+        category: Category::Library,
+        help: Msg::HelpUndo,
+    },
+    CommandSpec {
+        action: Action::Redo,
+        names: &["redo"],
+        category: Category::Library,
+        help: Msg::HelpRedo,
+    },
+    CommandSpec {
+        action: Action::Download,
+        names: &["d", "download"],
+        category: Category::Library,
+        help: Msg::HelpDownload,
+    },
+    CommandSpec {
+        action: Action::Record,
+        names: &["rec", "record"],
+        category: Category::Library,
+        help: Msg::HelpRecord,
+    },
+    CommandSpec {
+        action: Action::Open,
+        names: &["o", "open"],
+        category: Category::Library,
+        help: Msg::HelpOpen,
+    },
+    CommandSpec {
+        action: Action::Yank,
+        names: &["y", "yank"],
+        category: Category::Library,
+        help: Msg::HelpYank,
+    },
+    CommandSpec {
+        action: Action::YankPage,
+        names: &["Y"],
+        category: Category::Library,
+        help: Msg::HelpYankPage,
+    },
+    CommandSpec {
+        action: Action::Note,
+        names: &["note"],
+        category: Category::Library,
+        help: Msg::HelpNote,
+    },
+    CommandSpec {
+        action: Action::Info,
+        names: &["i", "info"],
+        category: Category::Session,
+        help: Msg::HelpInfo,
+    },
+    CommandSpec {
+        action: Action::Sleep,
+        names: &["sleep"],
+        category: Category::Session,
+        help: Msg::HelpSleep,
+    },
+    CommandSpec {
+        action: Action::StopAfter,
+        names: &["x", "stop-after"],
+        category: Category::Session,
+        help: Msg::HelpStopAfter,
+    },
+    CommandSpec {
+        action: Action::Device,
+        names: &["device"],
+        category: Category::Session,
+        help: Msg::HelpDevice,
+    },
+    CommandSpec {
+        action: Action::Help,
+        names: &["h", "?", "help"],
+        category: Category::Session,
+        help: Msg::HelpHelp,
+    },
+    CommandSpec {
+        action: Action::Quit,
+        names: &["q", "quit", "exit"],
+        category: Category::Session,
+        help: Msg::HelpQuit,
+    },
+];
+
+/// Current values shown next to the toggles in the help panel
+pub struct ToggleState {
+    pub shuffle: bool,
+    pub repeat: RepeatMode,
+    pub muted: bool,
+    pub stop_after: bool,
+}
+
+/// Commands that accept free text after their name (`note great track`, `volsave clear`)
+const TAKES_TEXT: &[Action] = &[
+    Action::Note,
+    Action::AddToPlaylist,
+    Action::VolumeSave,
+    Action::Seek,
+    Action::Goto,
+    Action::Episode,
+    Action::Queue,
+    Action::Volume,
+    Action::Sleep,
+];
+
+pub fn parse(input: &str) -> Option<Action> {
+    let (name, text) = split(input);
+    // `50%` on its own is short for `goto 50`
+    if text.is_empty() && name.strip_suffix('%').and_then(percent).is_some() {
+        return Some(Action::Goto);
+    }
+    // `goto #19` is an episode, `goto 19` always a percentage (`g 19` for the episode)
+    if name == "goto" && text.starts_with('#') {
+        return Some(Action::Episode);
+    }
+    // `q` alone quits, `q 19` queues
+    if name == "q" && !text.is_empty() {
+        return Some(Action::Queue);
+    }
+
+    COMMANDS
+        .iter()
+        .find(|spec| spec.names.contains(&name))
+        .map(|spec| spec.action)
+        .filter(|action| text.is_empty() || TAKES_TEXT.contains(action))
+}
+
+/// Position a `seek` argument points at: `12:30` or `1:02:03`, plain seconds, or either with
+/// `+`/`-` to move from `elapsed`. Moving back past the start stops at 0.
+pub fn seek_target(argument: &str, elapsed: u64) -> Option<u64> {
+    let (sign, time) = match argument.as_bytes().first() {
+        Some(b'+') => (1, &argument[1..]),
+        Some(b'-') => (-1, &argument[1..]),
+        _ => (0, argument),
+    };
+    let secs = match time.parse::<u64>() {
+        Ok(secs) => secs,
+        Err(_) => crate::player::parse_duration(time)?,
+    };
+
+    Some(match sign {
+        1 => elapsed.saturating_add(secs),
+        -1 => elapsed.saturating_sub(secs),
+        _ => secs,
+    })
+}
+
+/// Percentage of the episode a `goto` command (`goto 50`, `goto 50%`) or a bare `50%` asks
+/// for; None when it isn't a number
+pub fn goto_percent(input: &str) -> Option<f64> {
+    let (name, text) = split(input);
+    match name.strip_suffix('%') {
+        Some(number) if text.is_empty() => percent(number),
+        _ => percent(text.strip_suffix('%').unwrap_or(text)),
+    }
+}
+
+/// Episode a `goto` past 100 without a `%` was probably meant for: `goto 120` can't be a
+/// share of the episode, but `goto 120%` is asked for on purpose
+pub fn goto_meant_episode(input: &str) -> Option<usize> {
+    let (_, text) = split(input);
+    text.parse().ok().filter(|&number| number > 100)
+}
+
+/// Episode number a `g 19`, `ep 19`, `goto #19` or `q 19` command asks for
+pub fn episode_number(input: &str) -> Option<usize> {
+    let (_, text) = split(input);
+    text.strip_prefix('#').unwrap_or(text).parse().ok()
+}
+
+/// Second `percent` of the way into an episode of `total_secs`; 100 and over stop just before
+/// the end, so the episode still plays out rather than ending at once
+pub fn percent_target(percent: f64, total_secs: u64) -> u64 {
+    let secs = (total_secs as f64 * percent / 100.0) as u64;
+    secs.min(total_secs.saturating_sub(1))
+}
+
+fn percent(number: &str) -> Option<f64> {
+    number
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|percent| percent.is_finite() && *percent >= 0.0)
+}
+
+/// Percentage a `volume` argument sets: `60` or `60%`, up to 200
+pub fn volume_percent(argument: &str) -> Option<u8> {
+    let percent = argument.strip_suffix('%').unwrap_or(argument).trim();
+    percent.parse::<u8>().ok().filter(|&percent| percent <= 200)
+}
+
+/// What a `sleep` argument asks for
+#[derive(Debug, PartialEq, Eq)]
+pub enum Sleep {
+    After(Duration),
+    Off,
+}
+
+/// `sleep` argument: `off`, or a delay as for `--in` (`45` is minutes, `1h30m`, `90s`)
+pub fn sleep_argument(argument: &str) -> Option<Sleep> {
+    if argument.eq_ignore_ascii_case("off") {
+        return Some(Sleep::Off);
+    }
+    schedule::parse_delay(argument).ok().map(Sleep::After)
+}
+
+/// Text typed after the command name, empty when there is none
+pub fn argument(input: &str) -> &str {
+    split(input).1
+}
+
+/// `[aliases]` from the config: names typed in place of a command and its text
+#[derive(Debug, Default)]
+pub struct Aliases {
+    entries: Vec<(String, String)>,
+}
+
+impl Aliases {
+    /// Check every alias up front, so a bad one fails at startup rather than mid-listening
+    pub fn from_config(config: &HashMap<String, String>) -> Result<Self> {
+        let mut entries: Vec<(String, String)> = config
+            .iter()
+            .map(|(name, target)| (name.trim().to_string(), target.trim().to_string()))
+            .collect();
+        entries.sort();
+
+        for (name, target) in &entries {
+            if name.is_empty() || name.contains(char::is_whitespace) {
+                bail!("Alias \"{}\" must be a single word in [aliases]", name);
+            }
+            if COMMANDS
+                .iter()
+                .any(|spec| spec.names.contains(&name.as_str()))
+            {
+                bail!(
+                    "Alias \"{}\" would hide the built-in command in [aliases]",
+                    name
+                );
+            }
+            let (command, _) = split(target);
+            // One level only, so aliases can never loop
+            if entries.iter().any(|(other, _)| other == command) {
+                bail!(
+                    "Alias \"{}\" points at the alias \"{}\"; aliases can't refer to aliases",
+                    name,
+                    command
+                );
+            }
+            if parse(target).is_none() {
+                bail!(
+                    "Alias \"{}\" = \"{}\" is not a player command in [aliases]",
+                    name,
+                    target
+                );
+            }
+        }
+
+        Ok(Aliases { entries })
+    }
+
+    /// The command an alias stands for, plus any text typed after it; other input is kept
+    pub fn expand(&self, input: &str) -> String {
+        let (name, text) = split(input);
+        match self.entries.iter().find(|(alias, _)| alias == name) {
+            Some((_, target)) if text.is_empty() => target.clone(),
+            Some((_, target)) => format!("{} {}", target, text),
+            None => input.to_string(),
+        }
+    }
+}
+
+fn split(input: &str) -> (&str, &str) {
+    match input.split_once(char::is_whitespace) {
+        Some((name, text)) => (name, text.trim()),
+        None => (input, ""),
+    }
+}
+
+/// Help panel listing every command and its keys, grouped by category
+pub fn help_panel(state: &ToggleState, keymap: &Keymap, aliases: &Aliases) -> String {
+    let mut panel = String::new();
+
+    for category in Category::ALL {
+        panel.push_str(&format!(
+            "\n{}\n",
+            style::paint(Role::Title, category.title())
+        ));
+
+        for spec in COMMANDS.iter().filter(|spec| spec.category == category) {
+            let value = match spec.action {
+                Action::Shuffle => Some(toggle_label(state.shuffle)),
+                Action::Repeat => Some(repeat_label(state.repeat)),
+                Action::Mute => Some(toggle_label(state.muted)),
+                Action::StopAfter => Some(toggle_label(state.stop_after)),
+                _ => None,
+            };
+            let value = value
+                .map(|label| style::paint(Role::Accent, &format!(" [{}]", label)))
+                .unwrap_or_default();
+
+            let keys: Vec<String> = keymap
+                .keys_for(spec.action)
+                .iter()
+                .map(|key| key.to_string())
+                .collect();
+            let keys = format!("{:<12}", keys.join(" "));
+
+            panel.push_str(&format!(
+                "  {:<20} {} {}{}\n",
+                spec.names.join(", "),
+                style::paint(Role::Accent, &keys),
+                t(spec.help),
+                value
+            ));
+        }
+    }
+
+    if !aliases.entries.is_empty() {
+        panel.push_str(&format!(
+            "\n{}\n",
+            style::paint(Role::Title, t(Msg::HelpAliases))
+        ));
+        for (name, target) in &aliases.entries {
+            panel.push_str(&format!("  {:<20} {}\n", name, target));
+        }
+    }
+
+    panel
+}
+
+fn toggle_label(on: bool) -> &'static str {
+    if on {
+        t(Msg::On)
+    } else {
+        t(Msg::Off)
+    }
+}
+
+/// Name of a repeat mode, as shown in the help panel and the `repeat` info line
+pub fn repeat_label(mode: RepeatMode) -> &'static str {
+    match mode {
+        RepeatMode::Off => t(Msg::Off),
+        RepeatMode::One => t(Msg::RepeatOne),
+        RepeatMode::All => t(Msg::RepeatAll),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn aliases(entries: &[(&str, &str)]) -> Result<Aliases> {
+        Aliases::from_config(
+            &entries
+                .iter()
+                .map(|(name, target)| (name.to_string(), target.to_string()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn aliases_expand_before_parsing() {
+        let aliases = aliases(&[("skip", "next"), ("nice", "note nice transition")]).unwrap();
+
+        assert_eq!(parse(&aliases.expand("skip")), Some(Action::Next));
+        assert_eq!(aliases.expand("nice"), "note nice transition");
+        assert_eq!(
+            aliases.expand("nice at 12:00"),
+            "note nice transition at 12:00"
+        );
+        assert_eq!(aliases.expand("pause"), "pause");
+    }
+
+    #[test]
+    fn seek_targets_are_absolute_or_relative() {
+        assert_eq!(seek_target("12:30", 60), Some(750));
+        assert_eq!(seek_target("1:02:03", 0), Some(3723));
+        assert_eq!(seek_target("90", 0), Some(90));
+        assert_eq!(seek_target("+1:00", 30), Some(90));
+        assert_eq!(seek_target("-30", 100), Some(70));
+        assert_eq!(seek_target("-5:00", 100), Some(0));
+        assert_eq!(seek_target("", 100), None);
+        assert_eq!(seek_target("soon", 100), None);
+    }
+
+    #[test]
+    fn episodes_are_picked_by_number() {
+        assert_eq!(parse("g 19"), Some(Action::Episode));
+        assert_eq!(parse("ep 19"), Some(Action::Episode));
+        assert_eq!(parse("goto #19"), Some(Action::Episode));
+        assert_eq!(parse("goto 19"), Some(Action::Goto));
+
+        assert_eq!(episode_number("g 19"), Some(19));
+        assert_eq!(episode_number("goto #19"), Some(19));
+        assert_eq!(episode_number("g #7"), Some(7));
+        assert_eq!(episode_number("g"), None);
+        assert_eq!(episode_number("g nineteen"), None);
+    }
+
+    #[test]
+    fn q_with_a_number_queues_rather_than_quits() {
+        assert_eq!(parse("q"), Some(Action::Quit));
+        assert_eq!(parse("q 19"), Some(Action::Queue));
+        assert_eq!(parse("queue 19"), Some(Action::Queue));
+        assert_eq!(parse("queue"), Some(Action::Queue));
+        assert_eq!(episode_number("q 19"), Some(19));
+    }
+
+    #[test]
+    fn goto_takes_a_percentage_of_the_episode() {
+        assert_eq!(parse("50%"), Some(Action::Goto));
+        assert_eq!(parse("goto 12.5"), Some(Action::Goto));
+        assert_eq!(parse("half%"), None);
+        assert_eq!(parse("50"), None);
+
+        assert_eq!(goto_percent("50%"), Some(50.0));
+        assert_eq!(goto_percent("goto 50"), Some(50.0));
+        assert_eq!(goto_percent("goto 50%"), Some(50.0));
+        assert_eq!(goto_percent("goto half"), None);
+        assert_eq!(goto_percent("goto -5"), None);
+        assert_eq!(goto_percent("goto"), None);
+
+        // Two hours
+        assert_eq!(percent_target(50.0, 7200), 3600);
+        assert_eq!(percent_target(12.5, 7200), 900);
+        assert_eq!(percent_target(0.0, 7200), 0);
+        assert_eq!(percent_target(100.0, 7200), 7199);
+        assert_eq!(percent_target(250.0, 7200), 7199);
+    }
+
+    #[test]
+    fn goto_past_100_hints_at_the_episode() {
+        assert_eq!(goto_meant_episode("goto 120"), Some(120));
+        assert_eq!(goto_meant_episode("goto 120%"), None);
+        assert_eq!(goto_meant_episode("goto 50"), None);
+        assert_eq!(goto_meant_episode("150%"), None);
+    }
+
+    #[test]
+    fn volume_takes_a_percentage() {
+        assert_eq!(parse("volume 60"), Some(Action::Volume));
+        assert_eq!(parse("vol 5%"), Some(Action::Volume));
+        assert_eq!(volume_percent("60"), Some(60));
+        assert_eq!(volume_percent("5%"), Some(5));
+        assert_eq!(volume_percent("200"), Some(200));
+        assert_eq!(volume_percent("201"), None);
+        assert_eq!(volume_percent("-10"), None);
+        assert_eq!(volume_percent(""), None);
+    }
+
+    #[test]
+    fn balance_commands_parse() {
+        assert_eq!(parse("bal+"), Some(Action::BalanceRight));
+        assert_eq!(parse("bal-"), Some(Action::BalanceLeft));
+        assert_eq!(parse("bal0"), Some(Action::BalanceCenter));
+        assert_eq!(parse("bal"), None);
+    }
+
+    #[test]
+    fn sleep_takes_minutes_a_delay_or_off() {
+        assert_eq!(parse("sleep 45"), Some(Action::Sleep));
+        assert_eq!(parse("sleep"), Some(Action::Sleep));
+        assert_eq!(
+            sleep_argument("45"),
+            Some(Sleep::After(Duration::from_secs(45 * 60)))
+        );
+        assert_eq!(
+            sleep_argument("1h30m"),
+            Some(Sleep::After(Duration::from_secs(90 * 60)))
+        );
+        assert_eq!(sleep_argument("off"), Some(Sleep::Off));
+        assert_eq!(sleep_argument("OFF"), Some(Sleep::Off));
+        assert_eq!(sleep_argument("later"), None);
+        assert_eq!(sleep_argument(""), None);
+    }
+
+    #[test]
+    fn bad_aliases_are_rejected() {
+        assert!(aliases(&[("ff", "fwd 60")]).is_err());
+        assert!(aliases(&[("q", "next")]).is_err());
+        assert!(aliases(&[("skip", "next"), ("go", "skip")]).is_err());
+        assert!(aliases(&[("two words", "next")]).is_err());
+        assert!(aliases(&[("skip", "next 3")]).is_err());
+    }
+}
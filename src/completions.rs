@@ -0,0 +1,80 @@
+//! Shell completion scripts
+//! Static flags come from clap_complete; episode numbers and favorites are
+//! completed dynamically through the hidden `__complete-*` subcommands
+
+use clap::{CommandFactory, ValueEnum};
+use clap_complete::{generate, Shell};
+use std::io::Write;
+
+use crate::Cli;
+
+/// Shells with dynamic completion hooks
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum CompletionShell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+const BASH_HOOKS: &str = r#"
+_mfp_dynamic() {
+    local cur="${COMP_WORDS[COMP_CWORD]}"
+    local prev="${COMP_WORDS[COMP_CWORD-1]}"
+    case "${COMP_WORDS[1]}:${prev}" in
+        play:-e|play:--episode|download:-e|download:--episode)
+            COMPREPLY=($(compgen -W "$(mfp __complete-episodes 2>/dev/null | cut -f1)" -- "${cur}"))
+            return 0
+            ;;
+        fav:-r|fav:--remove)
+            local IFS=$'\n'
+            COMPREPLY=($(compgen -W "$(mfp __complete-favorites 2>/dev/null)" -- "${cur}"))
+            COMPREPLY=("${COMPREPLY[@]// /\\ }")
+            return 0
+            ;;
+    esac
+    _mfp "$@"
+}
+complete -F _mfp_dynamic -o bashdefault -o default mfp
+"#;
+
+const ZSH_HOOKS: &str = r#"
+_mfp_dynamic() {
+    case "${words[2]}:${words[CURRENT-1]}" in
+        (play:-e|play:--episode|download:-e|download:--episode)
+            local -a episodes
+            local line
+            for line in ${(f)"$(mfp __complete-episodes 2>/dev/null)"}; do
+                episodes+=("${line%%$'\t'*}:${${line#*$'\t'}//:/\\:}")
+            done
+            _describe -V 'episode' episodes
+            return
+            ;;
+        (fav:-r|fav:--remove)
+            local -a favorites
+            favorites=(${(f)"$(mfp __complete-favorites 2>/dev/null)"})
+            compadd -a favorites
+            return
+            ;;
+    esac
+    _mfp "$@"
+}
+compdef _mfp_dynamic mfp
+"#;
+
+const FISH_HOOKS: &str = r#"
+complete -c mfp -n "__fish_seen_subcommand_from play download" -s e -l episode -x -k -a "(mfp __complete-episodes 2>/dev/null)"
+complete -c mfp -n "__fish_seen_subcommand_from fav" -s r -l remove -x -a "(mfp __complete-favorites 2>/dev/null)"
+"#;
+
+/// Write the completion script for `shell`, followed by the dynamic hooks
+pub fn write_script(shell: CompletionShell, out: &mut impl Write) -> std::io::Result<()> {
+    let mut cmd = Cli::command();
+    let (target, hooks) = match shell {
+        CompletionShell::Bash => (Shell::Bash, BASH_HOOKS),
+        CompletionShell::Zsh => (Shell::Zsh, ZSH_HOOKS),
+        CompletionShell::Fish => (Shell::Fish, FISH_HOOKS),
+    };
+
+    generate(target, &mut cmd, "mfp", out);
+    out.write_all(hooks.as_bytes())
+}
@@ -0,0 +1,160 @@
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::errors::StorageError;
+use crate::i18n::Language;
+use crate::paths;
+use crate::style::BarStyle;
+
+/// User settings read from `config.toml`. Every key is optional.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Interface language: "en", "es" or "auto" (from LANG)
+    pub language: Language,
+    pub scrobble: ScrobbleConfig,
+    /// Write full debug logs to this file
+    pub log_file: Option<PathBuf>,
+    /// RSS feed to read episodes from instead of musicforprogramming.net
+    pub feed_url: Option<String>,
+    /// Read only this many episodes (the newest) from very large feeds
+    pub feed_max_items: Option<usize>,
+    /// `[stations]` section: station name to the URL of its RSS feed
+    pub stations: BTreeMap<String, String>,
+    /// Proxy URL for every request ("http://host:3128"); HTTP_PROXY / HTTPS_PROXY apply
+    /// when unset
+    pub proxy: Option<String>,
+    /// Seconds a server may stay silent before a request fails (30 by default)
+    pub timeout_secs: Option<u64>,
+    /// Times a dropped stream is reconnected before mfp asks what to do (3 by default)
+    pub stream_reconnects: Option<u64>,
+    /// Seconds of audio buffered again after the stream ran dry before playback goes on (10
+    /// by default)
+    pub rebuffer_secs: Option<u64>,
+    /// Kilobytes buffered before an episode starts playing (512 by default); a download
+    /// faster than the audio starts after a few seconds of it instead
+    pub initial_buffer_kb: Option<u64>,
+    /// Megabytes a stream downloads ahead of playback at most, for metered connections (all
+    /// of it at once when unset)
+    pub max_readahead_mb: Option<u64>,
+    /// Seconds every new episode starts at, past its spoken intro (off by default)
+    pub skip_intro_secs: Option<u64>,
+    /// Seconds the arrow keys seek by (10 by default)
+    pub seek_step_secs: Option<u64>,
+    /// Seconds shift+arrow, `,` and `.` seek by (60 by default)
+    pub seek_long_step_secs: Option<u64>,
+    /// Seconds into an episode after which "previous" starts it over instead (5 by default,
+    /// 0 to always go back)
+    pub restart_threshold_secs: Option<u64>,
+    /// How many of the episodes played lately shuffle keeps for last, this run and the next
+    /// (10 by default, 0 to let them come round again at once)
+    pub shuffle_memory: Option<usize>,
+    /// Where offline downloads are stored
+    pub download_dir: Option<PathBuf>,
+    /// Keep the stream being played in a temporary file instead of memory
+    pub stream_to_disk: bool,
+    /// Starting volume in percent (100 leaves the audio unchanged)
+    #[serde(alias = "default_volume")]
+    pub volume: Option<u8>,
+    /// Percent `+` and `-` change the volume by (10 by default)
+    pub volume_step: Option<u8>,
+    /// Fade the sound out and back in around pauses, for DACs that pop when it's cut
+    pub fade_pauses: bool,
+    /// Skip up to ten seconds of silence at the start of an episode (not when resuming)
+    pub trim_silence: bool,
+    /// Level under which the start counts as silence, in dBFS (-50 by default)
+    pub trim_silence_db: Option<f32>,
+    /// How progress bars are drawn: "unicode", "ascii" or "blocks" (from the locale and TERM
+    /// when unset)
+    pub progress_style: Option<BarStyle>,
+    /// Register with MPRIS for media keys and desktop controls (on unless set to false)
+    pub mpris: Option<bool>,
+    /// Register with the system media controls (`media-keys` feature; on unless set to false)
+    #[cfg_attr(not(feature = "media-keys"), allow(dead_code))]
+    pub media_keys: Option<bool>,
+    /// Publish the current episode to Discord (`discord` feature)
+    pub discord_presence: bool,
+    /// Discord application whose assets are used for the presence
+    #[cfg_attr(not(feature = "discord"), allow(dead_code))]
+    pub discord_app_id: Option<String>,
+    /// Keep a one-line "now playing" text in this file
+    pub now_playing_file: Option<PathBuf>,
+    /// Template for the now-playing line ({title}, {elapsed}, {duration}, {status})
+    pub now_playing_format: Option<String>,
+    /// Text template of `mfp status`
+    pub status_format: Option<String>,
+    /// Longest `mfp status` text before it is cut with an ellipsis
+    pub status_max_length: Option<usize>,
+    /// Shell command run when an episode starts (see README for the MFP_* variables)
+    pub on_track_start: Option<String>,
+    /// Shell command run when an episode ends or is skipped
+    pub on_track_end: Option<String>,
+    /// Shell command run on pause
+    pub on_pause: Option<String>,
+    /// Shell command run when the player is quit
+    pub on_stop: Option<String>,
+    /// `[keys]` section: action name to key ("space", "ctrl+p", ...) or list of keys
+    pub keys: HashMap<String, KeyBinding>,
+    /// `[aliases]` section: typed name to a player command with optional text ("cool" = "note cool")
+    pub aliases: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScrobbleService {
+    Lastfm,
+    Listenbrainz,
+}
+
+/// `[scrobble]` section. Scrobbling stays disabled while `service` is unset.
+#[cfg_attr(not(feature = "scrobble"), allow(dead_code))]
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ScrobbleConfig {
+    pub service: Option<ScrobbleService>,
+    /// ListenBrainz user token
+    pub token: String,
+    /// Last.fm API key
+    pub api_key: String,
+    /// Last.fm API shared secret
+    pub api_secret: String,
+    /// Last.fm session key
+    pub session_key: String,
+}
+
+impl Config {
+    pub fn config_path() -> Result<PathBuf, StorageError> {
+        paths::config_file()
+    }
+
+    pub fn load() -> Result<Self, StorageError> {
+        let path = Self::config_path()?;
+
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(&path).map_err(StorageError::read(&path))?;
+
+        toml::from_str(&content).map_err(StorageError::parse(&path))
+    }
+}
+
+/// One key or a list of keys for an action in `[keys]`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum KeyBinding {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl KeyBinding {
+    pub fn specs(&self) -> Vec<&str> {
+        match self {
+            KeyBinding::One(spec) => vec![spec],
+            KeyBinding::Many(specs) => specs.iter().map(String::as_str).collect(),
+        }
+    }
+}
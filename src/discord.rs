@@ -0,0 +1,162 @@
+//! Discord Rich Presence integration
+//! A worker thread owns the IPC connection so the play loop never blocks on it.
+//! While Discord isn't running, connection attempts are retried periodically.
+
+use discord_rich_presence::{activity, DiscordIpc, DiscordIpcClient};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::history::unix_now;
+use crate::session::{SessionEvent, SessionListener};
+
+/// How often to reconnect (and refresh the activity) while idle
+const RETRY_INTERVAL: Duration = Duration::from_secs(30);
+const LARGE_IMAGE: &str = "mfp";
+const LARGE_TEXT: &str = "Music For Programming";
+
+/// Activity the worker should publish
+#[derive(Debug, Clone)]
+enum Presence {
+    Playing {
+        title: String,
+        start: i64,
+        end: Option<i64>,
+    },
+    Paused {
+        title: String,
+    },
+    Clear,
+}
+
+pub struct DiscordPresence {
+    tx: Option<Sender<Presence>>,
+    worker: Option<JoinHandle<()>>,
+    title: String,
+    duration_secs: u64,
+}
+
+impl DiscordPresence {
+    pub fn new(app_id: String) -> Self {
+        let (tx, rx) = mpsc::channel::<Presence>();
+
+        let worker = thread::spawn(move || {
+            let mut client: Option<DiscordIpcClient> = None;
+            let mut current = Presence::Clear;
+
+            loop {
+                match rx.recv_timeout(RETRY_INTERVAL) {
+                    Ok(presence) => current = presence,
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+
+                if client.is_none() && !matches!(current, Presence::Clear) {
+                    client = connect(&app_id);
+                }
+                if let Some(c) = client.as_mut() {
+                    if publish(c, &current).is_err() {
+                        client = None;
+                    }
+                }
+            }
+
+            if let Some(mut c) = client {
+                let _ = c.clear_activity();
+                let _ = c.close();
+            }
+        });
+
+        Self {
+            tx: Some(tx),
+            worker: Some(worker),
+            title: String::new(),
+            duration_secs: 0,
+        }
+    }
+
+    fn send(&self, presence: Presence) {
+        if let Some(tx) = &self.tx {
+            let _ = tx.send(presence);
+        }
+    }
+
+    fn playing(&self, elapsed_secs: u64) -> Presence {
+        let start = unix_now().saturating_sub(elapsed_secs) as i64;
+        Presence::Playing {
+            title: self.title.clone(),
+            start,
+            end: (self.duration_secs > 0).then(|| start + self.duration_secs as i64),
+        }
+    }
+}
+
+impl SessionListener for DiscordPresence {
+    fn on_event(&mut self, event: &SessionEvent) {
+        match event {
+            SessionEvent::TrackStarted {
+                title,
+                duration_secs,
+                ..
+            } => {
+                self.title = title.clone();
+                self.duration_secs = *duration_secs;
+                self.send(self.playing(0));
+            }
+            SessionEvent::Resumed { elapsed_secs } => self.send(self.playing(*elapsed_secs)),
+            SessionEvent::Paused { .. } => self.send(Presence::Paused {
+                title: self.title.clone(),
+            }),
+            SessionEvent::Stopped => self.send(Presence::Clear),
+            SessionEvent::TrackEnded { .. } | SessionEvent::SettingsChanged { .. } => {}
+        }
+    }
+}
+
+impl Drop for DiscordPresence {
+    fn drop(&mut self) {
+        // Closing the channel makes the worker clear the activity and exit
+        drop(self.tx.take());
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn connect(app_id: &str) -> Option<DiscordIpcClient> {
+    let mut client = DiscordIpcClient::new(app_id).ok()?;
+    client.connect().ok()?;
+    Some(client)
+}
+
+fn publish(
+    client: &mut DiscordIpcClient,
+    presence: &Presence,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let assets = activity::Assets::new()
+        .large_image(LARGE_IMAGE)
+        .large_text(LARGE_TEXT);
+
+    match presence {
+        Presence::Playing { title, start, end } => {
+            let mut timestamps = activity::Timestamps::new().start(*start);
+            if let Some(end) = end {
+                timestamps = timestamps.end(*end);
+            }
+            client.set_activity(
+                activity::Activity::new()
+                    .details(title)
+                    .state(LARGE_TEXT)
+                    .timestamps(timestamps)
+                    .assets(assets),
+            )
+        }
+        Presence::Paused { title } => client.set_activity(
+            activity::Activity::new()
+                .details(title)
+                .state("Paused")
+                .assets(assets),
+        ),
+        Presence::Clear => client.clear_activity(),
+    }
+}
@@ -1,98 +1,345 @@
-use anyhow::{Context, Result};
-use std::fs::{self, File};
-use std::io::{Read, Write};
+//! Offline downloads
+//! Episodes are saved as `<title>.mp3` in the download directory; partial files never take that name.
+//! A stream recorded while it plays becomes the same file once it's complete.
+
+use futures::stream::{self, StreamExt};
+use reqwest::header::RANGE;
+use reqwest::StatusCode;
+use std::fs;
+use std::io;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::fs::{File, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use tokio_util::sync::CancellationToken;
+
+use crate::config::Config;
+use crate::errors::{self, StorageError, EXIT_NETWORK, EXIT_NOT_FOUND};
+use crate::http;
+use crate::i18n::{t, tf, Msg};
+use crate::output;
+use crate::paths;
+use crate::station;
+use crate::style::{self, Role};
+use tracing::{debug, warn};
+
+/// Bytes between two progress updates
+const PROGRESS_STEP: u64 = 1024 * 1024;
+/// Cells of the bar in download progress lines
+const PROGRESS_BAR_WIDTH: usize = 20;
+
+/// Why a download or a download-directory operation failed
+#[derive(Debug, thiserror::Error)]
+pub enum DownloadError {
+    /// No response: DNS, connection, TLS or timeout
+    #[error("{}", http::describe(.0, Msg::ConnectFailed))]
+    Request(#[from] reqwest::Error),
+    /// The server answered with an error status
+    #[error("{}", tf(Msg::HttpError, &[status]))]
+    Status { status: u16 },
+    /// The connection dropped mid-transfer; the partial file is kept for resuming
+    #[error("{}", tf(Msg::DownloadInterrupted, &[received]))]
+    Interrupted {
+        received: u64,
+        #[source]
+        source: std::io::Error,
+    },
+    /// The transfer ended at a different size than the server announced
+    #[error("{}", tf(Msg::DownloadIncomplete, &[received, expected]))]
+    SizeMismatch { received: u64, expected: u64 },
+    /// `title` has no file in the download directory
+    #[error("{}", t(Msg::NotDownloaded))]
+    NotDownloaded { title: String },
+    /// The server went silent, or the download was cancelled
+    #[error(transparent)]
+    Aborted(#[from] http::Aborted),
+    #[error(transparent)]
+    Storage(#[from] StorageError),
+}
+
+impl DownloadError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            DownloadError::Request(_)
+            | DownloadError::Interrupted { .. }
+            | DownloadError::SizeMismatch { .. } => EXIT_NETWORK,
+            DownloadError::Status { status } => errors::status_exit_code(*status),
+            DownloadError::NotDownloaded { .. } => EXIT_NOT_FOUND,
+            DownloadError::Aborted(e) => e.exit_code(),
+            DownloadError::Storage(e) => e.exit_code(),
+        }
+    }
+}
 
-const CHUNK_SIZE: usize = 32 * 1024; // Chunk size: 32 KB
+/// How far a download has got, for showing it while it runs on another thread
+#[derive(Debug, Default)]
+pub struct DownloadProgress {
+    received: AtomicU64,
+    /// 0 until the server says
+    total: AtomicU64,
+}
+
+impl DownloadProgress {
+    /// Share of the file received so far, once its size is known
+    pub fn percent(&self) -> Option<u8> {
+        let total = self.total.load(Ordering::Relaxed);
+        let received = self.received.load(Ordering::Relaxed);
+        (total > 0).then(|| (received.min(total) * 100 / total) as u8)
+    }
+}
 
+/// Manages the download directory
 pub struct Downloader {
     download_dir: PathBuf,
+    /// Idle timeout instead of the configured one
+    timeout: Option<Duration>,
+    /// Print no status or progress lines
+    silent: bool,
+    /// Kept up to date as the bytes arrive
+    progress: Option<Arc<DownloadProgress>>,
 }
 
 impl Downloader {
-    pub fn new() -> Result<Self> {
-        let download_dir = dirs::config_dir()
-            .context("No se pudo obtener el directorio de configuración")?
-            .join("mfp")
-            .join("downloads");
+    /// Downloader for the configured directory, created if missing
+    pub fn new() -> Result<Self, DownloadError> {
+        let download_dir = match Config::load().ok().and_then(|c| c.download_dir) {
+            Some(dir) => dir,
+            None => Self::default_dir()?,
+        };
+
+        // Other stations get a subdirectory so their titles can't clash with MfP's
+        Self::with_dir(station::scoped_dir(download_dir))
+    }
+
+    /// Downloader for `download_dir`, created if missing
+    pub fn with_dir(download_dir: PathBuf) -> Result<Self, DownloadError> {
+        fs::create_dir_all(&download_dir).map_err(StorageError::write(&download_dir))?;
+
+        Ok(Downloader {
+            download_dir,
+            timeout: None,
+            silent: false,
+            progress: None,
+        })
+    }
+
+    /// Give up when the server stays silent this long (`timeout_secs`, 30 seconds by default)
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Keep quiet, for downloads running in the background while the player draws its line
+    pub fn silent(mut self) -> Self {
+        self.silent = true;
+        self
+    }
+
+    /// Report how far each download has got to `progress`
+    pub fn with_progress(mut self, progress: Arc<DownloadProgress>) -> Self {
+        self.progress = Some(progress);
+        self
+    }
 
-        fs::create_dir_all(&download_dir)?;
+    /// Download `url` as `title` unless it is already there; returns the file path
+    pub fn download_episode(&self, title: &str, url: &str) -> Result<PathBuf, DownloadError> {
+        http::block_on(self.download_episode_async(title, url, &CancellationToken::new()))
+    }
 
-        Ok(Downloader { download_dir })
+    /// Download several `(title, url)` episodes, at most `parallel` at a time. Results come
+    /// in the order given; one failure doesn't stop the others.
+    pub fn download_many(
+        &self,
+        episodes: &[(String, String)],
+        parallel: usize,
+    ) -> Vec<Result<PathBuf, DownloadError>> {
+        http::block_on(self.download_many_async(episodes, parallel, &CancellationToken::new()))
     }
 
-    pub fn download_episode(&self, title: &str, url: &str) -> Result<PathBuf> {
-        let filename = self.sanitize_filename(title);
+    /// [`Downloader::download_many`] on the async layer
+    pub async fn download_many_async(
+        &self,
+        episodes: &[(String, String)],
+        parallel: usize,
+        cancel: &CancellationToken,
+    ) -> Vec<Result<PathBuf, DownloadError>> {
+        stream::iter(episodes)
+            .map(|(title, url)| self.download_episode_async(title, url, cancel))
+            .buffered(parallel.max(1))
+            .collect()
+            .await
+    }
+
+    /// [`Downloader::download_episode`] on the async layer. Cancelling keeps the partial
+    /// file, so the next attempt resumes it.
+    pub async fn download_episode_async(
+        &self,
+        title: &str,
+        url: &str,
+        cancel: &CancellationToken,
+    ) -> Result<PathBuf, DownloadError> {
+        let filename = sanitize_filename(title);
         let file_path = self.download_dir.join(&filename);
 
         if file_path.exists() {
-            println!("Episode already downloaded: {}", filename);
+            if !self.silent {
+                output::status(tf(Msg::AlreadyDownloaded, &[&filename]));
+            }
             return Ok(file_path);
         }
 
-        println!("Downloading: {}", title);
+        if !self.silent {
+            output::status(tf(Msg::Downloading, &[&title]));
+        }
 
-        let mut response =
-            reqwest::blocking::get(url).context("No se pudo conectar al servidor")?;
+        let idle = self.timeout.unwrap_or_else(http::idle_timeout);
+        let client = http::client()?;
+        // A `.tmp` left by an interrupted download is continued rather than restarted
+        let temp_path = self.partial_path(title);
 
-        if !response.status().is_success() {
-            anyhow::bail!("Error HTTP: {}", response.status());
-        }
+        let (mut response, resume_from) = loop {
+            let resume_from = fs::metadata(&temp_path).map_or(0, |m| m.len());
+            let mut request = http::audio_request(client, url);
+            if resume_from > 0 {
+                request = request.header(RANGE, format!("bytes={}-", resume_from));
+            }
+
+            debug!(url, resume_from, "Downloading episode");
+            let response = http::step(request.send(), idle, cancel).await??;
 
-        let total_size = response.content_length();
+            if response.status() == StatusCode::RANGE_NOT_SATISFIABLE && resume_from > 0 {
+                // The partial file is no prefix of what the server has now
+                fs::remove_file(&temp_path).map_err(StorageError::write(&temp_path))?;
+                continue;
+            }
+            break (response, resume_from);
+        };
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(DownloadError::Status {
+                status: status.as_u16(),
+            });
+        }
 
-        let temp_path = file_path.with_extension("tmp");
-        let mut file = File::create(&temp_path).context("No se pudo crear el archivo")?;
+        // Servers that ignore the range send the whole file again
+        let offset = if status == StatusCode::PARTIAL_CONTENT {
+            if !self.silent {
+                output::status(tf(
+                    Msg::DownloadResuming,
+                    &[&format!("{:.1}", resume_from as f64 / 1_048_576.0)],
+                ));
+            }
+            resume_from
+        } else {
+            0
+        };
+        let total_size = response.content_length().map(|len| len + offset);
 
-        let mut downloaded = 0u64;
-        let mut buffer = vec![0u8; CHUNK_SIZE];
+        let mut file = if offset > 0 {
+            OpenOptions::new().append(true).open(&temp_path).await
+        } else {
+            File::create(&temp_path).await
+        }
+        .map_err(StorageError::write(&temp_path))?;
+
+        let mut downloaded = offset;
+        let mut next_report = downloaded + PROGRESS_STEP;
+        if let Some(progress) = &self.progress {
+            progress
+                .total
+                .store(total_size.unwrap_or(0), Ordering::Relaxed);
+            progress.received.store(downloaded, Ordering::Relaxed);
+        }
 
         loop {
-            match response.read(&mut buffer) {
-                Ok(0) => break,
-                Ok(n) => {
-                    file.write_all(&buffer[..n])?;
-                    downloaded += n as u64;
-
-                    if downloaded % (1024 * 1024) == 0 {
-                        if let Some(total) = total_size {
-                            let percent = (downloaded as f64 / total as f64) * 100.0;
-                            print!(
-                                "\r  Progress: {:.1}% ({:.1}/{:.1} MB)",
-                                percent,
-                                downloaded as f64 / 1_048_576.0,
-                                total as f64 / 1_048_576.0
-                            );
-                            std::io::stdout().flush().ok();
-                        }
-                    }
-                }
+            let chunk = match http::step(response.chunk(), idle, cancel).await? {
+                Ok(Some(chunk)) => chunk,
+                Ok(None) => break,
                 Err(e) => {
+                    // The partial file stays for the next attempt to resume
+                    warn!(downloaded, "Download interrupted: {}", e);
+                    return Err(DownloadError::Interrupted {
+                        received: downloaded,
+                        source: io::Error::other(e),
+                    });
+                }
+            };
+
+            file.write_all(&chunk)
+                .await
+                .map_err(StorageError::write(&temp_path))?;
+            downloaded += chunk.len() as u64;
+            if let Some(progress) = &self.progress {
+                progress.received.store(downloaded, Ordering::Relaxed);
+            }
+
+            if downloaded >= next_report && !self.silent {
+                next_report = downloaded + PROGRESS_STEP;
+                if let Some(total) = total_size {
+                    let fraction = downloaded as f64 / total as f64;
+                    let bar = style::render_bar(PROGRESS_BAR_WIDTH, fraction, style::bar_style());
+                    output::progress(tf(
+                        Msg::DownloadProgress,
+                        &[
+                            &format!(
+                                "{} {}",
+                                bar.paint(),
+                                style::paint(Role::Accent, &format!("{:.1}%", fraction * 100.0))
+                            ),
+                            &format!("{:.1}", downloaded as f64 / 1_048_576.0),
+                            &format!("{:.1}", total as f64 / 1_048_576.0),
+                        ],
+                    ));
+                }
+            }
+        }
+        file.flush()
+            .await
+            .map_err(StorageError::write(&temp_path))?;
+        drop(file);
+
+        if let Some(total) = total_size {
+            if downloaded != total {
+                warn!(downloaded, total, "Download size mismatch");
+                // A short file can still be resumed; an oversized one is garbage
+                if downloaded > total {
                     let _ = fs::remove_file(&temp_path);
-                    return Err(e.into());
                 }
+                return Err(DownloadError::SizeMismatch {
+                    received: downloaded,
+                    expected: total,
+                });
             }
         }
 
-        println!(
-            "\rDownload complete: {:.2} MB                    ",
-            downloaded as f64 / 1_048_576.0
-        );
+        if !self.silent {
+            output::progress_done(format!(
+                "{}: {:.2} MB",
+                style::paint(Role::Success, t(Msg::DownloadComplete)),
+                downloaded as f64 / 1_048_576.0
+            ));
+        }
 
-        fs::rename(&temp_path, &file_path)?;
+        fs::rename(&temp_path, &file_path).map_err(StorageError::write(&file_path))?;
 
         Ok(file_path)
     }
 
-    pub fn list_downloaded(&self) -> Result<Vec<PathBuf>> {
+    /// Audio files in the download directory, sorted
+    pub fn list_downloaded(&self) -> Result<Vec<PathBuf>, StorageError> {
         let mut episodes = Vec::new();
 
         if !self.download_dir.exists() {
             return Ok(episodes);
         }
 
-        for entry in fs::read_dir(&self.download_dir)? {
-            let entry = entry?;
+        for entry in
+            fs::read_dir(&self.download_dir).map_err(StorageError::read(&self.download_dir))?
+        {
+            let entry = entry.map_err(StorageError::read(&self.download_dir))?;
             let path = entry.path();
 
             if path.is_file() && path.extension().is_some() {
@@ -108,14 +355,25 @@ impl Downloader {
     }
 
     pub fn is_downloaded(&self, title: &str) -> bool {
-        let filename = self.sanitize_filename(title);
+        let filename = sanitize_filename(title);
         let file_path = self.download_dir.join(&filename);
         file_path.exists()
     }
 
+    /// Where the download of `title` goes, whether it is there or not
+    pub fn file_path(&self, title: &str) -> PathBuf {
+        self.download_dir.join(sanitize_filename(title))
+    }
+
+    /// Where the download of `title` collects until it is complete; whatever is there is
+    /// taken as the start of the file and only the rest is requested
+    pub fn partial_path(&self, title: &str) -> PathBuf {
+        self.file_path(title).with_extension("tmp")
+    }
+
+    /// Path of the downloaded file for `title`, if any
     pub fn get_path(&self, title: &str) -> Option<PathBuf> {
-        let filename = self.sanitize_filename(title);
-        let file_path = self.download_dir.join(&filename);
+        let file_path = self.file_path(title);
 
         if file_path.exists() {
             Some(file_path)
@@ -124,29 +382,35 @@ impl Downloader {
         }
     }
 
-    pub fn delete_episode(&self, title: &str) -> Result<()> {
-        let filename = self.sanitize_filename(title);
+    /// Remove the downloaded file for `title`
+    pub fn delete_episode(&self, title: &str) -> Result<(), DownloadError> {
+        let filename = sanitize_filename(title);
         let file_path = self.download_dir.join(&filename);
 
         if file_path.exists() {
-            fs::remove_file(&file_path)?;
-            println!("Deleted: {}", filename);
+            fs::remove_file(&file_path).map_err(StorageError::write(&file_path))?;
+            output::status(tf(Msg::Deleted, &[&filename]));
         } else {
-            println!("Episode not downloaded");
+            return Err(DownloadError::NotDownloaded {
+                title: title.to_string(),
+            });
         }
 
         Ok(())
     }
 
-    pub fn get_total_size(&self) -> Result<u64> {
+    /// Bytes used by the download directory
+    pub fn get_total_size(&self) -> Result<u64, StorageError> {
         let mut total = 0u64;
 
         if !self.download_dir.exists() {
             return Ok(0);
         }
 
-        for entry in fs::read_dir(&self.download_dir)? {
-            let entry = entry?;
+        for entry in
+            fs::read_dir(&self.download_dir).map_err(StorageError::read(&self.download_dir))?
+        {
+            let entry = entry.map_err(StorageError::read(&self.download_dir))?;
             if let Ok(metadata) = entry.metadata() {
                 total += metadata.len();
             }
@@ -155,28 +419,117 @@ impl Downloader {
         Ok(total)
     }
 
-    fn sanitize_filename(&self, title: &str) -> String {
-        let ext = ".mp3";
+    /// Where downloads go unless `download_dir` is set in the config
+    pub fn default_dir() -> Result<PathBuf, StorageError> {
+        paths::download_dir()
+    }
 
-        let mut filename = title
-            .replace('/', "-")
-            .replace('\\', "-")
-            .replace(':', "-")
-            .replace('*', "")
-            .replace('?', "")
-            .replace('"', "")
-            .replace('<', "")
-            .replace('>', "")
-            .replace('|', "");
+    pub fn download_dir(&self) -> &Path {
+        &self.download_dir
+    }
+}
 
-        if filename.len() > 200 {
-            filename.truncate(200);
+/// File name for an episode, valid on Windows as well as Unix: no reserved characters,
+/// control characters or device names (CON, NUL, COM1, ...), no trailing dots or spaces,
+/// and at most 200 bytes before the extension
+fn sanitize_filename(title: &str) -> String {
+    let mut stem: String = title
+        .chars()
+        .filter_map(|c| match c {
+            '/' | '\\' | ':' => Some('-'),
+            '*' | '?' | '"' | '<' | '>' | '|' => None,
+            c if c.is_control() => None,
+            c => Some(c),
+        })
+        .collect();
+
+    if stem.len() > 200 {
+        let mut end = 200;
+        while !stem.is_char_boundary(end) {
+            end -= 1;
         }
+        stem.truncate(end);
+    }
 
-        format!("{}{}", filename, ext)
+    let stem = stem.trim_end_matches(['.', ' ']);
+    if stem.is_empty() || is_reserved_name(stem) {
+        format!("_{}.mp3", stem)
+    } else {
+        format!("{}.mp3", stem)
     }
+}
 
-    pub fn download_dir(&self) -> &Path {
-        &self.download_dir
+/// Windows device names, which stay reserved whatever extension follows them
+fn is_reserved_name(stem: &str) -> bool {
+    let device = stem.split('.').next().unwrap_or(stem).trim_end();
+    let device = device.to_ascii_uppercase();
+
+    matches!(
+        device.as_bytes(),
+        b"CON"
+            | b"PRN"
+            | b"AUX"
+            | b"NUL"
+            | [b'C', b'O', b'M', b'1'..=b'9']
+            | [b'L', b'P', b'T', b'1'..=b'9']
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replaces_reserved_characters() {
+        assert_eq!(sanitize_filename("Episode 1: Test"), "Episode 1- Test.mp3");
+        assert_eq!(
+            sanitize_filename("Episode 2: A/B \\ <C>|\"D\"?*"),
+            "Episode 2- A-B - CD.mp3"
+        );
+        assert_eq!(sanitize_filename("Tab\there\u{7}"), "Tabhere.mp3");
+    }
+
+    #[test]
+    fn strips_trailing_dots_and_spaces() {
+        assert_eq!(
+            sanitize_filename("Episode 3: Ends..."),
+            "Episode 3- Ends.mp3"
+        );
+        assert_eq!(
+            sanitize_filename("Episode 4: Spaced . "),
+            "Episode 4- Spaced.mp3"
+        );
+        assert_eq!(sanitize_filename("..."), "_.mp3");
+    }
+
+    #[test]
+    fn avoids_device_names() {
+        assert_eq!(sanitize_filename("CON"), "_CON.mp3");
+        assert_eq!(sanitize_filename("nul"), "_nul.mp3");
+        assert_eq!(sanitize_filename("Com7"), "_Com7.mp3");
+        assert_eq!(sanitize_filename("LPT1.old"), "_LPT1.old.mp3");
+        assert_eq!(sanitize_filename("Console"), "Console.mp3");
+        assert_eq!(sanitize_filename("COM10"), "COM10.mp3");
+    }
+
+    #[test]
+    fn progress_is_a_share_of_a_known_size() {
+        let progress = DownloadProgress::default();
+        progress.received.store(512, Ordering::Relaxed);
+        assert_eq!(progress.percent(), None);
+        progress.total.store(2048, Ordering::Relaxed);
+        assert_eq!(progress.percent(), Some(25));
+        progress.received.store(4096, Ordering::Relaxed);
+        assert_eq!(progress.percent(), Some(100));
+    }
+
+    #[test]
+    fn truncates_on_a_character_boundary() {
+        let title = "é".repeat(150);
+        let name = sanitize_filename(&title);
+        assert_eq!(name, format!("{}.mp3", "é".repeat(100)));
+
+        let name = sanitize_filename(&format!("a{}", "é".repeat(150)));
+        assert_eq!(name.len(), 199 + ".mp3".len());
     }
 }
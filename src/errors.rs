@@ -0,0 +1,155 @@
+//! Error types shared across modules and the process exit codes they map to
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::backup::BackupError;
+use crate::downloader::DownloadError;
+use crate::feed::FeedError;
+use crate::i18n::{t, tf, Msg};
+use crate::player::PlayerError;
+
+pub const EXIT_FAILURE: i32 = 1;
+pub const EXIT_NOT_FOUND: i32 = 3;
+pub const EXIT_NETWORK: i32 = 4;
+pub const EXIT_DISK: i32 = 5;
+/// Stopped by SIGTERM or SIGHUP
+pub const EXIT_SIGNAL: i32 = 6;
+
+/// Shown at the end of `--help`
+pub const EXIT_CODES_HELP: &str = "Exit codes:
+  0  Success
+  1  Other error
+  2  Invalid arguments
+  3  Episode or file not found
+  4  Network error
+  5  Disk error
+  6  Stopped by SIGTERM or SIGHUP";
+
+/// The requested episode or file does not exist
+#[derive(Debug)]
+pub struct NotFound(pub String);
+
+impl fmt::Display for NotFound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for NotFound {}
+
+/// The server could not be reached or answered with an error
+#[derive(Debug)]
+pub struct NetworkError(pub String);
+
+impl fmt::Display for NetworkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for NetworkError {}
+
+/// Reading or writing one of mfp's own files (config, favorites, history, caches) failed
+#[derive(Debug, thiserror::Error)]
+pub enum StorageError {
+    /// The system has no per-user config or cache directory
+    #[error("{}", t(Msg::ConfigDirFailed))]
+    NoDirectory,
+    #[error("{}", tf(Msg::StorageReadFailed, &[&path.display()]))]
+    Read {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("{}", tf(Msg::StorageWriteFailed, &[&path.display()]))]
+    Write {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    /// The file exists but its contents are not what mfp wrote
+    #[error("{}", tf(Msg::StorageParseFailed, &[&path.display()]))]
+    Parse {
+        path: PathBuf,
+        #[source]
+        source: Box<dyn Error + Send + Sync>,
+    },
+}
+
+impl StorageError {
+    /// `map_err` adapter for a failed read of `path`
+    pub(crate) fn read<E: Into<io::Error>>(path: &Path) -> impl FnOnce(E) -> Self + '_ {
+        move |source| StorageError::Read {
+            path: path.to_path_buf(),
+            source: source.into(),
+        }
+    }
+
+    /// `map_err` adapter for a failed write of `path`
+    pub(crate) fn write<E: Into<io::Error>>(path: &Path) -> impl FnOnce(E) -> Self + '_ {
+        move |source| StorageError::Write {
+            path: path.to_path_buf(),
+            source: source.into(),
+        }
+    }
+
+    /// `map_err` adapter for unparseable contents of `path`
+    pub(crate) fn parse<E: Into<Box<dyn Error + Send + Sync>>>(
+        path: &Path,
+    ) -> impl FnOnce(E) -> Self + '_ {
+        move |source| StorageError::Parse {
+            path: path.to_path_buf(),
+            source: source.into(),
+        }
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            StorageError::Read { .. } | StorageError::Write { .. } => EXIT_DISK,
+            StorageError::NoDirectory | StorageError::Parse { .. } => EXIT_FAILURE,
+        }
+    }
+}
+
+/// Exit code for an HTTP status: 404 means the thing is gone, anything else is the network
+pub(crate) fn status_exit_code(status: u16) -> i32 {
+    if status == 404 {
+        EXIT_NOT_FOUND
+    } else {
+        EXIT_NETWORK
+    }
+}
+
+/// Exit code for an error, from the outermost cause that can be classified
+pub fn exit_code(error: &anyhow::Error) -> i32 {
+    for cause in error.chain() {
+        if let Some(e) = cause.downcast_ref::<FeedError>() {
+            return e.exit_code();
+        }
+        if let Some(e) = cause.downcast_ref::<DownloadError>() {
+            return e.exit_code();
+        }
+        if let Some(e) = cause.downcast_ref::<PlayerError>() {
+            return e.exit_code();
+        }
+        if let Some(e) = cause.downcast_ref::<BackupError>() {
+            return e.exit_code();
+        }
+        if let Some(e) = cause.downcast_ref::<StorageError>() {
+            return e.exit_code();
+        }
+        if cause.is::<NotFound>() {
+            return EXIT_NOT_FOUND;
+        }
+        if cause.is::<NetworkError>() || cause.is::<reqwest::Error>() {
+            return EXIT_NETWORK;
+        }
+        if cause.is::<std::io::Error>() {
+            return EXIT_DISK;
+        }
+    }
+    EXIT_FAILURE
+}
@@ -1,71 +1,199 @@
-use anyhow::{Context, Result};
+//! The persistent favorites list
+//! Stored as JSON in the config directory; every change is saved immediately. Favorites of
+//! stations other than MfP are kept per station, so equal titles in two feeds stay separate.
+//! The last few adds and removals are saved along with the titles so they can be undone.
+
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::fs;
 use std::path::PathBuf;
 
+use crate::errors::StorageError;
+use crate::paths;
+use crate::station;
+
+/// How many changes `undo` can take back
+pub const UNDO_LIMIT: usize = 20;
+
+/// Favorite episode titles of the active station
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct Favorites {
+    /// Titles from the default station
     episodes: HashSet<String>,
+    /// Titles from the other stations, by station name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    stations: BTreeMap<String, HashSet<String>>,
+    /// Recent changes, newest last
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    undo: Vec<Change>,
+    /// Changes taken back by `undo`, newest last; any new change clears them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    redo: Vec<Change>,
 }
 
-impl Favorites {
-    fn config_path() -> Result<PathBuf> {
-        let config_dir = dirs::config_dir()
-            .context("Failed to find config directory")?
-            .join("mfp");
+/// One add or removal, with the station it was made in
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Change {
+    pub station: String,
+    pub title: String,
+    /// True for an add, false for a removal
+    pub added: bool,
+}
 
-        fs::create_dir_all(&config_dir).context("Failed to create config directory")?;
+impl Favorites {
+    fn config_path() -> Result<PathBuf, StorageError> {
+        let config_dir = paths::config_dir()?;
+        fs::create_dir_all(&config_dir).map_err(StorageError::write(&config_dir))?;
 
-        Ok(config_dir.join("favorites.json"))
+        paths::favorites_file()
     }
 
-    pub fn load() -> Result<Self> {
+    /// Read the saved list; empty if there is none yet
+    pub fn load() -> Result<Self, StorageError> {
         let path = Self::config_path()?;
 
         if !path.exists() {
             return Ok(Self::default());
         }
 
-        let content = fs::read_to_string(&path).context("Failed to read favorites file")?;
+        let content = fs::read_to_string(&path).map_err(StorageError::read(&path))?;
 
-        serde_json::from_str(&content).context("Failed to parse favorites file")
+        serde_json::from_str(&content).map_err(StorageError::parse(&path))
     }
 
-    pub fn save(&self) -> Result<()> {
+    pub fn save(&self) -> Result<(), StorageError> {
         let path = Self::config_path()?;
-        let content =
-            serde_json::to_string_pretty(self).context("Failed to serialize favorites")?;
+        let content = serde_json::to_string_pretty(self).map_err(StorageError::write(&path))?;
+
+        fs::write(&path, content).map_err(StorageError::write(&path))
+    }
+
+    fn titles(&self) -> Option<&HashSet<String>> {
+        match station::active() {
+            station::DEFAULT => Some(&self.episodes),
+            name => self.stations.get(name),
+        }
+    }
 
-        fs::write(&path, content).context("Failed to write favorites file")
+    fn titles_mut(&mut self, station: &str) -> &mut HashSet<String> {
+        match station {
+            station::DEFAULT => &mut self.episodes,
+            name => self.stations.entry(name.to_string()).or_default(),
+        }
     }
 
+    /// Add `title`; false if it was already a favorite
     pub fn add(&mut self, title: String) -> bool {
-        let added = self.episodes.insert(title);
+        let added = self.titles_mut(station::active()).insert(title.clone());
         if added {
+            self.record(title, true);
             let _ = self.save();
         }
         added
     }
 
+    /// Remove `title`; false if it was not a favorite
     pub fn remove(&mut self, title: &str) -> bool {
-        let removed = self.episodes.remove(title);
+        let removed = self.titles_mut(station::active()).remove(title);
         if removed {
+            self.record(title.to_string(), false);
             let _ = self.save();
         }
         removed
     }
 
+    fn record(&mut self, title: String, added: bool) {
+        self.undo.push(Change {
+            station: station::active().to_string(),
+            title,
+            added,
+        });
+        if self.undo.len() > UNDO_LIMIT {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+    }
+
+    /// Take back the newest change, in whichever station it was made, and save; returns
+    /// the change that was taken back, or None if there is nothing left to undo
+    pub fn undo(&mut self) -> Result<Option<Change>, StorageError> {
+        let Some(change) = self.undo.pop() else {
+            return Ok(None);
+        };
+
+        self.apply(&change.station, &change.title, !change.added);
+        self.redo.push(change.clone());
+        self.save()?;
+        Ok(Some(change))
+    }
+
+    /// Make the newest undone change again and save; None if nothing was undone since
+    /// the last change
+    pub fn redo(&mut self) -> Result<Option<Change>, StorageError> {
+        let Some(change) = self.redo.pop() else {
+            return Ok(None);
+        };
+
+        self.apply(&change.station, &change.title, change.added);
+        self.undo.push(change.clone());
+        self.save()?;
+        Ok(Some(change))
+    }
+
+    fn apply(&mut self, station: &str, title: &str, add: bool) {
+        let titles = self.titles_mut(station);
+        if add {
+            titles.insert(title.to_string());
+        } else {
+            titles.remove(title);
+        }
+    }
+
+    /// Drop the undo history, for when the titles were rewritten wholesale, and save
+    pub fn forget_changes(&mut self) -> Result<(), StorageError> {
+        if self.undo.is_empty() && self.redo.is_empty() {
+            return Ok(());
+        }
+
+        self.undo.clear();
+        self.redo.clear();
+        self.save()
+    }
+
+    /// Add every title of `other`, of every station, and save; returns how many were new.
+    /// The undo history is dropped, since it no longer describes how the list came to be.
+    pub fn merge(&mut self, other: Favorites) -> Result<usize, StorageError> {
+        let before = self.count_all();
+        self.episodes.extend(other.episodes);
+        for (name, titles) in other.stations {
+            self.stations.entry(name).or_default().extend(titles);
+        }
+
+        let added = self.count_all() - before;
+        if added > 0 {
+            self.undo.clear();
+            self.redo.clear();
+            self.save()?;
+        }
+        Ok(added)
+    }
+
+    fn count_all(&self) -> usize {
+        self.episodes.len() + self.stations.values().map(HashSet::len).sum::<usize>()
+    }
+
     pub fn is_favorite(&self, title: &str) -> bool {
-        self.episodes.contains(title)
+        self.titles().is_some_and(|titles| titles.contains(title))
     }
 
+    /// Titles in alphabetical order
     pub fn list(&self) -> Vec<&String> {
-        let mut list: Vec<_> = self.episodes.iter().collect();
+        let mut list: Vec<_> = self.titles().into_iter().flatten().collect();
         list.sort();
         list
     }
 
+    /// Add or remove `title`; true if it is now a favorite
     pub fn toggle(&mut self, title: String) -> bool {
         if self.is_favorite(&title) {
             self.remove(&title);
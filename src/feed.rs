@@ -1,62 +1,592 @@
-use anyhow::{Context, Result};
+//! The musicforprogramming.net RSS feed
+//! Fetched over HTTP and cached as JSON so the episode list survives going offline.
+
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
 use serde::{Deserialize, Serialize};
+use std::cell::OnceCell;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info};
+
+use crate::config::Config;
+use crate::errors::{self, StorageError, EXIT_FAILURE, EXIT_NETWORK};
+use crate::http;
+use crate::i18n::{t, tf, Msg};
+use crate::paths;
+use crate::station;
+
+/// Feed used unless `feed_url` is set in the config
+pub const DEFAULT_FEED_URL: &str = "https://musicforprogramming.net/rss.xml";
+
+/// Body chunks buffered between the download and the parser
+const CHUNKS_IN_FLIGHT: usize = 16;
+
+/// How long the cached feed stands in for the published one: episodes come out every few
+/// weeks, so one from the last few hours is as good as a fetch
+pub const CACHE_MAX_AGE: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// Why the feed could not be fetched
+#[derive(Debug, thiserror::Error)]
+pub enum FeedError {
+    /// No response: DNS, connection, TLS or timeout
+    #[error("{}", http::describe(.0, Msg::FeedFetchFailed))]
+    Request(#[from] reqwest::Error),
+    /// The server answered with an error status
+    #[error("{}", tf(Msg::HttpError, &[status]))]
+    Status { status: u16 },
+    /// The response is not a readable RSS feed
+    #[error("{}", t(Msg::FeedParseFailed))]
+    Parse(#[from] ParseError),
+    /// The server went silent, or the fetch was cancelled
+    #[error(transparent)]
+    Aborted(#[from] http::Aborted),
+}
 
-const RSS_URL: &str = "https://musicforprogramming.net/rss.xml";
+/// What is wrong with a document that should have been RSS
+#[derive(Debug, thiserror::Error)]
+pub enum ParseError {
+    #[error(transparent)]
+    Xml(#[from] quick_xml::Error),
+    #[error("the document has no <rss> root element")]
+    NotRss,
+}
+
+impl FeedError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            FeedError::Request(_) => EXIT_NETWORK,
+            FeedError::Status { status } => errors::status_exit_code(*status),
+            FeedError::Parse(_) => EXIT_FAILURE,
+            FeedError::Aborted(e) => e.exit_code(),
+        }
+    }
+}
 
+/// One episode as published in the feed
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Episode {
+    /// Full title, e.g. `Episode 75: Datassette`
     pub title: String,
+    /// Direct URL of the MP3
     pub audio_url: String,
+    /// `HH:MM:SS` or `MM:SS` as the feed spells it, `Unknown` when missing
     pub duration: String,
+    /// Publication date, RFC 2822 as in the feed
     pub pub_date: String,
+    /// Episode notes as published (may contain HTML)
     pub description: String,
+    /// Episode web page (notes and download links)
+    #[serde(default)]
+    pub page_url: Option<String>,
+    /// Cover art: the episode's own image, else the feed's
+    #[serde(default)]
+    pub image_url: Option<String>,
 }
 
 impl Episode {
+    /// Name shown in lists and notifications
     pub fn display_name(&self) -> &str {
         &self.title
     }
+
+    /// Whether the title or notes contain `keyword`, ignoring case (`play --filter`)
+    pub fn mentions(&self, keyword: &str) -> bool {
+        let keyword = keyword.to_lowercase();
+        self.title.to_lowercase().contains(&keyword)
+            || self.description.to_lowercase().contains(&keyword)
+    }
 }
 
+/// The episode list, newest first
 pub struct Feed {
-    episodes: Vec<Episode>,
+    episodes: Arc<[Episode]>,
+    /// Whether titles carry numbers (`Episode 75: ...`); other feeds are numbered by position
+    numbered: bool,
 }
 
 impl Feed {
-    pub fn fetch() -> Result<Self> {
-        let content = reqwest::blocking::get(RSS_URL)
-            .context("Failed to fetch RSS feed")?
-            .bytes()
-            .context("Failed to read RSS content")?;
+    fn new(episodes: Vec<Episode>) -> Self {
+        let numbered = episodes
+            .iter()
+            .any(|episode| extract_episode_number(&episode.title).is_some());
 
-        let channel = rss::Channel::read_from(&content[..]).context("Failed to parse RSS feed")?;
+        Feed {
+            episodes: episodes.into(),
+            numbered,
+        }
+    }
 
-        let episodes = channel
-            .items()
-            .iter()
-            .filter_map(|item| {
-                Some(Episode {
-                    title: item.title()?.to_string(),
-                    audio_url: item.enclosure()?.url().to_string(),
-                    duration: item
-                        .itunes_ext()?
-                        .duration()
-                        .unwrap_or("Unknown")
-                        .to_string(),
-                    pub_date: item.pub_date().unwrap_or("Unknown").to_string(),
-                    description: item.description().unwrap_or("").to_string(),
-                })
-            })
-            .collect();
+    /// Download and parse the active station's feed, refreshing the local cache
+    pub fn fetch() -> Result<Self, FeedError> {
+        http::block_on(Self::fetch_async(&CancellationToken::new()))
+    }
 
-        Ok(Feed { episodes })
+    /// Download and parse the feed at `url`, leaving the cache alone
+    pub fn fetch_from(url: &str) -> Result<Self, FeedError> {
+        http::block_on(Self::fetch_from_async(url, &CancellationToken::new()))
     }
 
+    /// [`Feed::fetch`] on the async layer
+    pub async fn fetch_async(cancel: &CancellationToken) -> Result<Self, FeedError> {
+        let url = station::feed_url(&Config::load().unwrap_or_default(), station::active())
+            .unwrap_or_else(|| DEFAULT_FEED_URL.to_string());
+
+        let feed = Self::fetch_from_async(&url, cancel).await?;
+        // The cache only spares the next run a fetch, so a failed write is not fatal
+        let _ = feed.save_cache();
+
+        Ok(feed)
+    }
+
+    /// [`Feed::fetch_from`] on the async layer
+    pub async fn fetch_from_async(
+        url: &str,
+        cancel: &CancellationToken,
+    ) -> Result<Self, FeedError> {
+        debug!(url, "Fetching feed");
+        let idle = http::idle_timeout();
+        let response = http::step(http::client()?.get(url).send(), idle, cancel).await??;
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(FeedError::Status {
+                status: status.as_u16(),
+            });
+        }
+
+        let max_items = Config::load().unwrap_or_default().feed_max_items;
+        // The parser reads on a blocking thread while the body arrives, so a multi-megabyte
+        // feed is never held whole, and stopping at `max_items` stops the download too
+        let (sender, receiver) = mpsc::channel(CHUNKS_IN_FLIGHT);
+        let parser = tokio::task::spawn_blocking(move || {
+            Feed::read_from(BufReader::new(ChunkReader::new(receiver)), max_items)
+        });
+
+        let mut response = response;
+        let download = async {
+            while let Some(chunk) = http::step(response.chunk(), idle, cancel).await?? {
+                if sender.send(chunk.to_vec()).await.is_err() {
+                    // The parser has all it wants, or gave up on the document
+                    break;
+                }
+            }
+            Ok::<_, FeedError>(())
+        }
+        .await;
+        drop(sender);
+
+        let parsed = parser
+            .await
+            .unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()));
+        // A broken transfer also breaks the XML; report the transfer
+        download?;
+        parsed
+    }
+
+    /// Parse an RSS document as it is read, keeping at most `max_items` episodes (the first
+    /// ones in the document, newest first in podcast feeds). Reading stops at the cap.
+    pub fn read_from<R: BufRead>(reader: R, max_items: Option<usize>) -> Result<Self, FeedError> {
+        let mut reader = Reader::from_reader(reader);
+        reader.config_mut().trim_text(true);
+
+        let mut buf = Vec::new();
+        // Names of the open elements, from the root down
+        let mut path: Vec<Vec<u8>> = Vec::new();
+        let mut root = false;
+        let mut text = String::new();
+        let mut item: Option<Item> = None;
+        let mut channel_image: Option<String> = None;
+        let mut channel_itunes_image: Option<String> = None;
+        let mut episodes = Vec::new();
+
+        loop {
+            let event = reader.read_event_into(&mut buf).map_err(ParseError::from)?;
+            match event {
+                Event::Start(ref element) | Event::Empty(ref element) => {
+                    let name = element.name().as_ref().to_vec();
+                    if path.is_empty() {
+                        if root || name != b"rss" {
+                            return Err(ParseError::NotRss.into());
+                        }
+                        root = true;
+                    }
+                    match (path.len(), name.as_slice()) {
+                        (2, b"item") if path[1] == b"channel" => item = Some(Item::default()),
+                        (2, b"itunes:image") => channel_itunes_image = attribute(element, b"href"),
+                        (3, _) => {
+                            if let Some(item) = item.as_mut() {
+                                item.start(&name, element);
+                            }
+                        }
+                        _ => {}
+                    }
+                    if matches!(event, Event::Start(_)) {
+                        path.push(name);
+                        text.clear();
+                    }
+                }
+                Event::Text(ref content) => match content.unescape() {
+                    Ok(content) => text.push_str(&content),
+                    // An unknown entity shouldn't cost the whole feed; keep it as written
+                    Err(_) => text.push_str(&String::from_utf8_lossy(content)),
+                },
+                Event::CData(content) => text.push_str(&String::from_utf8_lossy(&content)),
+                Event::End(_) => {
+                    let name = path.pop().unwrap_or_default();
+                    let content = std::mem::take(&mut text);
+                    match (path.len(), name.as_slice()) {
+                        (2, b"item") => {
+                            if let Some(episode) = item.take().and_then(Item::into_episode) {
+                                episodes.push(episode);
+                            }
+                            if max_items.is_some_and(|max| episodes.len() >= max) {
+                                break;
+                            }
+                        }
+                        (3, b"url") if path[2] == b"image" => channel_image = Some(content),
+                        (3, _) => {
+                            if let Some(item) = item.as_mut() {
+                                item.end(&name, content);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+            buf.clear();
+        }
+
+        if !root {
+            return Err(ParseError::NotRss.into());
+        }
+
+        // Channel art may come after the items, so it fills in once the document is read
+        let channel_image = channel_itunes_image.or(channel_image);
+        for episode in &mut episodes {
+            if episode.image_url.is_none() {
+                episode.image_url.clone_from(&channel_image);
+            }
+        }
+        info!(episodes = episodes.len(), "Feed parsed");
+
+        Ok(Feed::new(episodes))
+    }
+
+    /// Load the episodes saved by the last successful fetch, without touching the network
+    pub fn load_cached() -> Option<Self> {
+        let path = Self::cache_path()?;
+        let content = fs::read(path).ok()?;
+        let episodes = serde_json::from_slice(&content).ok()?;
+
+        Some(Feed::new(episodes))
+    }
+
+    /// A feed of only `title`, for playing its downloaded copy when neither the feed nor the
+    /// cache can be had
+    pub fn downloaded(title: &str) -> Self {
+        Feed::new(vec![Episode {
+            title: title.to_string(),
+            audio_url: String::new(),
+            duration: "Unknown".to_string(),
+            pub_date: String::new(),
+            description: String::new(),
+            page_url: None,
+            image_url: None,
+        }])
+    }
+
+    /// How long ago the last successful fetch saved the cache; None without one
+    pub fn cache_age() -> Option<Duration> {
+        let modified = fs::metadata(Self::cache_path()?).ok()?.modified().ok()?;
+        Some(modified.elapsed().unwrap_or_default())
+    }
+
+    fn cache_path() -> Option<PathBuf> {
+        paths::feed_cache_file().ok()
+    }
+
+    fn save_cache(&self) -> Result<(), StorageError> {
+        let path = paths::feed_cache_file()?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(StorageError::write(parent))?;
+        }
+
+        let content = serde_json::to_vec(&*self.episodes).map_err(StorageError::write(&path))?;
+
+        fs::write(&path, content).map_err(StorageError::write(&path))
+    }
+
+    /// All episodes, in feed order
     pub fn episodes(&self) -> &[Episode] {
         &self.episodes
     }
 
+    /// All episodes as a shared list, for a [`Playlist`](crate::playlist::Playlist) over the
+    /// feed without copying it
+    pub fn shared_episodes(&self) -> Arc<[Episode]> {
+        Arc::clone(&self.episodes)
+    }
+
+    /// Episode whose title matches exactly
     pub fn find_by_title(&self, title: &str) -> Option<&Episode> {
         self.episodes.iter().find(|e| e.title == title)
     }
+
+    /// Number of the episode at `index`: the one in its title, or for feeds that don't number
+    /// their titles, its position counted from the oldest episode. None for an unnumbered
+    /// episode in a numbered feed.
+    pub fn number(&self, index: usize) -> Option<usize> {
+        let episode = self.episodes.get(index)?;
+        if self.numbered {
+            extract_episode_number(&episode.title)
+        } else {
+            Some(self.episodes.len() - index)
+        }
+    }
+
+    /// Episode numbered `number` as [`Feed::number`] counts
+    pub fn find_by_number(&self, number: usize) -> Option<&Episode> {
+        (0..self.episodes.len())
+            .find(|&i| self.number(i) == Some(number))
+            .map(|i| &self.episodes[i])
+    }
+}
+
+/// The feed for one command, loaded once something asks for it. [`get`](Self::get) takes the
+/// cache while it is recent, so playing from the list known already doesn't wait for the
+/// network; what needs the feed as published now (an episode number, the favorites) asks for
+/// [`fresh`](Self::fresh), which fetches it once.
+#[derive(Default)]
+pub struct LazyFeed {
+    cached: OnceCell<Option<Feed>>,
+    fetched: OnceCell<Feed>,
+}
+
+impl LazyFeed {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The feed fetched in this run if it was, otherwise the cached one while it is less than
+    /// [`CACHE_MAX_AGE`] old; fetched only without a recent cache
+    pub fn get(&self) -> Result<&Feed, FeedError> {
+        if let Some(feed) = self.fetched.get() {
+            return Ok(feed);
+        }
+        let recent = Feed::cache_age().is_some_and(|age| age < CACHE_MAX_AGE);
+        match self.cached().filter(|_| recent) {
+            Some(feed) => Ok(feed),
+            None => self.fresh(),
+        }
+    }
+
+    /// The feed as published now: fetched on the first call, which refreshes the cache
+    pub fn fresh(&self) -> Result<&Feed, FeedError> {
+        if let Some(feed) = self.fetched.get() {
+            return Ok(feed);
+        }
+        let feed = Feed::fetch()?;
+        Ok(self.fetched.get_or_init(|| feed))
+    }
+
+    /// The feed as the last fetch left it in the cache, however old, read once
+    pub fn cached(&self) -> Option<&Feed> {
+        self.cached.get_or_init(Feed::load_cached).as_ref()
+    }
+}
+
+/// Fields of one `<item>` while it is being read
+#[derive(Default)]
+struct Item {
+    title: Option<String>,
+    audio_url: Option<String>,
+    duration: Option<String>,
+    pub_date: Option<String>,
+    description: Option<String>,
+    link: Option<String>,
+    image: Option<String>,
+    /// Whether the item has any `itunes:` element; items without one are skipped
+    itunes: bool,
+}
+
+impl Item {
+    fn start(&mut self, name: &[u8], element: &BytesStart) {
+        if name.starts_with(b"itunes:") {
+            self.itunes = true;
+        }
+        match name {
+            b"enclosure" => self.audio_url = attribute(element, b"url"),
+            b"itunes:image" => self.image = attribute(element, b"href"),
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, name: &[u8], mut content: String) {
+        // Thousands of descriptions add up; don't keep the spare capacity of the text buffer
+        content.shrink_to_fit();
+        let field = match name {
+            b"title" => &mut self.title,
+            b"pubDate" => &mut self.pub_date,
+            b"description" => &mut self.description,
+            b"link" => &mut self.link,
+            b"itunes:duration" => &mut self.duration,
+            _ => return,
+        };
+        *field = Some(content);
+    }
+
+    /// The episode, or None for items without a title or audio (announcements and the like)
+    fn into_episode(self) -> Option<Episode> {
+        if !self.itunes {
+            return None;
+        }
+        Some(Episode {
+            title: self.title?,
+            audio_url: self.audio_url?,
+            duration: self.duration.unwrap_or_else(|| "Unknown".to_string()),
+            pub_date: self.pub_date.unwrap_or_else(|| "Unknown".to_string()),
+            description: self.description.unwrap_or_default(),
+            page_url: self.link.filter(|link| !link.trim().is_empty()),
+            image_url: self.image,
+        })
+    }
+}
+
+fn attribute(element: &BytesStart, key: &[u8]) -> Option<String> {
+    element
+        .attributes()
+        .flatten()
+        .find(|attribute| attribute.key.as_ref() == key)
+        .and_then(|attribute| attribute.unescape_value().ok())
+        .map(|value| value.into_owned())
+}
+
+/// Blocking reader over body chunks sent from the async download
+struct ChunkReader {
+    chunks: mpsc::Receiver<Vec<u8>>,
+    current: Vec<u8>,
+    offset: usize,
+}
+
+impl ChunkReader {
+    fn new(chunks: mpsc::Receiver<Vec<u8>>) -> Self {
+        ChunkReader {
+            chunks,
+            current: Vec::new(),
+            offset: 0,
+        }
+    }
+}
+
+impl Read for ChunkReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.offset == self.current.len() {
+            match self.chunks.blocking_recv() {
+                Some(chunk) => {
+                    self.current = chunk;
+                    self.offset = 0;
+                }
+                // The download is over (or failed, which the fetch reports)
+                None => return Ok(0),
+            }
+        }
+
+        let n = buf.len().min(self.current.len() - self.offset);
+        buf[..n].copy_from_slice(&self.current[self.offset..self.offset + n]);
+        self.offset += n;
+        Ok(n)
+    }
+}
+
+/// The number in an `Episode 75: Datassette` title
+pub fn extract_episode_number(title: &str) -> Option<usize> {
+    title
+        .split(':')
+        .next()?
+        .trim()
+        .strip_prefix("Episode ")?
+        .parse()
+        .ok()
+}
+
+/// The curator in an `Episode 75: Datassette` title
+pub fn extract_curator(title: &str) -> Option<&str> {
+    extract_episode_number(title)?;
+    let (_, curator) = title.split_once(':')?;
+    Some(curator.trim()).filter(|curator| !curator.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(titles: &[&str]) -> Feed {
+        Feed::new(
+            titles
+                .iter()
+                .map(|title| Episode {
+                    title: title.to_string(),
+                    audio_url: String::new(),
+                    duration: "Unknown".into(),
+                    pub_date: String::new(),
+                    description: String::new(),
+                    page_url: None,
+                    image_url: None,
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn numbers_come_from_titles() {
+        let feed = feed(&["Episode 75: Datassette", "Episode 7: Tahlhoff Garten"]);
+
+        assert_eq!(feed.number(0), Some(75));
+        assert_eq!(feed.number(1), Some(7));
+        // Not "Episode 75" just because it starts the same
+        assert_eq!(
+            feed.find_by_number(7).map(|ep| ep.title.as_str()),
+            Some("Episode 7: Tahlhoff Garten")
+        );
+        assert!(feed.find_by_number(3).is_none());
+    }
+
+    #[test]
+    fn curators_follow_the_number() {
+        assert_eq!(
+            extract_curator("Episode 75: Datassette"),
+            Some("Datassette")
+        );
+        assert_eq!(extract_curator("Episode 75:"), None);
+        assert_eq!(extract_curator("Interview: Someone"), None);
+    }
+
+    #[test]
+    fn unnumbered_feeds_count_from_the_oldest_episode() {
+        let feed = feed(&["Rainy night", "Late shift", "First light"]);
+
+        assert_eq!(feed.number(0), Some(3));
+        assert_eq!(feed.number(2), Some(1));
+        assert_eq!(feed.number(3), None);
+        assert_eq!(
+            feed.find_by_number(2).map(|ep| ep.title.as_str()),
+            Some("Late shift")
+        );
+    }
+
+    #[test]
+    fn odd_titles_in_a_numbered_feed_have_no_number() {
+        let feed = feed(&["Episode 2: Uberdog", "Bonus: live set"]);
+
+        assert_eq!(feed.number(1), None);
+        assert!(feed.find_by_number(1).is_none());
+    }
 }
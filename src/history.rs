@@ -0,0 +1,184 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::errors::StorageError;
+use crate::paths;
+use crate::session::{self, SessionEvent, SessionListener};
+
+const MAX_ENTRIES: usize = 5000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub title: String,
+    /// Unix timestamp (seconds) of the track start
+    pub started_at: u64,
+    pub listened_secs: u64,
+    pub completed: bool,
+}
+
+pub struct History;
+
+impl History {
+    fn history_path() -> Result<PathBuf, StorageError> {
+        let data_dir = paths::data_dir()?;
+        fs::create_dir_all(&data_dir).map_err(StorageError::write(&data_dir))?;
+
+        paths::history_file()
+    }
+
+    /// Load all entries, oldest first. Malformed lines are skipped.
+    pub fn load() -> Result<Vec<HistoryEntry>, StorageError> {
+        let path = Self::history_path()?;
+
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let content = fs::read_to_string(&path).map_err(StorageError::read(&path))?;
+
+        Ok(content
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+
+    /// Most recent entries first, at most `limit` of them
+    pub fn recent(limit: usize) -> Result<Vec<HistoryEntry>, StorageError> {
+        let mut entries = Self::load()?;
+        entries.reverse();
+        entries.truncate(limit);
+        Ok(entries)
+    }
+
+    pub fn append(entry: &HistoryEntry) -> Result<(), StorageError> {
+        let path = Self::history_path()?;
+        let line = serde_json::to_string(entry).map_err(StorageError::write(&path))?;
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(StorageError::write(&path))?;
+        writeln!(file, "{}", line).map_err(StorageError::write(&path))?;
+
+        Self::truncate_oldest()
+    }
+
+    /// Add the entries not recorded yet (same title and start) and keep the file in start
+    /// order; returns how many were added
+    pub fn merge(entries: Vec<HistoryEntry>) -> Result<usize, StorageError> {
+        let mut all = Self::load()?;
+        let mut seen: HashSet<(String, u64)> = all
+            .iter()
+            .map(|entry| (entry.title.clone(), entry.started_at))
+            .collect();
+
+        let before = all.len();
+        all.extend(
+            entries
+                .into_iter()
+                .filter(|entry| seen.insert((entry.title.clone(), entry.started_at))),
+        );
+        let added = all.len() - before;
+        if added == 0 {
+            return Ok(0);
+        }
+
+        all.sort_by_key(|entry| entry.started_at);
+        let kept = &all[all.len().saturating_sub(MAX_ENTRIES)..];
+
+        let path = Self::history_path()?;
+        let mut content = String::new();
+        for entry in kept {
+            content += &serde_json::to_string(entry).map_err(StorageError::write(&path))?;
+            content.push('\n');
+        }
+        fs::write(&path, content).map_err(StorageError::write(&path))?;
+        Ok(added)
+    }
+
+    fn truncate_oldest() -> Result<(), StorageError> {
+        let path = Self::history_path()?;
+        let content = fs::read_to_string(&path).map_err(StorageError::read(&path))?;
+        let lines: Vec<&str> = content.lines().collect();
+
+        if lines.len() <= MAX_ENTRIES {
+            return Ok(());
+        }
+
+        let mut kept = lines[lines.len() - MAX_ENTRIES..].join("\n");
+        kept.push('\n');
+        fs::write(&path, kept).map_err(StorageError::write(&path))
+    }
+}
+
+/// Session listener that records every track into the history file
+#[derive(Default)]
+pub struct HistoryRecorder {
+    started_at: Option<u64>,
+}
+
+impl HistoryRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl SessionListener for HistoryRecorder {
+    fn on_event(&mut self, event: &SessionEvent) {
+        match event {
+            SessionEvent::TrackStarted { .. } => {
+                self.started_at = Some(unix_now());
+            }
+            SessionEvent::TrackEnded {
+                title,
+                duration_secs,
+                listened_secs,
+            } => {
+                let entry = HistoryEntry {
+                    title: title.clone(),
+                    started_at: self.started_at.take().unwrap_or_else(unix_now),
+                    listened_secs: *listened_secs,
+                    completed: session::is_completed(*listened_secs, *duration_secs),
+                };
+                if let Err(e) = History::append(&entry) {
+                    tracing::warn!("Failed to record history: {:#}", e);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Human readable distance between `timestamp` and now ("3 hours ago")
+pub fn format_relative(timestamp: u64) -> String {
+    let ago = unix_now().saturating_sub(timestamp);
+
+    let (amount, unit) = match ago {
+        0..=59 => return "just now".to_string(),
+        60..=3599 => (ago / 60, "minute"),
+        3600..=86_399 => (ago / 3600, "hour"),
+        86_400..=172_799 => return "yesterday".to_string(),
+        172_800..=1_209_599 => (ago / 86_400, "day"),
+        1_209_600..=5_183_999 => (ago / 604_800, "week"),
+        _ => (ago / 2_592_000, "month"),
+    };
+
+    format!(
+        "{} {}{} ago",
+        amount,
+        unit,
+        if amount == 1 { "" } else { "s" }
+    )
+}
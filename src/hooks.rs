@@ -0,0 +1,170 @@
+//! User hook commands
+//! Shell commands from `config.toml` run on playback events (`on_track_start`, `on_track_end`,
+//! `on_pause`, `on_stop`) with the episode in `MFP_*` environment variables. They run in the
+//! background: a slow or failing hook is logged and never holds up playback.
+
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+use crate::config::Config;
+use crate::session::{SessionEvent, SessionListener};
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// `--no-hooks` turns every hook off, whatever the config says
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// What the hooks are told about the current episode
+#[derive(Default)]
+struct Track {
+    title: String,
+    number: Option<usize>,
+    duration_secs: u64,
+    url: String,
+    elapsed_secs: u64,
+}
+
+/// Session listener that runs the configured hook commands
+pub struct Hooks {
+    on_track_start: Option<String>,
+    on_track_end: Option<String>,
+    on_pause: Option<String>,
+    on_stop: Option<String>,
+    track: Track,
+}
+
+impl Hooks {
+    /// None when hooks are disabled or none is configured
+    pub fn from_config(config: &Config) -> Option<Self> {
+        if !ENABLED.load(Ordering::Relaxed) {
+            return None;
+        }
+
+        let hooks = Hooks {
+            on_track_start: config.on_track_start.clone(),
+            on_track_end: config.on_track_end.clone(),
+            on_pause: config.on_pause.clone(),
+            on_stop: config.on_stop.clone(),
+            track: Track::default(),
+        };
+        let configured = [
+            &hooks.on_track_start,
+            &hooks.on_track_end,
+            &hooks.on_pause,
+            &hooks.on_stop,
+        ]
+        .iter()
+        .any(|command| command.is_some());
+
+        configured.then_some(hooks)
+    }
+
+    fn run(&self, hook: &'static str, command: &Option<String>) {
+        let Some(command) = command else {
+            return;
+        };
+
+        let track = &self.track;
+        let mut process = shell(command);
+        process
+            .env("MFP_TITLE", &track.title)
+            .env(
+                "MFP_NUMBER",
+                track.number.map(|n| n.to_string()).unwrap_or_default(),
+            )
+            .env("MFP_DURATION", track.duration_secs.to_string())
+            .env("MFP_ELAPSED", track.elapsed_secs.to_string())
+            .env("MFP_URL", &track.url)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        detach(&mut process);
+
+        let child = match process.spawn() {
+            Ok(child) => child,
+            Err(e) => {
+                tracing::warn!(hook, "Failed to run hook: {}", e);
+                return;
+            }
+        };
+        tracing::debug!(hook, command = command.as_str(), "Hook started");
+
+        // Nobody waits on this thread, so a hook may take as long as it likes
+        thread::spawn(move || match child.wait_with_output() {
+            Ok(output) => {
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                if !stdout.trim().is_empty() {
+                    tracing::debug!(hook, "{}", stdout.trim_end());
+                }
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                if !stderr.trim().is_empty() {
+                    tracing::warn!(hook, "{}", stderr.trim_end());
+                }
+                if !output.status.success() {
+                    tracing::warn!(hook, "Hook exited with {}", output.status);
+                }
+            }
+            Err(e) => tracing::warn!(hook, "Hook failed: {}", e),
+        });
+    }
+}
+
+impl SessionListener for Hooks {
+    fn on_event(&mut self, event: &SessionEvent) {
+        match event {
+            SessionEvent::TrackStarted {
+                title,
+                duration_secs,
+                url,
+            } => {
+                self.track = Track {
+                    title: title.clone(),
+                    number: crate::feed::extract_episode_number(title),
+                    duration_secs: *duration_secs,
+                    url: url.clone(),
+                    elapsed_secs: 0,
+                };
+                self.run("on_track_start", &self.on_track_start);
+            }
+            SessionEvent::TrackEnded { listened_secs, .. } => {
+                self.track.elapsed_secs = *listened_secs;
+                self.run("on_track_end", &self.on_track_end);
+            }
+            SessionEvent::Paused { elapsed_secs } => {
+                self.track.elapsed_secs = *elapsed_secs;
+                self.run("on_pause", &self.on_pause);
+            }
+            SessionEvent::Resumed { elapsed_secs } => self.track.elapsed_secs = *elapsed_secs,
+            SessionEvent::Stopped => self.run("on_stop", &self.on_stop),
+            SessionEvent::SettingsChanged { .. } => {}
+        }
+    }
+}
+
+#[cfg(unix)]
+fn shell(command: &str) -> Command {
+    let mut process = Command::new("sh");
+    process.arg("-c").arg(command);
+    process
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut process = Command::new("cmd");
+    process.arg("/C").arg(command);
+    process
+}
+
+/// Keep the hook out of the player's process group, so a Ctrl+C meant for mfp
+/// doesn't reach it
+#[cfg(unix)]
+fn detach(process: &mut Command) {
+    use std::os::unix::process::CommandExt;
+    process.process_group(0);
+}
+
+#[cfg(not(unix))]
+fn detach(_process: &mut Command) {}
@@ -0,0 +1,222 @@
+//! The network runtime and the HTTP client every request goes through
+//! Requests are async and run on one tokio runtime owned by the library; the blocking API
+//! (what the CLI calls) wraps them with [`block_on`]. The client is built once and shared, so
+//! the feed, streams, downloads and scrobbles to the same host reuse open connections.
+//! Timeouts, proxy, redirects and user agent are applied here and nowhere else.
+
+use reqwest::header::{ACCEPT_ENCODING, LOCATION, RANGE};
+use reqwest::redirect::Policy;
+use reqwest::{Client, ClientBuilder, IntoUrl, Proxy, RequestBuilder, Response, StatusCode};
+use std::future::Future;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tokio::runtime::{self, Runtime};
+use tokio_util::sync::CancellationToken;
+use tracing::debug;
+
+use crate::config::Config;
+use crate::errors::{EXIT_FAILURE, EXIT_NETWORK};
+use crate::i18n::{t, tf, Msg};
+
+/// Sent with every request
+pub const USER_AGENT: &str = concat!(
+    "mfp/",
+    env!("CARGO_PKG_VERSION"),
+    " (+https://github.com/4drian0rtiz/mfp)"
+);
+
+/// How long a server may stay silent before a request fails, unless `timeout_secs` is set
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Redirects followed before a request gives up, against loops
+pub const MAX_REDIRECTS: usize = 10;
+/// Idle connections kept per host; downloads and streams rarely need more than a couple
+const IDLE_PER_HOST: usize = 4;
+const WORKER_THREADS: usize = 2;
+
+static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+static CLIENT: OnceLock<Client> = OnceLock::new();
+static AUDIO_CLIENT: OnceLock<Client> = OnceLock::new();
+
+/// A request that stopped waiting before the server was done
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum Aborted {
+    /// Nothing arrived for this long
+    #[error("{}", tf(Msg::ServerSilent, &[&after.as_secs_f32()]))]
+    Stalled { after: Duration },
+    /// The caller's cancellation token fired
+    #[error("{}", t(Msg::RequestCancelled))]
+    Cancelled,
+}
+
+impl Aborted {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Aborted::Stalled { .. } => EXIT_NETWORK,
+            Aborted::Cancelled => EXIT_FAILURE,
+        }
+    }
+}
+
+/// Why [`get_audio_from`] got no final answer
+#[derive(Debug, thiserror::Error)]
+pub enum RedirectError {
+    #[error(transparent)]
+    Request(#[from] reqwest::Error),
+    #[error(transparent)]
+    Aborted(#[from] Aborted),
+    /// More than [`MAX_REDIRECTS`] hops, most likely a loop
+    #[error("{}", t(Msg::TooManyRedirects))]
+    TooMany,
+}
+
+/// What to tell the user about a failed request: a timeout means the server didn't answer,
+/// anything else is `otherwise`
+pub(crate) fn describe(error: &reqwest::Error, otherwise: Msg) -> &'static str {
+    if error.is_timeout() {
+        t(Msg::ServerNoAnswer)
+    } else if error.is_redirect() {
+        t(Msg::TooManyRedirects)
+    } else {
+        t(otherwise)
+    }
+}
+
+/// The runtime network futures run on, started on first use
+pub fn runtime() -> &'static Runtime {
+    RUNTIME.get_or_init(|| {
+        runtime::Builder::new_multi_thread()
+            .worker_threads(WORKER_THREADS)
+            .thread_name("mfp-net")
+            .enable_all()
+            .build()
+            .expect("failed to start the network runtime")
+    })
+}
+
+/// Run `future` to completion from synchronous code. Not for use inside an async context.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    runtime().block_on(future)
+}
+
+/// Client settings from the configuration. For a client that differs in one setting;
+/// everything else should use [`client`].
+pub fn builder() -> Result<ClientBuilder, reqwest::Error> {
+    let config = Config::load().unwrap_or_default();
+
+    let mut builder = Client::builder()
+        .user_agent(USER_AGENT)
+        .redirect(Policy::limited(MAX_REDIRECTS))
+        .connect_timeout(CONNECT_TIMEOUT)
+        .pool_max_idle_per_host(IDLE_PER_HOST);
+
+    // Without a `proxy` setting reqwest follows HTTP_PROXY / HTTPS_PROXY / NO_PROXY
+    if let Some(proxy) = config.proxy.filter(|proxy| !proxy.is_empty()) {
+        builder = builder.proxy(Proxy::all(proxy)?);
+    }
+
+    Ok(builder)
+}
+
+/// The shared client, built on first use
+pub fn client() -> Result<&'static Client, reqwest::Error> {
+    if let Some(client) = CLIENT.get() {
+        return Ok(client);
+    }
+
+    let _runtime = runtime().enter();
+    let client = builder()?.build()?;
+    Ok(CLIENT.get_or_init(|| client))
+}
+
+/// The shared client minus redirects, for [`get_audio_from`] to follow them itself
+fn audio_client() -> Result<&'static Client, reqwest::Error> {
+    if let Some(client) = AUDIO_CLIENT.get() {
+        return Ok(client);
+    }
+
+    let _runtime = runtime().enter();
+    let client = builder()?.redirect(Policy::none()).build()?;
+    Ok(AUDIO_CLIENT.get_or_init(|| client))
+}
+
+/// GET for audio bytes, asking for them as the file has them: ranges and lengths count
+/// those, so a mirror or proxy must not compress them on the way
+pub fn audio_request(client: &Client, url: impl IntoUrl) -> RequestBuilder {
+    client.get(url).header(ACCEPT_ENCODING, "identity")
+}
+
+/// The answer of [`get_audio_from`]
+#[derive(Debug)]
+pub struct Followed {
+    pub response: Response,
+    /// Where the audio lives now, when only permanent redirects (301, 308) led there: the
+    /// next request can go straight to it
+    pub moved_to: Option<String>,
+}
+
+/// GET the audio at `url` from byte `from` on. Redirects are followed here rather than by
+/// the client, up to [`MAX_REDIRECTS`], so every hop is asked for the same range and a
+/// permanent move is known. A redirect without a `Location` is the answer as it stands.
+pub async fn get_audio_from(
+    url: &str,
+    from: u64,
+    idle: Duration,
+    cancel: &CancellationToken,
+) -> Result<Followed, RedirectError> {
+    let client = audio_client()?;
+    let mut location = url.to_string();
+    let mut permanent = true;
+
+    for _ in 0..=MAX_REDIRECTS {
+        let mut request = audio_request(client, location.as_str());
+        if from > 0 {
+            request = request.header(RANGE, format!("bytes={}-", from));
+        }
+        let response = step(request.send(), idle, cancel).await??;
+        let status = response.status();
+        let next = status
+            .is_redirection()
+            .then(|| response.headers().get(LOCATION))
+            .flatten()
+            .and_then(|to| to.to_str().ok())
+            .and_then(|to| response.url().join(to).ok());
+
+        let Some(next) = next else {
+            let moved_to =
+                (permanent && status.is_success() && location != url).then_some(location);
+            return Ok(Followed { response, moved_to });
+        };
+        debug!(%status, from = %response.url(), to = %next, "Audio redirected");
+        permanent &= matches!(
+            status,
+            StatusCode::MOVED_PERMANENTLY | StatusCode::PERMANENT_REDIRECT
+        );
+        location = next.into();
+    }
+
+    Err(RedirectError::TooMany)
+}
+
+/// How long a server may stay silent: the `timeout_secs` setting or 30 seconds
+pub fn idle_timeout() -> Duration {
+    Config::load()
+        .ok()
+        .and_then(|config| config.timeout_secs)
+        .map_or(DEFAULT_TIMEOUT, Duration::from_secs)
+}
+
+/// Await one step of a transfer (the response, the next chunk), giving up when it takes
+/// longer than `idle` or `cancel` fires. Long transfers stay alive as long as data flows.
+pub async fn step<F: Future>(
+    future: F,
+    idle: Duration,
+    cancel: &CancellationToken,
+) -> Result<F::Output, Aborted> {
+    tokio::select! {
+        _ = cancel.cancelled() => Err(Aborted::Cancelled),
+        output = tokio::time::timeout(idle, future) => {
+            output.map_err(|_| Aborted::Stalled { after: idle })
+        }
+    }
+}
@@ -0,0 +1,1524 @@
+//! User-facing message catalog
+//! Every message has an English and a Spanish text; `{}` marks a placeholder
+//! that `tf` fills in order. Missing translations fall back to English.
+
+use serde::Deserialize;
+use std::fmt::Display;
+use std::sync::OnceLock;
+
+static LANG: OnceLock<Lang> = OnceLock::new();
+
+/// `language` setting in config.toml
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    #[default]
+    Auto,
+    En,
+    Es,
+}
+
+/// Resolved language
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Lang {
+    En,
+    Es,
+}
+
+macro_rules! messages {
+    ($($key:ident),* $(,)?) => {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum Msg {
+            $($key),*
+        }
+
+        impl Msg {
+            #[cfg(test)]
+            pub const ALL: &'static [Msg] = &[$(Msg::$key),*];
+        }
+    };
+}
+
+messages! {
+    // Feed and listing
+    FetchingEpisodes,
+    FetchingEpisode,
+    LoadingFeed,
+    NoEpisodesAvailable,
+    EpisodeNotFound,
+    // Play loop
+    NoFavoritesHint,
+    DurationShuffleRepeat,
+    Controls,
+    ControlsTransport,
+    ControlsOther,
+    Playing,
+    Paused,
+    Volume,
+    Muted,
+    InfoEpisode,
+    InfoDuration,
+    InfoStatus,
+    InfoShuffle,
+    InfoRepeat,
+    RepeatOne,
+    RepeatAll,
+    PlaylistEnded,
+    ProgressRepeat,
+    InfoFavorite,
+    On,
+    Off,
+    Yes,
+    No,
+    AddedToFavorites,
+    RemovedFromFavorites,
+    DownloadingForOffline,
+    EpisodeDownloaded,
+    BackgroundDownloading,
+    BackgroundDownloadFailed,
+    DownloadAlreadyRunning,
+    DownloadStoppedOnQuit,
+    ErrorLabel,
+    UnknownCommand,
+    CommandsHint,
+    MprisUpdateFailed,
+    // Integrations
+    ScrobbleFeatureMissing,
+    ScrobbleNotConfigured,
+    ScrobbleAddSection,
+    ScrobbleCredentialsOk,
+    ScrobbleQueueSent,
+    ScrobbleUsage,
+    DiscordAppIdMissing,
+    DiscordFeatureMissing,
+    // History
+    NoHistory,
+    NoHistoryEntry,
+    CannotReplay,
+    HistoryWhen,
+    HistoryListened,
+    HistoryDone,
+    HistoryEpisode,
+    // Favorites
+    FavAdded,
+    FavAlreadyAdded,
+    FavRemoved,
+    FavNotFound,
+    NoFavorites,
+    FavoritesHeader,
+    // Downloads
+    DiskUsage,
+    Location,
+    NoDownloads,
+    DownloadedHeader,
+    DownloadsHelp,
+    AlreadyDownloaded,
+    Downloading,
+    DownloadProgress,
+    DownloadComplete,
+    Deleted,
+    NotDownloaded,
+    ConfigDirFailed,
+    CreateFileFailed,
+    // Player
+    Connecting,
+    Buffering,
+    AudioDeviceFailed,
+    SinkFailed,
+    ConnectFailed,
+    HttpError,
+    NoDataReceived,
+    DecodeFailed,
+    // No-argument help
+    AvailableCommands,
+    // Help overlay
+    HelpTransport,
+    HelpVolume,
+    HelpPlaylist,
+    HelpLibrary,
+    HelpSession,
+    HelpPause,
+    HelpNext,
+    HelpPrevious,
+    HelpVolumeUp,
+    HelpVolumeDown,
+    HelpMute,
+    HelpShuffle,
+    HelpRepeat,
+    HelpFavorite,
+    HelpAddToPlaylist,
+    AddToPlaylistUsage,
+    HelpDownload,
+    HelpInfo,
+    HelpHelp,
+    HelpQuit,
+    HelpPressAnyKey,
+    // Scheduled start
+    InvalidClock,
+    InvalidDelay,
+    InvalidReadahead,
+    StartingIn,
+    ScheduleCancelled,
+    // Episode page
+    OpeningPage,
+    NoBrowser,
+    NoPageUrl,
+    HelpOpen,
+    // Now playing
+    NothingPlaying,
+    // Episode picker
+    PickerNoMatches,
+    PickerHint,
+    // Title search
+    NoQueryMatch,
+    SeveralMatches,
+    ChooseEpisode,
+    AmbiguousQuery,
+    // Setup wizard
+    WizardWelcome,
+    WizardDownloadDir,
+    WizardVolume,
+    WizardMpris,
+    WizardPrefetch,
+    WizardInvalid,
+    WizardSaved,
+    WizardPaths,
+    WizardRerun,
+    ConfigFile,
+    ConfigMissing,
+    // Download resume
+    DownloadResuming,
+    DownloadIncomplete,
+    // Typed errors
+    FeedFetchFailed,
+    FeedParseFailed,
+    DownloadInterrupted,
+    StorageReadFailed,
+    StorageWriteFailed,
+    StorageParseFailed,
+    // Data migration
+    DataMigrated,
+    // Clipboard
+    HelpYank,
+    HelpYankPage,
+    Copied,
+    NoClipboard,
+    InfoPublished,
+    InfoAudioUrl,
+    InfoPage,
+    // Journal
+    HelpNote,
+    NotePrompt,
+    NoteSaved,
+    NoteEmpty,
+    NoteFailed,
+    NoJournal,
+    NoJournalForEpisode,
+    JournalExported,
+    // Play from stdin
+    StdinEmpty,
+    StdinUnreadable,
+    StdinSeveralLines,
+    StdinBadJson,
+    StdinSeveralValues,
+    StdinFieldType,
+    StdinMissingField,
+    StdinArray,
+    StdinUnexpectedJson,
+    NoTtyControls,
+    // Mouse
+    SeekUnavailable,
+    // Backup
+    BackupExported,
+    BackupRestored,
+    BackupMerged,
+    BackupEmpty,
+    BackupMissingDownloads,
+    BackupReplaceConfirm,
+    BackupReplaceNeedsYes,
+    BackupReplaceCancelled,
+    BackupNotAnArchive,
+    BackupUnsupportedVersion,
+    BackupDamaged,
+    // Network
+    ServerSilent,
+    RequestCancelled,
+    // Menu
+    MenuContinue,
+    MenuContinueQueued,
+    MenuContinueQueue,
+    MenuLatest,
+    MenuLatestUnknown,
+    MenuBrowse,
+    MenuFavorites,
+    MenuDownloads,
+    MenuQuit,
+    MenuPrompt,
+    // Preview mode
+    HelpFull,
+    PreviewTag,
+    PreviewControls,
+    PreviewFull,
+    PreviewNotActive,
+    // Stations
+    UnknownStation,
+    InvalidStationName,
+    StationExists,
+    CheckingFeed,
+    StationFeedEmpty,
+    StationAdded,
+    StationDefault,
+    StationRemoved,
+    UnknownPlaylist,
+    InvalidPlaylistName,
+    PlaylistExists,
+    PlaylistCreated,
+    PlaylistAdded,
+    PlaylistAlreadyHas,
+    PlaylistRemoved,
+    NotInPlaylist,
+    PlaylistDeleted,
+    NoPlaylists,
+    PlaylistSize,
+    PlaylistEmpty,
+    PlaylistMissingTag,
+    PlaylistEpisodeMissing,
+    PlaylistNothingToPlay,
+    FilterNoMatch,
+    EpisodeTitleNotFound,
+    // Casting
+    CastFeatureMissing,
+    CastSearching,
+    CastSearchingAll,
+    CastConnected,
+    CastConnectFailed,
+    CastDeviceNotFound,
+    CastNoDevices,
+    CastHint,
+    CastLost,
+    CastContinueHere,
+    // No audio
+    AudioFeatureMissing,
+    NoAudioOutput,
+    // Media keys
+    MediaKeysFeatureMissing,
+    MediaKeysFailed,
+    // Stalled stream
+    StreamStalled,
+    StreamStalledStopping,
+    StallRetrying,
+    OfflineDownloadStarted,
+    OfflinePlaying,
+    OfflineDownloadFailed,
+    // Per-episode volume
+    HelpVolumeSave,
+    VolumeSaved,
+    VolumeSaveCleared,
+    VolumeSaveNone,
+    VolumeSaveUsage,
+    VolumeSaveFailed,
+    InfoVolumeSaved,
+    // Restart
+    HelpRestart,
+    Restarting,
+    // Episode browser
+    HelpList,
+    HelpQueue,
+    QueueUsage,
+    Queued,
+    QueuedAgain,
+    QueueOutsidePlaylist,
+    QueueEmpty,
+    QueueHeader,
+    QueueAgainTag,
+    QueueRestored,
+    QueueEntryDropped,
+    BrowserPage,
+    BrowserHint,
+    BrowserInvalid,
+    // Finish times
+    ProgressEnds,
+    ProgressEndsAll,
+    // Favorites undo
+    HelpUndo,
+    HelpRedo,
+    FavUndone,
+    FavRedone,
+    FavIsFavorite,
+    FavIsNotFavorite,
+    NothingToUndo,
+    NothingToRedo,
+    FavUndoFailed,
+    // Instance lock
+    InstanceRunning,
+    InstanceTakenOver,
+    // Now-playing templates
+    UnknownFormatToken,
+    // Command aliases
+    HelpAliases,
+    // Seeking
+    HelpSeek,
+    HelpSeekForward,
+    HelpSeekBack,
+    HelpSeekForwardLong,
+    HelpSeekBackLong,
+    SeekUsage,
+    SeekBuffering,
+    HelpGoto,
+    HelpEpisode,
+    EpisodeUsage,
+    EpisodeOutsidePlaylist,
+    SkippedIntro,
+    ResumedAt,
+    GotoLanded,
+    GotoNoDuration,
+    GotoEpisodeHint,
+    // Local copies
+    InfoSource,
+    SourceLocal,
+    SourceStreaming,
+    FeedFromCache,
+    FeedFromDownload,
+    HelpRecord,
+    Recording,
+    RecordUnavailable,
+    // Reconnecting
+    ReconnectAttempt,
+    // Network
+    ServerNoAnswer,
+    TooManyRedirects,
+    // Failed streams
+    StreamFailed,
+    // Volume
+    HelpSetVolume,
+    VolumeUsage,
+    VolumeReset,
+    // Sleep timer
+    HelpSleep,
+    SleepUsage,
+    SleepSet,
+    SleepOff,
+    SleepStopping,
+    InfoSleep,
+    // Stop after episode
+    HelpStopAfter,
+    StopAfterOn,
+    StopAfterOff,
+    StoppedAfterEpisode,
+    // A-B loop
+    HelpLoop,
+    LoopStart,
+    LoopSet,
+    LoopCleared,
+    LoopTooShort,
+    // Audio device recovery
+    HelpDevice,
+    OutputLost,
+    OutputReopened,
+    OutputFailed,
+    // Balance
+    HelpBalanceRight,
+    HelpBalanceLeft,
+    HelpBalanceCenter,
+    InfoBalance,
+    BalanceCentered,
+    // Stream info
+    InfoStream,
+    InfoSkippedSilence,
+    StreamUnknown,
+    StreamMono,
+    StreamStereo,
+    StreamChannels,
+    // Buffer health
+    BufferFull,
+    BufferHeld,
+    Rebuffering,
+}
+
+const EN: &[(Msg, &str)] = &[
+    (Msg::FetchingEpisodes, "Fetching episodes..."),
+    (Msg::FetchingEpisode, "Fetching episode..."),
+    (Msg::LoadingFeed, "Loading feed..."),
+    (Msg::NoEpisodesAvailable, "No episodes available"),
+    (Msg::EpisodeNotFound, "Episode {} not found"),
+    (
+        Msg::NoFavoritesHint,
+        "You have no saved favorites. Use 'mfp fav --add \"Episode XX: Title\"'",
+    ),
+    (Msg::DurationShuffleRepeat, "Duration: {} | Shuffle: {} | Repeat: {}"),
+    (Msg::Controls, "Controls:"),
+    (
+        Msg::ControlsTransport,
+        "  [n]ext | [b]ack | [p]ause | [s]huffle | [f]avorite | [q]uit",
+    ),
+    (
+        Msg::ControlsOther,
+        "  [+/-] volume | [m]ute | [i]nfo | [d]ownload | [h]elp",
+    ),
+    (Msg::Playing, "Playing"),
+    (Msg::Paused, "Paused"),
+    (Msg::Volume, "Volume: {}%"),
+    (Msg::Muted, "Muted"),
+    (Msg::InfoEpisode, "Episode: {}"),
+    (Msg::InfoDuration, "Duration: {}"),
+    (Msg::InfoStatus, "Status: {}"),
+    (Msg::InfoShuffle, "Shuffle: {}"),
+    (Msg::InfoRepeat, "Repeat: {}"),
+    (Msg::RepeatOne, "ONE"),
+    (Msg::RepeatAll, "ALL"),
+    (Msg::PlaylistEnded, "End of the playlist (repeat is off)"),
+    (Msg::ProgressRepeat, "repeat {}"),
+    (Msg::InfoFavorite, "Favorite: {}"),
+    (Msg::On, "ON"),
+    (Msg::Off, "OFF"),
+    (Msg::Yes, "Yes"),
+    (Msg::No, "No"),
+    (Msg::AddedToFavorites, "Added to favorites"),
+    (Msg::RemovedFromFavorites, "Removed from favorites"),
+    (
+        Msg::DownloadingForOffline,
+        "Downloading the episode for offline in the background...",
+    ),
+    (Msg::EpisodeDownloaded, "Downloaded {}"),
+    (Msg::BackgroundDownloading, "downloading {}%"),
+    (Msg::BackgroundDownloadFailed, "Download of {} failed: {}"),
+    (Msg::DownloadAlreadyRunning, "Already downloading {}"),
+    (
+        Msg::DownloadStoppedOnQuit,
+        "Download of {} stopped; it picks up where it left off next time",
+    ),
+    (Msg::ErrorLabel, "Error:"),
+    (Msg::UnknownCommand, "Unknown command"),
+    (Msg::CommandsHint, "Type h or ? to list all commands"),
+    (Msg::MprisUpdateFailed, "Failed to update MPRIS: {}"),
+    (
+        Msg::ScrobbleFeatureMissing,
+        "Scrobbling is configured but mfp was built without the `scrobble` feature",
+    ),
+    (Msg::ScrobbleNotConfigured, "Scrobbling is not configured"),
+    (Msg::ScrobbleAddSection, "Add a [scrobble] section to {}"),
+    (Msg::ScrobbleCredentialsOk, "Credentials OK (user: {})"),
+    (Msg::ScrobbleQueueSent, "Submitted {} queued scrobbles"),
+    (
+        Msg::ScrobbleUsage,
+        "Usage:\n  mfp scrobble --test       Validate scrobbling credentials",
+    ),
+    (
+        Msg::DiscordAppIdMissing,
+        "discord_presence needs a discord_app_id in config.toml",
+    ),
+    (
+        Msg::DiscordFeatureMissing,
+        "Discord presence is enabled but mfp was built without the `discord` feature",
+    ),
+    (Msg::NoHistory, "No playback history"),
+    (Msg::NoHistoryEntry, "No history entry #{}"),
+    (Msg::CannotReplay, "Cannot replay: {}"),
+    (Msg::HistoryWhen, "When"),
+    (Msg::HistoryListened, "Listened"),
+    (Msg::HistoryDone, "Done"),
+    (Msg::HistoryEpisode, "Episode"),
+    (Msg::FavAdded, "Added: {}"),
+    (Msg::FavAlreadyAdded, "Already in favorites: {}"),
+    (Msg::FavRemoved, "Removed: {}"),
+    (Msg::FavNotFound, "Not in favorites: {}"),
+    (Msg::NoFavorites, "No favorites saved"),
+    (Msg::FavoritesHeader, "Favorites:"),
+    (Msg::DiskUsage, "Disk usage: {} MB"),
+    (Msg::Location, "Location: {}"),
+    (Msg::NoDownloads, "No downloaded episodes"),
+    (Msg::DownloadedHeader, "Downloaded episodes ({}):"),
+    (
+        Msg::DownloadsHelp,
+        "Offline downloads\n\nUsage:\n  mfp download -e 75        Download episode 75\n  mfp download --list       List downloaded episodes\n  mfp download --size       Show disk usage\n  mfp download --delete \"Episode 75\"  Delete episode",
+    ),
+    (Msg::AlreadyDownloaded, "Episode already downloaded: {}"),
+    (Msg::Downloading, "Downloading: {}"),
+    (Msg::DownloadProgress, "  Progress: {} ({}/{} MB)"),
+    (Msg::DownloadComplete, "Download complete"),
+    (Msg::Deleted, "Deleted: {}"),
+    (Msg::NotDownloaded, "Episode not downloaded"),
+    (Msg::ConfigDirFailed, "Could not find the configuration directory"),
+    (Msg::CreateFileFailed, "Could not create the file"),
+    (Msg::Connecting, "Connecting..."),
+    (Msg::Buffering, " buffering..."),
+    (
+        Msg::AudioDeviceFailed,
+        "Could not initialize the audio device. Check your audio configuration.",
+    ),
+    (Msg::SinkFailed, "Could not create the audio sink"),
+    (Msg::ConnectFailed, "Could not connect to the server"),
+    (Msg::HttpError, "HTTP error: {}"),
+    (Msg::NoDataReceived, "No data received"),
+    (Msg::DecodeFailed, "Could not decode the audio"),
+    (
+        Msg::AvailableCommands,
+        "Available commands:\n  mfp list                    - List all episodes\n  mfp pick                    - Choose an episode interactively\n  mfp play                    - Play from the beginning\n  mfp play -e 75              - Play a specific episode\n  mfp play -s                 - Play in shuffle mode\n  mfp play -f                 - Play favorites only\n  mfp fav -l                  - List favorites\n  mfp fav -a \"Episode XX\"     - Add to favorites\n  mfp fav -r \"Episode XX\"     - Remove from favorites\n  mfp download -e 75          - Download episode for offline\n  mfp download --list         - List downloaded episodes\n\nUse 'mfp play' to start listening",
+    ),
+    (Msg::HelpTransport, "Transport"),
+    (Msg::HelpVolume, "Volume"),
+    (Msg::HelpPlaylist, "Playlist"),
+    (Msg::HelpLibrary, "Library"),
+    (Msg::HelpSession, "Session"),
+    (Msg::HelpPause, "Pause / resume"),
+    (Msg::HelpNext, "Next episode"),
+    (Msg::HelpPrevious, "Previous episode"),
+    (Msg::HelpVolumeUp, "Volume up"),
+    (Msg::HelpVolumeDown, "Volume down"),
+    (Msg::HelpMute, "Mute / unmute"),
+    (Msg::HelpShuffle, "Toggle shuffle"),
+    (Msg::HelpRepeat, "Cycle repeat: off, all, one"),
+    (Msg::HelpFavorite, "Toggle favorite"),
+    (Msg::HelpAddToPlaylist, "Add this episode to a playlist: add deep-focus"),
+    (Msg::AddToPlaylistUsage, "Usage: add <playlist> (see mfp playlist list)"),
+    (Msg::HelpDownload, "Download for offline"),
+    (Msg::HelpInfo, "Episode information"),
+    (Msg::HelpHelp, "Show this help"),
+    (Msg::HelpQuit, "Quit"),
+    (Msg::HelpPressAnyKey, "Press any key to continue..."),
+    (Msg::InvalidClock, "Invalid time {}, expected HH:MM"),
+    (Msg::InvalidDelay, "Invalid duration {}, expected e.g. 25m, 1h30m or 90s"),
+    (
+        Msg::InvalidReadahead,
+        "Invalid readahead {}, expected megabytes (e.g. 20) or unlimited",
+    ),
+    (Msg::StartingIn, "Starting in {} (q to cancel)"),
+    (Msg::ScheduleCancelled, "Scheduled start cancelled"),
+    (Msg::OpeningPage, "Opening {}"),
+    (Msg::NoBrowser, "No browser available, open this link: {}"),
+    (Msg::NoPageUrl, "This episode has no web page"),
+    (Msg::HelpOpen, "Open the episode page in a browser"),
+    (Msg::NothingPlaying, "Nothing is playing"),
+    (Msg::PickerNoMatches, "No matching episodes"),
+    (
+        Msg::PickerHint,
+        "{}/{} | type to filter | ↑/↓ move | Enter play | Ctrl+F favorite | Ctrl+D queue download | Esc exit",
+    ),
+    (
+        Msg::NoQueryMatch,
+        "No episode matches \"{}\". Try mfp pick to search interactively, or mfp list",
+    ),
+    (Msg::SeveralMatches, "Several episodes match \"{}\":"),
+    (Msg::ChooseEpisode, "Choose one [1-{}] (Enter to cancel): "),
+    (Msg::AmbiguousQuery, "Several episodes match; be more specific or use -e"),
+    (
+        Msg::WizardWelcome,
+        "Welcome to mfp! A few questions to set things up (Enter keeps the default).",
+    ),
+    (Msg::WizardDownloadDir, "Download directory [{}] ({} free): "),
+    (Msg::WizardVolume, "Default volume, 0-100 [{}]: "),
+    (Msg::WizardMpris, "Enable media keys and desktop controls (MPRIS)? [Y/n]: "),
+    (Msg::WizardPrefetch, "Fetch the episode list now? [Y/n]: "),
+    (Msg::WizardInvalid, "Invalid answer, keeping the default"),
+    (Msg::WizardSaved, "Settings saved to {}"),
+    (
+        Msg::WizardPaths,
+        "Downloads: {}\nSettings and favorites: {}\nHistory: {}\nFeed cache: {}",
+    ),
+    (Msg::WizardRerun, "Run mfp config --init to change these answers later"),
+    (Msg::ConfigFile, "Config file: {}"),
+    (Msg::ConfigMissing, "No config file yet; run mfp config --init to create one"),
+    (Msg::DownloadResuming, "Resuming at {} MB"),
+    (Msg::DownloadIncomplete, "Download incomplete: {} of {} bytes received"),
+    (Msg::FeedFetchFailed, "Could not fetch the RSS feed"),
+    (Msg::FeedParseFailed, "Could not parse the RSS feed"),
+    (Msg::DownloadInterrupted, "Download interrupted after {} bytes"),
+    (Msg::StorageReadFailed, "Could not read {}"),
+    (Msg::StorageWriteFailed, "Could not write {}"),
+    (Msg::StorageParseFailed, "Could not parse {}"),
+    (Msg::DataMigrated, "Moved {} data file(s) from {} to {}"),
+    (Msg::HelpYank, "Copy the audio URL to the clipboard"),
+    (Msg::HelpYankPage, "Copy the episode page URL to the clipboard"),
+    (Msg::Copied, "Copied to clipboard: {}"),
+    (Msg::NoClipboard, "No clipboard available, copy this link: {}"),
+    (Msg::InfoPublished, "Published: {}"),
+    (Msg::InfoAudioUrl, "Audio: {}"),
+    (Msg::InfoPage, "Page: {}"),
+    (Msg::HelpNote, "Note something about this moment (note <text>)"),
+    (Msg::NotePrompt, "Note at {}: "),
+    (Msg::NoteSaved, "Noted at {}"),
+    (Msg::NoteEmpty, "Nothing noted"),
+    (Msg::NoteFailed, "Could not save the note: {}"),
+    (Msg::NoJournal, "No notes yet. Type note <text> while playing to add one"),
+    (Msg::NoJournalForEpisode, "No notes for episode {}"),
+    (Msg::JournalExported, "Exported {} notes to {}"),
+    (Msg::StdinEmpty, "Nothing on stdin: pipe an episode number, a title or a JSON object"),
+    (Msg::StdinUnreadable, "Could not read stdin: {}"),
+    (Msg::StdinSeveralLines, "stdin has {} lines; pipe exactly one episode"),
+    (Msg::StdinBadJson, "Invalid JSON on stdin: {}"),
+    (Msg::StdinSeveralValues, "stdin holds several JSON values; pipe exactly one episode"),
+    (Msg::StdinFieldType, "Unusable \"{}\" field on stdin: {}"),
+    (
+        Msg::StdinMissingField,
+        "The JSON object on stdin has neither a \"number\" nor a \"title\" field",
+    ),
+    (Msg::StdinArray, "stdin holds a JSON array; pipe a single episode (e.g. jq '.[0]')"),
+    (Msg::StdinUnexpectedJson, "Expected an episode number, title or object on stdin, found {}"),
+    (Msg::NoTtyControls, "No terminal for controls: playing this episode to the end"),
+    (Msg::SeekUnavailable, "Can't seek to {} before playback has started"),
+    (Msg::BackupExported, "Backed up {} to {}"),
+    (Msg::BackupRestored, "Restored {} from {}"),
+    (Msg::BackupMerged, "Merged {}: {} new favorites, {} playlist entries, {} history entries, {} notes, {} settings"),
+    (Msg::BackupEmpty, "The backup holds no state files"),
+    (
+        Msg::BackupMissingDownloads,
+        "{} episodes were downloaded when the backup was made and are not here; mfp download -e N gets them again:",
+    ),
+    (
+        Msg::BackupReplaceConfirm,
+        "Replace your settings, favorites, history and journal with the backup? [y/N]: ",
+    ),
+    (
+        Msg::BackupReplaceNeedsYes,
+        "--replace overwrites your current state; pass --yes to confirm without a terminal",
+    ),
+    (Msg::BackupReplaceCancelled, "Nothing was changed"),
+    (Msg::BackupNotAnArchive, "{} is not an mfp backup"),
+    (
+        Msg::BackupUnsupportedVersion,
+        "The backup uses format {}, but this mfp reads up to format {}; update mfp to restore it",
+    ),
+    (Msg::BackupDamaged, "The backup entry {} is damaged"),
+    (Msg::ServerSilent, "The server sent nothing for {} seconds"),
+    (Msg::RequestCancelled, "Cancelled"),
+    (Msg::MenuContinue, "Continue {} (left at {})"),
+    (Msg::MenuContinueQueued, "Continue {} (left at {}), then {} queued"),
+    (Msg::MenuContinueQueue, "Continue with the {} episodes queued"),
+    (Msg::MenuLatest, "Play latest: {}"),
+    (Msg::MenuLatestUnknown, "Play latest"),
+    (Msg::MenuBrowse, "Browse episodes"),
+    (Msg::MenuFavorites, "Play favorites shuffled ({})"),
+    (Msg::MenuDownloads, "Downloads ({})"),
+    (Msg::MenuQuit, "Quit"),
+    (Msg::MenuPrompt, "Choose an option: "),
+    (Msg::HelpFull, "Play the previewed episode in full"),
+    (Msg::PreviewTag, "PREVIEW"),
+    (Msg::PreviewControls, "Preview: {} seconds per episode | Enter/full: keep listening"),
+    (Msg::PreviewFull, "Playing the full episode"),
+    (Msg::PreviewNotActive, "Not previewing: this episode already plays in full"),
+    (Msg::UnknownStation, "No station named \"{}\" (see mfp station list)"),
+    (
+        Msg::InvalidStationName,
+        "Invalid station name \"{}\": use lowercase letters, digits, - and _",
+    ),
+    (Msg::StationExists, "Station \"{}\" already exists"),
+    (Msg::CheckingFeed, "Checking feed..."),
+    (Msg::StationFeedEmpty, "No playable episodes in {}"),
+    (Msg::StationAdded, "Added station {} ({} episodes)"),
+    (Msg::StationDefault, "(default)"),
+    (Msg::StationRemoved, "Removed station {}"),
+    (Msg::UnknownPlaylist, "No playlist named \"{}\" (see mfp playlist list)"),
+    (
+        Msg::InvalidPlaylistName,
+        "Invalid playlist name \"{}\": use lowercase letters, digits, - and _",
+    ),
+    (Msg::PlaylistExists, "Playlist \"{}\" already exists"),
+    (Msg::PlaylistCreated, "Created playlist {}"),
+    (Msg::PlaylistAdded, "Added {} to {}"),
+    (Msg::PlaylistAlreadyHas, "{} already has {}"),
+    (Msg::PlaylistRemoved, "Removed {} from {}"),
+    (Msg::NotInPlaylist, "{} is not in {}"),
+    (Msg::PlaylistDeleted, "Deleted playlist {}"),
+    (
+        Msg::NoPlaylists,
+        "No playlists yet; start one with mfp playlist create <name>",
+    ),
+    (Msg::PlaylistSize, "{} episodes"),
+    (
+        Msg::PlaylistEmpty,
+        "{} is empty; add episodes with mfp playlist add {} <number>",
+    ),
+    (Msg::PlaylistMissingTag, "(no longer in the feed)"),
+    (
+        Msg::PlaylistEpisodeMissing,
+        "\"{}\" is no longer in the feed; skipping it",
+    ),
+    (Msg::PlaylistNothingToPlay, "Nothing in {} is in the feed to play"),
+    (
+        Msg::FilterNoMatch,
+        "No episode mentions \"{}\" ({} scanned)",
+    ),
+    (Msg::EpisodeTitleNotFound, "No episode titled \"{}\""),
+    (
+        Msg::CastFeatureMissing,
+        "Casting needs a build with the cast feature (cargo install mfp --features cast)",
+    ),
+    (Msg::CastSearching, "Looking for \"{}\" on the network..."),
+    (Msg::CastSearchingAll, "Looking for Chromecasts on the network..."),
+    (Msg::CastConnected, "Playing on {}"),
+    (Msg::CastConnectFailed, "Could not connect to {}"),
+    (Msg::CastDeviceNotFound, "No Chromecast named \"{}\" answered (see mfp cast --list)"),
+    (Msg::CastNoDevices, "No Chromecasts found"),
+    (Msg::CastHint, "Use mfp cast --list to find devices, then mfp play --cast <DEVICE>"),
+    (Msg::CastLost, "Lost the connection to {}"),
+    (Msg::CastContinueHere, "Continue on this computer? [Y/n] "),
+    (
+        Msg::AudioFeatureMissing,
+        "mfp was built without the `audio` feature",
+    ),
+    (
+        Msg::NoAudioOutput,
+        "{} Printing the stream URL for another player:",
+    ),
+    (
+        Msg::MediaKeysFeatureMissing,
+        "media_keys is enabled but mfp was built without the `media-keys` feature",
+    ),
+    (Msg::MediaKeysFailed, "Media keys unavailable: {}"),
+    (
+        Msg::StreamStalled,
+        "Stream stalled ({} reconnects) — [r]etry, [d]ownload & play offline, [n]ext episode, [q]uit: ",
+    ),
+    (Msg::StreamStalledStopping, "Stream stalled ({} reconnects), stopping"),
+    (Msg::StallRetrying, "Reconnecting..."),
+    (
+        Msg::OfflineDownloadStarted,
+        "Downloading in the background; playback continues from {} once it is done",
+    ),
+    (Msg::OfflinePlaying, "Playing the downloaded copy from {}"),
+    (Msg::OfflineDownloadFailed, "Offline download failed: {}"),
+    (Msg::HelpVolumeSave, "Keep this volume for this episode (volsave clear forgets it)"),
+    (Msg::VolumeSaved, "Volume offset {}% saved for this episode"),
+    (Msg::VolumeSaveCleared, "Saved volume for this episode forgotten"),
+    (Msg::VolumeSaveNone, "No volume saved for this episode"),
+    (Msg::VolumeSaveUsage, "Usage: volsave [clear]"),
+    (Msg::VolumeSaveFailed, "Could not save the volume: {}"),
+    (Msg::InfoVolumeSaved, " (vol {}% saved)"),
+    (Msg::HelpRestart, "Start the current episode over"),
+    (Msg::Restarting, "Starting over"),
+    (Msg::HelpList, "Browse the playlist and pick an episode"),
+    (
+        Msg::HelpQueue,
+        "Play episode N after this one: q 19; queue alone lists what's queued",
+    ),
+    (Msg::QueueUsage, "Usage: q 19 (or queue 19); queue alone lists the queue"),
+    (Msg::Queued, "Queued: {} ({} waiting)"),
+    (Msg::QueuedAgain, "Queued again: {} was already waiting ({} waiting)"),
+    (
+        Msg::QueueOutsidePlaylist,
+        "Episode {} isn't in this playlist, so it can't be queued",
+    ),
+    (Msg::QueueEmpty, "Nothing queued"),
+    (Msg::QueueHeader, "Up next:"),
+    (Msg::QueueAgainTag, "(again)"),
+    (Msg::QueueRestored, "Queued from last time: {} waiting"),
+    (
+        Msg::QueueEntryDropped,
+        "{} was queued last time but isn't available any more; left out of the queue",
+    ),
+    (Msg::BrowserPage, "Playlist, page {} of {}"),
+    (Msg::BrowserHint, "n/p: next/previous page, number + Enter: play it, q: back > "),
+    (Msg::BrowserInvalid, "No episode {} in the playlist"),
+    (Msg::ProgressEnds, "ends {}"),
+    (Msg::ProgressEndsAll, "ends {}, all {}"),
+    (Msg::HelpUndo, "Undo the last favorites change"),
+    (Msg::HelpRedo, "Redo an undone favorites change"),
+    (Msg::FavUndone, "Undone: {}"),
+    (Msg::FavRedone, "Redone: {}"),
+    (Msg::FavIsFavorite, "{} is a favorite"),
+    (Msg::FavIsNotFavorite, "{} is no longer a favorite"),
+    (Msg::NothingToUndo, "No favorites change to undo"),
+    (Msg::NothingToRedo, "No undone favorites change to redo"),
+    (Msg::FavUndoFailed, "Could not save favorites: {}"),
+    (
+        Msg::InstanceRunning,
+        "mfp is already running (pid {}). Quit it first, or pass --takeover to stop it and continue here",
+    ),
+    (Msg::InstanceTakenOver, "Another mfp (pid {}) took over; stopping here"),
+    (Msg::UnknownFormatToken, "Unknown token {{}} in the format; valid tokens: {}"),
+    (Msg::HelpAliases, "Aliases"),
+    (Msg::HelpSeek, "Jump to a position: seek 12:30, seek +1:00, seek -30"),
+    (Msg::HelpSeekForward, "Forward 10 seconds (seek_step_secs)"),
+    (Msg::HelpSeekBack, "Back 10 seconds (seek_step_secs)"),
+    (Msg::HelpSeekForwardLong, "Forward a minute (seek_long_step_secs)"),
+    (Msg::HelpSeekBackLong, "Back a minute (seek_long_step_secs)"),
+    (Msg::SeekUsage, "Usage: seek 12:30, seek +1:00 or seek -30"),
+    (Msg::SeekBuffering, "Buffering up to {}…"),
+    (
+        Msg::HelpGoto,
+        "Jump to a share of the episode: goto 50, or just 50% (goto #19 is episode 19)",
+    ),
+    (Msg::HelpEpisode, "Play episode number N: g 19, or goto #19"),
+    (Msg::EpisodeUsage, "Usage: g 19 (or ep 19, goto #19)"),
+    (
+        Msg::EpisodeOutsidePlaylist,
+        "Episode {} isn't in this playlist; playing on through the whole feed",
+    ),
+    (Msg::GotoLanded, "→ {}"),
+    (Msg::SkippedIntro, "(skipped {} intro)"),
+    (Msg::ResumedAt, "(continuing at {})"),
+    (Msg::GotoNoDuration, "This episode's length is unknown, so there is no percentage to go to"),
+    (Msg::GotoEpisodeHint, "goto takes a percentage; for episode {}, type goto #{}"),
+    (Msg::InfoSource, "Source: {}"),
+    (Msg::SourceLocal, "local"),
+    (Msg::SourceStreaming, "streaming"),
+    (Msg::FeedFromCache, "Can't reach the feed ({}); using the episodes saved last time"),
+    (Msg::FeedFromDownload, "Can't reach the feed ({}); playing the downloaded copy"),
+    (Msg::HelpRecord, "Save the stream to the downloads as it plays"),
+    (Msg::Recording, "Recording to {}; it's kept once the whole episode has arrived"),
+    (Msg::RecordUnavailable, "Only a stream playing on this computer can be recorded"),
+    (Msg::ReconnectAttempt, "reconnecting… attempt {}/{}"),
+    (Msg::ServerNoAnswer, "The server didn't respond in time"),
+    (Msg::TooManyRedirects, "The server redirected too many times"),
+    (Msg::StreamFailed, "Can't play this episode: {} — [r]etry, [n]ext episode, [q]uit: "),
+    (
+        Msg::HelpSetVolume,
+        "Set the volume in percent: volume 60; volume reset goes back to the config's",
+    ),
+    (Msg::VolumeUsage, "Usage: volume 60 (0 to 200 percent), or volume reset"),
+    (Msg::VolumeReset, "{} (the configured volume; the last one chosen is forgotten)"),
+    (Msg::HelpSleep, "Stop playback after a while: sleep 45 (minutes), sleep off"),
+    (Msg::SleepUsage, "Usage: sleep 45 (minutes), sleep 1h30m or sleep off"),
+    (Msg::SleepSet, "Sleep timer: stopping in {}"),
+    (Msg::SleepOff, "Sleep timer off"),
+    (Msg::SleepStopping, "Sleep timer done, stopping"),
+    (Msg::InfoSleep, "Sleep timer: {} left"),
+    (Msg::HelpStopAfter, "Stop once this episode ends (again to cancel)"),
+    (Msg::StopAfterOn, "Stopping after this episode"),
+    (Msg::StopAfterOff, "Playing on after this episode"),
+    (Msg::StoppedAfterEpisode, "Stopped after episode"),
+    (Msg::HelpLoop, "A-B loop: mark the start, then the end; a third time clears it"),
+    (Msg::LoopStart, "Loop from {} (a again marks the end)"),
+    (Msg::LoopSet, "Looping {} - {}"),
+    (Msg::LoopCleared, "Loop cleared"),
+    (Msg::LoopTooShort, "The loop must end somewhere other than {}"),
+    (Msg::HelpDevice, "Play on the default audio device again (e.g. after plugging in headphones)"),
+    (Msg::OutputLost, "The audio device is gone, switching to the default one…"),
+    (Msg::OutputReopened, "Playing on the default audio device"),
+    (Msg::OutputFailed, "Can't play on the audio device: {} — [r]etry, [q]uit: "),
+    (Msg::HelpBalanceRight, "Move the balance toward the right speaker"),
+    (Msg::HelpBalanceLeft, "Move the balance toward the left speaker"),
+    (Msg::HelpBalanceCenter, "Center the balance"),
+    (Msg::InfoBalance, "Balance: {}"),
+    (Msg::BalanceCentered, "centered"),
+    (Msg::InfoStream, "Audio: {}"),
+    (Msg::InfoSkippedSilence, "Skipped {}s of silence at the start"),
+    (Msg::StreamUnknown, "unknown"),
+    (Msg::StreamMono, "mono"),
+    (Msg::StreamStereo, "stereo"),
+    (Msg::StreamChannels, "{} channels"),
+    (Msg::BufferFull, "full"),
+    (Msg::BufferHeld, "max"),
+    (Msg::Rebuffering, "buffering… {}s"),
+];
+
+const ES: &[(Msg, &str)] = &[
+    (Msg::FetchingEpisodes, "Obteniendo episodios..."),
+    (Msg::FetchingEpisode, "Obteniendo episodio..."),
+    (Msg::LoadingFeed, "Cargando feed..."),
+    (Msg::NoEpisodesAvailable, "No hay episodios disponibles"),
+    (Msg::EpisodeNotFound, "Episodio {} no encontrado"),
+    (
+        Msg::NoFavoritesHint,
+        "No tienes favoritos guardados. Usa 'mfp fav --add \"Episode XX: Title\"'",
+    ),
+    (
+        Msg::DurationShuffleRepeat,
+        "Duración: {} | Aleatorio: {} | Repetir: {}",
+    ),
+    (Msg::Controls, "Controles:"),
+    (
+        Msg::ControlsTransport,
+        "  [n]ext | [b]ack | [p]ausa | [s]huffle | [f]avorito | [q]uit",
+    ),
+    (
+        Msg::ControlsOther,
+        "  [+/-] volumen | [m]ute | [i]nfo | [d]escargar | [h] ayuda",
+    ),
+    (Msg::Playing, "Reproduciendo"),
+    (Msg::Paused, "En pausa"),
+    (Msg::Volume, "Volumen: {}%"),
+    (Msg::Muted, "Silenciado"),
+    (Msg::InfoEpisode, "Episodio: {}"),
+    (Msg::InfoDuration, "Duración: {}"),
+    (Msg::InfoStatus, "Estado: {}"),
+    (Msg::InfoShuffle, "Aleatorio: {}"),
+    (Msg::InfoRepeat, "Repetir: {}"),
+    (Msg::RepeatOne, "UNO"),
+    (Msg::RepeatAll, "TODO"),
+    (Msg::PlaylistEnded, "Fin de la lista (repetir está desactivado)"),
+    (Msg::ProgressRepeat, "repetir {}"),
+    (Msg::InfoFavorite, "Favorito: {}"),
+    (Msg::On, "SÍ"),
+    (Msg::Off, "NO"),
+    (Msg::Yes, "Sí"),
+    (Msg::No, "No"),
+    (Msg::AddedToFavorites, "Agregado a favoritos"),
+    (Msg::RemovedFromFavorites, "Eliminado de favoritos"),
+    (
+        Msg::DownloadingForOffline,
+        "Descargando el episodio para offline en segundo plano...",
+    ),
+    (Msg::EpisodeDownloaded, "Descargado {}"),
+    (Msg::BackgroundDownloading, "descargando {}%"),
+    (Msg::BackgroundDownloadFailed, "Falló la descarga de {}: {}"),
+    (Msg::DownloadAlreadyRunning, "Ya se está descargando {}"),
+    (
+        Msg::DownloadStoppedOnQuit,
+        "Descarga de {} detenida; la próxima vez sigue donde quedó",
+    ),
+    (Msg::ErrorLabel, "Error:"),
+    (Msg::UnknownCommand, "Comando desconocido"),
+    (Msg::CommandsHint, "Escribe h o ? para ver todos los comandos"),
+    (Msg::MprisUpdateFailed, "No se pudo actualizar MPRIS: {}"),
+    (
+        Msg::ScrobbleFeatureMissing,
+        "El scrobbling está configurado pero mfp se compiló sin la feature `scrobble`",
+    ),
+    (Msg::ScrobbleNotConfigured, "El scrobbling no está configurado"),
+    (Msg::ScrobbleAddSection, "Agrega una sección [scrobble] a {}"),
+    (Msg::ScrobbleCredentialsOk, "Credenciales correctas (usuario: {})"),
+    (Msg::ScrobbleQueueSent, "Se enviaron {} scrobbles pendientes"),
+    (
+        Msg::ScrobbleUsage,
+        "Uso:\n  mfp scrobble --test       Valida las credenciales de scrobbling",
+    ),
+    (
+        Msg::DiscordAppIdMissing,
+        "discord_presence necesita un discord_app_id en config.toml",
+    ),
+    (
+        Msg::DiscordFeatureMissing,
+        "Discord está activado pero mfp se compiló sin la feature `discord`",
+    ),
+    (Msg::NoHistory, "Sin historial de reproducción"),
+    (Msg::NoHistoryEntry, "No existe la entrada #{} del historial"),
+    (Msg::CannotReplay, "No se puede reproducir: {}"),
+    (Msg::HistoryWhen, "Cuándo"),
+    (Msg::HistoryListened, "Escuchado"),
+    (Msg::HistoryDone, "Fin"),
+    (Msg::HistoryEpisode, "Episodio"),
+    (Msg::FavAdded, "Agregado: {}"),
+    (Msg::FavAlreadyAdded, "Ya está en favoritos: {}"),
+    (Msg::FavRemoved, "Eliminado: {}"),
+    (Msg::FavNotFound, "No está en favoritos: {}"),
+    (Msg::NoFavorites, "No hay favoritos guardados"),
+    (Msg::FavoritesHeader, "Favoritos:"),
+    (Msg::DiskUsage, "Espacio usado: {} MB"),
+    (Msg::Location, "Ubicación: {}"),
+    (Msg::NoDownloads, "No hay episodios descargados"),
+    (Msg::DownloadedHeader, "Episodios descargados ({}):"),
+    (
+        Msg::DownloadsHelp,
+        "Gestión de descargas offline\n\nUso:\n  mfp download -e 75        Descargar episodio 75\n  mfp download --list       Listar descargados\n  mfp download --size       Mostrar espacio usado\n  mfp download --delete \"Episode 75\"  Eliminar episodio",
+    ),
+    (Msg::AlreadyDownloaded, "Episodio ya descargado: {}"),
+    (Msg::Downloading, "Descargando: {}"),
+    (Msg::DownloadProgress, "  Progreso: {} ({}/{} MB)"),
+    (Msg::DownloadComplete, "Descarga completa"),
+    (Msg::Deleted, "Eliminado: {}"),
+    (Msg::NotDownloaded, "Episodio no descargado"),
+    (Msg::ConfigDirFailed, "No se pudo obtener el directorio de configuración"),
+    (Msg::CreateFileFailed, "No se pudo crear el archivo"),
+    (Msg::Connecting, "Conectando..."),
+    (Msg::Buffering, " cargando buffer..."),
+    (
+        Msg::AudioDeviceFailed,
+        "No se pudo inicializar el dispositivo de audio. Verifica tu configuración de audio.",
+    ),
+    (Msg::SinkFailed, "No se pudo crear el sink de audio"),
+    (Msg::ConnectFailed, "No se pudo conectar al servidor"),
+    (Msg::HttpError, "Error HTTP: {}"),
+    (Msg::NoDataReceived, "No se recibieron datos"),
+    (Msg::DecodeFailed, "No se pudo decodificar el audio"),
+    (
+        Msg::AvailableCommands,
+        "Comandos disponibles:\n  mfp list                    - Lista todos los episodios\n  mfp pick                    - Elige un episodio de forma interactiva\n  mfp play                    - Reproduce desde el inicio\n  mfp play -e 75              - Reproduce episodio específico\n  mfp play -s                 - Reproduce en modo shuffle\n  mfp play -f                 - Reproduce solo favoritos\n  mfp fav -l                  - Lista favoritos\n  mfp fav -a \"Episode XX\"     - Agrega a favoritos\n  mfp fav -r \"Episode XX\"     - Remueve de favoritos\n  mfp download -e 75          - Descarga episodio para offline\n  mfp download --list         - Lista episodios descargados\n\nUsa 'mfp play' para comenzar a escuchar",
+    ),
+    (Msg::HelpTransport, "Reproducción"),
+    (Msg::HelpVolume, "Volumen"),
+    (Msg::HelpPlaylist, "Lista"),
+    (Msg::HelpLibrary, "Biblioteca"),
+    (Msg::HelpSession, "Sesión"),
+    (Msg::HelpPause, "Pausar / continuar"),
+    (Msg::HelpNext, "Siguiente episodio"),
+    (Msg::HelpPrevious, "Episodio anterior"),
+    (Msg::HelpVolumeUp, "Subir volumen"),
+    (Msg::HelpVolumeDown, "Bajar volumen"),
+    (Msg::HelpMute, "Silenciar / restaurar"),
+    (Msg::HelpShuffle, "Activar/desactivar aleatorio"),
+    (Msg::HelpRepeat, "Cambiar repetición: no, todo, uno"),
+    (Msg::HelpFavorite, "Marcar/desmarcar favorito"),
+    (Msg::HelpAddToPlaylist, "Añadir este episodio a una lista: add deep-focus"),
+    (Msg::AddToPlaylistUsage, "Uso: add <lista> (ver mfp playlist list)"),
+    (Msg::HelpDownload, "Descargar para offline"),
+    (Msg::HelpInfo, "Información del episodio"),
+    (Msg::HelpHelp, "Mostrar esta ayuda"),
+    (Msg::HelpQuit, "Salir"),
+    (Msg::HelpPressAnyKey, "Presiona cualquier tecla para continuar..."),
+    (Msg::InvalidClock, "Hora no válida {}, se esperaba HH:MM"),
+    (Msg::InvalidDelay, "Duración no válida {}, se esperaba p. ej. 25m, 1h30m o 90s"),
+    (
+        Msg::InvalidReadahead,
+        "Descarga anticipada no válida {}, se esperaban megabytes (p. ej. 20) o unlimited",
+    ),
+    (Msg::StartingIn, "Empieza en {} (q para cancelar)"),
+    (Msg::ScheduleCancelled, "Inicio programado cancelado"),
+    (Msg::OpeningPage, "Abriendo {}"),
+    (Msg::NoBrowser, "No hay navegador disponible, abre este enlace: {}"),
+    (Msg::NoPageUrl, "Este episodio no tiene página web"),
+    (Msg::HelpOpen, "Abrir la página del episodio en el navegador"),
+    (Msg::NothingPlaying, "No se está reproduciendo nada"),
+    (Msg::PickerNoMatches, "Ningún episodio coincide"),
+    (
+        Msg::PickerHint,
+        "{}/{} | escribe para filtrar | ↑/↓ mover | Enter reproducir | Ctrl+F favorito | Ctrl+D descargar después | Esc salir",
+    ),
+    (
+        Msg::NoQueryMatch,
+        "Ningún episodio coincide con \"{}\". Prueba mfp pick para buscar de forma interactiva, o mfp list",
+    ),
+    (Msg::SeveralMatches, "Varios episodios coinciden con \"{}\":"),
+    (Msg::ChooseEpisode, "Elige uno [1-{}] (Enter para cancelar): "),
+    (Msg::AmbiguousQuery, "Varios episodios coinciden; sé más específico o usa -e"),
+    (
+        Msg::WizardWelcome,
+        "¡Bienvenido a mfp! Unas preguntas para configurarlo (Enter mantiene el valor por defecto).",
+    ),
+    (Msg::WizardDownloadDir, "Directorio de descargas [{}] ({} libres): "),
+    (Msg::WizardVolume, "Volumen por defecto, 0-100 [{}]: "),
+    (Msg::WizardMpris, "¿Activar teclas multimedia y controles del escritorio (MPRIS)? [S/n]: "),
+    (Msg::WizardPrefetch, "¿Descargar la lista de episodios ahora? [S/n]: "),
+    (Msg::WizardInvalid, "Respuesta no válida, se mantiene el valor por defecto"),
+    (Msg::WizardSaved, "Configuración guardada en {}"),
+    (
+        Msg::WizardPaths,
+        "Descargas: {}\nAjustes y favoritos: {}\nHistorial: {}\nCaché del feed: {}",
+    ),
+    (Msg::WizardRerun, "Ejecuta mfp config --init para cambiar estas respuestas más adelante"),
+    (Msg::ConfigFile, "Archivo de configuración: {}"),
+    (
+        Msg::ConfigMissing,
+        "Aún no hay archivo de configuración; ejecuta mfp config --init para crearlo",
+    ),
+    (Msg::DownloadResuming, "Reanudando en {} MB"),
+    (Msg::DownloadIncomplete, "Descarga incompleta: {} de {} bytes recibidos"),
+    (Msg::FeedFetchFailed, "No se pudo obtener el feed RSS"),
+    (Msg::FeedParseFailed, "No se pudo interpretar el feed RSS"),
+    (Msg::DownloadInterrupted, "Descarga interrumpida tras {} bytes"),
+    (Msg::StorageReadFailed, "No se pudo leer {}"),
+    (Msg::StorageWriteFailed, "No se pudo escribir {}"),
+    (Msg::StorageParseFailed, "No se pudo interpretar {}"),
+    (Msg::DataMigrated, "Movidos {} archivo(s) de datos de {} a {}"),
+    (Msg::HelpYank, "Copiar la URL del audio al portapapeles"),
+    (Msg::HelpYankPage, "Copiar la URL de la página del episodio al portapapeles"),
+    (Msg::Copied, "Copiado al portapapeles: {}"),
+    (Msg::NoClipboard, "No hay portapapeles disponible, copia este enlace: {}"),
+    (Msg::InfoPublished, "Publicado: {}"),
+    (Msg::InfoAudioUrl, "Audio: {}"),
+    (Msg::InfoPage, "Página: {}"),
+    (Msg::HelpNote, "Anotar algo sobre este momento (note <texto>)"),
+    (Msg::NotePrompt, "Nota en {}: "),
+    (Msg::NoteSaved, "Anotado en {}"),
+    (Msg::NoteEmpty, "No se anotó nada"),
+    (Msg::NoteFailed, "No se pudo guardar la nota: {}"),
+    (Msg::NoJournal, "Aún no hay notas. Escribe note <texto> mientras suena un episodio"),
+    (Msg::NoJournalForEpisode, "No hay notas para el episodio {}"),
+    (Msg::JournalExported, "{} notas exportadas a {}"),
+    (
+        Msg::StdinEmpty,
+        "No hay nada en stdin: pasa un número de episodio, un título o un objeto JSON",
+    ),
+    (Msg::StdinUnreadable, "No se pudo leer stdin: {}"),
+    (Msg::StdinSeveralLines, "stdin tiene {} líneas; pasa exactamente un episodio"),
+    (Msg::StdinBadJson, "JSON no válido en stdin: {}"),
+    (Msg::StdinSeveralValues, "stdin contiene varios valores JSON; pasa exactamente un episodio"),
+    (Msg::StdinFieldType, "Campo \"{}\" no válido en stdin: {}"),
+    (Msg::StdinMissingField, "El objeto JSON de stdin no tiene campo \"number\" ni \"title\""),
+    (Msg::StdinArray, "stdin contiene un array JSON; pasa un solo episodio (p. ej. jq '.[0]')"),
+    (
+        Msg::StdinUnexpectedJson,
+        "Se esperaba un número de episodio, título u objeto en stdin, se encontró {}",
+    ),
+    (
+        Msg::NoTtyControls,
+        "Sin terminal para los controles: se reproduce este episodio hasta el final",
+    ),
+    (Msg::SeekUnavailable, "No se puede saltar a {} antes de que empiece la reproducción"),
+    (Msg::BackupExported, "Copia de seguridad de {} guardada en {}"),
+    (Msg::BackupRestored, "{} restaurado desde {}"),
+    (
+        Msg::BackupMerged,
+        "Combinado {}: {} favoritos nuevos, {} entradas de listas, {} entradas de historial, {} notas, {} ajustes",
+    ),
+    (Msg::BackupEmpty, "La copia de seguridad no contiene archivos de estado"),
+    (
+        Msg::BackupMissingDownloads,
+        "{} episodios estaban descargados al hacer la copia y aquí no están; mfp download -e N los vuelve a descargar:",
+    ),
+    (
+        Msg::BackupReplaceConfirm,
+        "¿Reemplazar tus ajustes, favoritos, historial y diario por la copia de seguridad? [s/N]: ",
+    ),
+    (
+        Msg::BackupReplaceNeedsYes,
+        "--replace sobrescribe tu estado actual; usa --yes para confirmar sin terminal",
+    ),
+    (Msg::BackupReplaceCancelled, "No se ha cambiado nada"),
+    (Msg::BackupNotAnArchive, "{} no es una copia de seguridad de mfp"),
+    (
+        Msg::BackupUnsupportedVersion,
+        "La copia usa el formato {}, pero este mfp lee hasta el formato {}; actualiza mfp para restaurarla",
+    ),
+    (Msg::BackupDamaged, "La entrada {} de la copia de seguridad está dañada"),
+    (Msg::ServerSilent, "El servidor no ha enviado nada en {} segundos"),
+    (Msg::RequestCancelled, "Cancelado"),
+    (Msg::MenuContinue, "Continuar {} (en {})"),
+    (Msg::MenuContinueQueued, "Continuar {} (en {}), y luego {} en cola"),
+    (Msg::MenuContinueQueue, "Continuar con los {} episodios en cola"),
+    (Msg::MenuLatest, "Reproducir el último: {}"),
+    (Msg::MenuLatestUnknown, "Reproducir el último"),
+    (Msg::MenuBrowse, "Explorar episodios"),
+    (Msg::MenuFavorites, "Reproducir favoritos en aleatorio ({})"),
+    (Msg::MenuDownloads, "Descargas ({})"),
+    (Msg::MenuQuit, "Salir"),
+    (Msg::MenuPrompt, "Elige una opción: "),
+    (Msg::HelpFull, "Reproducir entero el episodio en vista previa"),
+    (Msg::PreviewTag, "VISTA PREVIA"),
+    (
+        Msg::PreviewControls,
+        "Vista previa: {} segundos por episodio | Enter/full: seguir escuchando",
+    ),
+    (Msg::PreviewFull, "Reproduciendo el episodio entero"),
+    (Msg::PreviewNotActive, "Sin vista previa: este episodio ya se reproduce entero"),
+    (Msg::UnknownStation, "No hay ninguna emisora llamada \"{}\" (ver mfp station list)"),
+    (Msg::InvalidStationName, "Nombre de emisora no válido \"{}\": usa minúsculas, dígitos, - y _"),
+    (Msg::StationExists, "La emisora \"{}\" ya existe"),
+    (Msg::CheckingFeed, "Comprobando el feed..."),
+    (Msg::StationFeedEmpty, "No hay episodios reproducibles en {}"),
+    (Msg::StationAdded, "Emisora {} añadida ({} episodios)"),
+    (Msg::StationDefault, "(predeterminada)"),
+    (Msg::StationRemoved, "Emisora {} eliminada"),
+    (Msg::UnknownPlaylist, "No hay ninguna lista llamada \"{}\" (ver mfp playlist list)"),
+    (
+        Msg::InvalidPlaylistName,
+        "Nombre de lista no válido \"{}\": usa minúsculas, dígitos, - y _",
+    ),
+    (Msg::PlaylistExists, "La lista \"{}\" ya existe"),
+    (Msg::PlaylistCreated, "Lista {} creada"),
+    (Msg::PlaylistAdded, "{} añadido a {}"),
+    (Msg::PlaylistAlreadyHas, "{} ya tiene {}"),
+    (Msg::PlaylistRemoved, "{} quitado de {}"),
+    (Msg::NotInPlaylist, "{} no está en {}"),
+    (Msg::PlaylistDeleted, "Lista {} eliminada"),
+    (
+        Msg::NoPlaylists,
+        "Todavía no hay listas; crea una con mfp playlist create <nombre>",
+    ),
+    (Msg::PlaylistSize, "{} episodios"),
+    (
+        Msg::PlaylistEmpty,
+        "{} está vacía; añade episodios con mfp playlist add {} <número>",
+    ),
+    (Msg::PlaylistMissingTag, "(ya no está en el feed)"),
+    (
+        Msg::PlaylistEpisodeMissing,
+        "\"{}\" ya no está en el feed; se salta",
+    ),
+    (Msg::PlaylistNothingToPlay, "Nada de {} está en el feed para reproducir"),
+    (
+        Msg::FilterNoMatch,
+        "Ningún episodio menciona \"{}\" ({} revisados)",
+    ),
+    (Msg::EpisodeTitleNotFound, "Ningún episodio se titula \"{}\""),
+    (
+        Msg::CastFeatureMissing,
+        "Para enviar a otro dispositivo hace falta compilar con la función cast (cargo install mfp --features cast)",
+    ),
+    (Msg::CastSearching, "Buscando \"{}\" en la red..."),
+    (Msg::CastSearchingAll, "Buscando Chromecasts en la red..."),
+    (Msg::CastConnected, "Reproduciendo en {}"),
+    (Msg::CastConnectFailed, "No se pudo conectar a {}"),
+    (
+        Msg::CastDeviceNotFound,
+        "Ningún Chromecast llamado \"{}\" ha respondido (ver mfp cast --list)",
+    ),
+    (Msg::CastNoDevices, "No se encontraron Chromecasts"),
+    (
+        Msg::CastHint,
+        "Usa mfp cast --list para buscar dispositivos y luego mfp play --cast <DISPOSITIVO>",
+    ),
+    (Msg::CastLost, "Se perdió la conexión con {}"),
+    (Msg::CastContinueHere, "¿Continuar en este ordenador? [S/n] "),
+    (
+        Msg::AudioFeatureMissing,
+        "mfp se compiló sin la feature `audio`",
+    ),
+    (
+        Msg::NoAudioOutput,
+        "{} URL del stream para otro reproductor:",
+    ),
+    (
+        Msg::MediaKeysFeatureMissing,
+        "media_keys está activado pero mfp se compiló sin la feature `media-keys`",
+    ),
+    (Msg::MediaKeysFailed, "Teclas multimedia no disponibles: {}"),
+    (
+        Msg::StreamStalled,
+        "Stream detenido ({} reconexiones) — [r]eintentar, [d]escargar y escuchar sin conexión, episodio siguie[n]te, [q] salir: ",
+    ),
+    (Msg::StreamStalledStopping, "Stream detenido ({} reconexiones), deteniendo"),
+    (Msg::StallRetrying, "Reconectando..."),
+    (
+        Msg::OfflineDownloadStarted,
+        "Descargando en segundo plano; la reproducción seguirá desde {} al terminar",
+    ),
+    (Msg::OfflinePlaying, "Reproduciendo la copia descargada desde {}"),
+    (Msg::OfflineDownloadFailed, "Falló la descarga sin conexión: {}"),
+    (Msg::HelpVolumeSave, "Guardar este volumen para este episodio (volsave clear lo olvida)"),
+    (Msg::VolumeSaved, "Ajuste de volumen {}% guardado para este episodio"),
+    (Msg::VolumeSaveCleared, "Volumen guardado de este episodio olvidado"),
+    (Msg::VolumeSaveNone, "No hay volumen guardado para este episodio"),
+    (Msg::VolumeSaveUsage, "Uso: volsave [clear]"),
+    (Msg::VolumeSaveFailed, "No se pudo guardar el volumen: {}"),
+    (Msg::InfoVolumeSaved, " (vol {}% guardado)"),
+    (Msg::HelpRestart, "Empezar de nuevo el episodio actual"),
+    (Msg::Restarting, "Empezando de nuevo"),
+    (Msg::HelpList, "Recorrer la lista y elegir un episodio"),
+    (
+        Msg::HelpQueue,
+        "Reproducir el episodio N después de este: q 19; queue solo muestra la cola",
+    ),
+    (Msg::QueueUsage, "Uso: q 19 (o queue 19); queue solo muestra la cola"),
+    (Msg::Queued, "En cola: {} ({} esperando)"),
+    (Msg::QueuedAgain, "En cola otra vez: {} ya estaba esperando ({} esperando)"),
+    (
+        Msg::QueueOutsidePlaylist,
+        "El episodio {} no está en esta lista, así que no se puede poner en cola",
+    ),
+    (Msg::QueueEmpty, "No hay nada en cola"),
+    (Msg::QueueHeader, "A continuación:"),
+    (Msg::QueueAgainTag, "(repetido)"),
+    (Msg::QueueRestored, "En cola desde la última vez: {} esperando"),
+    (
+        Msg::QueueEntryDropped,
+        "{} estaba en cola la última vez pero ya no está disponible; se quita de la cola",
+    ),
+    (Msg::BrowserPage, "Lista, página {} de {}"),
+    (
+        Msg::BrowserHint,
+        "n/p: página siguiente/anterior, número + Enter: reproducirlo, q: volver > ",
+    ),
+    (Msg::BrowserInvalid, "No hay episodio {} en la lista"),
+    (Msg::ProgressEnds, "termina {}"),
+    (Msg::ProgressEndsAll, "termina {}, todo {}"),
+    (Msg::HelpUndo, "Deshacer el último cambio de favoritos"),
+    (Msg::HelpRedo, "Rehacer un cambio de favoritos deshecho"),
+    (Msg::FavUndone, "Deshecho: {}"),
+    (Msg::FavRedone, "Rehecho: {}"),
+    (Msg::FavIsFavorite, "{} es favorito"),
+    (Msg::FavIsNotFavorite, "{} ya no es favorito"),
+    (Msg::NothingToUndo, "No hay cambios de favoritos que deshacer"),
+    (Msg::NothingToRedo, "No hay cambios de favoritos que rehacer"),
+    (Msg::FavUndoFailed, "No se pudieron guardar los favoritos: {}"),
+    (
+        Msg::InstanceRunning,
+        "mfp ya se está ejecutando (pid {}). Ciérralo primero, o usa --takeover para detenerlo y seguir aquí",
+    ),
+    (Msg::InstanceTakenOver, "Otro mfp (pid {}) tomó el control; se detiene aquí"),
+    (Msg::UnknownFormatToken, "Token desconocido {{}} en el formato; tokens válidos: {}"),
+    (Msg::HelpAliases, "Alias"),
+    (Msg::HelpSeek, "Saltar a una posición: seek 12:30, seek +1:00, seek -30"),
+    (Msg::HelpSeekForward, "Adelantar 10 segundos (seek_step_secs)"),
+    (Msg::HelpSeekBack, "Retroceder 10 segundos (seek_step_secs)"),
+    (Msg::HelpSeekForwardLong, "Adelantar un minuto (seek_long_step_secs)"),
+    (Msg::HelpSeekBackLong, "Retroceder un minuto (seek_long_step_secs)"),
+    (Msg::SeekUsage, "Uso: seek 12:30, seek +1:00 o seek -30"),
+    (Msg::SeekBuffering, "Cargando hasta {}…"),
+    (
+        Msg::HelpGoto,
+        "Saltar a una parte del episodio: goto 50, o solo 50% (goto #19 es el episodio 19)",
+    ),
+    (Msg::HelpEpisode, "Reproducir el episodio número N: g 19, o goto #19"),
+    (Msg::EpisodeUsage, "Uso: g 19 (o ep 19, goto #19)"),
+    (
+        Msg::EpisodeOutsidePlaylist,
+        "El episodio {} no está en esta lista; se sigue por todo el feed",
+    ),
+    (Msg::GotoLanded, "→ {}"),
+    (Msg::SkippedIntro, "(intro de {} saltada)"),
+    (Msg::ResumedAt, "(continuando en {})"),
+    (Msg::GotoNoDuration, "No se conoce la duración de este episodio, así que no hay porcentaje al que ir"),
+    (Msg::GotoEpisodeHint, "goto usa un porcentaje; para el episodio {}, escribe goto #{}"),
+    (Msg::InfoSource, "Origen: {}"),
+    (Msg::SourceLocal, "local"),
+    (Msg::SourceStreaming, "streaming"),
+    (
+        Msg::FeedFromCache,
+        "No se puede acceder al feed ({}); se usan los episodios guardados la última vez",
+    ),
+    (
+        Msg::FeedFromDownload,
+        "No se puede acceder al feed ({}); se reproduce la copia descargada",
+    ),
+    (Msg::HelpRecord, "Guardar el stream en las descargas mientras suena"),
+    (
+        Msg::Recording,
+        "Grabando en {}; se conserva cuando haya llegado el episodio entero",
+    ),
+    (
+        Msg::RecordUnavailable,
+        "Solo se puede grabar un stream que suena en este ordenador",
+    ),
+    (Msg::ReconnectAttempt, "reconectando… intento {}/{}"),
+    (Msg::ServerNoAnswer, "El servidor no respondió a tiempo"),
+    (Msg::TooManyRedirects, "El servidor redirigió demasiadas veces"),
+    (
+        Msg::StreamFailed,
+        "No se puede reproducir este episodio: {} — [r]eintentar, episodio siguie[n]te, [q] salir: ",
+    ),
+    (
+        Msg::HelpSetVolume,
+        "Fijar el volumen en porcentaje: volume 60; volume reset vuelve al de la configuración",
+    ),
+    (Msg::VolumeUsage, "Uso: volume 60 (de 0 a 200 por ciento), o volume reset"),
+    (Msg::VolumeReset, "{} (el volumen configurado; el último elegido se olvida)"),
+    (Msg::HelpSleep, "Detener la reproducción tras un rato: sleep 45 (minutos), sleep off"),
+    (Msg::SleepUsage, "Uso: sleep 45 (minutos), sleep 1h30m o sleep off"),
+    (Msg::SleepSet, "Temporizador: se detiene en {}"),
+    (Msg::SleepOff, "Temporizador desactivado"),
+    (Msg::SleepStopping, "Temporizador cumplido, deteniendo"),
+    (Msg::InfoSleep, "Temporizador: quedan {}"),
+    (Msg::HelpStopAfter, "Detener al acabar este episodio (otra vez para cancelar)"),
+    (Msg::StopAfterOn, "Se detendrá al acabar este episodio"),
+    (Msg::StopAfterOff, "Seguirá tras este episodio"),
+    (Msg::StoppedAfterEpisode, "Detenido tras el episodio"),
+    (Msg::HelpLoop, "Bucle A-B: marca el inicio y luego el final; una tercera vez lo quita"),
+    (Msg::LoopStart, "Bucle desde {} (a otra vez marca el final)"),
+    (Msg::LoopSet, "Repitiendo {} - {}"),
+    (Msg::LoopCleared, "Bucle quitado"),
+    (Msg::LoopTooShort, "El bucle debe acabar en otro punto que {}"),
+    (
+        Msg::HelpDevice,
+        "Volver a reproducir en el dispositivo de audio predeterminado (p. ej. al conectar auriculares)",
+    ),
+    (Msg::OutputLost, "El dispositivo de audio ha desaparecido, cambiando al predeterminado…"),
+    (Msg::OutputReopened, "Reproduciendo en el dispositivo de audio predeterminado"),
+    (
+        Msg::OutputFailed,
+        "No se puede reproducir en el dispositivo de audio: {} — [r]eintentar, [q] salir: ",
+    ),
+    (Msg::HelpBalanceRight, "Mover el balance hacia el altavoz derecho"),
+    (Msg::HelpBalanceLeft, "Mover el balance hacia el altavoz izquierdo"),
+    (Msg::HelpBalanceCenter, "Centrar el balance"),
+    (Msg::InfoBalance, "Balance: {}"),
+    (Msg::BalanceCentered, "centrado"),
+    (Msg::InfoStream, "Audio: {}"),
+    (Msg::InfoSkippedSilence, "Saltados {}s de silencio al inicio"),
+    (Msg::StreamUnknown, "desconocido"),
+    (Msg::StreamMono, "mono"),
+    (Msg::StreamStereo, "estéreo"),
+    (Msg::StreamChannels, "{} canales"),
+    (Msg::BufferFull, "completo"),
+    (Msg::BufferHeld, "máx"),
+    (Msg::Rebuffering, "cargando… {}s"),
+];
+
+/// Set the language for the rest of the run. Only the first call has effect.
+pub fn init(language: Language) {
+    let lang = match language {
+        Language::En => Lang::En,
+        Language::Es => Lang::Es,
+        Language::Auto => detect(),
+    };
+    let _ = LANG.set(lang);
+}
+
+/// Pick the language from the locale environment variables
+fn detect() -> Lang {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .map(|value| {
+            if value.starts_with("es") {
+                Lang::Es
+            } else {
+                Lang::En
+            }
+        })
+        .unwrap_or(Lang::En)
+}
+
+fn lookup(table: &[(Msg, &'static str)], msg: Msg) -> Option<&'static str> {
+    table
+        .iter()
+        .find(|(key, _)| *key == msg)
+        .map(|(_, text)| *text)
+}
+
+fn text(lang: Lang, msg: Msg) -> &'static str {
+    let table = match lang {
+        Lang::En => EN,
+        Lang::Es => ES,
+    };
+    lookup(table, msg).or_else(|| lookup(EN, msg)).unwrap_or("")
+}
+
+/// Message in the current language
+pub fn t(msg: Msg) -> &'static str {
+    text(*LANG.get_or_init(detect), msg)
+}
+
+/// Message in the current language with its `{}` placeholders filled in order
+pub fn tf(msg: Msg, args: &[&dyn Display]) -> String {
+    fill(t(msg), args)
+}
+
+fn fill(template: &str, args: &[&dyn Display]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut args = args.iter();
+    let mut parts = template.split("{}");
+
+    if let Some(first) = parts.next() {
+        out.push_str(first);
+    }
+    for part in parts {
+        if let Some(arg) = args.next() {
+            out.push_str(&arg.to_string());
+        }
+        out.push_str(part);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_key_exists_in_every_language() {
+        for table in [EN, ES] {
+            for msg in Msg::ALL {
+                let text = lookup(table, *msg);
+                assert!(text.is_some_and(|t| !t.is_empty()), "missing {:?}", msg);
+            }
+        }
+    }
+
+    #[test]
+    fn translations_keep_placeholders() {
+        for msg in Msg::ALL {
+            assert_eq!(
+                text(Lang::En, *msg).matches("{}").count(),
+                text(Lang::Es, *msg).matches("{}").count(),
+                "placeholder mismatch in {:?}",
+                msg
+            );
+        }
+    }
+
+    #[test]
+    fn tables_have_no_duplicates() {
+        for table in [EN, ES] {
+            assert_eq!(table.len(), Msg::ALL.len());
+        }
+    }
+
+    #[test]
+    fn fill_replaces_placeholders_in_order() {
+        assert_eq!(fill("{} of {}", &[&1, &"two"]), "1 of two");
+        assert_eq!(fill("no args", &[]), "no args");
+        assert_eq!(fill("missing {}", &[]), "missing ");
+    }
+}
@@ -0,0 +1,187 @@
+//! Listening journal
+//! Notes taken while playing ("great track around 34:00"), kept one per line in
+//! `journal.jsonl` next to the history and reviewed with `mfp journal`.
+
+use chrono::{Local, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+use crate::errors::StorageError;
+use crate::paths;
+use crate::player;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    /// Episode title as shown in the feed
+    pub episode: String,
+    /// Playback position the note refers to, in seconds
+    pub position_secs: u64,
+    pub text: String,
+    /// Unix timestamp (seconds) the note was written
+    pub written_at: u64,
+}
+
+impl JournalEntry {
+    /// Position as `[MM:SS]`, the form a note is shown and exported with
+    pub fn position(&self) -> String {
+        format!("[{}]", player::format_duration(self.position_secs))
+    }
+}
+
+pub struct Journal;
+
+impl Journal {
+    fn journal_path() -> Result<PathBuf, StorageError> {
+        let data_dir = paths::data_dir()?;
+        fs::create_dir_all(&data_dir).map_err(StorageError::write(&data_dir))?;
+
+        paths::journal_file()
+    }
+
+    /// Load all entries, oldest first. Malformed lines are skipped.
+    pub fn load() -> Result<Vec<JournalEntry>, StorageError> {
+        let path = Self::journal_path()?;
+
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let content = fs::read_to_string(&path).map_err(StorageError::read(&path))?;
+
+        Ok(content
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+
+    pub fn append(entry: &JournalEntry) -> Result<(), StorageError> {
+        let path = Self::journal_path()?;
+        let line = serde_json::to_string(entry).map_err(StorageError::write(&path))?;
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(StorageError::write(&path))?;
+        writeln!(file, "{}", line).map_err(StorageError::write(&path))
+    }
+
+    /// Add the notes not kept yet and keep the file in writing order; returns how many were
+    /// added
+    pub fn merge(entries: Vec<JournalEntry>) -> Result<usize, StorageError> {
+        let mut all = Self::load()?;
+        let before = all.len();
+        for entry in entries {
+            if !all.contains(&entry) {
+                all.push(entry);
+            }
+        }
+        let added = all.len() - before;
+        if added == 0 {
+            return Ok(0);
+        }
+
+        all.sort_by_key(|entry| entry.written_at);
+
+        let path = Self::journal_path()?;
+        let mut content = String::new();
+        for entry in &all {
+            content += &serde_json::to_string(entry).map_err(StorageError::write(&path))?;
+            content.push('\n');
+        }
+        fs::write(&path, content).map_err(StorageError::write(&path))?;
+        Ok(added)
+    }
+}
+
+/// Entries grouped by episode, episodes in the order they were first noted and
+/// notes within an episode by position
+pub fn group_by_episode(entries: Vec<JournalEntry>) -> Vec<(String, Vec<JournalEntry>)> {
+    let mut groups: Vec<(String, Vec<JournalEntry>)> = Vec::new();
+
+    for entry in entries {
+        match groups
+            .iter_mut()
+            .find(|(episode, _)| *episode == entry.episode)
+        {
+            Some((_, notes)) => notes.push(entry),
+            None => groups.push((entry.episode.clone(), vec![entry])),
+        }
+    }
+
+    for (_, notes) in &mut groups {
+        notes.sort_by_key(|note| note.position_secs);
+    }
+
+    groups
+}
+
+/// The journal as a markdown document, one section per episode
+pub fn to_markdown(entries: Vec<JournalEntry>) -> String {
+    let mut markdown = String::from("# Listening journal\n");
+
+    for (episode, notes) in group_by_episode(entries) {
+        markdown.push_str(&format!("\n## {}\n\n", episode));
+        for note in notes {
+            markdown.push_str(&format!(
+                "- **{}** {} _({})_\n",
+                note.position(),
+                note.text,
+                format_date(note.written_at)
+            ));
+        }
+    }
+
+    markdown
+}
+
+/// Local `YYYY-MM-DD HH:MM` for a unix timestamp
+pub fn format_date(timestamp: u64) -> String {
+    match Local.timestamp_opt(timestamp as i64, 0).single() {
+        Some(date) => date.format("%Y-%m-%d %H:%M").to_string(),
+        None => "?".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(episode: &str, position_secs: u64, text: &str) -> JournalEntry {
+        JournalEntry {
+            episode: episode.to_string(),
+            position_secs,
+            text: text.to_string(),
+            written_at: 0,
+        }
+    }
+
+    #[test]
+    fn groups_keep_first_noted_order_and_sort_by_position() {
+        let groups = group_by_episode(vec![
+            note("Episode 2: B", 2040, "great track"),
+            note("Episode 1: A", 60, "intro"),
+            note("Episode 2: B", 300, "calm part"),
+        ]);
+
+        let episodes: Vec<&str> = groups.iter().map(|(e, _)| e.as_str()).collect();
+        assert_eq!(episodes, ["Episode 2: B", "Episode 1: A"]);
+
+        let positions: Vec<u64> = groups[0].1.iter().map(|n| n.position_secs).collect();
+        assert_eq!(positions, [300, 2040]);
+    }
+
+    #[test]
+    fn markdown_has_a_section_per_episode() {
+        let markdown = to_markdown(vec![
+            note("Episode 2: B", 2040, "great track"),
+            note("Episode 1: A", 3725, "long one"),
+        ]);
+
+        assert!(markdown.starts_with("# Listening journal\n"));
+        assert!(markdown.contains("\n## Episode 2: B\n\n- **[34:00]** great track"));
+        assert!(markdown.contains("\n## Episode 1: A\n\n- **[01:02:05]** long one"));
+    }
+}
@@ -0,0 +1,295 @@
+//! Single-key bindings for the play loop
+//! Bound keys act immediately while the command line is empty; everything else is typed
+//! as a command. The `[keys]` config section overrides the defaults per action.
+
+use anyhow::{bail, Result};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::commands::Action;
+use crate::config::KeyBinding;
+
+/// Bindings used for every action the config doesn't mention; an action can have several
+const DEFAULTS: &[(Action, &str)] = &[
+    (Action::Pause, "space"),
+    (Action::SeekForward, "right"),
+    (Action::SeekBack, "left"),
+    (Action::SeekForwardLong, "shift+right"),
+    (Action::SeekForwardLong, "."),
+    (Action::SeekBackLong, "shift+left"),
+    (Action::SeekBackLong, ","),
+    (Action::Next, "pagedown"),
+    (Action::Previous, "pageup"),
+    (Action::VolumeUp, "up"),
+    (Action::VolumeDown, "down"),
+];
+
+/// A key plus modifiers, written like `n`, `space`, `ctrl+p` or `right`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeySpec {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeySpec {
+    pub fn parse(spec: &str) -> Result<Self> {
+        let spec = spec.trim().to_lowercase();
+        let mut parts: Vec<&str> = spec.split('+').collect();
+        // A lone "+" (or "ctrl++") is the plus key itself
+        if spec.ends_with("++") || spec == "+" {
+            parts.pop();
+            parts.pop();
+            parts.push("+");
+        }
+        let key = parts.pop().unwrap_or_default();
+
+        let mut modifiers = KeyModifiers::NONE;
+        for modifier in parts {
+            modifiers |= match modifier {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => bail!("Unknown modifier \"{}\" in key \"{}\"", modifier, spec),
+            };
+        }
+
+        let code = match key {
+            "space" => KeyCode::Char(' '),
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            "tab" => KeyCode::Tab,
+            "esc" | "escape" => KeyCode::Esc,
+            "enter" | "backspace" => {
+                bail!("\"{}\" is reserved for typing commands", key)
+            }
+            _ if key.len() > 1 && key.starts_with('f') => match key[1..].parse() {
+                Ok(n @ 1..=12) => KeyCode::F(n),
+                _ => bail!("Unknown key \"{}\"", spec),
+            },
+            _ => {
+                let mut chars = key.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => KeyCode::Char(c),
+                    _ => bail!("Unknown key \"{}\"", spec),
+                }
+            }
+        };
+
+        Ok(Self { code, modifiers })
+    }
+
+    fn matches(&self, event: &KeyEvent) -> bool {
+        // Terminals disagree on reporting shift for characters like '+', so it is ignored there
+        let ignored = match self.code {
+            KeyCode::Char(_) => KeyModifiers::SHIFT,
+            _ => KeyModifiers::NONE,
+        };
+        self.code == event.code && self.modifiers - ignored == event.modifiers - ignored
+    }
+}
+
+impl fmt::Display for KeySpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "ctrl+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "alt+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "shift+")?;
+        }
+
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::Left => write!(f, "left"),
+            KeyCode::Right => write!(f, "right"),
+            KeyCode::Up => write!(f, "up"),
+            KeyCode::Down => write!(f, "down"),
+            KeyCode::Home => write!(f, "home"),
+            KeyCode::End => write!(f, "end"),
+            KeyCode::PageUp => write!(f, "pageup"),
+            KeyCode::PageDown => write!(f, "pagedown"),
+            KeyCode::Tab => write!(f, "tab"),
+            KeyCode::Esc => write!(f, "esc"),
+            KeyCode::F(n) => write!(f, "f{}", n),
+            _ => write!(f, "?"),
+        }
+    }
+}
+
+/// Effective bindings: the config's, then the defaults of actions it leaves unbound
+#[derive(Debug, Default)]
+pub struct Keymap {
+    bindings: Vec<(KeySpec, Action)>,
+}
+
+impl Keymap {
+    pub fn from_config(config: &HashMap<String, KeyBinding>) -> Result<Self> {
+        let mut keymap = Keymap::default();
+
+        let mut configured: Vec<_> = config.iter().collect();
+        configured.sort_by_key(|(name, _)| name.as_str());
+
+        for (name, binding) in configured {
+            let Some(action) = Action::from_name(name) else {
+                bail!("Unknown action \"{}\" in [keys]", name);
+            };
+            for spec in binding.specs() {
+                let key = KeySpec::parse(spec)?;
+                if let Some(other) = keymap.lookup_spec(&key) {
+                    bail!(
+                        "Key \"{}\" is bound to both {} and {} in [keys]",
+                        key,
+                        other.name(),
+                        action.name()
+                    );
+                }
+                keymap.bindings.push((key, action));
+            }
+        }
+
+        for (action, spec) in DEFAULTS {
+            if config.contains_key(action.name()) {
+                continue;
+            }
+            let key = KeySpec::parse(spec)?;
+            // A key taken by the config wins over another action's default
+            if keymap.lookup_spec(&key).is_none() {
+                keymap.bindings.push((key, *action));
+            }
+        }
+
+        Ok(keymap)
+    }
+
+    pub fn lookup(&self, event: &KeyEvent) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(key, _)| key.matches(event))
+            .map(|(_, action)| *action)
+    }
+
+    fn lookup_spec(&self, spec: &KeySpec) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(key, _)| key == spec)
+            .map(|(_, action)| *action)
+    }
+
+    /// Keys bound to `action`, for the help overlay
+    pub fn keys_for(&self, action: Action) -> Vec<KeySpec> {
+        self.bindings
+            .iter()
+            .filter(|(_, bound)| *bound == action)
+            .map(|(key, _)| *key)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(entries: &[(&str, &str)]) -> HashMap<String, KeyBinding> {
+        entries
+            .iter()
+            .map(|(action, key)| (action.to_string(), KeyBinding::One(key.to_string())))
+            .collect()
+    }
+
+    fn press(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    #[test]
+    fn parses_key_specs() {
+        let ctrl_p = KeySpec::parse("ctrl+p").unwrap();
+        assert_eq!(ctrl_p.code, KeyCode::Char('p'));
+        assert_eq!(ctrl_p.modifiers, KeyModifiers::CONTROL);
+        assert_eq!(KeySpec::parse("space").unwrap().code, KeyCode::Char(' '));
+        assert_eq!(KeySpec::parse("Right").unwrap().code, KeyCode::Right);
+        assert_eq!(KeySpec::parse("+").unwrap().code, KeyCode::Char('+'));
+        assert_eq!(KeySpec::parse("f5").unwrap().code, KeyCode::F(5));
+        assert!(KeySpec::parse("enter").is_err());
+        assert!(KeySpec::parse("hyper+x").is_err());
+        assert!(KeySpec::parse("nope").is_err());
+    }
+
+    #[test]
+    fn formats_back_to_the_config_syntax() {
+        for spec in ["ctrl+p", "space", "right", "l", "f5"] {
+            assert_eq!(KeySpec::parse(spec).unwrap().to_string(), spec);
+        }
+    }
+
+    #[test]
+    fn unbound_actions_keep_their_defaults() {
+        let keymap = Keymap::from_config(&config(&[("next", "l")])).unwrap();
+        let l = press(KeyCode::Char('l'), KeyModifiers::NONE);
+        let space = press(KeyCode::Char(' '), KeyModifiers::NONE);
+
+        assert_eq!(keymap.lookup(&l), Some(Action::Next));
+        assert_eq!(keymap.lookup(&space), Some(Action::Pause));
+        assert!(keymap
+            .keys_for(Action::Next)
+            .iter()
+            .all(|k| k.to_string() == "l"));
+    }
+
+    #[test]
+    fn configured_keys_take_over_defaults() {
+        let keymap = Keymap::from_config(&config(&[("volume_up", "right")])).unwrap();
+        let right = press(KeyCode::Right, KeyModifiers::NONE);
+
+        assert_eq!(keymap.lookup(&right), Some(Action::VolumeUp));
+        assert!(keymap.keys_for(Action::SeekForward).is_empty());
+    }
+
+    #[test]
+    fn arrows_seek_and_shift_seeks_further() {
+        let keymap = Keymap::from_config(&HashMap::new()).unwrap();
+
+        assert_eq!(
+            keymap.lookup(&press(KeyCode::Right, KeyModifiers::NONE)),
+            Some(Action::SeekForward)
+        );
+        assert_eq!(
+            keymap.lookup(&press(KeyCode::Left, KeyModifiers::SHIFT)),
+            Some(Action::SeekBackLong)
+        );
+        assert_eq!(
+            keymap.lookup(&press(KeyCode::Char('.'), KeyModifiers::NONE)),
+            Some(Action::SeekForwardLong)
+        );
+        assert_eq!(keymap.keys_for(Action::SeekBackLong).len(), 2);
+    }
+
+    #[test]
+    fn rejects_conflicts_and_unknown_actions() {
+        assert!(Keymap::from_config(&config(&[("next", "l"), ("pause", "l")])).is_err());
+        assert!(Keymap::from_config(&config(&[("teleport", "t")])).is_err());
+    }
+
+    #[test]
+    fn modifiers_must_match() {
+        let keymap = Keymap::from_config(&config(&[("previous", "ctrl+p")])).unwrap();
+
+        assert_eq!(
+            keymap.lookup(&press(KeyCode::Char('p'), KeyModifiers::CONTROL)),
+            Some(Action::Previous)
+        );
+        assert_eq!(
+            keymap.lookup(&press(KeyCode::Char('p'), KeyModifiers::NONE)),
+            None
+        );
+    }
+}
@@ -0,0 +1,106 @@
+//! Music For Programming player library
+//!
+//! The pieces behind the `mfp` binary, usable on their own:
+//!
+//! - [`feed`]: fetch and cache the episode list from the RSS feed
+//! - [`playlist`]: episode order, navigation and shuffle
+//! - [`player`]: progressive streaming playback
+//! - [`audio`]: the audio backend the play loop drives, and one that plays nothing
+//! - [`downloader`]: offline downloads
+//! - [`favorites`]: the persistent favorites list
+//! - [`playlists`]: named playlists of episode titles
+//!
+//! ```no_run
+//! use mfp::feed::Feed;
+//! use mfp::playlist::Playlist;
+//!
+//! let feed = Feed::fetch()?;
+//! let mut playlist = Playlist::new(feed.shared_episodes());
+//! playlist.enable_shuffle();
+//! println!("{:?}", playlist.current().map(|ep| &ep.title));
+//! # Ok::<(), mfp::feed::FeedError>(())
+//! ```
+
+pub mod audio;
+pub mod downloader;
+pub mod favorites;
+pub mod feed;
+pub mod player;
+pub mod playlist;
+pub mod playlists;
+
+// Internals of `player`
+#[cfg(feature = "audio")]
+mod stream;
+
+// Shared with the binary; not part of the supported API
+#[doc(hidden)]
+pub mod app;
+#[doc(hidden)]
+pub mod backup;
+#[doc(hidden)]
+pub mod config;
+#[doc(hidden)]
+pub mod errors;
+#[doc(hidden)]
+pub mod history;
+#[doc(hidden)]
+pub mod hooks;
+#[doc(hidden)]
+pub mod http;
+#[doc(hidden)]
+pub mod i18n;
+#[doc(hidden)]
+pub mod journal;
+#[doc(hidden)]
+pub mod lock;
+#[doc(hidden)]
+pub mod logging;
+#[doc(hidden)]
+pub mod mouse;
+#[doc(hidden)]
+pub mod output;
+#[doc(hidden)]
+pub mod paths;
+#[doc(hidden)]
+pub mod piped;
+#[doc(hidden)]
+pub mod play;
+#[doc(hidden)]
+pub mod schedule;
+#[doc(hidden)]
+pub mod session;
+#[doc(hidden)]
+pub mod shutdown;
+#[doc(hidden)]
+pub mod state;
+#[doc(hidden)]
+pub mod station;
+#[doc(hidden)]
+pub mod style;
+#[doc(hidden)]
+pub mod terminal;
+#[doc(hidden)]
+pub mod wizard;
+
+// Internals of `app` and `play`
+mod ab_loop;
+mod browser;
+#[cfg(feature = "cast")]
+mod cast;
+mod clipboard;
+mod commands;
+#[cfg(feature = "discord")]
+mod discord;
+mod keys;
+#[cfg(feature = "media-keys")]
+mod media_keys;
+mod menu;
+mod mpris;
+mod now_playing;
+mod nudge;
+mod picker;
+mod progress;
+mod renderer;
+#[cfg(feature = "scrobble")]
+mod scrobbler;
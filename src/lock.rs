@@ -0,0 +1,126 @@
+//! One mfp at a time for playback and state changes
+//! The lock is an OS file lock on `mfp.lock` in the runtime dir, holding the owner's pid for
+//! the message shown to a second instance. The OS drops the lock when its process dies, so a
+//! pid left behind by a crash never blocks anyone and is simply overwritten. The lock is
+//! advisory: state files are still written whole, as if it were absent.
+
+use fs2::FileExt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::errors::StorageError;
+use crate::i18n::{tf, Msg};
+use crate::paths;
+
+/// How long `--takeover` waits for the running instance to let go
+const TAKEOVER_TIMEOUT: Duration = Duration::from_secs(5);
+const TAKEOVER_POLL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, thiserror::Error)]
+pub enum LockError {
+    #[error(transparent)]
+    Storage(#[from] StorageError),
+    /// Another mfp holds the lock; `pid` is None while it is still writing it
+    #[error("{}", tf(Msg::InstanceRunning, &[&pid_text(*pid)]))]
+    Held { pid: Option<u32> },
+}
+
+/// Held until dropped
+#[derive(Debug)]
+pub struct InstanceLock {
+    file: File,
+}
+
+impl InstanceLock {
+    /// Take the lock, or fail with the pid of the instance holding it
+    pub fn acquire() -> Result<Self, LockError> {
+        let path = paths::lock_file()?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(StorageError::write(parent))?;
+        }
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .map_err(StorageError::write(&path))?;
+
+        if let Err(e) = file.try_lock_exclusive() {
+            if e.raw_os_error() == fs2::lock_contended_error().raw_os_error() {
+                return Err(LockError::Held {
+                    pid: read_pid(&mut file),
+                });
+            }
+            // Some filesystems can't lock at all; carry on as if there were no lock
+            tracing::warn!("Could not lock {}: {}", path.display(), e);
+        }
+
+        if let Some(pid) = read_pid(&mut file).filter(|&pid| pid != std::process::id()) {
+            tracing::info!("Cleared a stale lock left by pid {}", pid);
+        }
+        write_pid(&mut file).map_err(StorageError::write(&path))?;
+        // A request nobody answered would stop this instance at once
+        let _ = fs::remove_file(paths::takeover_file()?);
+
+        Ok(InstanceLock { file })
+    }
+
+    /// Ask the running instance to stop, then take the lock once it has
+    pub fn take_over() -> Result<Self, LockError> {
+        let takeover = paths::takeover_file()?;
+        fs::write(&takeover, std::process::id().to_string())
+            .map_err(StorageError::write(&takeover))?;
+
+        let started = Instant::now();
+        let result = loop {
+            match Self::acquire() {
+                Err(LockError::Held { .. }) if started.elapsed() < TAKEOVER_TIMEOUT => {
+                    thread::sleep(TAKEOVER_POLL)
+                }
+                result => break result,
+            }
+        };
+
+        let _ = fs::remove_file(&takeover);
+        result
+    }
+}
+
+impl Drop for InstanceLock {
+    fn drop(&mut self) {
+        // The file stays: removing it could let two instances lock different files
+        let _ = self.file.set_len(0);
+        let _ = self.file.unlock();
+    }
+}
+
+/// Pid of another instance that asked to take over; the play loop checks on every tick
+pub fn takeover_requested() -> Option<u32> {
+    let path = paths::takeover_file().ok()?;
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|pid| pid.trim().parse().ok())
+        .filter(|&pid| pid != std::process::id())
+}
+
+fn pid_text(pid: Option<u32>) -> String {
+    pid.map_or("?".to_string(), |pid| pid.to_string())
+}
+
+fn read_pid(file: &mut File) -> Option<u32> {
+    let mut content = String::new();
+    file.seek(SeekFrom::Start(0)).ok()?;
+    file.read_to_string(&mut content).ok()?;
+    content.trim().parse().ok()
+}
+
+fn write_pid(file: &mut File) -> io::Result<()> {
+    file.set_len(0)?;
+    file.seek(SeekFrom::Start(0))?;
+    write!(file, "{}", std::process::id())?;
+    file.flush()
+}
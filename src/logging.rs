@@ -0,0 +1,117 @@
+//! Diagnostic logging
+//! Warnings go to stderr (more with -v/-vv), and `--log-file` receives full debug output.
+//! While the progress line owns the terminal, stderr log lines are held back and
+//! printed once it is released, so they never corrupt the display.
+
+use anyhow::{Context, Result};
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::EnvFilter;
+
+/// Environment variable overriding the stderr filter (e.g. `MFP_LOG=mfp=trace`)
+const FILTER_ENV: &str = "MFP_LOG";
+
+static PROGRESS_ACTIVE: AtomicBool = AtomicBool::new(false);
+static PENDING: Mutex<Vec<u8>> = Mutex::new(Vec::new());
+
+/// `verbosity` is the number of `-v` flags, or None in quiet mode (errors only)
+pub fn init(verbosity: Option<u8>, log_file: Option<&Path>, ansi: bool) -> Result<()> {
+    let level = match verbosity {
+        None => "error",
+        Some(0) => "warn",
+        Some(1) => "info",
+        Some(2) => "debug",
+        Some(_) => "trace",
+    };
+    let stderr_filter = EnvFilter::try_from_env(FILTER_ENV).unwrap_or_else(|_| {
+        let others = if verbosity.is_none() { "error" } else { "warn" };
+        EnvFilter::new(format!("{},mfp={}", others, level))
+    });
+
+    let stderr_layer = tracing_subscriber::fmt::layer()
+        .with_writer(TerminalWriter)
+        .with_ansi(ansi)
+        .with_target(false)
+        .without_time()
+        .with_filter(stderr_filter);
+
+    let file_layer = match log_file {
+        Some(path) => {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .with_context(|| format!("Failed to open log file {}", path.display()))?;
+            Some(
+                tracing_subscriber::fmt::layer()
+                    .with_ansi(false)
+                    .with_writer(Mutex::new(file))
+                    .with_filter(EnvFilter::new("debug")),
+            )
+        }
+        None => None,
+    };
+
+    tracing_subscriber::registry()
+        .with(stderr_layer)
+        .with(file_layer)
+        .try_init()
+        .context("Failed to initialize logging")
+}
+
+/// Mark whether the progress line currently owns the terminal.
+/// Releasing it prints the log lines held back in the meantime.
+pub fn set_progress_active(active: bool) {
+    PROGRESS_ACTIVE.store(active, Ordering::Relaxed);
+
+    if !active {
+        let mut pending = PENDING.lock().unwrap();
+        if !pending.is_empty() {
+            let _ = io::stderr().write_all(&pending);
+            pending.clear();
+        }
+    }
+}
+
+struct TerminalWriter;
+
+enum TerminalOutput {
+    Stderr(io::Stderr),
+    Pending,
+}
+
+impl Write for TerminalOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            TerminalOutput::Stderr(stderr) => stderr.write(buf),
+            TerminalOutput::Pending => {
+                PENDING.lock().unwrap().extend_from_slice(buf);
+                Ok(buf.len())
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            TerminalOutput::Stderr(stderr) => stderr.flush(),
+            TerminalOutput::Pending => Ok(()),
+        }
+    }
+}
+
+impl<'a> MakeWriter<'a> for TerminalWriter {
+    type Writer = TerminalOutput;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        if PROGRESS_ACTIVE.load(Ordering::Relaxed) {
+            TerminalOutput::Pending
+        } else {
+            TerminalOutput::Stderr(io::stderr())
+        }
+    }
+}
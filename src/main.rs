@@ -1,39 +1,79 @@
-mod downloader;
-mod favorites;
-mod feed;
-mod mpris;
-mod player;
-mod playlist;
+mod completions;
 
-use anyhow::Result;
-use clap::{Parser, Subcommand};
-use crossterm::{
-    event::{self, Event, KeyCode, KeyEvent},
-    terminal::{disable_raw_mode, enable_raw_mode},
+use mfp::{
+    app, config, errors, hooks, i18n, lock, logging, mouse, output, paths, piped, play, player,
+    schedule, shutdown, style, wizard,
 };
-use downloader::Downloader;
-use favorites::Favorites;
-use feed::Feed;
-use player::Player;
-use playlist::Playlist;
-use crate::mpris::{MprisController, MprisCommand, PlaybackStatus};
-use std::io::{self, Write};
+
+use anyhow::Result;
+use chrono::NaiveTime;
+use clap::{ArgAction, Parser, Subcommand};
+use completions::CompletionShell;
+use config::Config;
+use i18n::{t, tf, Msg};
+use lock::InstanceLock;
+use std::io;
+use std::path::PathBuf;
 use std::time::Duration;
+use style::{ColorMode, Role};
 
 #[derive(Parser)]
 #[command(name = "mfp")]
 #[command(about = "Music For Programming - Radio player ligero", long_about = None)]
+#[command(after_help = errors::EXIT_CODES_HELP)]
 struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
+    /// When to use colors (NO_COLOR is honored in auto mode)
+    #[arg(long, value_enum, global = true, default_value_t = ColorMode::Auto)]
+    color: ColorMode,
+    /// Show more diagnostics on stderr (-v info, -vv debug)
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    verbose: u8,
+    /// Write full debug logs to a file
+    #[arg(long, global = true, value_name = "PATH")]
+    log_file: Option<PathBuf>,
+    /// Only print errors and explicitly requested output
+    #[arg(short, long, global = true)]
+    quiet: bool,
+    /// Never run the first-run setup questions
+    #[arg(long, global = true)]
+    no_wizard: bool,
+    /// Don't run the on_track_start/on_track_end/on_pause/on_stop commands from the config
+    #[arg(long, global = true)]
+    no_hooks: bool,
+    /// Leave the mouse to the terminal during playback (no click-to-seek or wheel volume)
+    #[arg(long, global = true)]
+    no_mouse: bool,
+    /// Keep config, favorites, history, downloads and caches under this directory
+    /// (also MFP_CONFIG_DIR)
+    #[arg(long, global = true, value_name = "PATH")]
+    config_dir: Option<PathBuf>,
+    /// Stop an mfp that is already playing and carry on in this one
+    #[arg(long, global = true)]
+    takeover: bool,
 }
 
+// Parsed once at startup, so the size of the Play variant costs nothing
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand)]
 enum Commands {
     /// List all available episodes
-    List,
+    List {
+        /// Print the episodes as JSON (for jq, or piping into `play --stdin`)
+        #[arg(long)]
+        json: bool,
+        /// List another station's episodes (see `mfp station list`)
+        #[arg(long, value_name = "NAME")]
+        station: Option<String>,
+    },
+    /// Choose an episode interactively (default on a terminal)
+    Pick,
     /// Play a specific episode
     Play {
+        /// Episode title or curator to look for (-e wins if both are given)
+        #[arg(value_name = "TITLE")]
+        query: Vec<String>,
         /// Episode number (e.g. 75)
         #[arg(short, long)]
         episode: Option<usize>,
@@ -43,6 +83,71 @@ enum Commands {
         /// Play only favorites
         #[arg(short, long)]
         favorites: bool,
+        /// Play a named playlist, in its order (see `mfp playlist list`)
+        #[arg(long, value_name = "NAME", conflicts_with = "favorites")]
+        playlist: Option<String>,
+        /// Play only episodes whose title or notes mention KEYWORD (any case)
+        #[arg(long, value_name = "KEYWORD")]
+        filter: Option<String>,
+        /// Play from another station (see `mfp station list`)
+        #[arg(long, value_name = "NAME")]
+        station: Option<String>,
+        /// Start at a given time (HH:MM, tomorrow if it has already passed)
+        #[arg(long, value_name = "HH:MM", value_parser = schedule::parse_clock)]
+        at: Option<NaiveTime>,
+        /// Start after a delay (e.g. 25m, 1h30m, 90s)
+        #[arg(
+            long = "in",
+            value_name = "DURATION",
+            value_parser = schedule::parse_delay,
+            conflicts_with = "at"
+        )]
+        delay: Option<Duration>,
+        /// Start at this volume in percent (0-200), over the one remembered from last time
+        #[arg(long, value_name = "PERCENT", value_parser = clap::value_parser!(u8).range(0..=200))]
+        volume: Option<u8>,
+        /// Raise the volume from silence over the first 30 seconds
+        #[arg(long)]
+        fade_in: bool,
+        /// Stop playback after a while (e.g. 45, 1h30m), fading out over the last 30 seconds
+        #[arg(long, value_name = "DURATION", value_parser = schedule::parse_delay)]
+        sleep: Option<Duration>,
+        /// Keep a one-line "now playing" text in this file (for OBS, tmux, ...)
+        #[arg(long, value_name = "PATH")]
+        now_playing_file: Option<PathBuf>,
+        /// Play only the first SECONDS of each episode (default 60), then move on
+        #[arg(
+            long,
+            value_name = "SECONDS",
+            num_args = 0..=1,
+            default_missing_value = "60"
+        )]
+        preview: Option<u64>,
+        /// Don't fetch the start of the next episode ahead of time (for metered connections)
+        #[arg(long)]
+        no_prefetch: bool,
+        /// Start episodes from the top this time, whatever `skip_intro_secs` says
+        #[arg(long)]
+        no_skip_intro: bool,
+        /// Save every streamed episode to the downloads as it plays (`rec` does it for one)
+        #[arg(long)]
+        record: bool,
+        /// Kilobytes to buffer before an episode starts (512 by default; less on a fast download)
+        #[arg(long = "buffer", value_name = "KB")]
+        buffer_kb: Option<u64>,
+        /// Megabytes to download ahead of playback at most, or `unlimited` (over
+        /// `max_readahead_mb`)
+        #[arg(long, value_name = "MB", value_parser = player::parse_readahead)]
+        readahead: Option<player::Readahead>,
+        /// Play on a Chromecast instead of this computer (see `mfp cast --list`)
+        #[arg(long, value_name = "DEVICE")]
+        cast: Option<String>,
+        /// Read the episode from stdin: a number, a title or a JSON object with `number` or `title`
+        #[arg(long, conflicts_with_all = ["query", "episode"])]
+        stdin: bool,
+        /// Pick up where the last run left off: the unfinished episode, then what was queued
+        #[arg(long = "continue", conflicts_with_all = ["query", "episode", "stdin"])]
+        resume: bool,
     },
     /// Manage favorites
     Fav {
@@ -55,12 +160,26 @@ enum Commands {
         /// List favorites
         #[arg(short, long)]
         list: bool,
+        /// Take back the last add or remove (up to 20 of them)
+        #[arg(long, conflicts_with = "redo")]
+        undo: bool,
+        /// Make the last undone change again
+        #[arg(long)]
+        redo: bool,
+    },
+    /// Manage named playlists
+    Playlist {
+        #[command(subcommand)]
+        action: PlaylistAction,
     },
     /// Manage offline downloads
     Download {
         /// Download episode by number
         #[arg(short, long)]
         episode: Option<usize>,
+        /// Download the newest episode
+        #[arg(long, conflicts_with = "episode")]
+        latest: bool,
         /// List downloaded episodes
         #[arg(short, long)]
         list: bool,
@@ -71,484 +190,436 @@ enum Commands {
         #[arg(short = 's', long)]
         size: bool,
     },
+    /// Open an episode's web page in the browser
+    Open {
+        /// Episode number (e.g. 75)
+        #[arg(short, long)]
+        episode: usize,
+    },
+    /// Show an episode's details and links
+    Info {
+        /// Episode number (e.g. 75)
+        #[arg(short, long)]
+        episode: usize,
+        /// Copy the audio URL to the clipboard
+        #[arg(long)]
+        copy_url: bool,
+    },
+    /// Print the episode currently playing
+    #[command(name = "nowplaying")]
+    NowPlaying {
+        /// Line template: {title}, {number}, {curator}, {elapsed}, {duration}, {remaining},
+        /// {percent}, {volume}, {state} and {shuffle}
+        #[arg(long)]
+        format: Option<String>,
+        /// Don't end the line with a newline (for shell prompts)
+        #[arg(long)]
+        no_newline: bool,
+    },
+    /// Print the player status for status bars
+    Status {
+        /// Print Waybar custom-module JSON
+        #[arg(long)]
+        waybar: bool,
+        /// Keep running and print a new line whenever the status changes
+        #[arg(long)]
+        follow: bool,
+        /// Text template ({title}, {elapsed}, {duration}, {status})
+        #[arg(long)]
+        format: Option<String>,
+        /// Cut the text to this many characters
+        #[arg(long, value_name = "CHARS")]
+        max_length: Option<usize>,
+    },
+    /// Show recently played episodes
+    History {
+        /// Number of entries to show
+        #[arg(short = 'n', long, default_value_t = 20)]
+        limit: usize,
+        /// Print entries as JSON
+        #[arg(long)]
+        json: bool,
+        /// Replay the Nth entry of the list
+        #[arg(short, long)]
+        play: Option<usize>,
+    },
+    /// Review the notes taken while listening
+    Journal {
+        /// Only notes for this episode number
+        #[arg(short, long)]
+        episode: Option<usize>,
+        /// Write the notes to a markdown file instead
+        #[arg(long, value_name = "FILE")]
+        export: Option<PathBuf>,
+    },
+    /// Save settings, favorites, history and journal to a .tar.gz (downloads are not included)
+    #[command(name = "export-state")]
+    ExportState {
+        /// Archive to write
+        path: PathBuf,
+    },
+    /// Restore a backup made with export-state
+    #[command(name = "import-state")]
+    ImportState {
+        /// Archive to read
+        path: PathBuf,
+        /// Add the backup to the current state, keeping local settings (default)
+        #[arg(long, conflicts_with = "replace")]
+        merge: bool,
+        /// Make the state exactly what the backup holds
+        #[arg(long)]
+        replace: bool,
+        /// Don't ask before replacing
+        #[arg(short, long, requires = "replace")]
+        yes: bool,
+    },
+    /// Manage stations: other RSS feeds to play besides Music For Programming
+    Station {
+        #[command(subcommand)]
+        action: StationAction,
+    },
+    /// Find Chromecasts to play on with `play --cast`
+    #[cfg(feature = "cast")]
+    Cast {
+        /// List the devices on the local network
+        #[arg(long)]
+        list: bool,
+    },
+    /// Manage Last.fm / ListenBrainz scrobbling
+    #[cfg(feature = "scrobble")]
+    Scrobble {
+        /// Validate the configured credentials
+        #[arg(long)]
+        test: bool,
+    },
+    /// Show where the settings file lives
+    Config {
+        /// Run the setup questions again and update the settings file
+        #[arg(long)]
+        init: bool,
+    },
+    /// Generate shell completion script
+    Completions {
+        /// Target shell
+        #[arg(value_enum)]
+        shell: CompletionShell,
+    },
+    /// Print cached episode numbers and titles for shell completion
+    #[command(name = "__complete-episodes", hide = true)]
+    CompleteEpisodes,
+    /// Print favorites for shell completion
+    #[command(name = "__complete-favorites", hide = true)]
+    CompleteFavorites,
 }
 
-fn main() -> Result<()> {
-    let cli = Cli::parse();
-
-    match cli.command {
-        Some(Commands::List) => list_episodes()?,
-        Some(Commands::Play {
-            episode,
-            shuffle,
-            favorites: fav_mode,
-        }) => play_radio(episode, shuffle, fav_mode)?,
-        Some(Commands::Fav { add, remove, list }) => manage_favorites(add, remove, list)?,
-        Some(Commands::Download {
-            episode,
-            list,
-            delete,
-            size,
-        }) => manage_downloads(episode, list, delete, size)?,
-        None => interactive_mode()?,
-    }
-
-    Ok(())
+#[derive(Subcommand)]
+enum PlaylistAction {
+    /// Start an empty playlist
+    Create {
+        /// Playlist name (lowercase letters, digits, - and _)
+        name: String,
+    },
+    /// Add episodes to the end of a playlist
+    Add {
+        /// Playlist name
+        name: String,
+        /// Episode numbers or exact titles
+        #[arg(required = true)]
+        episodes: Vec<String>,
+    },
+    /// Take episodes out of a playlist
+    Remove {
+        /// Playlist name
+        name: String,
+        /// Episode numbers or exact titles
+        #[arg(required = true)]
+        episodes: Vec<String>,
+    },
+    /// Delete a playlist (the episodes are not touched)
+    Delete {
+        /// Playlist name
+        name: String,
+    },
+    /// List the playlists
+    List,
+    /// Show a playlist's episodes in order
+    Show {
+        /// Playlist name
+        name: String,
+    },
 }
 
-fn list_episodes() -> Result<()> {
-    println!("Obteniendo episodios...");
-    let feed = Feed::fetch()?;
-    let favorites = Favorites::load()?;
+#[derive(Subcommand)]
+enum StationAction {
+    /// Add a station; its feed is checked first
+    Add {
+        /// Name to use with --station (lowercase letters, digits, - and _)
+        name: String,
+        /// RSS feed URL
+        url: String,
+    },
+    /// List the stations and their feeds
+    List,
+    /// Remove a station (its downloads and favorites are kept)
+    Remove {
+        /// Station name
+        name: String,
+    },
+}
 
-    for (i, episode) in feed.episodes().iter().enumerate() {
-        let fav_marker = if favorites.is_favorite(&episode.title) {
-            "*"
-        } else {
-            " "
-        };
-        println!(
-            "{} {:3}. {} [{}]",
-            fav_marker,
-            extract_episode_number(&episode.title).unwrap_or(i + 1),
-            episode.title,
-            episode.duration
+fn main() {
+    let cli = Cli::parse();
+    if let Some(dir) = cli.config_dir.clone() {
+        paths::set_root(dir);
+    }
+    style::init(cli.color);
+    output::set_quiet(cli.quiet);
+    hooks::set_enabled(!cli.no_hooks);
+    mouse::set_enabled(!cli.no_mouse);
+    play::restore_terminal_on_panic();
+    let config = Config::load().unwrap_or_default();
+    i18n::init(config.language);
+    style::set_bar_style(
+        config
+            .progress_style
+            .unwrap_or_else(style::BarStyle::detect),
+    );
+
+    let log_file = cli.log_file.or(config.log_file);
+    let verbosity = if cli.quiet { None } else { Some(cli.verbose) };
+    if let Err(e) = logging::init(verbosity, log_file.as_deref(), style::stderr_enabled()) {
+        eprintln!(
+            "{} {:?}",
+            style::paint_err(Role::Error, t(Msg::ErrorLabel)),
+            e
         );
     }
-
-    Ok(())
-}
-
-fn extract_episode_number(title: &str) -> Option<usize> {
-    title
-        .split(':')
-        .next()?
-        .trim()
-        .strip_prefix("Episode ")?
-        .parse()
-        .ok()
-}
-
-fn play_radio(episode_num: Option<usize>, shuffle: bool, fav_mode: bool) -> Result<()> {
-    println!("Cargando feed...");
-    let feed = Feed::fetch()?;
-    let mut favorites = Favorites::load()?;
-
-    let mut playlist = if fav_mode {
-        let fav_list = favorites.list();
-        if fav_list.is_empty() {
-            println!("No tienes favoritos guardados. Usa 'mfp fav --add \"Episode XX: Title\"'");
-            return Ok(());
-        }
-        Playlist::from_favorites(feed.episodes(), &fav_list)
-    } else {
-        Playlist::new(feed.episodes().to_vec())
-    };
-
-    if shuffle {
-        playlist.enable_shuffle();
+    if let Err(e) = shutdown::install() {
+        tracing::warn!("Could not install signal handlers: {}", e);
     }
 
-    if let Some(num) = episode_num {
-        let target_title = format!("Episode {}", num);
-        if let Some(pos) = playlist
-            .all_episodes()
-            .iter()
-            .position(|e| e.title.contains(&target_title))
-        {
-            for _ in 0..pos {
-                playlist.next();
-            }
-        }
+    // On stderr: this can happen before `status` or `completions` output that scripts parse
+    match paths::migrate() {
+        Ok(Some(migration)) if !cli.quiet => eprintln!(
+            "{}",
+            tf(
+                Msg::DataMigrated,
+                &[
+                    &migration.files,
+                    &migration.from.display(),
+                    &migration.to.display()
+                ]
+            )
+        ),
+        Ok(_) => {}
+        Err(e) => eprintln!(
+            "{} {:?}",
+            style::paint_err(Role::Error, t(Msg::ErrorLabel)),
+            anyhow::Error::from(e)
+        ),
     }
 
-    let player = Player::new()?;
-
-    // MPRIS integration
-    let mpris = MprisController::new()?;
-    let mpris_cmd_rx = mpris.command_receiver();
-
-    loop {
-        let (episode_title, episode_duration, episode_url) = match playlist.current() {
-            Some(ep) => (ep.title.clone(), ep.duration.clone(), ep.audio_url.clone()),
-            None => {
-                println!("No hay episodios disponibles");
-                break;
-            }
-        };
-
-        // Update MPRIS metadata for new episode
-        let total_seconds = player::parse_duration(&episode_duration).unwrap_or(0);
-        if let Err(e) = mpris.update_metadata(episode_title.clone(), total_seconds) {
-            eprintln!("Failed to update MPRIS metadata: {}", e);
-        }
-        if let Err(e) = mpris.update_playback_status(PlaybackStatus::Playing) {
-            eprintln!("Failed to update MPRIS playback status: {}", e);
-        }
-        if let Err(e) = mpris.update_shuffle(playlist.is_shuffled()) {
-            eprintln!("Failed to update MPRIS shuffle: {}", e);
-        }
-        if let Err(e) = mpris.update_navigation(true, true) {
-            eprintln!("Failed to update MPRIS navigation: {}", e);
-        }
-
-        let is_fav = favorites.is_favorite(&episode_title);
-        println!("\n{} {}", if is_fav { "*" } else { ">" }, episode_title);
-        println!(
-            "Duración: {} | Shuffle: {}\n",
-            episode_duration,
-            if playlist.is_shuffled() { "ON" } else { "OFF" }
-        );
-
-        player.play(&episode_url)?;
-
-        println!("Controles:");
-        println!("  [n]ext | [b]ack | [p]ausa | [s]huffle | [f]avorite | [q]uit");
-        println!("  [+/-] volumen | [m]ute | [i]nfo | [d]ownload");
-
-        let downloader = Downloader::new()?;
-        let total_seconds = player::parse_duration(&episode_duration).unwrap_or(0);
-
-        enable_raw_mode()?;
-
-        let mut command_buffer = String::new();
-
-        loop {
-            // Process MPRIS commands
-            if let Ok(cmd) = mpris_cmd_rx.try_recv() {
-                match cmd {
-                    MprisCommand::PlayPause => {
-                        if player.is_paused() {
-                            player.resume();
-                            let _ = mpris.update_playback_status(PlaybackStatus::Playing);
-                        } else {
-                            player.pause();
-                            let _ = mpris.update_playback_status(PlaybackStatus::Paused);
-                        }
-                    }
-                    MprisCommand::Next => {
-                        player.stop();
-                        playlist.next();
-                        break; // exit inner loop to play next episode
-                    }
-                    MprisCommand::Previous => {
-                        player.stop();
-                        playlist.previous();
-                        break;
-                    }
-                    MprisCommand::SetVolume(vol) => {
-                        player.set_volume(vol);
-                        let _ = mpris.update_volume(vol);
-                    }
-                    MprisCommand::Quit => {
-                        player.stop();
-                        disable_raw_mode()?;
-                        return Ok(());
-                    }
-                }
-            }
-            let elapsed = player.elapsed_seconds();
-            let remaining = total_seconds.saturating_sub(elapsed);
-
-            let elapsed_str = player::format_duration(elapsed);
-            let total_str = player::format_duration(total_seconds);
-            let remaining_str = player::format_duration(remaining);
-
-            let percent = if total_seconds > 0 {
-                (elapsed as f32 / total_seconds as f32 * 100.0) as u8
-            } else {
-                0
-            };
-
-            let bar_length = 40;
-            let filled = ((percent as usize * bar_length) / 100).min(bar_length);
-            let bar: String = "━".repeat(filled) + &"─".repeat(bar_length - filled);
-
-            print!(
-                "\r[{}/{}] {} {}% | -{} > {}",
-                elapsed_str, total_str, bar, percent, remaining_str, command_buffer
+    let first_run = Config::config_path().is_ok_and(|path| !path.exists());
+    if first_run
+        && !cli.no_wizard
+        && !cli.quiet
+        && wizard::is_interactive()
+        && wants_wizard(&cli.command)
+    {
+        if let Err(e) = wizard::run() {
+            eprintln!(
+                "{} {:?}",
+                style::paint_err(Role::Error, t(Msg::ErrorLabel)),
+                e
             );
-            io::stdout().flush()?;
-
-            if event::poll(Duration::from_millis(100))? {
-                if let Event::Key(KeyEvent { code, .. }) = event::read()? {
-                    match code {
-                        KeyCode::Enter => {
-                            let command = command_buffer.trim().to_string();
-                            command_buffer.clear();
-
-                            disable_raw_mode()?;
-
-                            let should_break = match command.as_str() {
-                                "n" | "next" => {
-                                    print!("\r{}\r", " ".repeat(120));
-                                    player.stop();
-                                    playlist.next();
-                                    true
-                                }
-                                "b" | "back" | "prev" | "previous" => {
-                                    print!("\r{}\r", " ".repeat(120));
-                                    player.stop();
-                                    playlist.previous();
-                                    true
-                                }
-                                "p" | "pause" | "play" => {
-                                    print!("\r{}\r", " ".repeat(120));
-                                    let new_status = if player.is_paused() {
-                                        PlaybackStatus::Playing
-                                    } else {
-                                        PlaybackStatus::Paused
-                                    };
-                                    if player.is_paused() {
-                                        player.resume();
-                                        println!("Playing");
-                                    } else {
-                                        player.pause();
-                                        println!("Paused");
-                                    }
-                                    mpris.update_playback_status(new_status).ok();
-                                    false
-                                }
-                                "+" | "up" => {
-                                    print!("\r{}\r", " ".repeat(120));
-                                    let current_vol = player.volume();
-                                    let new_vol = (current_vol + 0.1).min(2.0);
-                                    player.set_volume(new_vol);
-                                    mpris.update_volume(new_vol).ok();
-                                    println!("Volume: {:.0}%", new_vol * 100.0);
-                                    false
-                                }
-                                "-" | "down" => {
-                                    print!("\r{}\r", " ".repeat(120));
-                                    let current_vol = player.volume();
-                                    let new_vol = (current_vol - 0.1).max(0.0);
-                                    player.set_volume(new_vol);
-                                    mpris.update_volume(new_vol).ok();
-                                    println!("Volume: {:.0}%", new_vol * 100.0);
-                                    false
-                                }
-                                "m" | "mute" => {
-                                    print!("\r{}\r", " ".repeat(120));
-                                    let current_vol = player.volume();
-                                    let new_vol = if current_vol > 0.0 { 0.0 } else { 1.0 };
-                                    player.set_volume(new_vol);
-                                    mpris.update_volume(new_vol).ok();
-                                    if current_vol > 0.0 {
-                                        println!("Muted");
-                                    } else {
-                                        println!("Volume: 100%");
-                                    }
-                                    false
-                                }
-                                "i" | "info" => {
-                                    print!("\r{}\r", " ".repeat(120));
-                                    println!("\nEpisode: {}", episode_title);
-                                    println!("Duration: {}", episode_duration);
-                                    println!("Volume: {:.0}%", player.volume() * 100.0);
-                                    println!(
-                                        "Status: {}",
-                                        if player.is_paused() {
-                                            "Paused"
-                                        } else {
-                                            "Playing"
-                                        }
-                                    );
-                                    println!(
-                                        "Shuffle: {}",
-                                        if playlist.is_shuffled() { "ON" } else { "OFF" }
-                                    );
-                                    println!(
-                                        "Favorite: {}\n",
-                                        if favorites.is_favorite(&episode_title) {
-                                            "Yes"
-                                        } else {
-                                            "No"
-                                        }
-                                    );
-                                    false
-                                }
-                                "s" | "shuffle" => {
-                                    print!("\r{}\r", " ".repeat(120));
-                                    playlist.toggle_shuffle();
-                                    mpris.update_shuffle(playlist.is_shuffled()).ok();
-                                    println!(
-                                        "Shuffle: {}",
-                                        if playlist.is_shuffled() { "ON" } else { "OFF" }
-                                    );
-                                    false
-                                }
-                                "f" | "fav" | "favorite" => {
-                                    print!("\r{}\r", " ".repeat(120));
-                                    let is_now_fav = favorites.toggle(episode_title.clone());
-                                    println!(
-                                        "{}",
-                                        if is_now_fav {
-                                            "Added to favorites"
-                                        } else {
-                                            "Removed from favorites"
-                                        }
-                                    );
-                                    false
-                                }
-                                "d" | "download" => {
-                                    print!("\r{}\r", " ".repeat(120));
-                                    println!("\nDownloading episode for offline...");
-                                    match downloader.download_episode(&episode_title, &episode_url)
-                                    {
-                                        Ok(_) => println!("Episode downloaded\n"),
-                                        Err(e) => println!("Error: {}\n", e),
-                                    }
-                                    false
-                                }
-                                "q" | "quit" | "exit" => {
-                                    print!("\r{}\r", " ".repeat(120));
-                                    player.stop();
-                                    disable_raw_mode()?;
-                                    return Ok(());
-                                }
-                                "" => false,
-                                _ => {
-                                    print!("\r{}\r", " ".repeat(120));
-                                    println!("Unknown command");
-                                    println!("Use: n (next) | b (back) | p (pause) | +/- (vol) | m (mute) | s (shuffle) | f (fav) | i (info) | d (download) | q (quit)");
-                                    false
-                                }
-                            };
-
-                            enable_raw_mode()?;
-
-                            if should_break {
-                                disable_raw_mode()?;
-                                break;
-                            }
-                        }
-                        KeyCode::Backspace => {
-                            command_buffer.pop();
-                        }
-                        KeyCode::Char(c) => {
-                            command_buffer.push(c);
-                        }
-                        _ => {}
-                    }
-                }
-            }
         }
     }
 
-    Ok(())
+    if let Err(e) = run(cli.command, cli.takeover) {
+        eprintln!(
+            "{} {:?}",
+            style::paint_err(Role::Error, t(Msg::ErrorLabel)),
+            e
+        );
+        std::process::exit(errors::exit_code(&e));
+    }
+    if shutdown::requested() {
+        std::process::exit(errors::EXIT_SIGNAL);
+    }
 }
 
-fn manage_favorites(add: Option<String>, remove: Option<String>, list: bool) -> Result<()> {
-    let mut favorites = Favorites::load()?;
+/// Commands meant for scripts and shell integration never trigger the wizard
+fn wants_wizard(command: &Option<Commands>) -> bool {
+    !matches!(
+        command,
+        Some(
+            Commands::Config { .. }
+                | Commands::Completions { .. }
+                | Commands::CompleteEpisodes
+                | Commands::CompleteFavorites
+                | Commands::Status { .. }
+                | Commands::NowPlaying { .. }
+                | Commands::ExportState { .. }
+                | Commands::ImportState { .. }
+        )
+    )
+}
 
-    if let Some(title) = add {
-        if favorites.add(title.clone()) {
-            println!("* Added: {}", title);
-        } else {
-            println!("Already in favorites: {}", title);
+/// Commands that play or change state take the instance lock; the rest only read and
+/// run alongside a player
+fn takes_lock(command: &Option<Commands>) -> bool {
+    match command {
+        None | Some(Commands::Play { .. } | Commands::Pick | Commands::ImportState { .. }) => true,
+        Some(Commands::Fav {
+            add,
+            remove,
+            undo,
+            redo,
+            ..
+        }) => add.is_some() || remove.is_some() || *undo || *redo,
+        Some(Commands::History { play, .. }) => play.is_some(),
+        Some(Commands::Playlist { action }) => {
+            !matches!(action, PlaylistAction::List | PlaylistAction::Show { .. })
         }
+        Some(Commands::Station { action }) => !matches!(action, StationAction::List),
+        Some(Commands::Config { init }) => *init,
+        _ => false,
     }
+}
 
-    if let Some(title) = remove {
-        if favorites.remove(&title) {
-            println!("Removed: {}", title);
-        } else {
-            println!("Not in favorites: {}", title);
-        }
-    }
+fn run(command: Option<Commands>, takeover: bool) -> Result<()> {
+    // Held until the command is done; the OS releases it if mfp dies
+    let _lock = match (takes_lock(&command), takeover) {
+        (false, _) => None,
+        (true, false) => Some(InstanceLock::acquire()?),
+        (true, true) => Some(InstanceLock::take_over()?),
+    };
 
-    if list {
-        let fav_list = favorites.list();
-        if fav_list.is_empty() {
-            println!("No favorites saved");
-        } else {
-            println!("Favorites:");
-            for title in fav_list {
-                println!("  * {}", title);
-            }
+    match command {
+        Some(Commands::List { json, station }) => {
+            app::select_station(station.as_deref())?;
+            app::list_episodes(json)?
         }
-    }
-
-    Ok(())
-}
-
-fn manage_downloads(
-    episode: Option<usize>,
-    list: bool,
-    delete: Option<String>,
-    size: bool,
-) -> Result<()> {
-    let downloader = Downloader::new()?;
+        Some(Commands::Pick) => app::pick_episode()?,
+        Some(Commands::Play {
+            query,
+            episode,
+            shuffle,
+            favorites: fav_mode,
+            playlist,
+            filter,
+            station,
+            at,
+            delay,
+            volume,
+            fade_in,
+            sleep,
+            now_playing_file,
+            preview,
+            no_prefetch,
+            no_skip_intro,
+            record,
+            buffer_kb,
+            readahead,
+            cast,
+            stdin,
+            resume,
+        }) => {
+            app::select_station(station.as_deref())?;
+            // Read before any wait, so bad input fails at once
+            let (episode, query) = if stdin {
+                match piped::read(io::stdin().lock())? {
+                    piped::Selection::Number(n) => (Some(n), None),
+                    piped::Selection::Title(title) => (None, Some(title)),
+                }
+            } else {
+                (episode, (!query.is_empty()).then(|| query.join(" ")))
+            };
 
-    if size {
-        let total_bytes = downloader.get_total_size()?;
-        let total_mb = total_bytes as f64 / 1_048_576.0;
-        println!("Disk usage: {:.2} MB", total_mb);
-        println!("Location: {}", downloader.download_dir().display());
-        return Ok(());
-    }
+            let options = play::PlayOptions {
+                shuffle,
+                favorites: fav_mode,
+                playlist,
+                filter,
+                volume,
+                fade_in,
+                sleep,
+                now_playing_file,
+                preview,
+                no_prefetch,
+                no_skip_intro,
+                record,
+                buffer_kb,
+                readahead,
+                cast,
+                ..play::PlayOptions::default()
+            };
 
-    if list {
-        let downloaded = downloader.list_downloaded()?;
-        if downloaded.is_empty() {
-            println!("No downloaded episodes");
-        } else {
-            println!("Downloaded episodes ({}):", downloaded.len());
-            for path in downloaded {
-                if let Some(filename) = path.file_name() {
-                    println!("  - {}", filename.to_string_lossy());
-                }
+            let wait = at.map(schedule::until_local).or(delay);
+            play::play_after(wait, resume, episode, query, options)?
+        }
+        Some(Commands::Fav {
+            add,
+            remove,
+            list,
+            undo,
+            redo,
+        }) => app::manage_favorites(add, remove, list, undo, redo)?,
+        Some(Commands::Playlist { action }) => match action {
+            PlaylistAction::Create { name } => app::create_playlist(&name)?,
+            PlaylistAction::Add { name, episodes } => app::add_to_playlist(&name, &episodes)?,
+            PlaylistAction::Remove { name, episodes } => {
+                app::remove_from_playlist(&name, &episodes)?
             }
+            PlaylistAction::Delete { name } => app::delete_playlist(&name)?,
+            PlaylistAction::List => app::list_playlists()?,
+            PlaylistAction::Show { name } => app::show_playlist(&name)?,
+        },
+        Some(Commands::Download {
+            episode,
+            latest,
+            list,
+            delete,
+            size,
+        }) => app::manage_downloads(episode, latest, list, delete, size)?,
+        Some(Commands::Open { episode }) => app::open_episode(episode)?,
+        Some(Commands::Info { episode, copy_url }) => app::show_info(episode, copy_url)?,
+        Some(Commands::NowPlaying { format, no_newline }) => {
+            app::show_now_playing(format, no_newline)?
         }
-        return Ok(());
-    }
-
-    if let Some(title) = delete {
-        downloader.delete_episode(&title)?;
-        return Ok(());
-    }
-
-    if let Some(ep_num) = episode {
-        println!("Obteniendo episodio...");
-        let feed = Feed::fetch()?;
-
-        let target_title = format!("Episode {}", ep_num);
-        if let Some(ep) = feed
-            .episodes()
-            .iter()
-            .find(|e| e.title.contains(&target_title))
-        {
-            downloader.download_episode(&ep.title, &ep.audio_url)?;
-        } else {
-            println!("Episode {} not found", ep_num);
+        Some(Commands::Status {
+            waybar,
+            follow,
+            format,
+            max_length,
+        }) => app::show_status(waybar, follow, format, max_length)?,
+        Some(Commands::History { limit, json, play }) => app::show_history(limit, json, play)?,
+        Some(Commands::Journal { episode, export }) => app::show_journal(episode, export)?,
+        Some(Commands::ExportState { path }) => app::export_state(&path)?,
+        Some(Commands::ImportState {
+            path,
+            merge: _,
+            replace,
+            yes,
+        }) => app::import_state(&path, replace, yes)?,
+        Some(Commands::Station { action }) => match action {
+            StationAction::Add { name, url } => app::add_station(&name, &url)?,
+            StationAction::List => app::list_stations()?,
+            StationAction::Remove { name } => app::remove_station(&name)?,
+        },
+        #[cfg(feature = "cast")]
+        Some(Commands::Cast { list }) => app::manage_cast(list)?,
+        #[cfg(feature = "scrobble")]
+        Some(Commands::Scrobble { test }) => app::manage_scrobbling(test)?,
+        Some(Commands::Config { init }) => app::manage_config(init)?,
+        Some(Commands::Completions { shell }) => {
+            completions::write_script(shell, &mut io::stdout())?
         }
-        return Ok(());
+        Some(Commands::CompleteEpisodes) => app::complete_episodes(),
+        Some(Commands::CompleteFavorites) => app::complete_favorites(),
+        None if wizard::is_interactive() => app::start_menu()?,
+        None => app::interactive_mode()?,
     }
 
-    println!("Gestión de descargas offline");
-    println!("\nUso:");
-    println!("  mfp download -e 75        Descargar episodio 75");
-    println!("  mfp download --list       Listar descargados");
-    println!("  mfp download --size       Mostrar espacio usado");
-    println!("  mfp download --delete \"Episode 75\"  Eliminar episodio");
-
-    Ok(())
-}
-
-fn interactive_mode() -> Result<()> {
-    println!("Music For Programming - Radio Player");
-    println!("\nComandos disponibles:");
-    println!("  mfp list                    - Lista todos los episodios");
-    println!("  mfp play                    - Reproduce desde el inicio");
-    println!("  mfp play -e 75              - Reproduce episodio específico");
-    println!("  mfp play -s                 - Reproduce en modo shuffle");
-    println!("  mfp play -f                 - Reproduce solo favoritos");
-    println!("  mfp fav -l                  - Lista favoritos");
-    println!("  mfp fav -a \"Episode XX\"     - Agrega a favoritos");
-    println!("  mfp fav -r \"Episode XX\"     - Remueve de favoritos");
-    println!("  mfp download -e 75          - Descarga episodio para offline");
-    println!("  mfp download --list         - Lista episodios descargados");
-    println!("\nUsa 'mfp play' para comenzar a escuchar");
-
     Ok(())
 }
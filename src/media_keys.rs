@@ -0,0 +1,158 @@
+//! System media controls (`media-keys` feature)
+//! Registers with the platform's now-playing session (SMTC on Windows, MPNowPlayingInfoCenter
+//! on macOS, MPRIS elsewhere) so hardware media keys reach the player. Events go into the same
+//! command channel as the MPRIS server's, and the play loop handles both alike.
+
+use anyhow::{anyhow, Result};
+use async_channel::Sender;
+use souvlaki::{
+    MediaControlEvent, MediaControls, MediaMetadata, MediaPlayback, MediaPosition, PlatformConfig,
+    SeekDirection,
+};
+use std::time::Duration;
+
+use crate::mpris::MprisCommand;
+
+/// How far the bare seek keys jump
+const SEEK_STEP_SECS: i64 = 10;
+
+/// Registered media session; dropping it deregisters
+pub struct MediaKeys {
+    controls: MediaControls,
+    /// Last (seconds, paused) reported, so ticks only talk to the OS on a change
+    reported: Option<(u64, bool)>,
+}
+
+impl MediaKeys {
+    /// Register and forward control events to `commands`
+    pub fn attach(commands: Sender<MprisCommand>) -> Result<Self> {
+        let config = PlatformConfig {
+            dbus_name: "mfp",
+            display_name: "MFP",
+            hwnd: console_window(),
+        };
+        let mut controls = MediaControls::new(config).map_err(|e| anyhow!("{:?}", e))?;
+        controls
+            .attach(move |event| {
+                if let Some(command) = command_for(event) {
+                    let _ = commands.send_blocking(command);
+                }
+            })
+            .map_err(|e| anyhow!("{:?}", e))?;
+
+        Ok(MediaKeys {
+            controls,
+            reported: None,
+        })
+    }
+
+    /// Show a new episode; `cover_url` is its artwork, if the feed has any
+    pub fn update_metadata(&mut self, title: &str, duration_secs: u64, cover_url: Option<&str>) {
+        let metadata = MediaMetadata {
+            title: Some(title),
+            artist: Some("Music For Programming"),
+            album: None,
+            cover_url,
+            duration: (duration_secs > 0).then(|| Duration::from_secs(duration_secs)),
+        };
+        if let Err(e) = self.controls.set_metadata(metadata) {
+            tracing::warn!("Failed to update media controls: {:?}", e);
+        }
+        self.reported = None;
+    }
+
+    /// Called every tick; only a new second or a pause/resume reaches the OS
+    pub fn update_position(&mut self, elapsed_secs: u64, paused: bool) {
+        if self.reported == Some((elapsed_secs, paused)) {
+            return;
+        }
+        self.reported = Some((elapsed_secs, paused));
+
+        let progress = Some(MediaPosition(Duration::from_secs(elapsed_secs)));
+        let playback = if paused {
+            MediaPlayback::Paused { progress }
+        } else {
+            MediaPlayback::Playing { progress }
+        };
+        if let Err(e) = self.controls.set_playback(playback) {
+            tracing::warn!("Failed to update media controls: {:?}", e);
+        }
+    }
+}
+
+impl Drop for MediaKeys {
+    fn drop(&mut self) {
+        let _ = self.controls.set_playback(MediaPlayback::Stopped);
+        let _ = self.controls.detach();
+    }
+}
+
+fn command_for(event: MediaControlEvent) -> Option<MprisCommand> {
+    let step = |direction| match direction {
+        SeekDirection::Forward => 1,
+        SeekDirection::Backward => -1,
+    };
+
+    Some(match event {
+        MediaControlEvent::Play => MprisCommand::Play,
+        MediaControlEvent::Pause => MprisCommand::Pause,
+        MediaControlEvent::Toggle => MprisCommand::PlayPause,
+        MediaControlEvent::Next => MprisCommand::Next,
+        MediaControlEvent::Previous => MprisCommand::Previous,
+        MediaControlEvent::Seek(direction) => {
+            MprisCommand::SeekBy(step(direction) * SEEK_STEP_SECS)
+        }
+        MediaControlEvent::SeekBy(direction, by) => {
+            MprisCommand::SeekBy(step(direction) * by.as_secs() as i64)
+        }
+        MediaControlEvent::SetPosition(MediaPosition(at)) => {
+            MprisCommand::SetPosition(at.as_secs())
+        }
+        MediaControlEvent::Stop | MediaControlEvent::Quit => MprisCommand::Quit,
+        _ => return None,
+    })
+}
+
+/// Windows attaches the controls to a window; a terminal app only has its console's
+#[cfg(windows)]
+fn console_window() -> Option<*mut std::ffi::c_void> {
+    // SAFETY: GetConsoleWindow takes no arguments and returns 0 without a console
+    let hwnd = unsafe { windows_sys::Win32::System::Console::GetConsoleWindow() };
+    (hwnd != 0).then_some(hwnd as *mut std::ffi::c_void)
+}
+
+#[cfg(not(windows))]
+fn console_window() -> Option<*mut std::ffi::c_void> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seek_keys_become_relative_jumps() {
+        assert!(matches!(
+            command_for(MediaControlEvent::Seek(SeekDirection::Backward)),
+            Some(MprisCommand::SeekBy(-10))
+        ));
+        assert!(matches!(
+            command_for(MediaControlEvent::SeekBy(
+                SeekDirection::Forward,
+                Duration::from_secs(30)
+            )),
+            Some(MprisCommand::SeekBy(30))
+        ));
+        assert!(matches!(
+            command_for(MediaControlEvent::SetPosition(MediaPosition(
+                Duration::from_secs(90)
+            ))),
+            Some(MprisCommand::SetPosition(90))
+        ));
+    }
+
+    #[test]
+    fn raise_is_ignored() {
+        assert!(command_for(MediaControlEvent::Raise).is_none());
+    }
+}
@@ -0,0 +1,239 @@
+//! Start menu shown by plain `mfp` in a terminal
+//! A handful of numbered shortcuts (continue, latest, browse, favorites, downloads), each
+//! leading into the same code as its subcommand. One key picks; no full-screen UI.
+
+use anyhow::Result;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
+use std::io::{self, Write};
+
+use crate::i18n::{t, tf, Msg};
+use crate::player;
+use crate::style::{self, Role};
+
+/// What the menu leads to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Choice {
+    /// The episode listened to last and left unfinished, and the queue of that run
+    Continue,
+    Latest,
+    Browse,
+    Favorites,
+    Downloads,
+    Quit,
+}
+
+/// Where the last unfinished episode was left
+pub struct Resume {
+    pub title: String,
+    pub position_secs: u64,
+}
+
+/// What the menu shows besides the fixed entries
+pub struct Context {
+    pub resume: Option<Resume>,
+    /// Episodes queued when the player was last left
+    pub queued: usize,
+    /// Title of the newest episode in the feed cache
+    pub latest: Option<String>,
+    pub favorites: usize,
+    pub downloads: usize,
+}
+
+/// Numbered entries in display order; favorites are left out while there are none
+fn items(context: &Context) -> Vec<(Choice, String)> {
+    let mut items = Vec::new();
+
+    let continued = match (&context.resume, context.queued) {
+        (Some(resume), 0) => Some(tf(
+            Msg::MenuContinue,
+            &[
+                &resume.title,
+                &player::format_duration(resume.position_secs),
+            ],
+        )),
+        (Some(resume), queued) => Some(tf(
+            Msg::MenuContinueQueued,
+            &[
+                &resume.title,
+                &player::format_duration(resume.position_secs),
+                &queued,
+            ],
+        )),
+        (None, 0) => None,
+        (None, queued) => Some(tf(Msg::MenuContinueQueue, &[&queued])),
+    };
+    if let Some(label) = continued {
+        items.push((Choice::Continue, label));
+    }
+    items.push((
+        Choice::Latest,
+        match &context.latest {
+            Some(title) => tf(Msg::MenuLatest, &[title]),
+            None => t(Msg::MenuLatestUnknown).to_string(),
+        },
+    ));
+    items.push((Choice::Browse, t(Msg::MenuBrowse).to_string()));
+    if context.favorites > 0 {
+        items.push((
+            Choice::Favorites,
+            tf(Msg::MenuFavorites, &[&context.favorites]),
+        ));
+    }
+    items.push((
+        Choice::Downloads,
+        tf(Msg::MenuDownloads, &[&context.downloads]),
+    ));
+
+    items
+}
+
+/// The choice bound to a pressed key: digits pick entries, Enter the first one
+fn choice_for(items: &[(Choice, String)], code: KeyCode) -> Option<Choice> {
+    match code {
+        KeyCode::Char('q') | KeyCode::Esc => Some(Choice::Quit),
+        KeyCode::Enter => items.first().map(|(choice, _)| choice.clone()),
+        KeyCode::Char(c) => {
+            let n = c.to_digit(10)? as usize;
+            items
+                .get(n.checked_sub(1)?)
+                .map(|(choice, _)| choice.clone())
+        }
+        _ => None,
+    }
+}
+
+/// Print the menu and wait for one key
+pub fn run(context: &Context) -> Result<Choice> {
+    let items = items(context);
+
+    println!("\n{}\n", style::paint(Role::Title, "Music For Programming"));
+    for (i, (_, label)) in items.iter().enumerate() {
+        println!(
+            "  {} {}",
+            style::paint(Role::Accent, &format!("{})", i + 1)),
+            label
+        );
+    }
+    println!(
+        "  {} {}\n",
+        style::paint(Role::Accent, "q)"),
+        t(Msg::MenuQuit)
+    );
+    print!("{}", style::paint(Role::Dim, t(Msg::MenuPrompt)));
+    io::stdout().flush()?;
+
+    enable_raw_mode()?;
+    let choice = read_choice(&items);
+    disable_raw_mode()?;
+    println!();
+
+    choice
+}
+
+fn read_choice(items: &[(Choice, String)]) -> Result<Choice> {
+    loop {
+        // Releases (reported on Windows) would otherwise be read as a second key
+        let Event::Key(KeyEvent {
+            code,
+            modifiers,
+            kind: KeyEventKind::Press,
+            ..
+        }) = event::read()?
+        else {
+            continue;
+        };
+
+        if modifiers.contains(KeyModifiers::CONTROL) && code == KeyCode::Char('c') {
+            return Ok(Choice::Quit);
+        }
+        if let Some(choice) = choice_for(items, code) {
+            return Ok(choice);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> Context {
+        Context {
+            resume: Some(Resume {
+                title: "Episode 63: Datassette".into(),
+                position_secs: 2530,
+            }),
+            queued: 0,
+            latest: Some("Episode 75: Datassette".into()),
+            favorites: 3,
+            downloads: 0,
+        }
+    }
+
+    #[test]
+    fn full_menu_follows_the_fixed_order() {
+        let choices: Vec<Choice> = items(&context()).into_iter().map(|(c, _)| c).collect();
+        assert_eq!(
+            choices,
+            [
+                Choice::Continue,
+                Choice::Latest,
+                Choice::Browse,
+                Choice::Favorites,
+                Choice::Downloads,
+            ]
+        );
+    }
+
+    #[test]
+    fn continue_shows_where_the_episode_was_left() {
+        let items = items(&context());
+        assert!(items[0].1.contains("Episode 63: Datassette"));
+        assert!(items[0].1.contains("42:10"));
+    }
+
+    #[test]
+    fn continue_is_offered_for_a_queue_alone() {
+        let queued = Context {
+            resume: None,
+            queued: 2,
+            ..context()
+        };
+        let alone = items(&queued);
+        assert_eq!(alone[0].0, Choice::Continue);
+        assert!(alone[0].1.contains('2'));
+
+        let both = items(&Context {
+            queued: 3,
+            ..context()
+        });
+        assert!(both[0].1.contains("Episode 63: Datassette") && both[0].1.contains('3'));
+    }
+
+    #[test]
+    fn entries_without_state_are_left_out_and_renumbered() {
+        let items = items(&Context {
+            resume: None,
+            queued: 0,
+            latest: None,
+            favorites: 0,
+            downloads: 0,
+        });
+        assert_eq!(choice_for(&items, KeyCode::Char('1')), Some(Choice::Latest));
+        assert_eq!(
+            choice_for(&items, KeyCode::Char('3')),
+            Some(Choice::Downloads)
+        );
+        assert_eq!(choice_for(&items, KeyCode::Char('4')), None);
+    }
+
+    #[test]
+    fn keys_map_to_choices() {
+        let items = items(&context());
+        assert_eq!(choice_for(&items, KeyCode::Enter), Some(Choice::Continue));
+        assert_eq!(choice_for(&items, KeyCode::Char('q')), Some(Choice::Quit));
+        assert_eq!(choice_for(&items, KeyCode::Esc), Some(Choice::Quit));
+        assert_eq!(choice_for(&items, KeyCode::Char('0')), None);
+        assert_eq!(choice_for(&items, KeyCode::Char('x')), None);
+    }
+}
@@ -0,0 +1,29 @@
+//! Mouse capture for the play loop
+//! Clicks on the progress bar and the scroll wheel reach the player while it owns the line.
+//! `--no-mouse` leaves the mouse to the terminal, for text selection.
+
+use crossterm::event::{DisableMouseCapture, EnableMouseCapture};
+use crossterm::execute;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+static CAPTURED: AtomicBool = AtomicBool::new(false);
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Start receiving mouse events, unless disabled
+pub fn capture() {
+    if ENABLED.load(Ordering::Relaxed) && execute!(io::stdout(), EnableMouseCapture).is_ok() {
+        CAPTURED.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Hand the mouse back to the terminal; safe to call any number of times
+pub fn release() {
+    if CAPTURED.swap(false, Ordering::Relaxed) {
+        let _ = execute!(io::stdout(), DisableMouseCapture);
+    }
+}
@@ -18,13 +18,23 @@ pub enum PlaybackStatus {
     Stopped,
 }
 
-/// Commands sent from MPRIS callbacks to main thread
+/// Commands sent from MPRIS callbacks (and media keys) to main thread
 #[derive(Debug)]
 pub enum MprisCommand {
     PlayPause,
+    #[cfg_attr(not(feature = "media-keys"), allow(dead_code))]
+    Play,
+    #[cfg_attr(not(feature = "media-keys"), allow(dead_code))]
+    Pause,
     Next,
     Previous,
     SetVolume(f32),
+    /// Jump forward (or back, when negative) by this many seconds
+    #[cfg_attr(not(feature = "media-keys"), allow(dead_code))]
+    SeekBy(i64),
+    /// Jump to this many seconds into the episode
+    #[cfg_attr(not(feature = "media-keys"), allow(dead_code))]
+    SetPosition(u64),
     Quit,
 }
 
@@ -49,6 +59,8 @@ pub struct MprisController {
     state_tx: Option<Sender<MprisStateUpdate>>,
     /// Channel to receive commands from MPRIS callbacks
     callback_rx: Receiver<MprisCommand>,
+    /// Sending side of `callback_rx`, for other command sources
+    callback_tx: Sender<MprisCommand>,
     /// Channel to send stop signal to MPRIS thread
     stop_tx: Option<Sender<()>>,
     /// Handle to the async thread
@@ -62,31 +74,54 @@ impl MprisController {
         let (callback_tx, callback_rx) = async_channel::unbounded();
         let (stop_tx, stop_rx) = async_channel::unbounded();
 
+        let mpris_callback_tx = callback_tx.clone();
         let thread_handle = std::thread::spawn(move || {
             // Run async runtime in this thread
-            if let Err(e) = async_std::task::block_on(Self::run_async_mpris(state_rx, callback_tx, stop_rx)) {
-                eprintln!("MPRIS thread error: {}", e);
+            if let Err(e) = async_std::task::block_on(Self::run_async_mpris(state_rx, mpris_callback_tx, stop_rx)) {
+                tracing::warn!("MPRIS thread error: {}", e);
             }
         });
 
         Ok(Self {
             state_tx: Some(state_tx),
             callback_rx,
+            callback_tx,
             stop_tx: Some(stop_tx),
             thread_handle: Some(thread_handle),
         })
     }
 
+    /// Controller that never registers on D-Bus (`mpris = false` in the config).
+    /// Updates are accepted and dropped; commands only arrive through `command_sender`.
+    pub fn disabled() -> Self {
+        let (callback_tx, callback_rx) = async_channel::unbounded();
+
+        Self {
+            state_tx: None,
+            callback_rx,
+            callback_tx,
+            stop_tx: None,
+            thread_handle: None,
+        }
+    }
+
     /// Get receiver for commands from MPRIS callbacks
     pub fn command_receiver(&self) -> Receiver<MprisCommand> {
         self.callback_rx.clone()
     }
 
+    /// Sender feeding the same receiver, so media keys drive the player like MPRIS does
+    #[cfg_attr(not(feature = "media-keys"), allow(dead_code))]
+    pub fn command_sender(&self) -> Sender<MprisCommand> {
+        self.callback_tx.clone()
+    }
+
     /// Update metadata (title and duration)
     pub fn update_metadata(&self, title: String, duration_secs: u64) -> Result<()> {
-        self.state_tx
-            .as_ref()
-            .unwrap()
+        let Some(state_tx) = &self.state_tx else {
+            return Ok(());
+        };
+        state_tx
             .send_blocking(MprisStateUpdate::Metadata {
                 title,
                 duration_secs,
@@ -96,40 +131,42 @@ impl MprisController {
 
     /// Update playback status
     pub fn update_playback_status(&self, status: PlaybackStatus) -> Result<()> {
-        self.state_tx
-            .as_ref()
-            .unwrap()
+        let Some(state_tx) = &self.state_tx else {
+            return Ok(());
+        };
+        state_tx
             .send_blocking(MprisStateUpdate::PlaybackStatus(status))
             .map_err(|e| anyhow!("Failed to send playback status: {}", e))
     }
 
     /// Update volume (0.0 to 1.0)
     pub fn update_volume(&self, volume: f32) -> Result<()> {
-        self.state_tx
-            .as_ref()
-            .unwrap()
+        let Some(state_tx) = &self.state_tx else {
+            return Ok(());
+        };
+        state_tx
             .send_blocking(MprisStateUpdate::Volume(volume))
             .map_err(|e| anyhow!("Failed to send volume update: {}", e))
     }
 
     /// Update shuffle status
     pub fn update_shuffle(&self, shuffle: bool) -> Result<()> {
-        self.state_tx
-            .as_ref()
-            .unwrap()
+        let Some(state_tx) = &self.state_tx else {
+            return Ok(());
+        };
+        state_tx
             .send_blocking(MprisStateUpdate::Shuffle(shuffle))
             .map_err(|e| anyhow!("Failed to send shuffle update: {}", e))
     }
 
     /// Update navigation capabilities
     pub fn update_navigation(&self, can_go_next: bool, can_go_previous: bool) -> Result<()> {
-        self.state_tx
-            .as_ref()
-            .unwrap()
+        let Some(state_tx) = &self.state_tx else {
+            return Ok(());
+        };
+        state_tx
             .send_blocking(MprisStateUpdate::CanGoNext(can_go_next))?;
-        self.state_tx
-            .as_ref()
-            .unwrap()
+        state_tx
             .send_blocking(MprisStateUpdate::CanGoPrevious(can_go_previous))
             .map_err(|e| anyhow!("Failed to send navigation update: {}", e))
     }
@@ -185,7 +222,7 @@ impl MprisController {
                 match select(state_fut, stop_fut).await {
                     futures::future::Either::Left((Ok(update), _)) => {
                         if let Err(e) = handle_state_update(&player_clone, update).await {
-                            eprintln!("Error handling state update: {}", e);
+                            tracing::warn!("Error handling state update: {}", e);
                         }
                         continue;
                     }
@@ -207,9 +244,8 @@ impl MprisController {
 
         // Run player event loop with combined event handler
         match select(player_run, event_loop).await {
-            futures::future::Either::Left((run_result, _)) => {
+            futures::future::Either::Left(_) => {
                 // player.run() completed (maybe error)
-                run_result;
             }
             futures::future::Either::Right((_, _)) => {
                 // event loop terminated (stop signal or state channel closed)
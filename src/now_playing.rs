@@ -0,0 +1,374 @@
+//! Now-playing state shared with other programs
+//! The session writes a small JSON state file that `mfp nowplaying` (and status bars)
+//! read back, plus an optional plain-text line for OBS, tmux and the like.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::feed::{extract_curator, extract_episode_number};
+use crate::history::unix_now;
+use crate::i18n::{t, tf, Msg};
+use crate::paths;
+use crate::player::format_duration;
+use crate::session::{SessionEvent, SessionListener};
+
+/// Template used when neither `--format` nor `now_playing_format` is given
+pub const DEFAULT_FORMAT: &str = "{title} [{elapsed}/{duration}]";
+
+/// Default text template of `mfp status`
+pub const DEFAULT_STATUS_FORMAT: &str = "{elapsed} {title}";
+
+/// Default maximum length of the `mfp status` text, in characters
+pub const DEFAULT_STATUS_MAX_LENGTH: usize = 40;
+
+/// Longest line written to the now-playing file, in characters
+const MAX_LINE_CHARS: usize = 200;
+
+/// Every `{token}` a template may use
+pub const TOKENS: &[&str] = &[
+    "title",
+    "number",
+    "curator",
+    "elapsed",
+    "duration",
+    "remaining",
+    "percent",
+    "volume",
+    "state",
+    "status",
+    "shuffle",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Playing,
+    Paused,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NowPlaying {
+    pub title: String,
+    pub duration_secs: u64,
+    pub status: Status,
+    /// Playback position when the state was written
+    pub elapsed_secs: u64,
+    /// Unix timestamp of the write, used to extrapolate the position while playing
+    pub updated_at: u64,
+    /// Volume in percent; None from versions that didn't record it
+    #[serde(default)]
+    pub volume_percent: Option<u16>,
+    #[serde(default)]
+    pub shuffle: bool,
+}
+
+impl NowPlaying {
+    fn state_path() -> Option<PathBuf> {
+        paths::now_playing_file().ok()
+    }
+
+    /// Current state, or None when nothing is playing
+    pub fn load() -> Option<Self> {
+        let content = fs::read(Self::state_path()?).ok()?;
+        serde_json::from_slice(&content).ok()
+    }
+
+    fn save(&self) -> Result<()> {
+        let path = Self::state_path().context("Failed to find cache directory")?;
+        let content = serde_json::to_vec(self).context("Failed to serialize now-playing state")?;
+        write_atomic(&path, &content)
+    }
+
+    fn clear() {
+        if let Some(path) = Self::state_path() {
+            let _ = fs::remove_file(path);
+        }
+    }
+
+    /// Playback position right now, extrapolated from the last write while playing
+    pub fn elapsed_now(&self) -> u64 {
+        let elapsed = match self.status {
+            Status::Playing => self.elapsed_secs + unix_now().saturating_sub(self.updated_at),
+            Status::Paused => self.elapsed_secs,
+        };
+
+        if self.duration_secs > 0 {
+            elapsed.min(self.duration_secs)
+        } else {
+            elapsed
+        }
+    }
+
+    /// Fill the [`TOKENS`] in `template`; anything else in braces is kept as written
+    pub fn render(&self, template: &str) -> String {
+        let elapsed = self.elapsed_now();
+
+        fill(template, |token| {
+            Some(match token {
+                "title" => self.title.clone(),
+                "number" => {
+                    extract_episode_number(&self.title).map_or(String::new(), |n| n.to_string())
+                }
+                "curator" => extract_curator(&self.title).unwrap_or_default().to_string(),
+                "elapsed" => format_duration(elapsed),
+                "duration" => format_duration(self.duration_secs),
+                "remaining" => format_duration(self.duration_secs.saturating_sub(elapsed)),
+                "percent" => match self.duration_secs {
+                    0 => String::new(),
+                    total => (elapsed * 100 / total).to_string(),
+                },
+                "volume" => self.volume_percent.map_or(String::new(), |v| v.to_string()),
+                "state" | "status" => match self.status {
+                    Status::Playing => t(Msg::Playing),
+                    Status::Paused => t(Msg::Paused),
+                }
+                .to_string(),
+                "shuffle" => if self.shuffle {
+                    t(Msg::On)
+                } else {
+                    t(Msg::Off)
+                }
+                .to_string(),
+                _ => return None,
+            })
+        })
+    }
+}
+
+/// Fail on a `{token}` that isn't one of [`TOKENS`], listing the valid ones
+pub fn check_template(template: &str) -> Result<()> {
+    let mut unknown = None;
+    fill(template, |token| {
+        if !TOKENS.contains(&token) && unknown.is_none() {
+            unknown = Some(token.to_string());
+        }
+        None
+    });
+
+    match unknown {
+        Some(token) => bail!(tf(Msg::UnknownFormatToken, &[&token, &TOKENS.join(", ")])),
+        None => Ok(()),
+    }
+}
+
+/// Replace every `{name}` (letters and underscores) for which `value` has something
+fn fill(template: &str, mut value: impl FnMut(&str) -> Option<String>) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        let after = &rest[open + 1..];
+        let name_len = after
+            .find(|c: char| !(c.is_ascii_lowercase() || c == '_'))
+            .unwrap_or(after.len());
+
+        if name_len > 0 && after[name_len..].starts_with('}') {
+            let name = &after[..name_len];
+            match value(name) {
+                Some(text) => out.push_str(&text),
+                None => out.push_str(&rest[open..open + name_len + 2]),
+            }
+            rest = &after[name_len + 1..];
+        } else {
+            out.push('{');
+            rest = after;
+        }
+    }
+
+    out.push_str(rest);
+    out
+}
+
+/// One line of Waybar custom-module JSON. Stopped renders as an empty "stopped" object.
+pub fn waybar_json(state: Option<&NowPlaying>, template: &str, max_length: usize) -> String {
+    let (text, tooltip, class) = match state {
+        Some(state) => (
+            truncate(&state.render(template), max_length),
+            state.render("{title}\n{status} {elapsed}/{duration}"),
+            match state.status {
+                Status::Playing => "playing",
+                Status::Paused => "paused",
+            },
+        ),
+        None => (String::new(), String::new(), "stopped"),
+    };
+
+    serde_json::json!({
+        "text": text,
+        "tooltip": tooltip,
+        "class": class,
+    })
+    .to_string()
+}
+
+/// Session listener keeping the state file (and the optional text file) up to date
+pub struct NowPlayingWriter {
+    text_file: Option<PathBuf>,
+    template: String,
+    current: Option<NowPlaying>,
+    /// Last volume and shuffle reported, carried over to every new episode
+    volume_percent: Option<u16>,
+    shuffle: bool,
+}
+
+impl NowPlayingWriter {
+    pub fn new(text_file: Option<PathBuf>, template: String) -> Self {
+        Self {
+            text_file,
+            template,
+            current: None,
+            volume_percent: None,
+            shuffle: false,
+        }
+    }
+
+    fn update(&mut self, status: Status, elapsed_secs: u64) {
+        let Some(state) = self.current.as_mut() else {
+            return;
+        };
+        state.status = status;
+        state.elapsed_secs = elapsed_secs;
+        state.updated_at = unix_now();
+        self.write();
+    }
+
+    fn write(&self) {
+        let Some(state) = &self.current else {
+            return;
+        };
+
+        if let Err(e) = state.save() {
+            tracing::warn!("Failed to write now-playing state: {:#}", e);
+        }
+
+        if let Some(path) = &self.text_file {
+            let mut line = truncate(&state.render(&self.template), MAX_LINE_CHARS);
+            line.push('\n');
+            if let Err(e) = write_atomic(path, line.as_bytes()) {
+                tracing::warn!("Failed to write now-playing file: {:#}", e);
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.current = None;
+        NowPlaying::clear();
+        if let Some(path) = &self.text_file {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+impl SessionListener for NowPlayingWriter {
+    fn on_event(&mut self, event: &SessionEvent) {
+        match event {
+            SessionEvent::TrackStarted {
+                title,
+                duration_secs,
+                ..
+            } => {
+                self.current = Some(NowPlaying {
+                    title: title.clone(),
+                    duration_secs: *duration_secs,
+                    status: Status::Playing,
+                    elapsed_secs: 0,
+                    updated_at: unix_now(),
+                    volume_percent: self.volume_percent,
+                    shuffle: self.shuffle,
+                });
+                self.write();
+            }
+            SessionEvent::SettingsChanged {
+                volume_percent,
+                shuffle,
+            } => {
+                self.volume_percent = Some(*volume_percent);
+                self.shuffle = *shuffle;
+                if let Some(state) = self.current.as_mut() {
+                    state.volume_percent = self.volume_percent;
+                    state.shuffle = self.shuffle;
+                    self.write();
+                }
+            }
+            SessionEvent::Paused { elapsed_secs } => self.update(Status::Paused, *elapsed_secs),
+            SessionEvent::Resumed { elapsed_secs } => self.update(Status::Playing, *elapsed_secs),
+            SessionEvent::Stopped => self.clear(),
+            SessionEvent::TrackEnded { .. } => {}
+        }
+    }
+}
+
+impl Drop for NowPlayingWriter {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+/// Replace `path` in one step so readers never see a half-written file
+fn write_atomic(path: &Path, content: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+
+    fs::write(&tmp, content).with_context(|| format!("Failed to write {}", tmp.display()))?;
+    fs::rename(&tmp, path).with_context(|| format!("Failed to replace {}", path.display()))
+}
+
+/// Cut `text` to at most `max` characters (never inside one), marking the cut with an ellipsis
+pub fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+
+    let mut cut: String = text.chars().take(max.saturating_sub(1)).collect();
+    cut.push('…');
+    cut
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state() -> NowPlaying {
+        NowPlaying {
+            title: "Episode 75: Datassette".to_string(),
+            duration_secs: 3600,
+            status: Status::Paused,
+            elapsed_secs: 900,
+            updated_at: 0,
+            volume_percent: Some(80),
+            shuffle: true,
+        }
+    }
+
+    #[test]
+    fn tokens_are_filled_from_the_state() {
+        assert_eq!(
+            state().render(
+                "{number} {curator} [{elapsed}/{duration}] -{remaining} {percent}% {volume}"
+            ),
+            "75 Datassette [15:00/01:00:00] -45:00 25% 80"
+        );
+    }
+
+    #[test]
+    fn other_braces_are_left_alone() {
+        assert_eq!(state().render("{ {title"), "{ {title");
+        assert_eq!(state().render("{nope} {number}"), "{nope} 75");
+    }
+
+    #[test]
+    fn unknown_tokens_are_refused() {
+        assert!(check_template("{title} {state} {shuffle}").is_ok());
+        assert!(check_template("{title} {nope}").is_err());
+        assert!(check_template("{ not a token }").is_ok());
+    }
+}
@@ -0,0 +1,83 @@
+//! Seeking by steps from the keyboard (arrow keys, `,` and `.`)
+//! Presses in quick succession add up to a single seek once the keys settle, so holding an
+//! arrow down doesn't restart the decoder, and maybe the download, on every repeat.
+
+use std::time::{Duration, Instant};
+
+/// Quiet time after the last press before the seek is made
+pub const SETTLE: Duration = Duration::from_millis(200);
+
+/// Steps pressed but not yet seeked to
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Nudge {
+    /// Where the steps add up to, and when the last one was pressed
+    pending: Option<(u64, Instant)>,
+}
+
+impl Nudge {
+    /// Move `secs` on (back when negative) from the steps pending, or from `elapsed` when
+    /// there are none. The target stays within the episode; `total` 0 means its length is
+    /// unknown.
+    pub fn step(&mut self, elapsed: u64, secs: i64, total: u64, now: Instant) {
+        let from = self.target().unwrap_or(elapsed);
+        let mut target = from.saturating_add_signed(secs);
+        if total > 0 {
+            target = target.min(total);
+        }
+        self.pending = Some((target, now));
+    }
+
+    /// Where the steps pressed so far lead, for the progress line to show at once
+    pub fn target(&self) -> Option<u64> {
+        self.pending.map(|(target, _)| target)
+    }
+
+    /// The seek to make, once no step has come for [`SETTLE`]
+    pub fn settled(&mut self, now: Instant) -> Option<u64> {
+        match self.pending {
+            Some((target, pressed)) if now.duration_since(pressed) >= SETTLE => {
+                self.pending = None;
+                Some(target)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quick_presses_add_up_to_one_seek() {
+        let start = Instant::now();
+        let mut nudge = Nudge::default();
+        nudge.step(100, 10, 3600, start);
+        nudge.step(100, 10, 3600, start + Duration::from_millis(100));
+        nudge.step(100, -60, 3600, start + Duration::from_millis(250));
+        assert_eq!(nudge.target(), Some(60));
+
+        // Not while the keys keep coming
+        assert_eq!(nudge.settled(start + Duration::from_millis(400)), None);
+        assert_eq!(nudge.settled(start + Duration::from_millis(450)), Some(60));
+        assert_eq!(nudge.target(), None);
+        assert_eq!(nudge.settled(start + Duration::from_secs(1)), None);
+    }
+
+    #[test]
+    fn steps_stop_at_the_start_and_the_end() {
+        let now = Instant::now();
+        let mut nudge = Nudge::default();
+        nudge.step(5, -10, 3600, now);
+        assert_eq!(nudge.target(), Some(0));
+
+        let mut nudge = Nudge::default();
+        nudge.step(3590, 60, 3600, now);
+        assert_eq!(nudge.target(), Some(3600));
+
+        // No known length, no end to stop at
+        let mut nudge = Nudge::default();
+        nudge.step(3590, 60, 0, now);
+        assert_eq!(nudge.target(), Some(3650));
+    }
+}
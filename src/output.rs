@@ -0,0 +1,48 @@
+//! Informational output that `--quiet` suppresses
+//! Requested output (lists, JSON, summaries) keeps using println! directly.
+
+use crossterm::cursor::MoveToColumn;
+use crossterm::execute;
+use crossterm::terminal::{Clear, ClearType};
+use std::fmt::Display;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// Print a status line unless running quiet
+pub fn status(text: impl Display) {
+    if !is_quiet() {
+        println!("{}", text);
+    }
+}
+
+/// Overwrite the current line with a progress update unless running quiet
+pub fn progress(text: impl Display) {
+    if !is_quiet() {
+        clear_line();
+        print!("{}", text);
+        io::stdout().flush().ok();
+    }
+}
+
+/// Replace a progress line with a final status line unless running quiet
+pub fn progress_done(text: impl Display) {
+    if !is_quiet() {
+        clear_line();
+        println!("{}", text);
+    }
+}
+
+// Clear sequences rather than `\r` and padding, which leaves artifacts in conhost
+fn clear_line() {
+    let _ = execute!(io::stdout(), Clear(ClearType::CurrentLine), MoveToColumn(0));
+}
@@ -0,0 +1,312 @@
+//! Where mfp keeps its files
+//! Every state path is built here: settings in the config dir, history and downloads in the
+//! data dir, rebuildable files in the cache dir. `--config-dir` or `MFP_CONFIG_DIR` moves all
+//! of them under one directory, for tests, sandboxes and separate profiles.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tracing::{info, warn};
+
+use crate::errors::StorageError;
+use crate::station;
+
+/// Environment variable equivalent of `--config-dir`
+pub const ENV_VAR: &str = "MFP_CONFIG_DIR";
+
+const HISTORY: &str = "history.jsonl";
+const SCROBBLE_QUEUE: &str = "scrobble_queue.jsonl";
+const DOWNLOADS: &str = "downloads";
+
+/// What older versions kept in the config dir and now belongs in the data dir
+const MOVED_TO_DATA: &[&str] = &[HISTORY, SCROBBLE_QUEUE, DOWNLOADS];
+
+static ROOT: OnceLock<PathBuf> = OnceLock::new();
+
+/// Keep every file under `dir` (the `--config-dir` flag, which wins over the environment).
+/// Only the first call has an effect; call it before anything reads a path.
+pub fn set_root(dir: PathBuf) {
+    let _ = ROOT.set(dir);
+}
+
+fn root() -> Option<PathBuf> {
+    ROOT.get().cloned().or_else(|| {
+        env::var_os(ENV_VAR)
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+    })
+}
+
+/// Settings: config.toml and favorites
+pub fn config_dir() -> Result<PathBuf, StorageError> {
+    match root() {
+        Some(root) => Ok(root),
+        None => Ok(dirs::config_dir()
+            .ok_or(StorageError::NoDirectory)?
+            .join("mfp")),
+    }
+}
+
+/// Data that grows with use: history, scrobble queue, downloads
+pub fn data_dir() -> Result<PathBuf, StorageError> {
+    match root() {
+        Some(root) => Ok(root),
+        None => Ok(dirs::data_dir()
+            .ok_or(StorageError::NoDirectory)?
+            .join("mfp")),
+    }
+}
+
+/// Files that can be rebuilt at any time: feed cache, now-playing state
+pub fn cache_dir() -> Result<PathBuf, StorageError> {
+    match root() {
+        Some(root) => Ok(root.join("cache")),
+        None => Ok(dirs::cache_dir()
+            .ok_or(StorageError::NoDirectory)?
+            .join("mfp")),
+    }
+}
+
+pub fn config_file() -> Result<PathBuf, StorageError> {
+    Ok(config_dir()?.join("config.toml"))
+}
+
+pub fn favorites_file() -> Result<PathBuf, StorageError> {
+    Ok(config_dir()?.join("favorites.json"))
+}
+
+/// Named playlists
+pub fn playlists_file() -> Result<PathBuf, StorageError> {
+    Ok(config_dir()?.join("playlists.json"))
+}
+
+/// Player state such as per-episode volume offsets
+pub fn state_file() -> Result<PathBuf, StorageError> {
+    Ok(config_dir()?.join("state.json"))
+}
+
+pub fn history_file() -> Result<PathBuf, StorageError> {
+    Ok(data_dir()?.join(HISTORY))
+}
+
+/// Scrobbles waiting for the service to come back
+pub fn scrobble_queue_file() -> Result<PathBuf, StorageError> {
+    Ok(data_dir()?.join(SCROBBLE_QUEUE))
+}
+
+/// Notes taken while listening
+pub fn journal_file() -> Result<PathBuf, StorageError> {
+    Ok(data_dir()?.join("journal.jsonl"))
+}
+
+/// Where downloads go unless `download_dir` is set in the config
+pub fn download_dir() -> Result<PathBuf, StorageError> {
+    Ok(data_dir()?.join(DOWNLOADS))
+}
+
+/// Episode list of the active station as last fetched
+pub fn feed_cache_file() -> Result<PathBuf, StorageError> {
+    match station::active() {
+        station::DEFAULT => Ok(cache_dir()?.join("feed.json")),
+        name => Ok(cache_dir()?.join("feeds").join(format!("{}.json", name))),
+    }
+}
+
+/// Temporary files of streams played with `stream_to_disk`
+pub fn stream_spool_dir() -> Result<PathBuf, StorageError> {
+    Ok(cache_dir()?.join("stream"))
+}
+
+pub fn now_playing_file() -> Result<PathBuf, StorageError> {
+    Ok(cache_dir()?.join("now_playing.json"))
+}
+
+/// Files that only mean something while mfp runs: the instance lock. Under `--config-dir`
+/// they go in its cache dir.
+pub fn runtime_dir() -> Result<PathBuf, StorageError> {
+    if root().is_some() {
+        return cache_dir();
+    }
+    match dirs::runtime_dir() {
+        Some(dir) => Ok(dir.join("mfp")),
+        None => cache_dir(),
+    }
+}
+
+/// Held by the instance that plays or changes state
+pub fn lock_file() -> Result<PathBuf, StorageError> {
+    Ok(runtime_dir()?.join("mfp.lock"))
+}
+
+/// Written by `--takeover` to ask the running instance to stop
+pub fn takeover_file() -> Result<PathBuf, StorageError> {
+    Ok(runtime_dir()?.join("mfp.takeover"))
+}
+
+/// Data files moved out of the config dir by [`migrate`]
+#[derive(Debug)]
+pub struct Migration {
+    pub from: PathBuf,
+    pub to: PathBuf,
+    pub files: usize,
+}
+
+/// Move data left in the config dir by older versions into the data dir.
+///
+/// Runs on every start and does nothing once the old files are gone. Each file is renamed
+/// (or, across filesystems, copied under a temporary name and renamed into place) before the
+/// original is removed, so an interrupted run loses nothing and the next one finishes the job.
+pub fn migrate() -> Result<Option<Migration>, StorageError> {
+    if root().is_some() {
+        return Ok(None);
+    }
+
+    let from = dirs::config_dir()
+        .ok_or(StorageError::NoDirectory)?
+        .join("mfp");
+    let to = data_dir()?;
+    // Same directory on macOS and Windows
+    if from == to {
+        return Ok(None);
+    }
+
+    let files = move_data(&from, &to)?;
+    if files == 0 {
+        return Ok(None);
+    }
+
+    info!(files, from = %from.display(), to = %to.display(), "Migrated data files");
+    Ok(Some(Migration { from, to, files }))
+}
+
+/// Move the `MOVED_TO_DATA` entries from `from` to `to`, returning how many files moved
+fn move_data(from: &Path, to: &Path) -> Result<usize, StorageError> {
+    let mut files = 0;
+    for name in MOVED_TO_DATA {
+        let old = from.join(name);
+        if old.is_dir() {
+            for entry in fs::read_dir(&old).map_err(StorageError::read(&old))? {
+                let path = entry.map_err(StorageError::read(&old))?.path();
+                if path.is_file()
+                    && move_file(&path, &to.join(name).join(path.file_name().unwrap()))?
+                {
+                    files += 1;
+                }
+            }
+            // Only goes away once empty
+            let _ = fs::remove_dir(&old);
+        } else if old.is_file() && move_file(&old, &to.join(name))? {
+            files += 1;
+        }
+    }
+
+    Ok(files)
+}
+
+/// Move `from` to `to`; false when a different file already sits at `to` and both are kept
+fn move_file(from: &Path, to: &Path) -> Result<bool, StorageError> {
+    if to.exists() {
+        // An interrupted run got the copy in place but not the original removed
+        if same_size(from, to) {
+            fs::remove_file(from).map_err(StorageError::write(from))?;
+            return Ok(true);
+        }
+        warn!(from = %from.display(), to = %to.display(), "Not migrating over an existing file");
+        return Ok(false);
+    }
+
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent).map_err(StorageError::write(parent))?;
+    }
+
+    if fs::rename(from, to).is_err() {
+        // Different filesystems: copy beside the target first so `to` is never half-written
+        let mut temp = to.as_os_str().to_owned();
+        temp.push(".migrating");
+        let temp = PathBuf::from(temp);
+
+        fs::copy(from, &temp).map_err(StorageError::write(&temp))?;
+        fs::rename(&temp, to).map_err(StorageError::write(to))?;
+        fs::remove_file(from).map_err(StorageError::write(from))?;
+    }
+
+    Ok(true)
+}
+
+fn same_size(a: &Path, b: &Path) -> bool {
+    match (fs::metadata(a), fs::metadata(b)) {
+        (Ok(a), Ok(b)) => a.len() == b.len(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("mfp-paths-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("config")).unwrap();
+        dir
+    }
+
+    #[test]
+    fn moves_data_and_leaves_settings() {
+        let dir = temp_dir("move");
+        let (from, to) = (dir.join("config"), dir.join("data"));
+        fs::write(from.join("config.toml"), "volume = 50").unwrap();
+        fs::write(from.join(HISTORY), "{}\n").unwrap();
+        fs::create_dir_all(from.join(DOWNLOADS)).unwrap();
+        fs::write(from.join(DOWNLOADS).join("Episode 1.mp3"), [1, 2, 3]).unwrap();
+
+        assert_eq!(move_data(&from, &to).unwrap(), 2);
+        assert!(from.join("config.toml").exists());
+        assert!(!from.join(HISTORY).exists());
+        assert!(!from.join(DOWNLOADS).exists());
+        assert_eq!(fs::read(to.join(HISTORY)).unwrap(), b"{}\n");
+        assert_eq!(
+            fs::read(to.join(DOWNLOADS).join("Episode 1.mp3")).unwrap(),
+            [1, 2, 3]
+        );
+
+        // Nothing left to do the second time
+        assert_eq!(move_data(&from, &to).unwrap(), 0);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn finishes_an_interrupted_move() {
+        let dir = temp_dir("interrupted");
+        let (from, to) = (dir.join("config"), dir.join("data"));
+        fs::create_dir_all(&to).unwrap();
+        fs::write(from.join(HISTORY), "same").unwrap();
+        fs::write(to.join(HISTORY), "same").unwrap();
+
+        assert_eq!(move_data(&from, &to).unwrap(), 1);
+        assert!(!from.join(HISTORY).exists());
+        assert_eq!(fs::read_to_string(to.join(HISTORY)).unwrap(), "same");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn keeps_both_when_the_target_differs() {
+        let dir = temp_dir("conflict");
+        let (from, to) = (dir.join("config"), dir.join("data"));
+        fs::create_dir_all(&to).unwrap();
+        fs::write(from.join(HISTORY), "old entries").unwrap();
+        fs::write(to.join(HISTORY), "new").unwrap();
+
+        assert_eq!(move_data(&from, &to).unwrap(), 0);
+        assert_eq!(
+            fs::read_to_string(from.join(HISTORY)).unwrap(),
+            "old entries"
+        );
+        assert_eq!(fs::read_to_string(to.join(HISTORY)).unwrap(), "new");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,443 @@
+//! Interactive episode picker
+//! Type to fuzzy-filter the episode list, move with the arrows and press Enter to play.
+
+use anyhow::Result;
+use crossterm::{
+    cursor::{Hide, MoveTo, Show},
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute, queue,
+    terminal::{self, disable_raw_mode, enable_raw_mode, Clear, ClearType},
+    terminal::{EnterAlternateScreen, LeaveAlternateScreen},
+};
+use std::borrow::Borrow;
+use std::collections::HashSet;
+use std::io::{self, Write};
+
+use crate::favorites::Favorites;
+use crate::feed::Episode;
+use crate::i18n::{t, tf, Msg};
+use crate::now_playing::truncate;
+use crate::style::{self, Role};
+
+/// What the user picked
+#[derive(Debug, Default)]
+pub struct Picked {
+    /// Index of the episode to play, None if the picker was left with Esc
+    pub play: Option<usize>,
+    /// Indexes of the episodes queued for download, in queueing order
+    pub downloads: Vec<usize>,
+}
+
+/// Per-episode markers shown next to the titles
+pub struct Markers {
+    pub downloaded: HashSet<String>,
+    pub played: HashSet<String>,
+}
+
+struct State<'a> {
+    episodes: &'a [Episode],
+    query: String,
+    matches: Vec<usize>,
+    selected: usize,
+    scroll: usize,
+    queued: Vec<usize>,
+}
+
+impl State<'_> {
+    fn refilter(&mut self) {
+        let mut scored: Vec<(i64, usize)> = self
+            .episodes
+            .iter()
+            .enumerate()
+            .filter_map(|(i, ep)| fuzzy_score(&self.query, &ep.title).map(|score| (score, i)))
+            .collect();
+        // Best score first; the feed order (newest first) breaks ties
+        scored.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+
+        self.matches = scored.into_iter().map(|(_, i)| i).collect();
+        self.selected = 0;
+        self.scroll = 0;
+    }
+
+    fn current(&self) -> Option<usize> {
+        self.matches.get(self.selected).copied()
+    }
+
+    fn move_by(&mut self, delta: isize) {
+        if self.matches.is_empty() {
+            return;
+        }
+        let last = self.matches.len() as isize - 1;
+        self.selected = (self.selected as isize + delta).clamp(0, last) as usize;
+    }
+}
+
+pub fn run(episodes: &[Episode], favorites: &mut Favorites, markers: &Markers) -> Result<Picked> {
+    let mut state = State {
+        episodes,
+        query: String::new(),
+        matches: Vec::new(),
+        selected: 0,
+        scroll: 0,
+        queued: Vec::new(),
+    };
+    state.refilter();
+
+    let mut out = io::stdout();
+    enable_raw_mode()?;
+    execute!(out, EnterAlternateScreen, Hide)?;
+
+    let result = pick(&mut out, &mut state, favorites, markers);
+
+    execute!(out, Show, LeaveAlternateScreen)?;
+    disable_raw_mode()?;
+
+    let play = result?;
+    Ok(Picked {
+        play,
+        downloads: state.queued,
+    })
+}
+
+fn pick(
+    out: &mut impl Write,
+    state: &mut State,
+    favorites: &mut Favorites,
+    markers: &Markers,
+) -> Result<Option<usize>> {
+    loop {
+        draw(out, state, favorites, markers)?;
+
+        let Event::Key(KeyEvent {
+            code,
+            modifiers,
+            kind: KeyEventKind::Press,
+            ..
+        }) = event::read()?
+        else {
+            // Resizes are handled by the redraw at the top of the loop, and releases
+            // (reported on Windows) would act on every key twice
+            continue;
+        };
+        let ctrl = modifiers.contains(KeyModifiers::CONTROL);
+
+        match code {
+            KeyCode::Esc => return Ok(None),
+            KeyCode::Char('c') if ctrl => return Ok(None),
+            KeyCode::Enter => {
+                if let Some(i) = state.current() {
+                    return Ok(Some(i));
+                }
+            }
+            KeyCode::Char('f') if ctrl => {
+                if let Some(i) = state.current() {
+                    favorites.toggle(state.episodes[i].title.clone());
+                }
+            }
+            KeyCode::Char('d') if ctrl => {
+                if let Some(i) = state.current() {
+                    match state.queued.iter().position(|&q| q == i) {
+                        Some(pos) => {
+                            state.queued.remove(pos);
+                        }
+                        None => state.queued.push(i),
+                    }
+                }
+            }
+            KeyCode::Up => state.move_by(-1),
+            KeyCode::Down => state.move_by(1),
+            KeyCode::PageUp => state.move_by(-(list_height() as isize)),
+            KeyCode::PageDown => state.move_by(list_height() as isize),
+            KeyCode::Backspace => {
+                state.query.pop();
+                state.refilter();
+            }
+            KeyCode::Char(c) if !ctrl => {
+                state.query.push(c);
+                state.refilter();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Rows available for episodes (the prompt and the hint take one line each)
+fn list_height() -> usize {
+    terminal::size()
+        .map(|(_, rows)| rows as usize)
+        .unwrap_or(24)
+        .saturating_sub(2)
+        .max(1)
+}
+
+fn draw(
+    out: &mut impl Write,
+    state: &mut State,
+    favorites: &Favorites,
+    markers: &Markers,
+) -> Result<()> {
+    let width = terminal::size()
+        .map(|(cols, _)| cols as usize)
+        .unwrap_or(80);
+    let height = list_height();
+
+    if state.selected < state.scroll {
+        state.scroll = state.selected;
+    } else if state.selected >= state.scroll + height {
+        state.scroll = state.selected + 1 - height;
+    }
+
+    queue!(out, MoveTo(0, 0), Clear(ClearType::All))?;
+    write!(
+        out,
+        "{} {}\r\n",
+        style::paint(Role::Accent, ">"),
+        state.query
+    )?;
+
+    if state.matches.is_empty() {
+        write!(
+            out,
+            "{}\r\n",
+            style::paint(Role::Dim, t(Msg::PickerNoMatches))
+        )?;
+    }
+
+    for (row, &i) in state
+        .matches
+        .iter()
+        .enumerate()
+        .skip(state.scroll)
+        .take(height)
+    {
+        let episode = &state.episodes[i];
+        let marker = |on: bool, role: Role, symbol: &str| {
+            if on {
+                style::paint(role, symbol)
+            } else {
+                " ".to_string()
+            }
+        };
+        let markers = format!(
+            "{}{}{}{}",
+            marker(favorites.is_favorite(&episode.title), Role::Favorite, "*"),
+            marker(
+                markers.downloaded.contains(&episode.title),
+                Role::Success,
+                "↓"
+            ),
+            marker(markers.played.contains(&episode.title), Role::Dim, "✓"),
+            marker(state.queued.contains(&i), Role::Accent, "+"),
+        );
+        // Four marker columns, the cursor and two spaces
+        let title = truncate(&episode.title, width.saturating_sub(7));
+
+        if row == state.selected {
+            write!(
+                out,
+                "{} {} {}\r\n",
+                style::paint(Role::Accent, "›"),
+                markers,
+                style::paint(Role::Title, &title)
+            )?;
+        } else {
+            write!(out, "  {} {}\r\n", markers, title)?;
+        }
+    }
+
+    queue!(out, MoveTo(0, height as u16 + 1))?;
+    write!(
+        out,
+        "{}",
+        style::paint(
+            Role::Dim,
+            &truncate(
+                &tf(
+                    Msg::PickerHint,
+                    &[&state.matches.len(), &state.episodes.len()]
+                ),
+                width
+            )
+        )
+    )?;
+    out.flush()?;
+
+    Ok(())
+}
+
+/// Case-insensitive subsequence match. Higher is better; consecutive and word-start
+/// matches score more. None when `query` doesn't match at all.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i64> {
+    let query: Vec<char> = query
+        .to_lowercase()
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+
+    let Some(&first) = query.first() else {
+        return Some(0);
+    };
+
+    // Greedy matching from the first occurrence alone misses better later runs
+    // ("data" in "Episode 75: Datassette"), so try every start
+    (0..text.len())
+        .filter(|&start| text[start] == first)
+        .filter_map(|start| score_from(&query, &text, start))
+        .max()
+}
+
+fn score_from(query: &[char], text: &[char], start: usize) -> Option<i64> {
+    let mut score = 0i64;
+    let mut pos = start;
+    let mut previous: Option<usize> = None;
+
+    for &q in query {
+        let found = pos + text[pos..].iter().position(|&c| c == q)?;
+
+        score += 10;
+        if previous.is_some_and(|p| p + 1 == found) {
+            score += 15;
+        }
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += 10;
+        }
+        score -= (found - pos) as i64;
+
+        previous = Some(found);
+        pos = found + 1;
+    }
+
+    Some(score)
+}
+
+/// Most candidates offered when a free-text query is ambiguous
+const SHORTLIST_LEN: usize = 9;
+
+/// Outcome of matching free text (`mfp play "boards of canada"`) against the episodes
+#[derive(Debug, PartialEq, Eq)]
+pub enum Resolution {
+    /// One confident match: play it
+    Unique(usize),
+    /// Several plausible matches, best first: let the user choose
+    Ambiguous(Vec<usize>),
+    NoMatch,
+}
+
+/// Substring matches win outright; otherwise fall back to fuzzy matches that keep
+/// most of the query together (scattered letters across a long title don't count). Takes
+/// the episodes or references to them, such as those of a playlist.
+pub fn resolve<E: Borrow<Episode>>(query: &str, episodes: &[E]) -> Resolution {
+    let episodes: Vec<&Episode> = episodes.iter().map(Borrow::borrow).collect();
+    let needle = query.trim().to_lowercase();
+    if needle.is_empty() {
+        return Resolution::NoMatch;
+    }
+
+    let substring: Vec<usize> = episodes
+        .iter()
+        .enumerate()
+        .filter(|(_, ep)| ep.title.to_lowercase().contains(&needle))
+        .map(|(i, _)| i)
+        .collect();
+
+    let candidates = if substring.is_empty() {
+        let min_score = 15 * needle.chars().filter(|c| !c.is_whitespace()).count() as i64;
+        let mut scored: Vec<(i64, usize)> = episodes
+            .iter()
+            .enumerate()
+            .filter_map(|(i, ep)| fuzzy_score(&needle, &ep.title).map(|score| (score, i)))
+            .filter(|(score, _)| *score >= min_score)
+            .collect();
+        scored.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+        scored.into_iter().map(|(_, i)| i).collect()
+    } else {
+        substring
+    };
+
+    match candidates.as_slice() {
+        [] => Resolution::NoMatch,
+        [only] => Resolution::Unique(*only),
+        _ => Resolution::Ambiguous(candidates.into_iter().take(SHORTLIST_LEN).collect()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn episodes(titles: &[&str]) -> Vec<Episode> {
+        titles
+            .iter()
+            .map(|title| Episode {
+                title: title.to_string(),
+                audio_url: String::new(),
+                duration: String::new(),
+                pub_date: String::new(),
+                description: String::new(),
+                page_url: None,
+                image_url: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn unique_substring_plays_immediately() {
+        let eps = episodes(&[
+            "Episode 63: Boards Of Canada",
+            "Episode 62: Datassette",
+            "Episode 61: Canadian Shield",
+        ]);
+        assert_eq!(resolve("boards of canada", &eps), Resolution::Unique(0));
+        assert_eq!(resolve("  DATASSETTE ", &eps), Resolution::Unique(1));
+    }
+
+    #[test]
+    fn several_substring_matches_are_ambiguous() {
+        let eps = episodes(&[
+            "Episode 63: Boards Of Canada",
+            "Episode 62: Datassette",
+            "Episode 61: Canadian Shield",
+        ]);
+        assert_eq!(resolve("canad", &eps), Resolution::Ambiguous(vec![0, 2]));
+    }
+
+    #[test]
+    fn falls_back_to_close_fuzzy_matches() {
+        let eps = episodes(&["Episode 63: Boards Of Canada", "Episode 62: Datassette"]);
+        assert_eq!(resolve("datasete", &eps), Resolution::Unique(1));
+    }
+
+    #[test]
+    fn scattered_or_missing_letters_do_not_match() {
+        let eps = episodes(&["Episode 63: Boards Of Canada", "Episode 62: Datassette"]);
+        assert_eq!(resolve("zzz", &eps), Resolution::NoMatch);
+        assert_eq!(resolve("eoc", &eps), Resolution::NoMatch);
+        assert_eq!(resolve("", &eps), Resolution::NoMatch);
+    }
+
+    #[test]
+    fn shortlist_is_capped() {
+        let titles: Vec<String> = (1..=20).map(|n| format!("Episode {}: Test", n)).collect();
+        let titles: Vec<&str> = titles.iter().map(String::as_str).collect();
+        match resolve("test", &episodes(&titles)) {
+            Resolution::Ambiguous(shortlist) => assert_eq!(shortlist.len(), SHORTLIST_LEN),
+            other => panic!("expected a shortlist, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn matches_subsequences_case_insensitively() {
+        assert!(fuzzy_score("dts", "Episode 75: Datassette").is_some());
+        assert!(fuzzy_score("DATA", "Episode 75: Datassette").is_some());
+        assert!(fuzzy_score("xyz", "Episode 75: Datassette").is_none());
+        assert!(fuzzy_score("", "anything").is_some());
+    }
+
+    #[test]
+    fn prefers_contiguous_word_matches() {
+        let contiguous = fuzzy_score("data", "Episode 75: Datassette").unwrap();
+        let scattered = fuzzy_score("data", "Episode 12: Dreams At The Abyss").unwrap();
+        assert!(contiguous > scattered);
+    }
+}
@@ -0,0 +1,143 @@
+//! Episode chosen through a pipe (`mfp play --stdin`)
+//! Accepts an episode number, a title, or one JSON value as printed by `mfp list --json`
+//! and jq: an object with a `number` or `title` field, a quoted title or a bare number.
+
+use anyhow::{bail, Result};
+use serde_json::Value;
+use std::io::Read;
+
+use crate::i18n::{t, tf, Msg};
+
+/// What the pipe asked for, still to be resolved against the feed
+#[derive(Debug, PartialEq, Eq)]
+pub enum Selection {
+    Number(usize),
+    Title(String),
+}
+
+/// Read all of `input` and parse it as one episode
+pub fn read(mut input: impl Read) -> Result<Selection> {
+    let mut text = String::new();
+    if let Err(e) = input.read_to_string(&mut text) {
+        bail!(tf(Msg::StdinUnreadable, &[&e]));
+    }
+
+    parse(&text)
+}
+
+pub fn parse(input: &str) -> Result<Selection> {
+    let text = input.trim();
+
+    match text.chars().next() {
+        None => bail!(t(Msg::StdinEmpty)),
+        Some('{' | '[' | '"') => parse_json(text),
+        // What jq prints for a field that isn't there
+        Some(_) if matches!(text, "null" | "true" | "false") => parse_json(text),
+        Some(_) => {
+            let lines: Vec<&str> = text
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .collect();
+            if lines.len() > 1 {
+                bail!(tf(Msg::StdinSeveralLines, &[&lines.len()]));
+            }
+
+            Ok(parse_line(lines[0]))
+        }
+    }
+}
+
+/// `75` or `#75` is a number, anything else a title
+fn parse_line(line: &str) -> Selection {
+    match line.strip_prefix('#').unwrap_or(line).parse() {
+        Ok(number) => Selection::Number(number),
+        Err(_) => Selection::Title(line.to_string()),
+    }
+}
+
+fn parse_json(text: &str) -> Result<Selection> {
+    let mut values = serde_json::Deserializer::from_str(text).into_iter::<Value>();
+
+    let value = match values.next() {
+        Some(Ok(value)) => value,
+        Some(Err(e)) => bail!(tf(Msg::StdinBadJson, &[&e])),
+        None => bail!(t(Msg::StdinEmpty)),
+    };
+    match values.next() {
+        None => {}
+        Some(Ok(_)) => bail!(t(Msg::StdinSeveralValues)),
+        Some(Err(e)) => bail!(tf(Msg::StdinBadJson, &[&e])),
+    }
+
+    match value {
+        Value::Object(object) => match (object.get("number"), object.get("title")) {
+            (Some(number), _) => match number.as_u64() {
+                Some(number) => Ok(Selection::Number(number as usize)),
+                None => bail!(tf(Msg::StdinFieldType, &[&"number", &number])),
+            },
+            (None, Some(title)) => match title.as_str() {
+                Some(title) if !title.trim().is_empty() => {
+                    Ok(Selection::Title(title.trim().to_string()))
+                }
+                _ => bail!(tf(Msg::StdinFieldType, &[&"title", &title])),
+            },
+            (None, None) => bail!(t(Msg::StdinMissingField)),
+        },
+        Value::String(title) if !title.trim().is_empty() => Ok(parse_line(title.trim())),
+        Value::Number(number) => match number.as_u64() {
+            Some(number) => Ok(Selection::Number(number as usize)),
+            None => bail!(tf(Msg::StdinUnexpectedJson, &[&number])),
+        },
+        Value::Array(_) => bail!(t(Msg::StdinArray)),
+        other => bail!(tf(Msg::StdinUnexpectedJson, &[&other])),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(input: &str) -> String {
+        parse(input).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn numbers_and_titles() {
+        assert_eq!(parse("75\n").unwrap(), Selection::Number(75));
+        assert_eq!(parse("#75").unwrap(), Selection::Number(75));
+        assert_eq!(
+            parse("  Episode 75: Datassette \n\n").unwrap(),
+            Selection::Title("Episode 75: Datassette".to_string())
+        );
+    }
+
+    #[test]
+    fn json_values() {
+        assert_eq!(
+            parse(r#"{"number": 12, "title": "Episode 12: Box"}"#).unwrap(),
+            Selection::Number(12)
+        );
+        assert_eq!(
+            parse("{\n  \"title\": \"Episode 12: Box\"\n}").unwrap(),
+            Selection::Title("Episode 12: Box".to_string())
+        );
+        assert_eq!(
+            parse(r#""Episode 12: Box""#).unwrap(),
+            Selection::Title("Episode 12: Box".to_string())
+        );
+        assert_eq!(parse(r#""12""#).unwrap(), Selection::Number(12));
+    }
+
+    #[test]
+    fn malformed_input_says_what_is_wrong() {
+        assert!(parse("").is_err());
+        assert!(error("{\"title\": ").contains("line 1"));
+        assert!(error("{\"number\": \"twelve\"}").contains("number"));
+        assert!(error("{\"name\": \"x\"}").contains("\"title\""));
+        assert!(error("[1, 2]").contains("array"));
+        assert!(error("{\"number\": 1}\n{\"number\": 2}").contains("several"));
+        assert!(error("75\n76").contains("2 lines"));
+        assert!(error("null").contains("null"));
+    }
+}
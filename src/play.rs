@@ -0,0 +1,2396 @@
+//! The play loop behind `mfp play`
+//! Streams or plays from the downloads episode after episode, reading single keys and typed
+//! commands while it goes, until the playlist runs out or the listener quits.
+
+#[cfg(feature = "cast")]
+use crate::cast;
+#[cfg(feature = "discord")]
+use crate::discord;
+#[cfg(feature = "media-keys")]
+use crate::media_keys;
+#[cfg(feature = "scrobble")]
+use crate::scrobbler;
+use crate::{
+    ab_loop, browser, clipboard, commands, config, downloader, errors, favorites, feed, history,
+    hooks, i18n, journal, keys, lock, menu, mouse, mpris, now_playing, nudge, output, paths,
+    picker, player, playlist, playlists, progress, renderer, schedule, session, shutdown, state,
+    style,
+};
+
+use crate::app::{open_in_browser, undo_favorite, unknown_playlist};
+use crate::audio::AudioPlayer;
+use crate::terminal::{Events, Terminal};
+use ab_loop::AbLoop;
+use anyhow::{Context, Result};
+use chrono::Local;
+use commands::{Action, ToggleState};
+use config::Config;
+use crossterm::{
+    cursor,
+    event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEventKind},
+    terminal::disable_raw_mode,
+};
+use downloader::{DownloadProgress, Downloader};
+use errors::NotFound;
+use favorites::Favorites;
+use feed::{Episode, Feed, LazyFeed};
+use history::{History, HistoryRecorder};
+use i18n::{t, tf, Msg};
+use journal::{Journal, JournalEntry};
+use mpris::{MprisCommand, MprisController, PlaybackStatus};
+use now_playing::NowPlayingWriter;
+use nudge::Nudge;
+use picker::Resolution;
+#[cfg(feature = "audio")]
+use player::Player;
+use player::Starting;
+use playlist::{Playlist, RepeatMode};
+use playlists::{Edit, Playlists};
+use renderer::Renderer;
+use session::{Session, SessionEvent};
+use state::State;
+use std::cell::Cell;
+use std::io::{self, IsTerminal, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use style::Role;
+
+/// How long `--fade-in` takes to reach full volume
+const FADE_IN: Duration = Duration::from_secs(30);
+/// How long the volume ramps down when a signal stops playback
+const FADE_OUT: Duration = Duration::from_millis(500);
+const FADE_OUT_STEPS: u32 = 10;
+/// Percent `+` and `-` change the volume by, unless configured
+const VOLUME_STEP: u8 = 10;
+/// How far `bal+` and `bal-` move the balance
+const BALANCE_STEP: f32 = 0.1;
+/// Seconds the arrow keys seek by, and with shift, unless configured
+const SEEK_STEP_SECS: u64 = 10;
+const SEEK_LONG_STEP_SECS: u64 = 60;
+/// Seconds into an episode after which "previous" starts it over, unless configured
+const RESTART_THRESHOLD_SECS: u64 = 5;
+/// How long playback may wait for stream data, reconnects included, before asking what to do
+const STALL_PROMPT_AFTER: Duration = Duration::from_secs(20);
+/// How long the level meter may stay dark while playing before it warns
+const SILENCE_WARNING_AFTER: Duration = Duration::from_secs(5);
+/// dBFS under which the start of an episode counts as silence, when `trim_silence` is on
+const TRIM_SILENCE_DB: f32 = -50.0;
+/// Seconds the current episode must be downloaded ahead of the listener before the start of
+/// the next one is fetched
+const PREFETCH_AHEAD_SECS: u64 = 120;
+/// How often the play loop checks that the audio device is still there
+const DEVICE_PROBE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// The episode played last, if it was left unfinished, and where
+pub(crate) fn last_unfinished() -> Option<menu::Resume> {
+    History::load()
+        .unwrap_or_default()
+        .pop()
+        .filter(|entry| !entry.completed)
+        .map(|entry| menu::Resume {
+            title: entry.title,
+            position_secs: entry.listened_secs,
+        })
+}
+
+/// Where `play --continue` and the menu pick up: `options` with the episode left unfinished,
+/// where it was left rather than from the top, then the episodes queued at the time
+pub fn continued(options: PlayOptions) -> PlayOptions {
+    let resume = last_unfinished();
+    PlayOptions {
+        resume_secs: resume.as_ref().map(|resume| resume.position_secs),
+        resume_title: resume.map(|resume| resume.title),
+        restore_queue: true,
+        ..options
+    }
+}
+
+/// `mfp play` once `wait` is over, counting down meanwhile; with `resume` where
+/// `play --continue` picks up instead of `episode` and `query`. The feed is fetched only once
+/// the wait is over so it isn't stale.
+pub fn play_after(
+    wait: Option<Duration>,
+    resume: bool,
+    episode: Option<usize>,
+    query: Option<String>,
+    options: PlayOptions,
+) -> Result<()> {
+    if !wait.map_or(Ok(true), countdown)? {
+        return Ok(());
+    }
+
+    if resume {
+        play_radio(None, None, continued(options))
+    } else {
+        play_radio(episode, query.as_deref(), options)
+    }
+}
+
+/// Resolve `mfp play <title>`, asking when several episodes match.
+/// None when the user backs out of the shortlist.
+fn choose_episode(query: &str, episodes: &[&Episode]) -> Result<Option<String>> {
+    let shortlist = match picker::resolve(query, episodes) {
+        Resolution::Unique(i) => return Ok(Some(episodes[i].title.clone())),
+        Resolution::NoMatch => return Err(NotFound(tf(Msg::NoQueryMatch, &[&query])).into()),
+        Resolution::Ambiguous(shortlist) => shortlist,
+    };
+
+    println!("{}", tf(Msg::SeveralMatches, &[&query]));
+    for (n, &i) in shortlist.iter().enumerate() {
+        println!("  {}. {}", n + 1, episodes[i].title);
+    }
+
+    if !io::stdin().is_terminal() {
+        anyhow::bail!(t(Msg::AmbiguousQuery));
+    }
+
+    print!("{}", tf(Msg::ChooseEpisode, &[&shortlist.len()]));
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+
+    Ok(answer
+        .trim()
+        .parse::<usize>()
+        .ok()
+        .and_then(|n| shortlist.get(n.checked_sub(1)?))
+        .map(|&i| episodes[i].title.clone()))
+}
+
+/// How `play` runs, apart from the episode it starts at
+#[derive(Default)]
+pub struct PlayOptions {
+    pub shuffle: bool,
+    /// Only favorites
+    pub favorites: bool,
+    /// Named playlist to play instead of the feed
+    pub playlist: Option<String>,
+    /// Keyword the episodes must mention
+    pub filter: Option<String>,
+    /// Volume to start at in percent, over the remembered one
+    pub volume: Option<u8>,
+    pub fade_in: bool,
+    /// Stop playing after this long
+    pub sleep: Option<Duration>,
+    pub now_playing_file: Option<PathBuf>,
+    /// Seconds to sample of each episode
+    pub preview: Option<u64>,
+    /// Don't fetch the start of the next episode ahead
+    pub no_prefetch: bool,
+    /// Ignore `skip_intro_secs` from the config
+    pub no_skip_intro: bool,
+    /// Where to pick the first episode up, instead of skipping its intro
+    pub resume_secs: Option<u64>,
+    /// Episode to pick up, by title, when no other is asked for; the cache is enough to
+    /// find it, however old
+    pub resume_title: Option<String>,
+    /// Queue again what was queued when the player was last left
+    pub restore_queue: bool,
+    /// Save every streamed episode to the downloads
+    pub record: bool,
+    /// Initial buffer, over the config's
+    pub buffer_kb: Option<u64>,
+    /// Readahead limit, over the config's
+    pub readahead: Option<player::Readahead>,
+    /// Chromecast to play on instead of this computer
+    pub cast: Option<String>,
+}
+
+/// `mfp play` on this computer, or on the Chromecast in `options`, with keys from the terminal
+pub fn play_radio(
+    episode_num: Option<usize>,
+    query: Option<&str>,
+    options: PlayOptions,
+) -> Result<()> {
+    // Before the feed, so a missing device or feature fails at once
+    let output = options.cast.as_deref().map(cast_renderer).transpose()?;
+    play_through(output, &Terminal, episode_num, query, options)
+}
+
+/// [`play_radio`] on `player` instead of the output device, with the keys and answers from
+/// `events`, so the loop runs without sound or a keyboard
+pub fn play_with(
+    player: Box<dyn AudioPlayer>,
+    events: &dyn Events,
+    episode_num: Option<usize>,
+    query: Option<&str>,
+    options: PlayOptions,
+) -> Result<()> {
+    play_through(
+        Some(Renderer::Local(player)),
+        events,
+        episode_num,
+        query,
+        options,
+    )
+}
+
+/// The play loop, on `output` or, without one, on this computer
+fn play_through(
+    output: Option<Renderer>,
+    events: &dyn Events,
+    episode_num: Option<usize>,
+    query: Option<&str>,
+    options: PlayOptions,
+) -> Result<()> {
+    let PlayOptions {
+        shuffle,
+        favorites: fav_mode,
+        playlist: named_playlist,
+        filter,
+        volume: start_percent,
+        fade_in,
+        sleep,
+        now_playing_file,
+        preview,
+        no_prefetch,
+        no_skip_intro,
+        mut resume_secs,
+        resume_title,
+        restore_queue,
+        record,
+        buffer_kb,
+        readahead,
+        cast: _,
+    } = options;
+    // From here on a signal stops playback through the `q` path instead of killing mfp
+    let _watch = shutdown::watch();
+
+    output::status(t(Msg::LoadingFeed));
+    let feeds = LazyFeed::new();
+    let downloader = Downloader::new()?;
+    // The episode to pick up plays from any cache that has it. A number, a favorite, a
+    // playlist entry or an episode to pick up the cache lacks may be newer than the cache, so
+    // those fetch the feed; otherwise a recent cache will do.
+    let resumed_cached = resume_title.as_deref().and_then(|title| {
+        feeds
+            .cached()
+            .filter(|feed| feed.find_by_title(title).is_some())
+    });
+    let loaded = match resumed_cached {
+        Some(cached) => Ok(cached),
+        None if episode_num.is_some()
+            || fav_mode
+            || named_playlist.is_some()
+            || resume_title.is_some() =>
+        {
+            feeds.fresh()
+        }
+        None => feeds.get(),
+    };
+    // Downloaded episodes still play without a connection
+    let stand_in;
+    let feed = match (loaded, feeds.cached()) {
+        (Ok(feed), _) => feed,
+        (Err(e), Some(cached)) => {
+            eprintln!("{}", tf(Msg::FeedFromCache, &[&e]));
+            cached
+        }
+        (Err(e), None) => match resume_title
+            .as_deref()
+            .filter(|title| downloader.get_path(title).is_some())
+        {
+            Some(title) => {
+                eprintln!("{}", tf(Msg::FeedFromDownload, &[&e]));
+                stand_in = Feed::downloaded(title);
+                &stand_in
+            }
+            None => return Err(e.into()),
+        },
+    };
+    let mut favorites = Favorites::load()?;
+    let mut player_state = State::load()?;
+    let config = Config::load()?;
+
+    let mut playlist = if let Some(name) = &named_playlist {
+        let Some(titles) = Playlists::load()?.get(name).map(<[String]>::to_vec) else {
+            return Err(unknown_playlist(name));
+        };
+        // The feed may have dropped or renamed some since they were added
+        for title in titles
+            .iter()
+            .filter(|title| feed.find_by_title(title).is_none())
+        {
+            eprintln!("{}", tf(Msg::PlaylistEpisodeMissing, &[title]));
+        }
+        let playlist = Playlist::from_titles(feed.shared_episodes(), &titles);
+        if playlist.is_empty() {
+            println!("{}", tf(Msg::PlaylistNothingToPlay, &[name]));
+            return Ok(());
+        }
+        playlist
+    } else if fav_mode {
+        let fav_list = favorites.list();
+        if fav_list.is_empty() {
+            println!("{}", t(Msg::NoFavoritesHint));
+            return Ok(());
+        }
+        Playlist::from_favorites(feed.shared_episodes(), &fav_list)
+    } else {
+        Playlist::new(feed.shared_episodes())
+    };
+    // Narrows whichever list was chosen, so it combines with favorites and playlists
+    if let Some(keyword) = &filter {
+        let scanned = playlist.len();
+        playlist = playlist.narrowed(|episode| episode.mentions(keyword));
+        if playlist.is_empty() {
+            println!("{}", tf(Msg::FilterNoMatch, &[keyword, &scanned]));
+            return Ok(());
+        }
+    }
+    // Last night's episodes wait until the rest have had their turn
+    let shuffle_memory = config.shuffle_memory.unwrap_or(playlist::SHUFFLE_MEMORY);
+    playlist.set_shuffle_memory(shuffle_memory);
+    playlist.set_recently_played(player_state.recently_played());
+
+    if shuffle {
+        playlist.enable_shuffle();
+    }
+
+    let target_title = match (episode_num, query) {
+        (Some(num), _) => feed.find_by_number(num).map(|ep| ep.title.clone()),
+        (None, Some(query)) => {
+            let episodes: Vec<&Episode> = playlist.episodes().collect();
+            match choose_episode(query, &episodes)? {
+                Some(title) => Some(title),
+                None => return Ok(()),
+            }
+        }
+        (None, None) => resume_title,
+    };
+
+    if let Some(target_title) = target_title {
+        playlist.jump_to_title(&target_title);
+    }
+    if restore_queue {
+        let saved = player_state.queue().to_vec();
+        for title in playlist.restore_queue(&saved) {
+            eprintln!("{}", tf(Msg::QueueEntryDropped, &[&title]));
+        }
+        let waiting = playlist.queued().count();
+        if waiting > 0 {
+            println!("{}", tf(Msg::QueueRestored, &[&waiting]));
+        }
+    }
+
+    // After the favorites check and the episode pick, which need no audio
+    let mut player = match output {
+        Some(output) => output,
+        None => match local_renderer() {
+            Ok(local) => local,
+            // No device, or no audio in this build: another player can still have the stream
+            Err(e) => {
+                eprintln!("{}", tf(Msg::NoAudioOutput, &[&e]));
+                if let Some(episode) = playlist.current() {
+                    println!("{}", episode.audio_url);
+                }
+                return Ok(());
+            }
+        },
+    };
+
+    let keymap = keys::Keymap::from_config(&config.keys).context("Invalid [keys] config")?;
+    let aliases =
+        commands::Aliases::from_config(&config.aliases).context("Invalid [aliases] config")?;
+    let interactive = events.interactive();
+
+    let restart_after = config
+        .restart_threshold_secs
+        .unwrap_or(RESTART_THRESHOLD_SECS);
+    // `--volume` first, then the level last chosen in the player, then the config's
+    let configured_volume = config
+        .volume
+        .map_or(1.0, |percent| f32::from(percent) / 100.0);
+    let mut volume = start_percent
+        .map(|percent| f32::from(percent) / 100.0)
+        .or(player_state.volume())
+        .unwrap_or(configured_volume);
+    let volume_step = i32::from(config.volume_step.unwrap_or(VOLUME_STEP));
+    let skip_intro = config
+        .skip_intro_secs
+        .filter(|&secs| secs > 0 && !no_skip_intro);
+    let trim_silence = config
+        .trim_silence
+        .then(|| config.trim_silence_db.unwrap_or(TRIM_SILENCE_DB));
+    let seek_step = config.seek_step_secs.unwrap_or(SEEK_STEP_SECS) as i64;
+    let seek_long_step = config.seek_long_step_secs.unwrap_or(SEEK_LONG_STEP_SECS) as i64;
+    player.set_start_volume(if fade_in { 0.0 } else { volume });
+    // A run that crashed may have left its stream behind
+    let spool_dir = paths::stream_spool_dir()?;
+    player::clear_spool(&spool_dir);
+    let spool_dir = config.stream_to_disk.then_some(spool_dir);
+    player.set_spool_dir(spool_dir.clone());
+    if let Some(max) = config.stream_reconnects {
+        player.set_max_reconnects(max);
+    }
+    if let Some(secs) = config.rebuffer_secs {
+        player.set_rebuffer_secs(secs);
+    }
+    let buffer_kb = buffer_kb.or(config.initial_buffer_kb);
+    if let Some(kb) = buffer_kb {
+        player.set_initial_buffer_kb(kb);
+    }
+    let readahead_mb = match readahead {
+        Some(player::Readahead::Unlimited) => None,
+        Some(player::Readahead::Mb(mb)) => Some(mb),
+        None => config.max_readahead_mb,
+    };
+    player.set_max_readahead_mb(readahead_mb);
+    player.set_fade_pauses(config.fade_pauses);
+    let mut fade_started: Option<Instant> = None;
+    // Counts across episodes; the volume is the one the last seconds fade down from
+    let mut sleep_until = sleep.map(|after| Instant::now() + after);
+    let mut sleep_volume: Option<f32> = None;
+    // `x`: quit when the episode playing ends instead of moving on; `n` clears it
+    let mut stop_after = false;
+    // Volume a restart that had to play the episode again carries on at
+    let mut restart_volume: Option<f32> = None;
+    let mut device_probed = Instant::now();
+    // Last volume and shuffle sent to listeners; a change is sent on the next tick
+    let mut last_settings: Option<(u16, bool)> = None;
+
+    // MPRIS integration (D-Bus, so off by default on Windows)
+    let mpris_enabled = config.mpris.unwrap_or(!cfg!(windows));
+    let mpris = if mpris_enabled {
+        MprisController::new()?
+    } else {
+        MprisController::disabled()
+    };
+    let mpris_cmd_rx = mpris.command_receiver();
+
+    // Media keys feed the MPRIS command channel. Where the system controls are MPRIS
+    // themselves, a running MPRIS server already gets the keys: registering twice would show
+    // two players and act on every key twice.
+    #[cfg(feature = "media-keys")]
+    let mut media_keys = if config.media_keys.unwrap_or(true)
+        && !(mpris_enabled && cfg!(all(unix, not(target_os = "macos"))))
+    {
+        media_keys::MediaKeys::attach(mpris.command_sender())
+            .map_err(|e| tracing::warn!("{}", tf(Msg::MediaKeysFailed, &[&e])))
+            .ok()
+    } else {
+        None
+    };
+    #[cfg(not(feature = "media-keys"))]
+    if config.media_keys == Some(true) {
+        eprintln!("{}", t(Msg::MediaKeysFeatureMissing));
+    }
+
+    let mut session = Session::new();
+    session.add_listener(Box::new(HistoryRecorder::new()));
+    let now_playing_format = config
+        .now_playing_format
+        .clone()
+        .unwrap_or_else(|| now_playing::DEFAULT_FORMAT.to_string());
+    if let Err(e) = now_playing::check_template(&now_playing_format) {
+        tracing::warn!("now_playing_format: {}", e);
+    }
+    session.add_listener(Box::new(NowPlayingWriter::new(
+        now_playing_file.or(config.now_playing_file.clone()),
+        now_playing_format,
+    )));
+    #[cfg(feature = "scrobble")]
+    if let Some(scrobbler) = scrobbler::Scrobbler::new(&config.scrobble) {
+        let retry = scrobbler.clone();
+        std::thread::spawn(move || {
+            let _ = retry.flush_queue();
+        });
+        session.add_listener(Box::new(scrobbler));
+    }
+    #[cfg(not(feature = "scrobble"))]
+    if config.scrobble.service.is_some() {
+        eprintln!("{}", t(Msg::ScrobbleFeatureMissing));
+    }
+    if config.discord_presence {
+        #[cfg(feature = "discord")]
+        match config.discord_app_id.clone() {
+            Some(app_id) => session.add_listener(Box::new(discord::DiscordPresence::new(app_id))),
+            None => eprintln!("{}", t(Msg::DiscordAppIdMissing)),
+        }
+        #[cfg(not(feature = "discord"))]
+        eprintln!("{}", t(Msg::DiscordFeatureMissing));
+    }
+    if let Some(hooks) = hooks::Hooks::from_config(&config) {
+        session.add_listener(Box::new(hooks));
+    }
+
+    // Download started with `d`, which carries on across episodes: its title and how far it is
+    let mut saving: Option<(String, Arc<DownloadProgress>, JoinHandle<Result<PathBuf>>)> = None;
+
+    loop {
+        // Borrowed through a handle on the shared list, so the playlist stays free to move on
+        let episodes = playlist.shared_episodes();
+        let Some(episode) = playlist.current_shared_index().map(|i| &episodes[i]) else {
+            println!("{}", t(Msg::NoEpisodesAvailable));
+            break;
+        };
+        let (episode_title, episode_duration, episode_url, page_url) = (
+            &episode.title,
+            &episode.duration,
+            &episode.audio_url,
+            &episode.page_url,
+        );
+
+        // Played once it starts, for `previous` and the shuffle, in this run and the next
+        playlist.mark_played();
+        if let Err(e) = player_state.record_played(episode_title, shuffle_memory) {
+            tracing::warn!("Can't save the played episodes: {}", e);
+        }
+        // Moving on may have taken the episode from the queue
+        save_queue(&mut player_state, &playlist);
+
+        // Update MPRIS metadata for new episode
+        let total_seconds = player::parse_duration(episode_duration).unwrap_or(0);
+        if let Err(e) = mpris.update_metadata(episode_title.clone(), total_seconds) {
+            tracing::warn!("{}", tf(Msg::MprisUpdateFailed, &[&e]));
+        }
+        if let Err(e) = mpris.update_playback_status(PlaybackStatus::Playing) {
+            tracing::warn!("{}", tf(Msg::MprisUpdateFailed, &[&e]));
+        }
+        if let Err(e) = mpris.update_shuffle(playlist.is_shuffled()) {
+            tracing::warn!("{}", tf(Msg::MprisUpdateFailed, &[&e]));
+        }
+        if let Err(e) = mpris.update_navigation(true, true) {
+            tracing::warn!("{}", tf(Msg::MprisUpdateFailed, &[&e]));
+        }
+        #[cfg(feature = "media-keys")]
+        if let Some(keys) = media_keys.as_mut() {
+            keys.update_metadata(episode_title, total_seconds, episode.image_url.as_deref());
+        }
+
+        // Seconds left to sample of this episode; `full` clears it for the episode
+        let mut sample_limit = preview;
+        // Another episode starts without one
+        let mut ab_loop = AbLoop::Off;
+        // Arrow-key steps waiting for the keys to settle
+        let mut nudge = Nudge::default();
+
+        let is_fav = favorites.is_favorite(episode_title);
+        println!(
+            "\n{} {}{}",
+            if is_fav {
+                style::paint(Role::Favorite, "*")
+            } else {
+                style::paint(Role::Accent, ">")
+            },
+            style::paint(Role::Title, episode_title),
+            match sample_limit {
+                Some(_) => format!(" {}", style::paint(Role::Accent, t(Msg::PreviewTag))),
+                None => String::new(),
+            }
+        );
+        println!(
+            "{}\n",
+            tf(
+                Msg::DurationShuffleRepeat,
+                &[
+                    &episode_duration,
+                    &on_off(playlist.is_shuffled()),
+                    &commands::repeat_label(playlist.repeat())
+                ]
+            )
+        );
+
+        // A saved offset applies to this episode only; the next one starts from `volume` again
+        let episode_base = volume;
+        let mut episode_volume = player_state
+            .volume_offset(episode_title)
+            .map_or(volume, |percent| state::apply_offset(volume, percent));
+        let kept_volume = restart_volume.take();
+        let fading = fade_in && fade_started.is_none() && kept_volume.is_none();
+        if !fading {
+            player.set_start_volume(kept_volume.unwrap_or(episode_volume));
+        }
+        // A new episode skips its intro; one picked up where it was left starts there instead
+        let resume = resume_secs.take();
+        // Leading silence is only skipped from the very start
+        player.set_trim_silence(trim_silence.filter(|_| resume.is_none() && skip_intro.is_none()));
+        // Downloaded copies play from disk; `info` tells which
+        let mut local_file = player
+            .plays_files()
+            .then(|| downloader.get_path(episode_title))
+            .flatten();
+        let started = match &local_file {
+            Some(path) => player.play_file(path, 0),
+            None => {
+                // Keys are read while it buffers, so `q` gives the stream up at once
+                if interactive {
+                    events.enter_progress_display()?;
+                }
+                let shown = Cell::new(None);
+                let started =
+                    player.play(episode_url, &|| events.start_interrupted(), &|starting| {
+                        output::progress(starting_line(starting));
+                        shown.set(Some(starting));
+                    });
+                if interactive {
+                    events.leave_progress_display()?;
+                }
+                // Finished here rather than in raw mode, where a newline doesn't return
+                if let (Ok(()), Some(starting)) = (&started, shown.get()) {
+                    output::progress_done(format!("{} OK\n", starting_line(starting)));
+                }
+                started
+            }
+        };
+        // Offered a retry below, the same as a stream that fails later on
+        let mut start_error = started.err();
+        if start_error
+            .as_ref()
+            .and_then(|e| e.downcast_ref::<player::PlayerError>())
+            .is_some_and(player::PlayerError::is_cancelled)
+        {
+            println!();
+            session.emit(SessionEvent::Stopped);
+            announce_unfinished_download(saving);
+            return Ok(());
+        }
+        if start_error.is_none() {
+            match resume {
+                Some(secs) if secs > 0 && player.seek(secs) => {
+                    println!("{}", tf(Msg::ResumedAt, &[&player::format_duration(secs)]))
+                }
+                Some(_) => {}
+                None => {
+                    if let Some(secs) = skip_intro.filter(|&secs| player.seek(secs)) {
+                        println!(
+                            "{}",
+                            tf(Msg::SkippedIntro, &[&player::format_duration(secs)])
+                        );
+                    }
+                }
+            }
+        }
+        if record && start_error.is_none() && local_file.is_none() {
+            start_recording(&player, &downloader, episode_title);
+        }
+        player.set_start_volume(volume);
+        if fading {
+            fade_started = Some(Instant::now());
+        }
+        session.emit(SessionEvent::TrackStarted {
+            title: episode_title.clone(),
+            duration_secs: total_seconds,
+            url: episode_url.clone(),
+        });
+
+        if interactive {
+            println!("{}", t(Msg::Controls));
+            println!("{}", t(Msg::ControlsTransport));
+            println!("{}", t(Msg::ControlsOther));
+            if let Some(limit) = sample_limit {
+                println!("{}", tf(Msg::PreviewControls, &[&limit]));
+            }
+        } else {
+            output::status(t(Msg::NoTtyControls));
+        }
+
+        let total_seconds = player::parse_duration(episode_duration).unwrap_or(0);
+
+        if interactive {
+            events.enter_progress_display()?;
+        }
+
+        let mut command_buffer = String::new();
+        let mut quit = false;
+        // Background download taken over from a stalled stream, and where to continue
+        let mut offline: Option<(u64, JoinHandle<Result<PathBuf>>)> = None;
+        // Position as of the last tick; stopping the player resets its own to 0
+        let mut listened_secs;
+        // Since when the audio has been too quiet to light the level meter while playing
+        let mut silent_since: Option<Instant> = None;
+
+        loop {
+            listened_secs = player.elapsed_seconds();
+            if let Some(device) = player.lost_device() {
+                let device = device.to_string();
+                if interactive {
+                    progress::clear_line();
+                    events.leave_progress_display()?;
+                }
+                if !confirm_local_playback(&device, events)? {
+                    quit = true;
+                    break;
+                }
+                // Starts the current episode over on this computer
+                player = local_renderer()?;
+                player.set_start_volume(volume);
+                player.set_spool_dir(spool_dir.clone());
+                if let Some(max) = config.stream_reconnects {
+                    player.set_max_reconnects(max);
+                }
+                if let Some(secs) = config.rebuffer_secs {
+                    player.set_rebuffer_secs(secs);
+                }
+                if let Some(kb) = buffer_kb {
+                    player.set_initial_buffer_kb(kb);
+                }
+                player.set_max_readahead_mb(readahead_mb);
+                player.set_fade_pauses(config.fade_pauses);
+                break;
+            }
+
+            // Headphones unplugged, a speaker gone: carry on on the default device rather than
+            // play to nothing
+            if device_probed.elapsed() >= DEVICE_PROBE_INTERVAL {
+                device_probed = Instant::now();
+                if player.output_lost() {
+                    if interactive {
+                        progress::clear_line();
+                        events.leave_progress_display()?;
+                    }
+                    println!("{}", t(Msg::OutputLost));
+                    if !reopen_output(&mut player, events)? {
+                        quit = true;
+                        break;
+                    }
+                    if interactive {
+                        events.enter_progress_display()?;
+                    }
+                }
+            }
+
+            // SIGTERM or SIGHUP: leave the way `q` does, so everything is saved
+            if shutdown::requested() {
+                if interactive {
+                    progress::clear_line();
+                    events.leave_progress_display()?;
+                }
+                fade_out(&player);
+                quit = true;
+                break;
+            }
+
+            // Another mfp started with --takeover: leave it the audio device and the state
+            if let Some(pid) = lock::takeover_requested() {
+                if interactive {
+                    progress::clear_line();
+                    events.leave_progress_display()?;
+                }
+                output::status(tf(Msg::InstanceTakenOver, &[&pid]));
+                quit = true;
+                break;
+            }
+
+            // Process MPRIS commands
+            if let Ok(cmd) = mpris_cmd_rx.try_recv() {
+                match cmd {
+                    MprisCommand::PlayPause => {
+                        if player.is_paused() {
+                            player.resume();
+                            let _ = mpris.update_playback_status(PlaybackStatus::Playing);
+                            session.emit(SessionEvent::Resumed {
+                                elapsed_secs: player.elapsed_seconds(),
+                            });
+                        } else {
+                            player.pause();
+                            let _ = mpris.update_playback_status(PlaybackStatus::Paused);
+                            session.emit(SessionEvent::Paused {
+                                elapsed_secs: player.elapsed_seconds(),
+                            });
+                        }
+                    }
+                    MprisCommand::Play if player.is_paused() => {
+                        player.resume();
+                        let _ = mpris.update_playback_status(PlaybackStatus::Playing);
+                        session.emit(SessionEvent::Resumed {
+                            elapsed_secs: player.elapsed_seconds(),
+                        });
+                    }
+                    MprisCommand::Pause if !player.is_paused() => {
+                        player.pause();
+                        let _ = mpris.update_playback_status(PlaybackStatus::Paused);
+                        session.emit(SessionEvent::Paused {
+                            elapsed_secs: player.elapsed_seconds(),
+                        });
+                    }
+                    // Already in the state asked for
+                    MprisCommand::Play | MprisCommand::Pause => {}
+                    MprisCommand::SeekBy(secs) => {
+                        player.seek(player.elapsed_seconds().saturating_add_signed(secs));
+                    }
+                    MprisCommand::SetPosition(secs) => {
+                        player.seek(secs);
+                    }
+                    MprisCommand::Next => {
+                        stop_after = false;
+                        player.stop();
+                        playlist.skip();
+                        break; // exit inner loop to play next episode
+                    }
+                    // Like `p`: back to the start of the episode once past the threshold
+                    MprisCommand::Previous
+                        if restart_after > 0 && player.elapsed_seconds() > restart_after =>
+                    {
+                        if !restart_in_place(
+                            &player,
+                            &mut session,
+                            &mpris,
+                            episode_title,
+                            episode_url,
+                            total_seconds,
+                        ) {
+                            restart_volume = Some(player.volume());
+                            player.stop();
+                            break;
+                        }
+                    }
+                    MprisCommand::Previous => {
+                        player.stop();
+                        playlist.previous();
+                        break;
+                    }
+                    MprisCommand::SetVolume(vol) => {
+                        fade_started = None;
+                        sleep_volume = None;
+                        player.set_volume(vol);
+                        volume = remember_volume(&mut player_state, episode_title, vol);
+                        let _ = mpris.update_volume(vol);
+                    }
+                    MprisCommand::Quit => {
+                        quit = true;
+                        break;
+                    }
+                }
+            }
+            if let Some(started) = fade_started {
+                let ramp = started.elapsed().as_secs_f32() / FADE_IN.as_secs_f32();
+                player.set_volume(ramp.min(1.0) * episode_volume);
+                if ramp >= 1.0 {
+                    fade_started = None;
+                }
+            }
+            if let Some(ends) = sleep_until {
+                let left = ends.saturating_duration_since(Instant::now());
+                if left.is_zero() {
+                    if interactive {
+                        progress::clear_line();
+                    }
+                    println!("{}", t(Msg::SleepStopping));
+                    quit = true;
+                    break;
+                }
+                if left < schedule::SLEEP_FADE && !player.is_muted() {
+                    fade_started = None;
+                    let from = *sleep_volume.get_or_insert_with(|| player.volume());
+                    player.set_volume(from * schedule::sleep_fade(left));
+                }
+            }
+
+            // While fading, report the level faded to or from rather than every step
+            let level = if fade_started.is_some() {
+                episode_volume
+            } else {
+                sleep_volume.unwrap_or_else(|| player.volume())
+            };
+            let settings = ((level * 100.0).round() as u16, playlist.is_shuffled());
+            if last_settings != Some(settings) {
+                last_settings = Some(settings);
+                session.emit(SessionEvent::SettingsChanged {
+                    volume_percent: settings.0,
+                    shuffle: settings.1,
+                });
+            }
+
+            #[cfg(feature = "media-keys")]
+            if let Some(keys) = media_keys.as_mut() {
+                keys.update_position(player.elapsed_seconds(), player.is_paused());
+            }
+
+            if saving
+                .as_ref()
+                .is_some_and(|(_, _, download)| download.is_finished())
+            {
+                if let Some((title, _, download)) = saving.take() {
+                    let result = download
+                        .join()
+                        .unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+                    if interactive {
+                        progress::clear_line();
+                        events.leave_progress_display()?;
+                    }
+                    match result {
+                        Ok(_) => println!(
+                            "{}",
+                            style::paint(Role::Success, &tf(Msg::EpisodeDownloaded, &[&title]))
+                        ),
+                        Err(e) => println!(
+                            "{} {}",
+                            style::paint(Role::Error, t(Msg::ErrorLabel)),
+                            tf(Msg::BackgroundDownloadFailed, &[&title, &e])
+                        ),
+                    }
+                    if interactive {
+                        events.enter_progress_display()?;
+                    }
+                }
+            }
+
+            if offline
+                .as_ref()
+                .is_some_and(|(_, download)| download.is_finished())
+            {
+                if let Some((position, download)) = offline.take() {
+                    let result = download
+                        .join()
+                        .unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+                    if interactive {
+                        progress::clear_line();
+                        events.leave_progress_display()?;
+                    }
+                    match result {
+                        Ok(path) => {
+                            println!(
+                                "{}",
+                                tf(Msg::OfflinePlaying, &[&player::format_duration(position)])
+                            );
+                            // Same episode, same volume
+                            player.set_start_volume(player.volume());
+                            player.play_file(&path, position)?;
+                            player.set_start_volume(volume);
+                            local_file = Some(path);
+                            let _ = mpris.update_playback_status(PlaybackStatus::Playing);
+                            session.emit(SessionEvent::Resumed {
+                                elapsed_secs: player.elapsed_seconds(),
+                            });
+                        }
+                        Err(e) => println!("{}", tf(Msg::OfflineDownloadFailed, &[&e])),
+                    }
+                    if interactive {
+                        events.enter_progress_display()?;
+                    }
+                }
+            }
+
+            // The stream or its decoding failed: say why rather than play silence
+            if let Some(error) = start_error.take().or_else(|| player.take_error()) {
+                player.stop();
+                if !interactive {
+                    return Err(error);
+                }
+
+                progress::clear_line();
+                events.leave_progress_display()?;
+                let _ = mpris.update_playback_status(PlaybackStatus::Stopped);
+                print!(
+                    "{}",
+                    style::paint(Role::Error, &tf(Msg::StreamFailed, &[&error]))
+                );
+                io::stdout().flush()?;
+                match events.read_line()?.trim().to_lowercase().as_str() {
+                    // Back round the outer loop, which starts the same episode again
+                    "r" => break,
+                    "q" => {
+                        quit = true;
+                        break;
+                    }
+                    _ => {
+                        playlist.skip();
+                        break;
+                    }
+                }
+            }
+
+            // Ran dry: wait for a few seconds of audio rather than stutter along the download
+            let rebuffering = player.poll_underrun();
+
+            // Reconnecting on its own didn't help: stop pretending and ask
+            if offline.is_none()
+                && !player.is_paused()
+                && player
+                    .stalled_for()
+                    .is_some_and(|stalled| stalled >= STALL_PROMPT_AFTER)
+            {
+                let reconnects = player.reconnects();
+                if !interactive {
+                    println!("{}", tf(Msg::StreamStalledStopping, &[&reconnects]));
+                    quit = true;
+                    break;
+                }
+
+                progress::clear_line();
+                events.leave_progress_display()?;
+                player.pause();
+                let _ = mpris.update_playback_status(PlaybackStatus::Paused);
+                session.emit(SessionEvent::Paused {
+                    elapsed_secs: player.elapsed_seconds(),
+                });
+
+                print!("{}", tf(Msg::StreamStalled, &[&reconnects]));
+                io::stdout().flush()?;
+                match events.read_line()?.trim().to_lowercase().as_str() {
+                    "d" => {
+                        let position = player
+                            .played_seconds()
+                            .unwrap_or_else(|| player.elapsed_seconds());
+                        // A `d` download of this episode already under way is the one to wait for
+                        let download = match saving.take() {
+                            Some((title, _, download)) if title == *episode_title => download,
+                            other => {
+                                saving = other;
+                                download_in_background(&player, episode_title, episode_url).1
+                            }
+                        };
+                        offline = Some((position, download));
+                        println!(
+                            "{}",
+                            tf(
+                                Msg::OfflineDownloadStarted,
+                                &[&player::format_duration(position)]
+                            )
+                        );
+                    }
+                    "n" => {
+                        player.stop();
+                        playlist.skip();
+                        break;
+                    }
+                    "q" => {
+                        quit = true;
+                        break;
+                    }
+                    _ => {
+                        println!("{}", t(Msg::StallRetrying));
+                        player.reconnect();
+                        player.resume();
+                        let _ = mpris.update_playback_status(PlaybackStatus::Playing);
+                        session.emit(SessionEvent::Resumed {
+                            elapsed_secs: player.elapsed_seconds(),
+                        });
+                    }
+                }
+                events.enter_progress_display()?;
+            }
+
+            // The sample is measured in played audio, so buffering doesn't eat into it
+            if sample_limit.is_some_and(|limit| player.played_seconds().unwrap_or(0) >= limit) {
+                if interactive {
+                    progress::clear_line();
+                    events.leave_progress_display()?;
+                }
+                player.stop();
+                if stop_after {
+                    println!("{}", t(Msg::StoppedAfterEpisode));
+                    quit = true;
+                } else if playlist.next().is_none() {
+                    println!("{}", t(Msg::PlaylistEnded));
+                    quit = true;
+                }
+                break;
+            }
+
+            // Played to the end: on to whatever the order and the repeat mode make next, unless
+            // told to stop
+            if interactive && offline.is_none() && player.is_finished() {
+                progress::clear_line();
+                events.leave_progress_display()?;
+                if stop_after {
+                    println!("{}", t(Msg::StoppedAfterEpisode));
+                    quit = true;
+                } else if playlist.next().is_none() {
+                    println!("{}", t(Msg::PlaylistEnded));
+                    quit = true;
+                }
+                break;
+            }
+
+            // Without a terminal there are no keys to read: play this one out and stop
+            if !interactive {
+                if player.is_finished() {
+                    quit = true;
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(100));
+                continue;
+            }
+
+            if let Some(start) = ab_loop.jump_back(player.elapsed_seconds()) {
+                player.seek(start);
+            }
+
+            if let Some(secs) = nudge.settled(Instant::now()) {
+                if !player.seek(secs) {
+                    events.leave_progress_display()?;
+                    progress::clear_line();
+                    println!(
+                        "{}",
+                        tf(Msg::SeekUnavailable, &[&player::format_duration(secs)])
+                    );
+                    events.enter_progress_display()?;
+                }
+            }
+            // Steps still settling show where they lead
+            let position = nudge.target().unwrap_or_else(|| player.elapsed_seconds());
+
+            // Well ahead of the listener, fetch the start of the next episode so skipping to it
+            // is instant. A different next one (shuffle, a jump) replaces it; a downloaded one
+            // plays from disk anyway, and repeating this one needs nothing new.
+            if !no_prefetch
+                && player
+                    .buffered_seconds(total_seconds)
+                    .is_some_and(|buffered| {
+                        buffered >= player.elapsed_seconds() + PREFETCH_AHEAD_SECS
+                            || buffered >= total_seconds
+                    })
+            {
+                if let Some(next) = playlist
+                    .peek_next()
+                    .filter(|next| next.title != *episode_title)
+                    .filter(|next| downloader.get_path(&next.title).is_none())
+                {
+                    player.prefetch(&next.audio_url);
+                }
+            }
+
+            // Recomputed from the clock every tick, so a pause or a seek moves it
+            let finish = progress::finish_times(
+                Local::now().naive_local(),
+                (total_seconds > 0).then(|| total_seconds.saturating_sub(player.elapsed_seconds())),
+                playlist.remaining_after_current(),
+            );
+            // A reconnect or a refill says so where the finish times go, rather than the line
+            // just freezing
+            let status = match (player.reconnecting(), rebuffering) {
+                (Some((attempt, max)), _) => tf(Msg::ReconnectAttempt, &[&attempt, &max]),
+                (None, Some(secs)) => tf(Msg::Rebuffering, &[&secs]),
+                (None, None) => match saving.as_ref().map(|(_, progress, _)| progress.percent()) {
+                    Some(Some(percent)) => tf(Msg::BackgroundDownloading, &[&percent]),
+                    Some(None) => tf(Msg::BackgroundDownloading, &[&"--"]),
+                    None => finish,
+                },
+            };
+            // The usual wrap-around goes unmentioned
+            let status = match playlist.repeat() {
+                RepeatMode::All => status,
+                mode => format!(
+                    "{} | {}",
+                    tf(Msg::ProgressRepeat, &[&commands::repeat_label(mode)]),
+                    status
+                ),
+            };
+            let buffer = progress::Buffer {
+                until: player.buffered_seconds(total_seconds),
+                ahead: player.buffered_ahead_secs(),
+            };
+            // Silence is normal for a moment, between tracks or in a quiet passage; for
+            // longer it's a sign the audio isn't getting through
+            let meter = match player.level().filter(|_| !player.is_paused()) {
+                Some(level) if progress::audible(level) => {
+                    silent_since = None;
+                    progress::Meter::Level(level)
+                }
+                Some(level) => {
+                    let since = *silent_since.get_or_insert_with(Instant::now);
+                    if since.elapsed() >= SILENCE_WARNING_AFTER {
+                        progress::Meter::Silent
+                    } else {
+                        progress::Meter::Level(level)
+                    }
+                }
+                None => {
+                    silent_since = None;
+                    progress::Meter::Idle
+                }
+            };
+            let line = progress::render(
+                progress::terminal_width(),
+                position,
+                total_seconds,
+                buffer,
+                meter,
+                ab_loop.bounds(),
+                &status,
+                &command_buffer,
+            );
+            progress::clear_line();
+            print!("{}", line);
+            io::stdout().flush()?;
+
+            // Resize events need no handling: the next tick re-reads the width
+            if events.poll(Duration::from_millis(100))? {
+                let dispatch = match events.read()? {
+                    // Windows also reports releases, which would type every letter twice
+                    Event::Key(key) if key.kind == KeyEventKind::Press => {
+                        // Bound keys act at once, unless a command is being typed
+                        let bound = if command_buffer.is_empty() {
+                            keymap.lookup(&key)
+                        } else {
+                            None
+                        };
+
+                        match key.code {
+                            _ if bound.is_some() => Some((bound, String::new())),
+                            KeyCode::Enter => {
+                                let command = aliases.expand(command_buffer.trim());
+                                command_buffer.clear();
+                                // A bare Enter while previewing keeps the episode playing
+                                if command.is_empty() && sample_limit.is_some() {
+                                    Some((Some(Action::Full), command))
+                                } else {
+                                    Some((commands::parse(&command), command))
+                                }
+                            }
+                            KeyCode::Backspace => {
+                                command_buffer.pop();
+                                None
+                            }
+                            KeyCode::Char(c) => {
+                                command_buffer.push(c);
+                                None
+                            }
+                            _ => None,
+                        }
+                    }
+                    Event::Mouse(mouse) => match mouse.kind {
+                        MouseEventKind::ScrollUp => Some((Some(Action::VolumeUp), String::new())),
+                        MouseEventKind::ScrollDown => {
+                            Some((Some(Action::VolumeDown), String::new()))
+                        }
+                        MouseEventKind::Down(MouseButton::Left) => {
+                            // Only the progress line, which holds the cursor, has a bar
+                            let on_line =
+                                cursor::position().map_or(true, |(_, row)| row == mouse.row);
+                            let target = progress::position_at(
+                                mouse.column as usize,
+                                progress::terminal_width(),
+                                position,
+                                total_seconds,
+                                buffer.ahead,
+                                &status,
+                                &command_buffer,
+                            );
+                            if let Some(secs) = target.filter(|_| on_line) {
+                                if !player.seek(secs) {
+                                    events.leave_progress_display()?;
+                                    progress::clear_line();
+                                    println!(
+                                        "{}",
+                                        tf(Msg::SeekUnavailable, &[&player::format_duration(secs)])
+                                    );
+                                    events.enter_progress_display()?;
+                                }
+                            }
+                            None
+                        }
+                        _ => None,
+                    },
+                    _ => None,
+                };
+
+                if let Some((action, command)) = dispatch {
+                    events.leave_progress_display()?;
+
+                    // As in most players, "previous" only goes back near the start of an episode
+                    let action = match action {
+                        Some(Action::Previous)
+                            if restart_after > 0 && player.elapsed_seconds() > restart_after =>
+                        {
+                            Some(Action::Restart)
+                        }
+                        action => action,
+                    };
+
+                    let should_break = match action {
+                        Some(Action::Next) => {
+                            stop_after = false;
+                            progress::clear_line();
+                            player.stop();
+                            playlist.skip();
+                            true
+                        }
+                        Some(Action::Previous) => {
+                            progress::clear_line();
+                            player.stop();
+                            playlist.previous();
+                            true
+                        }
+                        Some(Action::Pause) => {
+                            progress::clear_line();
+                            let new_status = if player.is_paused() {
+                                PlaybackStatus::Playing
+                            } else {
+                                PlaybackStatus::Paused
+                            };
+                            if player.is_paused() {
+                                player.resume();
+                                session.emit(SessionEvent::Resumed {
+                                    elapsed_secs: player.elapsed_seconds(),
+                                });
+                                println!("{}", t(Msg::Playing));
+                            } else {
+                                player.pause();
+                                session.emit(SessionEvent::Paused {
+                                    elapsed_secs: player.elapsed_seconds(),
+                                });
+                                println!("{}", t(Msg::Paused));
+                            }
+                            mpris.update_playback_status(new_status).ok();
+                            false
+                        }
+                        Some(Action::Restart) => {
+                            progress::clear_line();
+                            println!("{}", t(Msg::Restarting));
+                            if restart_in_place(
+                                &player,
+                                &mut session,
+                                &mpris,
+                                episode_title,
+                                episode_url,
+                                total_seconds,
+                            ) {
+                                false
+                            } else {
+                                // The outer loop plays the same playlist entry again
+                                restart_volume = Some(player.volume());
+                                player.stop();
+                                true
+                            }
+                        }
+                        Some(Action::Seek) => {
+                            progress::clear_line();
+                            let elapsed = player.elapsed_seconds();
+                            match commands::seek_target(commands::argument(&command), elapsed) {
+                                Some(secs) => {
+                                    let secs = match total_seconds {
+                                        0 => secs,
+                                        total => secs.min(total),
+                                    };
+                                    let position = player::format_duration(secs);
+                                    if !player.seek(secs) {
+                                        println!("{}", tf(Msg::SeekUnavailable, &[&position]));
+                                    } else if player
+                                        .buffered_seconds(total_seconds)
+                                        .is_some_and(|buffered| secs > buffered)
+                                    {
+                                        println!("{}", tf(Msg::SeekBuffering, &[&position]));
+                                    }
+                                }
+                                None => println!("{}", t(Msg::SeekUsage)),
+                            }
+                            false
+                        }
+                        Some(Action::Goto) => {
+                            progress::clear_line();
+                            // `goto 120` is an episode number typed the wrong way, not a
+                            // seek to the end: nothing moves and the hint says how
+                            match (
+                                commands::goto_meant_episode(&command),
+                                commands::goto_percent(&command),
+                            ) {
+                                (Some(number), _) => {
+                                    println!("{}", tf(Msg::GotoEpisodeHint, &[&number, &number]));
+                                }
+                                (_, Some(_)) if total_seconds == 0 => {
+                                    println!("{}", t(Msg::GotoNoDuration))
+                                }
+                                (_, Some(percent)) => {
+                                    let secs = commands::percent_target(percent, total_seconds);
+                                    let position = player::format_duration(secs);
+                                    if !player.seek(secs) {
+                                        println!("{}", tf(Msg::SeekUnavailable, &[&position]));
+                                    } else {
+                                        println!("{}", tf(Msg::GotoLanded, &[&position]));
+                                        if player
+                                            .buffered_seconds(total_seconds)
+                                            .is_some_and(|buffered| secs > buffered)
+                                        {
+                                            println!("{}", tf(Msg::SeekBuffering, &[&position]));
+                                        }
+                                    }
+                                }
+                                (_, None) => {
+                                    println!(
+                                        "{}",
+                                        style::paint(Role::Error, t(Msg::UnknownCommand))
+                                    );
+                                    println!("{}", t(Msg::CommandsHint));
+                                }
+                            }
+                            false
+                        }
+                        Some(Action::Episode) => {
+                            progress::clear_line();
+                            match commands::episode_number(&command) {
+                                None => {
+                                    println!("{}", t(Msg::EpisodeUsage));
+                                    false
+                                }
+                                Some(number) => {
+                                    match feed.find_by_number(number).map(|e| e.title.clone()) {
+                                        None => {
+                                            println!("{}", tf(Msg::EpisodeNotFound, &[&number]));
+                                            false
+                                        }
+                                        Some(title) => {
+                                            // Favorites mode: the feed takes over, shuffled if
+                                            // it was
+                                            if playlist.jump_to_title(&title).is_none() {
+                                                println!(
+                                                    "{}",
+                                                    tf(Msg::EpisodeOutsidePlaylist, &[&number])
+                                                );
+                                                let shuffled = playlist.is_shuffled();
+                                                let queued: Vec<String> = playlist
+                                                    .queued()
+                                                    .map(|episode| episode.title.clone())
+                                                    .collect();
+                                                playlist = Playlist::new(feed.shared_episodes());
+                                                // Everything queued is in the feed too
+                                                playlist.restore_queue(&queued);
+                                                playlist.set_shuffle_memory(shuffle_memory);
+                                                playlist.set_recently_played(
+                                                    player_state.recently_played(),
+                                                );
+                                                if shuffled {
+                                                    playlist.enable_shuffle();
+                                                }
+                                                playlist.jump_to_title(&title);
+                                            }
+                                            stop_after = false;
+                                            player.stop();
+                                            true
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                        Some(
+                            action @ (Action::SeekForward
+                            | Action::SeekBack
+                            | Action::SeekForwardLong
+                            | Action::SeekBackLong),
+                        ) => {
+                            progress::clear_line();
+                            let secs = match action {
+                                Action::SeekForward => seek_step,
+                                Action::SeekBack => -seek_step,
+                                Action::SeekForwardLong => seek_long_step,
+                                _ => -seek_long_step,
+                            };
+                            let elapsed = player.elapsed_seconds();
+                            nudge.step(elapsed, secs, total_seconds, Instant::now());
+                            false
+                        }
+                        Some(Action::Loop) => {
+                            progress::clear_line();
+                            let position = player.elapsed_seconds();
+                            let marked = ab_loop.mark(position);
+                            let at = player::format_duration;
+                            match marked {
+                                _ if marked == ab_loop => {
+                                    println!("{}", tf(Msg::LoopTooShort, &[&at(position)]))
+                                }
+                                AbLoop::Off => println!("{}", t(Msg::LoopCleared)),
+                                AbLoop::Start(start) => {
+                                    println!("{}", tf(Msg::LoopStart, &[&at(start)]))
+                                }
+                                AbLoop::Looping { start, end } => {
+                                    println!("{}", tf(Msg::LoopSet, &[&at(start), &at(end)]))
+                                }
+                            }
+                            ab_loop = marked;
+                            false
+                        }
+                        Some(Action::Full) => {
+                            progress::clear_line();
+                            if sample_limit.take().is_some() {
+                                println!("{}", t(Msg::PreviewFull));
+                            } else {
+                                println!("{}", t(Msg::PreviewNotActive));
+                            }
+                            false
+                        }
+                        Some(Action::VolumeUp) => {
+                            fade_started = None;
+                            sleep_volume = None;
+                            progress::clear_line();
+                            // From the level before muting, not from silence
+                            if player.is_muted() {
+                                player.toggle_mute();
+                            }
+                            let new_vol = step_volume(player.volume(), volume_step);
+                            player.set_volume(new_vol);
+                            volume = remember_volume(&mut player_state, episode_title, new_vol);
+                            mpris.update_volume(new_vol).ok();
+                            println!("{}", volume_line(new_vol));
+                            false
+                        }
+                        Some(Action::VolumeDown) => {
+                            fade_started = None;
+                            sleep_volume = None;
+                            progress::clear_line();
+                            if player.is_muted() {
+                                player.toggle_mute();
+                            }
+                            let new_vol = step_volume(player.volume(), -volume_step);
+                            player.set_volume(new_vol);
+                            volume = remember_volume(&mut player_state, episode_title, new_vol);
+                            mpris.update_volume(new_vol).ok();
+                            println!("{}", volume_line(new_vol));
+                            false
+                        }
+                        Some(Action::Volume) => {
+                            fade_started = None;
+                            sleep_volume = None;
+                            progress::clear_line();
+                            let argument = commands::argument(&command);
+                            match commands::volume_percent(argument) {
+                                // Back to the configured level, remembered no more
+                                None if argument == "reset" => {
+                                    if let Err(e) = player_state.clear_volume() {
+                                        tracing::warn!("Can't forget the volume: {}", e);
+                                    }
+                                    volume = configured_volume;
+                                    let offset =
+                                        player_state.volume_offset(episode_title).unwrap_or(0);
+                                    let new_vol = state::apply_offset(volume, offset);
+                                    player.set_volume(new_vol);
+                                    mpris.update_volume(new_vol).ok();
+                                    println!("{}", tf(Msg::VolumeReset, &[&volume_line(new_vol)]));
+                                }
+                                Some(percent) => {
+                                    let new_vol = f32::from(percent) / 100.0;
+                                    player.set_volume(new_vol);
+                                    volume =
+                                        remember_volume(&mut player_state, episode_title, new_vol);
+                                    mpris.update_volume(new_vol).ok();
+                                    println!("{}", volume_line(new_vol));
+                                }
+                                None => println!("{}", t(Msg::VolumeUsage)),
+                            }
+                            false
+                        }
+                        Some(Action::Mute) => {
+                            fade_started = None;
+                            progress::clear_line();
+                            let muted = player.toggle_mute();
+                            mpris.update_volume(player.volume()).ok();
+                            if muted {
+                                println!("{}", t(Msg::Muted));
+                            } else {
+                                println!("{}", volume_line(player.volume()));
+                            }
+                            false
+                        }
+                        Some(
+                            action @ (Action::BalanceRight
+                            | Action::BalanceLeft
+                            | Action::BalanceCenter),
+                        ) => {
+                            progress::clear_line();
+                            let balance = match action {
+                                Action::BalanceRight => {
+                                    step_balance(player.balance(), BALANCE_STEP)
+                                }
+                                Action::BalanceLeft => {
+                                    step_balance(player.balance(), -BALANCE_STEP)
+                                }
+                                _ => 0.0,
+                            };
+                            player.set_balance(balance);
+                            println!("{}", balance_line(player.balance()));
+                            false
+                        }
+                        Some(Action::VolumeSave) => {
+                            progress::clear_line();
+                            let argument = commands::argument(&command);
+                            // Against the level the episode started at: a change made while
+                            // it plays is what belongs to it
+                            let saved = volume_save(
+                                &mut player_state,
+                                episode_title,
+                                argument,
+                                episode_base,
+                                player.volume(),
+                                &mut episode_volume,
+                            );
+                            match saved {
+                                Ok(line) => {
+                                    if argument.is_empty() {
+                                        volume = episode_base;
+                                        if let Err(e) = player_state.set_volume(volume) {
+                                            tracing::warn!("Can't save the volume: {}", e);
+                                        }
+                                    }
+                                    println!("{}", line);
+                                }
+                                Err(e) => eprintln!("{}", tf(Msg::VolumeSaveFailed, &[&e])),
+                            }
+                            false
+                        }
+                        Some(Action::Sleep) => {
+                            progress::clear_line();
+                            let argument = commands::argument(&command);
+                            match commands::sleep_argument(argument) {
+                                Some(sleep) => {
+                                    // Back to the level from before a fade that was under way
+                                    if let Some(from) =
+                                        sleep_volume.take().filter(|_| !player.is_muted())
+                                    {
+                                        player.set_volume(from);
+                                    }
+                                    sleep_until = match sleep {
+                                        commands::Sleep::After(after) => {
+                                            let after_text =
+                                                player::format_duration(after.as_secs());
+                                            println!("{}", tf(Msg::SleepSet, &[&after_text]));
+                                            Some(Instant::now() + after)
+                                        }
+                                        commands::Sleep::Off => {
+                                            println!("{}", t(Msg::SleepOff));
+                                            None
+                                        }
+                                    };
+                                }
+                                None => match sleep_until.filter(|_| argument.is_empty()) {
+                                    Some(ends) => {
+                                        let left = ends.saturating_duration_since(Instant::now());
+                                        let left_text = player::format_duration(left.as_secs());
+                                        println!("{}", tf(Msg::SleepSet, &[&left_text]));
+                                    }
+                                    None => println!("{}", t(Msg::SleepUsage)),
+                                },
+                            }
+                            false
+                        }
+                        Some(Action::StopAfter) => {
+                            progress::clear_line();
+                            stop_after = !stop_after;
+                            if stop_after {
+                                println!("{}", t(Msg::StopAfterOn));
+                            } else {
+                                println!("{}", t(Msg::StopAfterOff));
+                            }
+                            false
+                        }
+                        Some(Action::Device) => {
+                            progress::clear_line();
+                            quit = !reopen_output(&mut player, events)?;
+                            quit
+                        }
+                        Some(Action::Info) => {
+                            progress::clear_line();
+                            println!("\n{}", tf(Msg::InfoEpisode, &[&episode_title]));
+                            println!("{}", tf(Msg::InfoDuration, &[&episode_duration]));
+                            println!(
+                                "{}{}",
+                                if player.is_muted() {
+                                    t(Msg::Muted).to_string()
+                                } else {
+                                    volume_line(player.volume())
+                                },
+                                match player_state.volume_offset(episode_title) {
+                                    Some(percent) => {
+                                        tf(Msg::InfoVolumeSaved, &[&format!("{:+}", percent)])
+                                    }
+                                    None => String::new(),
+                                }
+                            );
+                            println!("{}", balance_line(player.balance()));
+                            println!(
+                                "{}",
+                                tf(
+                                    Msg::InfoStatus,
+                                    &[&if player.is_paused() {
+                                        t(Msg::Paused)
+                                    } else {
+                                        t(Msg::Playing)
+                                    }]
+                                )
+                            );
+                            if let Some(info) = player.stream_info() {
+                                println!("{}", stream_line(&info));
+                            }
+                            if let Some(secs) = player.trimmed_secs() {
+                                println!(
+                                    "{}",
+                                    tf(Msg::InfoSkippedSilence, &[&format!("{:.1}", secs)])
+                                );
+                            }
+                            println!(
+                                "{}",
+                                tf(
+                                    Msg::InfoSource,
+                                    &[&if local_file.is_some() {
+                                        t(Msg::SourceLocal)
+                                    } else {
+                                        t(Msg::SourceStreaming)
+                                    }]
+                                )
+                            );
+                            if stop_after {
+                                println!("{}", t(Msg::StopAfterOn));
+                            }
+                            if let Some(ends) = sleep_until {
+                                let left = ends.saturating_duration_since(Instant::now());
+                                println!(
+                                    "{}",
+                                    tf(Msg::InfoSleep, &[&player::format_duration(left.as_secs())])
+                                );
+                            }
+                            println!(
+                                "{}",
+                                tf(Msg::InfoShuffle, &[&on_off(playlist.is_shuffled())])
+                            );
+                            println!(
+                                "{}",
+                                tf(
+                                    Msg::InfoRepeat,
+                                    &[&commands::repeat_label(playlist.repeat())]
+                                )
+                            );
+                            println!(
+                                "{}\n",
+                                tf(
+                                    Msg::InfoFavorite,
+                                    &[&yes_no(favorites.is_favorite(episode_title))]
+                                )
+                            );
+                            false
+                        }
+                        Some(Action::Open) => {
+                            progress::clear_line();
+                            match &page_url {
+                                Some(url) => open_in_browser(url),
+                                None => println!("{}", t(Msg::NoPageUrl)),
+                            }
+                            false
+                        }
+                        Some(Action::Yank) => {
+                            progress::clear_line();
+                            clipboard::copy(episode_url);
+                            false
+                        }
+                        Some(Action::YankPage) => {
+                            progress::clear_line();
+                            match &page_url {
+                                Some(url) => clipboard::copy(url),
+                                None => println!("{}", t(Msg::NoPageUrl)),
+                            }
+                            false
+                        }
+                        Some(Action::Note) => {
+                            progress::clear_line();
+                            let position_secs = player.elapsed_seconds();
+                            let text = match commands::argument(&command) {
+                                "" => read_note(events, position_secs)?,
+                                text => Some(text.to_string()),
+                            };
+                            match text {
+                                Some(text) => {
+                                    let entry = JournalEntry {
+                                        episode: episode_title.clone(),
+                                        position_secs,
+                                        text,
+                                        written_at: history::unix_now(),
+                                    };
+                                    match Journal::append(&entry) {
+                                        Ok(()) => {
+                                            println!("{}", tf(Msg::NoteSaved, &[&entry.position()]))
+                                        }
+                                        Err(e) => eprintln!("{}", tf(Msg::NoteFailed, &[&e])),
+                                    }
+                                }
+                                None => println!("{}", t(Msg::NoteEmpty)),
+                            }
+                            false
+                        }
+                        Some(Action::List) => {
+                            progress::clear_line();
+                            let downloaded: std::collections::HashSet<String> = playlist
+                                .in_play_order()
+                                .filter(|ep| downloader.is_downloaded(&ep.title))
+                                .map(|ep| ep.title.clone())
+                                .collect();
+                            let markers = browser::Markers {
+                                favorites: &favorites,
+                                downloaded: &downloaded,
+                            };
+                            match browser::run(
+                                &playlist,
+                                &markers,
+                                || events.read_line(),
+                                &mut io::stdout(),
+                            )? {
+                                Some(position) => {
+                                    player.stop();
+                                    playlist.jump_to_position(position);
+                                    true
+                                }
+                                None => false,
+                            }
+                        }
+                        Some(Action::Shuffle) => {
+                            progress::clear_line();
+                            playlist.toggle_shuffle();
+                            // The next episode changed; the next tick prefetches the new one
+                            player.cancel_prefetch();
+                            mpris.update_shuffle(playlist.is_shuffled()).ok();
+                            println!(
+                                "{}",
+                                tf(Msg::InfoShuffle, &[&on_off(playlist.is_shuffled())])
+                            );
+                            false
+                        }
+                        Some(Action::Repeat) => {
+                            progress::clear_line();
+                            let mode = playlist.cycle_repeat();
+                            // As with shuffle, the next episode may have changed
+                            player.cancel_prefetch();
+                            println!("{}", tf(Msg::InfoRepeat, &[&commands::repeat_label(mode)]));
+                            false
+                        }
+                        Some(Action::Queue) if commands::argument(&command).is_empty() => {
+                            progress::clear_line();
+                            let queued: Vec<&Episode> = playlist.queued().collect();
+                            if queued.is_empty() {
+                                println!("{}", t(Msg::QueueEmpty));
+                            } else {
+                                println!("{}", t(Msg::QueueHeader));
+                                for (i, episode) in queued.iter().enumerate() {
+                                    let again = queued[..i]
+                                        .iter()
+                                        .any(|earlier| earlier.title == episode.title);
+                                    println!(
+                                        "  {}. {}{}",
+                                        i + 1,
+                                        episode.title,
+                                        if again {
+                                            format!(" {}", t(Msg::QueueAgainTag))
+                                        } else {
+                                            String::new()
+                                        }
+                                    );
+                                }
+                            }
+                            false
+                        }
+                        Some(Action::Queue) => {
+                            progress::clear_line();
+                            let number = commands::episode_number(&command);
+                            let title = number
+                                .and_then(|number| feed.find_by_number(number))
+                                .map(|e| e.title.clone());
+                            // Queued by list index, so it has to be in this playlist
+                            let index = title
+                                .as_ref()
+                                .and_then(|title| playlist.list_index_of(title));
+                            match (number, title, index) {
+                                (None, _, _) => println!("{}", t(Msg::QueueUsage)),
+                                (Some(number), None, _) => {
+                                    println!("{}", tf(Msg::EpisodeNotFound, &[&number]))
+                                }
+                                (Some(number), Some(_), None) => {
+                                    println!("{}", tf(Msg::QueueOutsidePlaylist, &[&number]))
+                                }
+                                (Some(_), Some(title), Some(index)) => {
+                                    let again = playlist.is_queued(index);
+                                    playlist.queue_next(index);
+                                    save_queue(&mut player_state, &playlist);
+                                    // The next episode changed; the next tick prefetches it
+                                    player.cancel_prefetch();
+                                    let waiting = playlist.queued().count();
+                                    let msg = if again { Msg::QueuedAgain } else { Msg::Queued };
+                                    println!("{}", tf(msg, &[&title, &waiting]));
+                                }
+                            }
+                            false
+                        }
+                        Some(Action::AddToPlaylist) => {
+                            progress::clear_line();
+                            let name = commands::argument(&command);
+                            // Loaded afresh, so `mfp playlist` changes made meanwhile are kept
+                            let added =
+                                match name {
+                                    "" => None,
+                                    name => Some(Playlists::load().and_then(|mut playlists| {
+                                        playlists.add(name, episode_title)
+                                    })),
+                                };
+                            match added {
+                                None => println!("{}", t(Msg::AddToPlaylistUsage)),
+                                Some(Ok(Edit::Done)) => {
+                                    println!("{}", tf(Msg::PlaylistAdded, &[&episode_title, &name]))
+                                }
+                                Some(Ok(Edit::Unchanged)) => println!(
+                                    "{}",
+                                    tf(Msg::PlaylistAlreadyHas, &[&name, &episode_title])
+                                ),
+                                Some(Ok(Edit::NoPlaylist)) => {
+                                    println!("{}", tf(Msg::UnknownPlaylist, &[&name]))
+                                }
+                                Some(Err(e)) => eprintln!("{}", e),
+                            }
+                            false
+                        }
+                        Some(Action::Favorite) => {
+                            progress::clear_line();
+                            let is_now_fav = favorites.toggle(episode_title.clone());
+                            println!(
+                                "{}",
+                                if is_now_fav {
+                                    style::paint(Role::Favorite, t(Msg::AddedToFavorites))
+                                } else {
+                                    t(Msg::RemovedFromFavorites).to_string()
+                                }
+                            );
+                            false
+                        }
+                        Some(action @ (Action::Undo | Action::Redo)) => {
+                            progress::clear_line();
+                            match undo_favorite(&mut favorites, action == Action::Redo) {
+                                Ok(what) => println!("{}", what),
+                                Err(e) => eprintln!("{}", tf(Msg::FavUndoFailed, &[&e])),
+                            }
+                            false
+                        }
+                        Some(Action::Download) => {
+                            progress::clear_line();
+                            if downloader.is_downloaded(episode_title) {
+                                println!(
+                                    "{}",
+                                    tf(
+                                        Msg::AlreadyDownloaded,
+                                        &[&downloader.file_path(episode_title).display()]
+                                    )
+                                );
+                            } else if let Some((title, _, _)) = &saving {
+                                println!("{}", tf(Msg::DownloadAlreadyRunning, &[title]));
+                            } else {
+                                // Playback goes on; the result is announced when it's in
+                                let (progress, download) =
+                                    download_in_background(&player, episode_title, episode_url);
+                                saving = Some((episode_title.clone(), progress, download));
+                                println!("{}", t(Msg::DownloadingForOffline));
+                            }
+                            false
+                        }
+                        Some(Action::Record) => {
+                            progress::clear_line();
+                            match &local_file {
+                                Some(path) => {
+                                    println!("{}", tf(Msg::AlreadyDownloaded, &[&path.display()]))
+                                }
+                                None => start_recording(&player, &downloader, episode_title),
+                            }
+                            false
+                        }
+                        Some(Action::Quit) => {
+                            progress::clear_line();
+                            quit = true;
+                            true
+                        }
+                        Some(Action::Help) => {
+                            progress::clear_line();
+                            let toggles = ToggleState {
+                                shuffle: playlist.is_shuffled(),
+                                repeat: playlist.repeat(),
+                                muted: player.is_muted(),
+                                stop_after,
+                            };
+                            print!("{}", commands::help_panel(&toggles, &keymap, &aliases));
+                            println!("\n{}", t(Msg::HelpPressAnyKey));
+                            events.wait_for_key()?;
+                            false
+                        }
+                        None if command.is_empty() => false,
+                        None => {
+                            progress::clear_line();
+                            println!("{}", style::paint(Role::Error, t(Msg::UnknownCommand)));
+                            println!("{}", t(Msg::CommandsHint));
+                            false
+                        }
+                    };
+
+                    events.enter_progress_display()?;
+
+                    if should_break {
+                        events.leave_progress_display()?;
+                        break;
+                    }
+                }
+            }
+        }
+
+        session.emit(SessionEvent::TrackEnded {
+            title: episode_title.clone(),
+            duration_secs: total_seconds,
+            listened_secs,
+        });
+
+        if quit {
+            session.emit(SessionEvent::Stopped);
+            player.stop();
+            events.leave_progress_display()?;
+            save_queue(&mut player_state, &playlist);
+            announce_unfinished_download(saving);
+            return Ok(());
+        }
+    }
+
+    save_queue(&mut player_state, &playlist);
+    announce_unfinished_download(saving);
+    Ok(())
+}
+
+/// Remember what is queued, for `play --continue`
+fn save_queue(player_state: &mut State, playlist: &Playlist) {
+    let titles = playlist
+        .queued()
+        .map(|episode| episode.title.clone())
+        .collect();
+    if let Err(e) = player_state.set_queue(titles) {
+        tracing::warn!("Can't save the queue: {}", e);
+    }
+}
+
+/// The `.tmp` of a download cut short by leaving stays for the next attempt to continue
+fn announce_unfinished_download(
+    saving: Option<(String, Arc<DownloadProgress>, JoinHandle<Result<PathBuf>>)>,
+) {
+    if let Some((title, _, download)) = saving {
+        if !download.is_finished() {
+            println!("{}", tf(Msg::DownloadStoppedOnQuit, &[&title]));
+        }
+    }
+}
+
+/// Save the stream playing to the downloads as `title` while it plays, and say so
+fn start_recording(player: &Renderer, downloader: &Downloader, title: &str) {
+    let path = downloader.file_path(title);
+    match player.record(&path) {
+        Ok(true) => println!("{}", tf(Msg::Recording, &[&path.display()])),
+        Ok(false) => println!("{}", t(Msg::RecordUnavailable)),
+        Err(e) => println!("{} {}", style::paint(Role::Error, t(Msg::ErrorLabel)), e),
+    }
+}
+
+/// Download `title` on a thread of its own, starting from what the stream playing already
+/// has so only the rest is fetched. Failing to set up shows when the thread is joined.
+fn download_in_background(
+    player: &Renderer,
+    title: &str,
+    url: &str,
+) -> (Arc<DownloadProgress>, JoinHandle<Result<PathBuf>>) {
+    let progress = Arc::new(DownloadProgress::default());
+    let downloader = Downloader::new().map(|downloader| {
+        match player.save_head(&downloader.partial_path(title)) {
+            Ok(bytes) => tracing::debug!(bytes, "Download starts from the stream"),
+            Err(e) => tracing::warn!("Can't reuse the stream for the download: {}", e),
+        }
+        downloader.silent().with_progress(Arc::clone(&progress))
+    });
+    let (title, url) = (title.to_string(), url.to_string());
+    let download = std::thread::spawn(move || Ok(downloader?.download_episode(&title, &url)?));
+    (progress, download)
+}
+
+/// Playback on the default output device of this computer
+#[cfg(feature = "audio")]
+fn local_renderer() -> Result<Renderer> {
+    Ok(Renderer::Local(Box::new(Player::new()?)))
+}
+
+#[cfg(not(feature = "audio"))]
+fn local_renderer() -> Result<Renderer> {
+    anyhow::bail!(t(Msg::AudioFeatureMissing))
+}
+
+#[cfg(feature = "cast")]
+fn cast_renderer(name: &str) -> Result<Renderer> {
+    output::status(tf(Msg::CastSearching, &[&name]));
+    let remote = cast::Remote::connect(cast::find(name)?)?;
+    output::status(tf(Msg::CastConnected, &[&remote.device.name]));
+
+    Ok(Renderer::Cast(remote))
+}
+
+#[cfg(not(feature = "cast"))]
+fn cast_renderer(_name: &str) -> Result<Renderer> {
+    anyhow::bail!(t(Msg::CastFeatureMissing))
+}
+
+/// After the cast device went away: carry on here? Only asked on a terminal; without one
+/// playback stops rather than starting on the local speakers unasked.
+fn confirm_local_playback(device: &str, events: &dyn Events) -> Result<bool> {
+    println!("{}", tf(Msg::CastLost, &[&device]));
+    if !events.interactive() {
+        return Ok(false);
+    }
+
+    print!("{}", t(Msg::CastContinueHere));
+    io::stdout().flush()?;
+    let answer = events.read_line()?;
+    Ok(!matches!(answer.trim().to_lowercase().as_str(), "n" | "no"))
+}
+
+/// Move playback to the default output device, offering a retry while that fails. False when
+/// the user would rather quit.
+fn reopen_output(player: &mut Renderer, events: &dyn Events) -> Result<bool> {
+    loop {
+        match player.reopen_output() {
+            Ok(()) => {
+                println!("{}", t(Msg::OutputReopened));
+                return Ok(true);
+            }
+            Err(e) if !events.interactive() => return Err(e),
+            Err(e) => {
+                print!(
+                    "{}",
+                    style::paint(Role::Error, &tf(Msg::OutputFailed, &[&e]))
+                );
+                io::stdout().flush()?;
+                if events.read_line()?.trim().eq_ignore_ascii_case("q") {
+                    return Ok(false);
+                }
+            }
+        }
+    }
+}
+
+/// Count down to a scheduled start. Returns false if cancelled with q or Ctrl+C.
+fn countdown(wait: Duration) -> Result<bool> {
+    let start_at = Instant::now() + wait;
+    let events = Terminal;
+
+    events.enter_progress_display()?;
+    let started = loop {
+        let left = start_at.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            break true;
+        }
+
+        progress::clear_line();
+        print!(
+            "{}",
+            tf(
+                Msg::StartingIn,
+                &[&player::format_duration(left.as_secs() + 1)]
+            )
+        );
+        io::stdout().flush()?;
+
+        if events.poll(Duration::from_millis(200))? {
+            if let Event::Key(KeyEvent {
+                code,
+                modifiers,
+                kind: KeyEventKind::Press,
+                ..
+            }) = events.read()?
+            {
+                let ctrl_c =
+                    code == KeyCode::Char('c') && modifiers.contains(KeyModifiers::CONTROL);
+                if ctrl_c || code == KeyCode::Char('q') || code == KeyCode::Esc {
+                    break false;
+                }
+            }
+        }
+    };
+    progress::clear_line();
+    events.leave_progress_display()?;
+
+    if !started {
+        output::status(t(Msg::ScheduleCancelled));
+    }
+    Ok(started)
+}
+
+/// Block until any key is pressed
+/// Ask for a note on a plain line (raw mode is already off); None when left empty
+fn read_note(events: &dyn Events, position_secs: u64) -> Result<Option<String>> {
+    let position = player::format_duration(position_secs);
+    print!(
+        "{}",
+        tf(Msg::NotePrompt, &[&style::paint(Role::Accent, &position)])
+    );
+    io::stdout().flush()?;
+
+    let text = events.read_line()?;
+    let text = text.trim();
+
+    Ok((!text.is_empty()).then(|| text.to_string()))
+}
+
+/// The line shown while a stream connects and fills its buffer
+fn starting_line(starting: Starting) -> String {
+    match starting {
+        Starting::Connecting => t(Msg::Connecting).to_string(),
+        Starting::Buffering(percent) => {
+            format!("{}{} {}%", t(Msg::Connecting), t(Msg::Buffering), percent)
+        }
+    }
+}
+
+fn on_off(enabled: bool) -> &'static str {
+    if enabled {
+        t(Msg::On)
+    } else {
+        t(Msg::Off)
+    }
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value {
+        t(Msg::Yes)
+    } else {
+        t(Msg::No)
+    }
+}
+
+/// `volume` moved by `step` percent, kept on whole percents between 0 and 200
+fn step_volume(volume: f32, step: i32) -> f32 {
+    let percent = (volume * 100.0).round() as i32 + step;
+    percent.clamp(0, 200) as f32 / 100.0
+}
+
+/// `balance` moved by `step`, kept on tenths so steps add up to exactly centered
+fn step_balance(balance: f32, step: f32) -> f32 {
+    ((balance + step) * 10.0).round().clamp(-10.0, 10.0) / 10.0
+}
+
+/// `Balance: centered`, or how far it leans, e.g. `Balance: L 30%`
+fn balance_line(balance: f32) -> String {
+    let percent = (balance.abs() * 100.0).round();
+    let side = if percent == 0.0 {
+        t(Msg::BalanceCentered).to_string()
+    } else if balance < 0.0 {
+        format!("L {:.0}%", percent)
+    } else {
+        format!("R {:.0}%", percent)
+    };
+    tf(Msg::InfoBalance, &[&side])
+}
+
+/// `Audio: MP3, 44.1 kHz stereo, ~192 kbps`, with `unknown` for what the decoder hasn't
+/// told yet
+fn stream_line(info: &player::StreamInfo) -> String {
+    let unknown = || t(Msg::StreamUnknown).to_string();
+    let format = info.sample_rate.map(|rate| {
+        let khz = if rate % 1000 == 0 {
+            format!("{} kHz", rate / 1000)
+        } else {
+            format!("{:.1} kHz", f64::from(rate) / 1000.0)
+        };
+        match info.channels {
+            Some(1) => format!("{} {}", khz, t(Msg::StreamMono)),
+            Some(2) => format!("{} {}", khz, t(Msg::StreamStereo)),
+            Some(n) => format!("{} {}", khz, tf(Msg::StreamChannels, &[&n])),
+            None => khz,
+        }
+    });
+    let bitrate = info.kbps.map(|kbps| format!("~{} kbps", kbps));
+    let audio = if info.codec.is_none() && format.is_none() && bitrate.is_none() {
+        unknown()
+    } else {
+        [info.codec.map(str::to_string), format, bitrate]
+            .into_iter()
+            .map(|part| part.unwrap_or_else(unknown))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    tf(Msg::InfoStream, &[&audio])
+}
+
+fn volume_line(volume: f32) -> String {
+    tf(Msg::Volume, &[&format!("{:.0}", volume * 100.0)])
+}
+
+/// Ramp the volume down before stopping, so playback doesn't end mid-sound
+fn fade_out(player: &Renderer) {
+    let from = player.volume();
+    for step in (0..FADE_OUT_STEPS).rev() {
+        player.set_volume(from * step as f32 / FADE_OUT_STEPS as f32);
+        std::thread::sleep(FADE_OUT / FADE_OUT_STEPS);
+    }
+}
+
+/// The volume every episode starts at after the listener set `current` on `title`: without
+/// the episode's own offset, so the next one starts where they left it. Saved for the next
+/// run as well; a failure to save is only logged.
+/// Seek back to the start as a fresh listen, for history and scrobbling, and unpause.
+/// False when the stream cannot seek and has to be played again from the start.
+fn restart_in_place(
+    player: &Renderer,
+    session: &mut Session,
+    mpris: &MprisController,
+    title: &str,
+    url: &str,
+    total_seconds: u64,
+) -> bool {
+    let listened_secs = player.elapsed_seconds();
+    if !player.seek(0) {
+        return false;
+    }
+    session.emit(SessionEvent::TrackEnded {
+        title: title.to_string(),
+        duration_secs: total_seconds,
+        listened_secs,
+    });
+    session.emit(SessionEvent::TrackStarted {
+        title: title.to_string(),
+        duration_secs: total_seconds,
+        url: url.to_string(),
+    });
+    // Starting over means listening again, even from a pause
+    if player.is_paused() {
+        player.resume();
+        session.emit(SessionEvent::Resumed { elapsed_secs: 0 });
+        mpris.update_playback_status(PlaybackStatus::Playing).ok();
+    }
+    true
+}
+
+fn remember_volume(player_state: &mut State, title: &str, current: f32) -> f32 {
+    let offset = player_state.volume_offset(title).unwrap_or(0);
+    let volume = state::apply_offset(current, -offset);
+    if let Err(e) = player_state.set_volume(volume) {
+        tracing::warn!("Can't save the volume: {}", e);
+    }
+    volume
+}
+
+/// `volsave` or `volsave clear` for `title`: the line to show. `volume` is what the episode
+/// started at before its offset; `episode_volume` follows the change.
+fn volume_save(
+    player_state: &mut State,
+    title: &str,
+    argument: &str,
+    volume: f32,
+    current: f32,
+    episode_volume: &mut f32,
+) -> Result<String, errors::StorageError> {
+    match argument {
+        "" => {
+            let percent = state::offset_between(volume, current);
+            player_state.set_volume_offset(title, percent)?;
+            *episode_volume = current;
+            Ok(match percent {
+                0 => t(Msg::VolumeSaveCleared).to_string(),
+                _ => tf(Msg::VolumeSaved, &[&format!("{:+}", percent)]),
+            })
+        }
+        "clear" => {
+            let cleared = player_state.clear_volume_offset(title)?;
+            *episode_volume = volume;
+            Ok(if cleared {
+                t(Msg::VolumeSaveCleared)
+            } else {
+                t(Msg::VolumeSaveNone)
+            }
+            .to_string())
+        }
+        _ => Ok(t(Msg::VolumeSaveUsage).to_string()),
+    }
+}
+
+/// A panic in the play loop must not leave the terminal in raw mode with the mouse captured
+pub fn restore_terminal_on_panic() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        mouse::release();
+        let _ = disable_raw_mode();
+        default_hook(info);
+    }));
+}
@@ -1,341 +1,3008 @@
-use anyhow::{Context, Result};
-use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink};
-use std::io::{BufReader, Read, Seek, SeekFrom};
-use std::sync::mpsc::{self, Receiver, Sender};
-use std::sync::{Arc, Mutex};
-use std::thread::{self, JoinHandle};
-use std::time::{Duration, Instant};
+//! Progressive streaming playback
+//! Audio starts once a small buffer has arrived; the rest keeps downloading in the background.
+//! Seeking restarts the decoder at an MP3 frame: the episodes are constant bitrate, so a time
+//! maps onto a byte offset, and the whole stream stays in the buffer to reopen it anywhere.
+//! The buffer is kept in memory, or with a spool dir in a temporary file there.
+//! Without the `audio` feature only the error type and the time helpers are left.
 
+#[cfg(feature = "audio")]
+use reqwest::StatusCode;
+#[cfg(feature = "audio")]
+use rodio::cpal::{
+    self,
+    traits::{DeviceTrait, HostTrait},
+};
+#[cfg(feature = "audio")]
+use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink, Source};
+use std::fs;
+use std::path::Path;
+#[cfg(feature = "audio")]
+use std::{
+    fs::File,
+    io::{self, BufReader, Read, Seek, SeekFrom},
+    path::PathBuf,
+    sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering},
+    sync::mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    sync::{Arc, Mutex},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "audio")]
+use crate::errors::EXIT_FAILURE;
+use crate::errors::{self, StorageError, EXIT_NETWORK};
+use crate::http;
+use crate::i18n::{t, tf, Msg};
+#[cfg(feature = "audio")]
+use crate::stream::{Chunk, Ranges, Spool, Starved, StreamingBuffer};
+#[cfg(feature = "audio")]
+use tokio::sync::Notify;
+#[cfg(feature = "audio")]
+use tokio_util::sync::CancellationToken;
+#[cfg(feature = "audio")]
+use tracing::debug;
+use tracing::{info, warn};
+
+#[cfg(feature = "audio")]
 const BUFFER_SIZE: usize = 512 * 1024; // Initial buffer: 512 KB
-const CHUNK_SIZE: usize = 32 * 1024; // Chunk size: 32 KB
+/// Audio that is enough to start on once the download is seen to run faster than it plays
+#[cfg(feature = "audio")]
+const START_SECS: u64 = 5;
+/// Chunks to arrive before the download speed is trusted
+#[cfg(feature = "audio")]
+const RATE_CHUNKS: u32 = 4;
+/// Reconnects after a dropped stream before it is left to the user, unless set otherwise
+#[cfg(feature = "audio")]
+const MAX_RECONNECTS: u64 = 3;
+/// Wait before the first reconnect; it doubles with every attempt up to `MAX_RECONNECT_DELAY`
+#[cfg(feature = "audio")]
+const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+#[cfg(feature = "audio")]
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+/// How long pausing and resuming ramp the volume, when they fade
+#[cfg(feature = "audio")]
+const PAUSE_FADE: Duration = Duration::from_millis(200);
+#[cfg(feature = "audio")]
+const PAUSE_FADE_STEPS: u32 = 10;
+/// Seconds of audio buffered again after the stream ran dry, before playback goes on
+#[cfg(feature = "audio")]
+const REBUFFER_SECS: u64 = 10;
+/// Bytes searched for an MP3 frame header around a seek target
+#[cfg(feature = "audio")]
+const FRAME_SEARCH: u64 = 16 * 1024;
+/// A seek target less than this far ahead of the download waits for it instead of starting
+/// a ranged request there
+#[cfg(feature = "audio")]
+const JUMP_DISTANCE: u64 = 512 * 1024;
+/// How often a download held back by the readahead limit looks at the playhead again
+#[cfg(feature = "audio")]
+const READAHEAD_POLL: Duration = Duration::from_millis(500);
+/// How often a stream that is starting checks whether it was given up
+#[cfg(feature = "audio")]
+const START_POLL: Duration = Duration::from_millis(100);
 
-struct StreamingBuffer {
-    buffer: Arc<Mutex<Vec<u8>>>,
-    position: usize,
-    download_complete: Arc<Mutex<bool>>,
+/// The start of the next episode, fetched while the current one plays so skipping to it
+/// doesn't wait for the connection and the initial buffer
+#[cfg(feature = "audio")]
+struct Prefetch {
+    url: String,
+    /// Up to [`BUFFER_SIZE`] bytes from the start of the stream
+    data: Arc<Mutex<Vec<u8>>>,
+    /// Size of the whole stream, as the server announced it; 0 when it didn't
+    total: Arc<AtomicU64>,
+    cancel: CancellationToken,
 }
 
-impl StreamingBuffer {
-    fn new(buffer: Arc<Mutex<Vec<u8>>>, download_complete: Arc<Mutex<bool>>) -> Self {
-        Self {
-            buffer,
-            position: 0,
-            download_complete,
+#[cfg(feature = "audio")]
+impl Prefetch {
+    fn start(url: &str) -> Self {
+        let prefetch = Prefetch {
+            url: url.to_string(),
+            data: Arc::default(),
+            total: Arc::default(),
+            cancel: CancellationToken::new(),
+        };
+        let url = prefetch.url.clone();
+        let data = Arc::clone(&prefetch.data);
+        let total = Arc::clone(&prefetch.total);
+        let cancel = prefetch.cancel.clone();
+        http::runtime().spawn(async move {
+            match Self::fetch(&url, &data, &total, &cancel).await {
+                Ok(()) => debug!(url, "Prefetch finished"),
+                Err(e) => debug!(url, "Prefetch stopped: {}", e),
+            }
+        });
+        prefetch
+    }
+
+    async fn fetch(
+        url: &str,
+        data: &Mutex<Vec<u8>>,
+        total: &AtomicU64,
+        cancel: &CancellationToken,
+    ) -> Result<(), PlayerError> {
+        let idle = http::idle_timeout();
+        let request = http::audio_request(http::client()?, url);
+        let mut response = http::step(request.send(), idle, cancel).await??;
+        if !response.status().is_success() {
+            return Err(PlayerError::Status {
+                status: response.status().as_u16(),
+            });
+        }
+        total.store(response.content_length().unwrap_or(0), Ordering::Relaxed);
+
+        while let Some(chunk) = http::step(response.chunk(), idle, cancel).await?? {
+            let mut data = data.lock().unwrap();
+            // Checked under the lock, so nothing lands after `take`
+            if cancel.is_cancelled() {
+                break;
+            }
+            let room = BUFFER_SIZE - data.len();
+            data.extend_from_slice(&chunk[..chunk.len().min(room)]);
+            if data.len() >= BUFFER_SIZE {
+                break;
+            }
         }
+
+        Ok(())
+    }
+
+    /// Stop fetching and hand over what arrived, with the size of the whole stream
+    fn take(&self) -> (Vec<u8>, u64) {
+        self.cancel.cancel();
+        let data = std::mem::take(&mut *self.data.lock().unwrap());
+        (data, self.total.load(Ordering::Relaxed))
     }
 }
 
-impl Read for StreamingBuffer {
-    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
-        loop {
-            let buffer = self.buffer.lock().unwrap();
-            let available = buffer.len();
+#[cfg(feature = "audio")]
+impl Drop for Prefetch {
+    fn drop(&mut self) {
+        self.cancel.cancel();
+    }
+}
 
-            if self.position < available {
-                let remaining = available - self.position;
-                let to_read = remaining.min(buf.len());
+/// Remove the spool files a crashed run left in `dir`. Only the instance holding the lock
+/// plays, so nothing else is using them.
+pub fn clear_spool(dir: &Path) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().is_some_and(|ext| ext == "part") {
+            match fs::remove_file(&path) {
+                Ok(()) => info!(path = %path.display(), "Removed leftover stream spool"),
+                Err(e) => {
+                    warn!(path = %path.display(), "Can't remove leftover stream spool: {}", e)
+                }
+            }
+        }
+    }
+}
 
-                buf[..to_read].copy_from_slice(&buffer[self.position..self.position + to_read]);
-                self.position += to_read;
+/// Why audio could not be set up or a stream could not be played
+#[derive(Debug, thiserror::Error)]
+pub enum PlayerError {
+    /// No usable audio output device
+    #[cfg(feature = "audio")]
+    #[error("{}", t(Msg::AudioDeviceFailed))]
+    AudioDevice(#[from] rodio::StreamError),
+    #[cfg(feature = "audio")]
+    #[error("{}", t(Msg::SinkFailed))]
+    Sink(#[from] rodio::PlayError),
+    /// No response: DNS, connection, TLS or timeout
+    #[error("{}", http::describe(.0, Msg::ConnectFailed))]
+    Request(#[from] reqwest::Error),
+    /// The server answered with an error status
+    #[error("{}", tf(Msg::HttpError, &[status]))]
+    Status { status: u16 },
+    /// The stream closed before any audio arrived
+    #[error("{}", t(Msg::NoDataReceived))]
+    NoData,
+    /// The data is not audio rodio can decode
+    #[cfg(feature = "audio")]
+    #[error("{}", t(Msg::DecodeFailed))]
+    Decode(#[from] rodio::decoder::DecoderError),
+    /// The server went silent, or the stream was stopped while connecting
+    #[error(transparent)]
+    Aborted(#[from] http::Aborted),
+    /// The stream redirected more than [`http::MAX_REDIRECTS`] times
+    #[error("{}", t(Msg::TooManyRedirects))]
+    TooManyRedirects,
+    /// A downloaded file could not be read
+    #[error(transparent)]
+    Storage(#[from] StorageError),
+}
 
-                return Ok(to_read);
-            }
+impl From<http::RedirectError> for PlayerError {
+    fn from(error: http::RedirectError) -> Self {
+        match error {
+            http::RedirectError::Request(e) => PlayerError::Request(e),
+            http::RedirectError::Aborted(e) => PlayerError::Aborted(e),
+            http::RedirectError::TooMany => PlayerError::TooManyRedirects,
+        }
+    }
+}
 
-            let is_complete = *self.download_complete.lock().unwrap();
-            if is_complete {
-                return Ok(0);
+impl PlayerError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            PlayerError::Request(_) | PlayerError::NoData | PlayerError::TooManyRedirects => {
+                EXIT_NETWORK
             }
+            PlayerError::Status { status } => errors::status_exit_code(*status),
+            #[cfg(feature = "audio")]
+            PlayerError::AudioDevice(_) | PlayerError::Sink(_) | PlayerError::Decode(_) => {
+                EXIT_FAILURE
+            }
+            PlayerError::Aborted(e) => e.exit_code(),
+            PlayerError::Storage(e) => e.exit_code(),
+        }
+    }
 
-            drop(buffer);
-            thread::sleep(Duration::from_millis(50));
+    /// The stream was stopped or given up on purpose, not lost
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self, PlayerError::Aborted(http::Aborted::Cancelled))
+    }
+}
+
+/// How much of the current stream has arrived, and how much of it has been played
+#[cfg(feature = "audio")]
+#[derive(Default)]
+struct StreamProgress {
+    /// Where the download has got to: the position of the next byte it fetches
+    received: AtomicU64,
+    /// Content-Length of the response, 0 while unknown; shared with a recording
+    total: Arc<AtomicU64>,
+    /// Samples handed to the output device so far
+    played_samples: AtomicU64,
+    /// Sample rate times channels, 0 until the decoder is ready
+    samples_per_sec: AtomicU64,
+    /// Since when playback has been waiting for data that hasn't arrived
+    starved_since: Starved,
+    /// Reconnects since the stream started or the user last asked to retry
+    reconnects: AtomicU64,
+    /// Between a dropped connection and the first bytes of the next one
+    reconnecting: AtomicBool,
+    /// Wakes a download that gave up, to connect once more
+    retry: Notify,
+    /// Asks the download to fetch from `jump_target` on, where a seek waits for data
+    jump: Notify,
+    jump_target: AtomicU64,
+    /// A seek waiting for the playback thread, to `seek_target` seconds
+    seek_pending: AtomicBool,
+    seek_target: AtomicU64,
+    /// Why the download or the playback thread gave up, until the play loop takes it
+    failure: Mutex<Option<PlayerError>>,
+    /// Opens the track again, once the decoder is ready; for moving it to another device
+    opener: Mutex<Option<Opener>>,
+    /// Codec and format, once the decoder is ready; the bitrate comes from `meter`
+    info: Mutex<StreamInfo>,
+    /// Of the decoder playing, replaced with it on a seek
+    meter: Mutex<Arc<Meter>>,
+    /// The whole track is here: the download finished, or it plays from a file
+    complete: AtomicBool,
+    /// The stream as it downloads, once the playback thread has set it up; None for a file
+    buffer: Mutex<Option<StreamingBuffer>>,
+    /// While the sink is paused for the buffer to refill: the bytes received when they last
+    /// grew, and when
+    rebuffering: Mutex<Option<(u64, Instant)>>,
+    /// RMS level of what played lately, as the bits of an `f32` from 0.0 to 1.0; shared with
+    /// the source measuring it
+    level: Arc<AtomicU32>,
+    /// Samples under this are leading silence to skip; None plays the track as it starts
+    trim_below: Option<i16>,
+    /// Samples of leading silence skipped
+    trimmed_samples: AtomicU64,
+    /// Most bytes the download gets ahead of the decoder, 0 for no limit
+    max_readahead: AtomicU64,
+    /// The download waits for playback to catch up with the readahead limit
+    held: AtomicBool,
+}
+
+#[cfg(feature = "audio")]
+impl StreamProgress {
+    /// Record why the stream can't play; the first reason is the one kept, since a failed
+    /// download also leaves playback without data
+    fn fail(&self, error: PlayerError) {
+        self.failure.lock().unwrap().get_or_insert(error);
+    }
+
+    /// Have the download go to `offset`, where a seek is about to read, if it is far enough
+    /// from where the download is for a ranged request to get there sooner
+    fn jump_to(&self, offset: u64) {
+        self.jump_target.store(offset, Ordering::Relaxed);
+        self.jump.notify_one();
+    }
+
+    /// Seconds into the track: the samples played, or the target of a seek still waiting.
+    /// A paused sink pulls no samples, so pauses need no bookkeeping of their own.
+    fn position_secs(&self) -> u64 {
+        if self.seek_pending.load(Ordering::Relaxed) {
+            return self.seek_target.load(Ordering::Relaxed);
+        }
+        played_secs(
+            self.played_samples.load(Ordering::Relaxed),
+            self.samples_per_sec.load(Ordering::Relaxed),
+        )
+        .unwrap_or(0)
+    }
+
+    /// Note the format of the decoder that just got ready; `open` reads the codec off the
+    /// start of the track
+    #[cfg(feature = "audio")]
+    fn set_format<S>(&self, source: &S, open: &Opener)
+    where
+        S: Source,
+        S::Item: rodio::Sample,
+    {
+        self.samples_per_sec.store(
+            u64::from(source.sample_rate()) * u64::from(source.channels()),
+            Ordering::Relaxed,
+        );
+        let mut info = self.info.lock().unwrap();
+        info.sample_rate = Some(source.sample_rate());
+        info.channels = Some(source.channels());
+        info.codec = sniff_codec(open);
+    }
+
+    /// Audio ready past the playhead; None until the bitrate estimate is in
+    fn buffered_ahead(&self) -> Option<BufferAhead> {
+        if self.complete.load(Ordering::Relaxed) {
+            return Some(BufferAhead::Full);
         }
+        let meter = self.meter.lock().unwrap();
+        let secs = meter.ahead_secs(
+            self.received.load(Ordering::Relaxed),
+            self.samples_per_sec.load(Ordering::Relaxed),
+        )?;
+        Some(if self.held.load(Ordering::Relaxed) {
+            BufferAhead::Held(secs)
+        } else {
+            BufferAhead::Secs(secs)
+        })
+    }
+
+    /// Bytes downloaded past what the decoder has read, once it reads
+    fn readahead(&self) -> Option<u64> {
+        self.opener.lock().unwrap().as_ref()?;
+        let position = self.meter.lock().unwrap().position.load(Ordering::Relaxed);
+        Some(
+            self.received
+                .load(Ordering::Relaxed)
+                .saturating_sub(position),
+        )
+    }
+
+    /// Whether the download has got `max_readahead` ahead of the decoder
+    fn readahead_reached(&self) -> bool {
+        let max = self.max_readahead.load(Ordering::Relaxed);
+        max > 0 && self.readahead().is_some_and(|ahead| ahead >= max)
     }
 }
 
-impl Seek for StreamingBuffer {
-    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
-        let buffer = self.buffer.lock().unwrap();
-        let buffer_len = buffer.len() as i64;
+/// What is known about the audio playing; each part is None until it is
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StreamInfo {
+    pub codec: Option<&'static str>,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u16>,
+    /// Estimated from the bytes read per second of audio decoded, so it settles over time
+    pub kbps: Option<u32>,
+}
 
-        let new_pos = match pos {
-            SeekFrom::Start(offset) => offset as i64,
-            SeekFrom::Current(offset) => self.position as i64 + offset,
-            SeekFrom::End(offset) => buffer_len + offset,
-        };
+/// How far a stream has got in starting, reported while `play` waits for it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Starting {
+    Connecting,
+    /// Share of the initial buffer filled, in percent
+    Buffering(u8),
+}
+
+/// What the playback thread tells `play` while the stream starts
+#[cfg(feature = "audio")]
+enum StartUpdate {
+    Buffering(u8),
+    Playing,
+}
+
+/// Audio ready to play past the playhead
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BufferAhead {
+    /// The whole track is here
+    Full,
+    Secs(u64),
+    /// Seconds, and the download waits for playback to use some: the readahead limit is reached
+    Held(u64),
+}
+
+/// Bytes one decoder has read and samples it has given out
+#[cfg(feature = "audio")]
+#[derive(Default)]
+struct Meter {
+    bytes: AtomicU64,
+    samples: AtomicU64,
+    /// Where in the track the decoder's reader is
+    position: AtomicU64,
+}
 
-        if new_pos < 0 {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::InvalidInput,
-                "Cannot seek before beginning",
-            ));
+#[cfg(feature = "audio")]
+impl Meter {
+    /// Kilobits per second of audio, once there is a second of it: before that the bytes the
+    /// decoder reads ahead outweigh what it decoded
+    fn kbps(&self, samples_per_sec: u64) -> Option<u32> {
+        let samples = self.samples.load(Ordering::Relaxed);
+        if samples_per_sec == 0 || samples < samples_per_sec {
+            return None;
         }
+        let bits = self.bytes.load(Ordering::Relaxed) * 8;
+        u32::try_from(bits * samples_per_sec / samples / 1000).ok()
+    }
 
-        self.position = new_pos as usize;
-        Ok(self.position as u64)
+    /// Seconds of audio between what the decoder has read and the `received` bytes
+    fn ahead_secs(&self, received: u64, samples_per_sec: u64) -> Option<u64> {
+        let bytes_per_sec = u64::from(self.kbps(samples_per_sec)?) * 1000 / 8;
+        if bytes_per_sec == 0 {
+            return None;
+        }
+        let ahead = received.saturating_sub(self.position.load(Ordering::Relaxed));
+        Some(ahead / bytes_per_sec)
     }
 }
 
-pub struct Player {
-    _stream: OutputStream,
-    stream_handle: OutputStreamHandle,
-    sink: Arc<Mutex<Option<Arc<Sink>>>>,
-    playback_thread: Arc<Mutex<Option<JoinHandle<()>>>>,
-    download_thread: Arc<Mutex<Option<JoinHandle<()>>>>,
-    is_paused: Arc<Mutex<bool>>,
-    start_time: Arc<Mutex<Option<Instant>>>,
-    paused_duration: Arc<Mutex<Duration>>,
+/// Passes a reader or a source through, counting into `meter` what is read or decoded
+#[cfg(feature = "audio")]
+struct Metered<T> {
+    inner: T,
+    meter: Arc<Meter>,
 }
 
-impl Player {
-    pub fn new() -> Result<Self> {
-        let (stream, stream_handle) = OutputStream::try_default().context(
-            "No se pudo inicializar el dispositivo de audio. Verifica tu configuración de audio.",
-        )?;
+#[cfg(feature = "audio")]
+impl<R: Read> Read for Metered<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.meter.bytes.fetch_add(n as u64, Ordering::Relaxed);
+        self.meter.position.fetch_add(n as u64, Ordering::Relaxed);
+        Ok(n)
+    }
+}
 
-        Ok(Player {
-            _stream: stream,
-            stream_handle,
-            sink: Arc::new(Mutex::new(None)),
-            playback_thread: Arc::new(Mutex::new(None)),
-            download_thread: Arc::new(Mutex::new(None)),
-            is_paused: Arc::new(Mutex::new(false)),
-            start_time: Arc::new(Mutex::new(None)),
-            paused_duration: Arc::new(Mutex::new(Duration::from_secs(0))),
-        })
+#[cfg(feature = "audio")]
+impl<R: Seek> Seek for Metered<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = self.inner.seek(pos)?;
+        self.meter.position.store(position, Ordering::Relaxed);
+        Ok(position)
     }
+}
 
-    pub fn play(&self, url: &str) -> Result<()> {
-        self.stop();
+#[cfg(feature = "audio")]
+impl<S: Source<Item = i16>> Iterator for Metered<S> {
+    type Item = i16;
 
-        *self.start_time.lock().unwrap() = Some(Instant::now());
-        *self.paused_duration.lock().unwrap() = Duration::from_secs(0);
+    fn next(&mut self) -> Option<i16> {
+        let sample = self.inner.next()?;
+        self.meter.samples.fetch_add(1, Ordering::Relaxed);
+        Some(sample)
+    }
 
-        print!("Connecting...");
-        use std::io::Write;
-        std::io::stdout().flush().ok();
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
 
-        let sink = Arc::new(
-            Sink::try_new(&self.stream_handle).context("No se pudo crear el sink de audio")?,
-        );
+#[cfg(feature = "audio")]
+impl<S: Source<Item = i16>> Source for Metered<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.inner.current_frame_len()
+    }
 
-        *self.sink.lock().unwrap() = Some(Arc::clone(&sink));
-        *self.is_paused.lock().unwrap() = false;
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
 
-        let (tx, rx): (Sender<Vec<u8>>, Receiver<Vec<u8>>) = mpsc::channel();
-        let download_complete = Arc::new(Mutex::new(false));
-        let download_complete_clone = Arc::clone(&download_complete);
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
 
-        let url = url.to_string();
-        let download_handle = thread::spawn(move || {
-            let _ = Self::download_stream(&url, tx, download_complete_clone);
-        });
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+}
 
-        let sink_clone = Arc::clone(&sink);
-        let playback_handle = thread::spawn(move || {
-            let _ = Self::play_stream(rx, &sink_clone, download_complete);
-        });
+/// Decoder for `reader`, which starts `offset` bytes into the track, metered; its meter
+/// becomes the one of `progress` once it has started
+#[cfg(feature = "audio")]
+fn metered_decoder<R: Read + Seek + Send + Sync + 'static>(
+    reader: R,
+    offset: u64,
+    progress: &StreamProgress,
+) -> Result<Metered<Decoder<BufReader<Metered<R>>>>, rodio::decoder::DecoderError> {
+    let meter = Arc::new(Meter {
+        position: AtomicU64::new(offset),
+        ..Meter::default()
+    });
+    let decoder = Decoder::new(BufReader::new(Metered {
+        inner: reader,
+        meter: Arc::clone(&meter),
+    }))?;
+    *progress.meter.lock().unwrap() = Arc::clone(&meter);
+    Ok(Metered {
+        inner: decoder,
+        meter,
+    })
+}
 
-        *self.download_thread.lock().unwrap() = Some(download_handle);
-        *self.playback_thread.lock().unwrap() = Some(playback_handle);
+/// Passes a source through, counting the samples pulled from it
+#[cfg(feature = "audio")]
+struct Counted<S> {
+    inner: S,
+    progress: Arc<StreamProgress>,
+}
 
-        std::thread::sleep(std::time::Duration::from_millis(1500));
+#[cfg(feature = "audio")]
+impl<S: Source<Item = i16>> Iterator for Counted<S> {
+    type Item = i16;
 
-        Ok(())
+    fn next(&mut self) -> Option<i16> {
+        let sample = self.inner.next()?;
+        self.progress.played_samples.fetch_add(1, Ordering::Relaxed);
+        Some(sample)
     }
 
-    fn download_stream(
-        url: &str,
-        tx: Sender<Vec<u8>>,
-        download_complete: Arc<Mutex<bool>>,
-    ) -> Result<()> {
-        let mut response =
-            reqwest::blocking::get(url).context("No se pudo conectar al servidor")?;
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
 
-        if !response.status().is_success() {
-            anyhow::bail!("Error HTTP: {}", response.status());
-        }
+#[cfg(feature = "audio")]
+impl<S: Source<Item = i16>> Source for Counted<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.inner.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
 
-        let mut buffer = vec![0u8; CHUNK_SIZE];
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
 
-        loop {
-            match response.read(&mut buffer) {
-                Ok(0) => break,
-                Ok(n) => {
-                    if tx.send(buffer[..n].to_vec()).is_err() {
-                        break;
-                    }
-                }
-                Err(_) => break,
-            }
-        }
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+}
 
-        *download_complete.lock().unwrap() = true;
+/// Left/right balance as the bits of an `f32` from -1.0 (left only) to 1.0 (right only),
+/// shared with the sources so a change is heard at once
+#[cfg(feature = "audio")]
+type Balance = Arc<AtomicU32>;
 
-        Ok(())
+/// Gains of the left and right channels at `balance`: the side it leans to stays as it is
+/// and the other one is turned down
+pub fn balance_gains(balance: f32) -> (f32, f32) {
+    ((1.0 - balance).min(1.0), (1.0 + balance).min(1.0))
+}
+
+/// Turns one channel of a stereo source down by the current balance. Centered, or with
+/// other than two channels, samples pass through untouched.
+#[cfg(feature = "audio")]
+struct Balanced<S> {
+    inner: S,
+    balance: Balance,
+    /// Samples given out, to tell left from right
+    emitted: u64,
+}
+
+#[cfg(feature = "audio")]
+impl<S: Source<Item = i16>> Balanced<S> {
+    fn new(inner: S, balance: &Balance) -> Self {
+        Self {
+            inner,
+            balance: Arc::clone(balance),
+            emitted: 0,
+        }
     }
+}
 
-    fn play_stream(
-        rx: Receiver<Vec<u8>>,
-        sink: &Sink,
-        download_complete: Arc<Mutex<bool>>,
-    ) -> Result<()> {
-        let mut initial_buffer = Vec::new();
+#[cfg(feature = "audio")]
+impl<S: Source<Item = i16>> Iterator for Balanced<S> {
+    type Item = i16;
 
-        print!(" buffering...");
-        use std::io::Write;
-        std::io::stdout().flush().ok();
+    fn next(&mut self) -> Option<i16> {
+        let sample = self.inner.next()?;
+        let right = self.emitted % 2 == 1;
+        self.emitted += 1;
 
-        while initial_buffer.len() < BUFFER_SIZE {
-            match rx.recv() {
-                Ok(chunk) => initial_buffer.extend_from_slice(&chunk),
-                Err(_) => {
-                    if initial_buffer.is_empty() {
-                        anyhow::bail!("No se recibieron datos");
-                    }
-                    break;
-                }
-            }
+        let balance = f32::from_bits(self.balance.load(Ordering::Relaxed));
+        if balance == 0.0 || self.inner.channels() != 2 {
+            return Some(sample);
         }
+        let (left_gain, right_gain) = balance_gains(balance);
+        let gain = if right { right_gain } else { left_gain };
+        Some((f32::from(sample) * gain) as i16)
+    }
 
-        println!(" OK\n");
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
 
-        let buffer_arc = Arc::new(Mutex::new(initial_buffer));
-        let buffer_clone = Arc::clone(&buffer_arc);
+#[cfg(feature = "audio")]
+impl<S: Source<Item = i16>> Source for Balanced<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.inner.current_frame_len()
+    }
 
-        thread::spawn(move || {
-            while let Ok(chunk) = rx.recv() {
-                buffer_clone.lock().unwrap().extend_from_slice(&chunk);
-            }
-        });
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
 
-        std::thread::sleep(std::time::Duration::from_millis(200));
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
 
-        let streaming_buffer = StreamingBuffer::new(buffer_arc, download_complete);
-        let buf_reader = BufReader::new(streaming_buffer);
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+}
 
-        let source = Decoder::new(buf_reader).context("No se pudo decodificar el audio")?;
+/// Length of the window the level is measured over
+#[cfg(feature = "audio")]
+const LEVEL_WINDOW: Duration = Duration::from_millis(50);
 
-        sink.append(source);
-        sink.sleep_until_end();
+/// Passes a source through, publishing the RMS level of every [`LEVEL_WINDOW`] of it to
+/// `level` as the bits of an `f32`, and 0.0 once it ends. Nothing is allocated per sample.
+#[cfg(feature = "audio")]
+struct Leveled<S> {
+    inner: S,
+    level: Arc<AtomicU32>,
+    /// Sum of the squared samples of the window so far, scaled to -1.0..1.0
+    squares: f32,
+    counted: u32,
+    /// Samples in a window, for the source's rate and channels
+    window: u32,
+}
 
-        Ok(())
+#[cfg(feature = "audio")]
+impl<S: Source<Item = i16>> Leveled<S> {
+    fn new(inner: S, level: Arc<AtomicU32>) -> Self {
+        let window = window_samples(&inner);
+        Self {
+            inner,
+            level,
+            squares: 0.0,
+            counted: 0,
+            window,
+        }
     }
+}
 
-    pub fn stop(&self) {
-        if let Some(sink) = self.sink.lock().unwrap().take() {
-            sink.stop();
+/// Samples in a [`LEVEL_WINDOW`] of `source`, all channels counted
+#[cfg(feature = "audio")]
+fn window_samples<S: Source<Item = i16>>(source: &S) -> u32 {
+    let per_sec = source.sample_rate() * u32::from(source.channels());
+    ((per_sec as f32 * LEVEL_WINDOW.as_secs_f32()) as u32).max(1)
+}
+
+#[cfg(feature = "audio")]
+impl<S: Source<Item = i16>> Iterator for Leveled<S> {
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        let Some(sample) = self.inner.next() else {
+            self.level.store(0.0f32.to_bits(), Ordering::Relaxed);
+            return None;
+        };
+        let x = f32::from(sample) / f32::from(i16::MAX);
+        self.squares += x * x;
+        self.counted += 1;
+
+        if self.counted >= self.window {
+            let rms = (self.squares / self.counted as f32).sqrt().min(1.0);
+            self.level.store(rms.to_bits(), Ordering::Relaxed);
+            self.squares = 0.0;
+            self.counted = 0;
+            // A seek can reopen the track in another format
+            self.window = window_samples(&self.inner);
         }
+        Some(sample)
+    }
 
-        let _ = self.playback_thread.lock().unwrap().take();
-        let _ = self.download_thread.lock().unwrap().take();
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
 
-        *self.is_paused.lock().unwrap() = false;
+#[cfg(feature = "audio")]
+impl<S: Source<Item = i16>> Source for Leveled<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.inner.current_frame_len()
     }
 
-    pub fn pause(&self) {
-        if let Some(ref sink) = *self.sink.lock().unwrap() {
-            if !*self.is_paused.lock().unwrap() {
-                sink.pause();
-                *self.is_paused.lock().unwrap() = true;
-                if let Some(start) = *self.start_time.lock().unwrap() {
-                    let elapsed = start.elapsed();
-                    *self.paused_duration.lock().unwrap() = elapsed;
-                }
-            }
-        }
+    fn channels(&self) -> u16 {
+        self.inner.channels()
     }
 
-    pub fn resume(&self) {
-        if let Some(ref sink) = *self.sink.lock().unwrap() {
-            if *self.is_paused.lock().unwrap() {
-                sink.play();
-                *self.is_paused.lock().unwrap() = false;
-                let paused = *self.paused_duration.lock().unwrap();
-                *self.start_time.lock().unwrap() = Some(Instant::now() - paused);
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+}
+
+/// Most leading silence skipped, so a quiet intro isn't mistaken for it all
+#[cfg(feature = "audio")]
+const TRIM_SILENCE_MAX: Duration = Duration::from_secs(10);
+
+/// Amplitude of a sample at `db` dBFS, at least 1 so digital silence always counts
+#[cfg(feature = "audio")]
+fn silence_threshold(db: f32) -> i16 {
+    (10f32.powf(db / 20.0) * f32::from(i16::MAX)).max(1.0) as i16
+}
+
+/// Starts a source at its first sound: frames whose samples all stay under
+/// `progress.trim_below` are dropped up front, for at most [`TRIM_SILENCE_MAX`]. The samples
+/// skipped count as played, so the position stays that of the track.
+#[cfg(feature = "audio")]
+struct Trimmed<S> {
+    inner: S,
+    /// The first loud frame, read to find it, last sample first
+    pending: Vec<i16>,
+}
+
+#[cfg(feature = "audio")]
+impl<S: Source<Item = i16>> Trimmed<S> {
+    fn new(mut inner: S, progress: &StreamProgress) -> Self {
+        let Some(below) = progress.trim_below.map(i16::unsigned_abs) else {
+            return Self {
+                inner,
+                pending: Vec::new(),
+            };
+        };
+        let channels = usize::from(inner.channels().max(1));
+        let max = TRIM_SILENCE_MAX.as_secs() * u64::from(inner.sample_rate()) * channels as u64;
+        let mut skipped = 0;
+        let mut pending = Vec::new();
+        while skipped < max && !progress.seek_pending.load(Ordering::Relaxed) {
+            let mut frame: Vec<i16> = inner.by_ref().take(channels).collect();
+            if frame.is_empty() {
+                break;
             }
+            if frame.iter().any(|sample| sample.unsigned_abs() >= below) {
+                frame.reverse();
+                pending = frame;
+                break;
+            }
+            skipped += frame.len() as u64;
+        }
+        if skipped > 0 {
+            debug!(samples = skipped, "Leading silence skipped");
+            progress
+                .played_samples
+                .fetch_add(skipped, Ordering::Relaxed);
+            progress.trimmed_samples.store(skipped, Ordering::Relaxed);
         }
+        Self { inner, pending }
     }
+}
 
-    pub fn is_paused(&self) -> bool {
-        *self.is_paused.lock().unwrap()
-    }
+#[cfg(feature = "audio")]
+impl<S: Source<Item = i16>> Iterator for Trimmed<S> {
+    type Item = i16;
 
-    pub fn is_empty(&self) -> bool {
-        self.sink
-            .lock()
-            .unwrap()
-            .as_ref()
-            .map(|s| s.empty())
-            .unwrap_or(true)
+    fn next(&mut self) -> Option<i16> {
+        self.pending.pop().or_else(|| self.inner.next())
     }
 
-    pub fn volume(&self) -> f32 {
-        self.sink
-            .lock()
-            .unwrap()
-            .as_ref()
-            .map(|s| s.volume())
-            .unwrap_or(1.0)
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (low, high) = self.inner.size_hint();
+        let pending = self.pending.len();
+        (low + pending, high.map(|high| high + pending))
     }
+}
 
-    pub fn set_volume(&self, volume: f32) {
-        if let Some(ref sink) = *self.sink.lock().unwrap() {
-            sink.set_volume(volume.clamp(0.0, 2.0));
+#[cfg(feature = "audio")]
+impl<S: Source<Item = i16>> Source for Trimmed<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        if self.pending.is_empty() {
+            self.inner.current_frame_len()
+        } else {
+            // Check the format again once the frame read ahead is out
+            Some(self.pending.len())
         }
     }
 
-    pub fn sleep_until_end(&self) {
-        if let Some(ref sink) = *self.sink.lock().unwrap() {
-            sink.sleep_until_end();
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+}
+
+/// Something a track can be opened on again at any byte offset: the stream or a file
+#[cfg(feature = "audio")]
+trait Media: Read + Seek + Send + Sync {}
+
+#[cfg(feature = "audio")]
+impl<T: Read + Seek + Send + Sync> Media for T {}
+
+#[cfg(feature = "audio")]
+type Opener = Arc<dyn Fn(u64) -> std::io::Result<Box<dyn Media>> + Send + Sync>;
+
+/// Decoded audio that starts over at another MP3 frame when a seek is asked for
+#[cfg(feature = "audio")]
+struct Seekable {
+    current: Box<dyn Source<Item = i16> + Send>,
+    open: Opener,
+    progress: Arc<StreamProgress>,
+    channels: u16,
+    /// Samples given out since the last (re)start, so a seek never splits a frame of channels
+    emitted: u64,
+}
+
+#[cfg(feature = "audio")]
+impl Seekable {
+    fn new(
+        source: impl Source<Item = i16> + Send + 'static,
+        open: Opener,
+        progress: Arc<StreamProgress>,
+    ) -> Self {
+        Self {
+            channels: source.channels().max(1),
+            current: Box::new(source),
+            open,
+            progress,
+            emitted: 0,
         }
     }
 
-    pub fn elapsed_seconds(&self) -> u64 {
-        if let Some(start) = *self.start_time.lock().unwrap() {
-            if *self.is_paused.lock().unwrap() {
-                self.paused_duration.lock().unwrap().as_secs()
-            } else {
-                start.elapsed().as_secs()
+    fn seek(&mut self, secs: u64) {
+        // The position reads as the target while the seek waits for data
+        let samples_per_sec = self.progress.samples_per_sec.load(Ordering::Relaxed);
+        let before = self
+            .progress
+            .played_samples
+            .swap(secs * samples_per_sec, Ordering::Relaxed);
+        let reopened = locate(&self.open, secs, &self.progress).and_then(|offset| {
+            let reader = (self.open)(offset).ok()?;
+            metered_decoder(reader, offset, &self.progress).ok()
+        });
+
+        match reopened {
+            Some(decoder) => {
+                debug!(secs, "Seeked");
+                self.current = Box::new(decoder);
+                self.emitted = 0;
+            }
+            None => {
+                warn!(secs, "Could not seek: no MP3 frame found at that position");
+                self.progress
+                    .played_samples
+                    .store(before, Ordering::Relaxed);
             }
-        } else {
-            0
         }
     }
 }
 
-pub fn parse_duration(duration_str: &str) -> Option<u64> {
-    let parts: Vec<&str> = duration_str.split(':').collect();
+#[cfg(feature = "audio")]
+impl Iterator for Seekable {
+    type Item = i16;
 
-    match parts.len() {
-        2 => {
-            let minutes = parts[0].parse::<u64>().ok()?;
-            let seconds = parts[1].parse::<u64>().ok()?;
-            Some(minutes * 60 + seconds)
-        }
-        3 => {
-            let hours = parts[0].parse::<u64>().ok()?;
-            let minutes = parts[1].parse::<u64>().ok()?;
-            let seconds = parts[2].parse::<u64>().ok()?;
-            Some(hours * 3600 + minutes * 60 + seconds)
+    fn next(&mut self) -> Option<i16> {
+        if self.emitted.is_multiple_of(u64::from(self.channels))
+            && self.progress.seek_pending.swap(false, Ordering::Relaxed)
+        {
+            self.seek(self.progress.seek_target.load(Ordering::Relaxed));
         }
-        _ => None,
+        let sample = self.current.next()?;
+        self.emitted += 1;
+        Some(sample)
     }
 }
 
-pub fn format_duration(seconds: u64) -> String {
-    let hours = seconds / 3600;
-    let minutes = (seconds % 3600) / 60;
-    let secs = seconds % 60;
+#[cfg(feature = "audio")]
+impl Source for Seekable {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.current.current_frame_len()
+    }
 
-    if hours > 0 {
-        format!("{:02}:{:02}:{:02}", hours, minutes, secs)
+    fn channels(&self) -> u16 {
+        self.current.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.current.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// Byte offset of the frame `secs` into the track, reading through `open`. The episodes are
+/// constant bitrate, so the first frame's bitrate tells where any second starts. Reads of
+/// the stream wait for the download; the download of `progress` is sent there first.
+#[cfg(feature = "audio")]
+fn locate(open: &Opener, secs: u64, progress: &StreamProgress) -> Option<u64> {
+    let audio_start = id3v2_len(&read_at(open, 0, 10)?);
+    let (skip, first) = find_frame(&read_at(open, audio_start, FRAME_SEARCH)?)?;
+    let target = audio_start + skip + secs * first.bytes_per_sec();
+    progress.jump_to(target);
+    let (skip, _) = find_frame(&read_at(open, target, FRAME_SEARCH)?)?;
+    Some(target + skip)
+}
+
+/// Up to `len` bytes of the track from `offset` on
+#[cfg(feature = "audio")]
+fn read_at(open: &Opener, offset: u64, len: u64) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+    open(offset).ok()?.take(len).read_to_end(&mut bytes).ok()?;
+    Some(bytes)
+}
+
+/// Codec of the track, from the first bytes after any ID3v2 tag
+#[cfg(feature = "audio")]
+fn sniff_codec(open: &Opener) -> Option<&'static str> {
+    let audio_start = id3v2_len(&read_at(open, 0, 10)?);
+    codec_name(&read_at(open, audio_start, FRAME_SEARCH)?)
+}
+
+/// Codec of audio starting with `head`
+#[cfg(feature = "audio")]
+fn codec_name(head: &[u8]) -> Option<&'static str> {
+    match head {
+        [b'f', b'L', b'a', b'C', ..] => Some("FLAC"),
+        [b'O', b'g', b'g', b'S', ..] => Some("Ogg"),
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'A', b'V', b'E', ..] => Some("WAV"),
+        [_, _, _, _, b'f', b't', b'y', b'p', ..] => Some("AAC"),
+        _ if find_frame(head).is_some() => Some("MP3"),
+        _ => None,
+    }
+}
+
+/// Size of the ID3v2 tag at the start of an MP3, 0 without one
+#[cfg(feature = "audio")]
+fn id3v2_len(head: &[u8]) -> u64 {
+    match head {
+        [b'I', b'D', b'3', _, _, flags, size @ ..] if size.len() >= 4 => {
+            // Sizes are "syncsafe": seven bits per byte
+            let size = size[..4]
+                .iter()
+                .fold(0u64, |size, &byte| (size << 7) | u64::from(byte & 0x7f));
+            let footer = if flags & 0x10 != 0 { 10 } else { 0 };
+            10 + size + footer
+        }
+        _ => 0,
+    }
+}
+
+/// An MPEG audio layer III frame header
+#[cfg(feature = "audio")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FrameHeader {
+    kbps: u32,
+    sample_rate: u32,
+    len: usize,
+}
+
+#[cfg(feature = "audio")]
+impl FrameHeader {
+    fn parse(bytes: &[u8]) -> Option<Self> {
+        const KBPS_V1: [u32; 15] = [
+            0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320,
+        ];
+        const KBPS_V2: [u32; 15] = [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160];
+
+        let &[0xff, b1, b2, ..] = bytes else {
+            return None;
+        };
+        // Sync bits, then layer III
+        if b1 & 0xe0 != 0xe0 || (b1 >> 1) & 0x03 != 0x01 {
+            return None;
+        }
+        let (kbps_table, rates, samples_factor) = match (b1 >> 3) & 0x03 {
+            0b11 => (&KBPS_V1, [44_100, 48_000, 32_000], 144_000),
+            0b10 => (&KBPS_V2, [22_050, 24_000, 16_000], 72_000),
+            0b00 => (&KBPS_V2, [11_025, 12_000, 8_000], 72_000),
+            _ => return None,
+        };
+        let kbps = *kbps_table
+            .get(usize::from(b2 >> 4))
+            .filter(|&&kbps| kbps > 0)?;
+        let sample_rate = *rates.get(usize::from((b2 >> 2) & 0x03))?;
+        let padding = u32::from((b2 >> 1) & 0x01);
+
+        Some(Self {
+            kbps,
+            sample_rate,
+            len: (samples_factor * kbps / sample_rate + padding) as usize,
+        })
+    }
+
+    fn bytes_per_sec(&self) -> u64 {
+        u64::from(self.kbps) * 1000 / 8
+    }
+}
+
+/// First frame header in `bytes` that is followed by another one where its length says,
+/// so stray 0xFF bytes inside audio data aren't taken for a frame
+#[cfg(feature = "audio")]
+fn find_frame(bytes: &[u8]) -> Option<(u64, FrameHeader)> {
+    (0..bytes.len()).find_map(|i| {
+        let header = FrameHeader::parse(&bytes[i..])?;
+        let next = bytes.get(i + header.len..)?;
+        FrameHeader::parse(next).map(|_| (i as u64, header))
+    })
+}
+
+/// Where the audio of an MP3 stream starts, past its tag, and its bytes per second; None
+/// until enough of it has arrived to find a frame
+#[cfg(feature = "audio")]
+fn audio_rate(buffer: &StreamingBuffer) -> Option<(u64, u64)> {
+    let audio_start = id3v2_len(&buffer.bytes_at(0, 10).ok()?);
+    let (_, frame) = find_frame(&buffer.bytes_at(audio_start, FRAME_SEARCH).ok()?)?;
+    Some((audio_start, frame.bytes_per_sec()))
+}
+
+/// Audio output plus the track currently playing on it
+#[cfg(feature = "audio")]
+pub struct Player {
+    _stream: OutputStream,
+    stream_handle: OutputStreamHandle,
+    sink: Arc<Mutex<Option<Arc<Sink>>>>,
+    playback_thread: Arc<Mutex<Option<JoinHandle<()>>>>,
+    /// Stops the download task of the current stream
+    download_cancel: Mutex<Option<CancellationToken>>,
+    is_paused: Arc<Mutex<bool>>,
+    /// Volume applied to the sink of every new track
+    start_volume: Mutex<f32>,
+    /// Volume of the current track as set, before [`amplitude`]; shared with pause ramps
+    volume: Arc<Mutex<f32>>,
+    /// While muted, the volume unmuting goes back to; kept from track to track
+    unmuted_volume: Mutex<Option<f32>>,
+    /// Reconnects a dropped stream gets on its own
+    max_reconnects: AtomicU64,
+    /// Bytes a stream downloads ahead of playback at most, 0 for all of it at once
+    max_readahead: AtomicU64,
+    /// Replaced on every `play` so a lingering old download can't count toward the new track
+    stream_progress: Mutex<Arc<StreamProgress>>,
+    /// Streams go to a temporary file here instead of memory
+    spool_dir: Mutex<Option<PathBuf>>,
+    /// Temporary file of the current stream
+    spool_file: Mutex<Option<PathBuf>>,
+    /// Numbers the spool files, so a stream still winding down never shares one
+    spools: AtomicU64,
+    /// Ramp the volume around pauses instead of cutting the sound
+    fade_pauses: AtomicBool,
+    /// Amplitude under which the start of a track is skipped as silence, if it is
+    trim_silence: Mutex<Option<i16>>,
+    /// Bumped by every pause, resume and stop, so a ramp still running knows to give up
+    ramps: Arc<AtomicU64>,
+    /// Start of the episode expected next
+    prefetch: Mutex<Option<Prefetch>>,
+    /// Name of the output device opened, to notice it going away
+    device: Option<String>,
+    /// Kept from track to track
+    balance: Balance,
+    /// Seconds buffered again after an underrun before playback goes on
+    rebuffer_secs: AtomicU64,
+    /// Bytes buffered before a stream starts playing, at most
+    initial_buffer: AtomicU64,
+}
+
+#[cfg(feature = "audio")]
+impl Player {
+    /// Open the default audio device
+    pub fn new() -> Result<Self, PlayerError> {
+        let (stream, stream_handle, device) = open_output()?;
+
+        Ok(Player {
+            _stream: stream,
+            stream_handle,
+            sink: Arc::new(Mutex::new(None)),
+            playback_thread: Arc::new(Mutex::new(None)),
+            download_cancel: Mutex::new(None),
+            is_paused: Arc::new(Mutex::new(false)),
+            start_volume: Mutex::new(1.0),
+            volume: Arc::new(Mutex::new(1.0)),
+            unmuted_volume: Mutex::new(None),
+            max_reconnects: AtomicU64::new(MAX_RECONNECTS),
+            max_readahead: AtomicU64::new(0),
+            stream_progress: Mutex::new(Arc::default()),
+            spool_dir: Mutex::new(None),
+            spool_file: Mutex::new(None),
+            spools: AtomicU64::new(0),
+            fade_pauses: AtomicBool::new(false),
+            trim_silence: Mutex::new(None),
+            ramps: Arc::new(AtomicU64::new(0)),
+            prefetch: Mutex::new(None),
+            device,
+            balance: Arc::new(AtomicU32::new(0.0f32.to_bits())),
+            rebuffer_secs: AtomicU64::new(REBUFFER_SECS),
+            initial_buffer: AtomicU64::new(BUFFER_SIZE as u64),
+        })
+    }
+
+    /// Whether the output device playback was opened on has gone: headphones unplugged, a
+    /// Bluetooth speaker dropped. Lists the devices, so it is meant for every few seconds.
+    pub fn output_lost(&self) -> bool {
+        let Some(name) = &self.device else {
+            return false;
+        };
+        match cpal::default_host().output_devices() {
+            Ok(mut devices) => !devices.any(|device| device.name().is_ok_and(|n| n == *name)),
+            // Can't tell, so keep playing
+            Err(_) => false,
+        }
+    }
+
+    /// Open the default output device again and carry on with the current track from where
+    /// it was, out of the audio already downloaded. Volume, mute and pause stay as they were.
+    pub fn reopen_output(&mut self) -> Result<(), PlayerError> {
+        let (stream, stream_handle, device) = open_output()?;
+        self._stream = stream;
+        self.stream_handle = stream_handle;
+        info!(?device, "Audio output reopened");
+        self.device = device;
+
+        let progress = Arc::clone(&self.stream_progress.lock().unwrap());
+        let Some(open) = progress.opener.lock().unwrap().clone() else {
+            // Nothing decoding yet: the next track opens on the new device
+            return Ok(());
+        };
+        let secs = progress.position_secs();
+        let offset = locate(&open, secs, &progress).ok_or(PlayerError::NoData)?;
+        let reader = open(offset).map_err(|_| PlayerError::NoData)?;
+        let source = metered_decoder(reader, offset, &progress)?;
+        progress.seek_pending.store(false, Ordering::Relaxed);
+        progress.played_samples.store(
+            secs * progress.samples_per_sec.load(Ordering::Relaxed),
+            Ordering::Relaxed,
+        );
+
+        let sink = Arc::new(Sink::try_new(&self.stream_handle)?);
+        sink.set_volume(amplitude(self.volume()));
+        if self.is_paused() {
+            sink.pause();
+        }
+        let level = Arc::clone(&progress.level);
+        sink.append(Leveled::new(
+            Balanced::new(
+                Counted {
+                    inner: Seekable::new(source, open, Arc::clone(&progress)),
+                    progress,
+                },
+                &self.balance,
+            ),
+            level,
+        ));
+        if let Some(old) = self.sink.lock().unwrap().replace(Arc::clone(&sink)) {
+            old.stop();
+        }
+        let playback_handle = thread::spawn(move || sink.sleep_until_end());
+        *self.playback_thread.lock().unwrap() = Some(playback_handle);
+
+        Ok(())
+    }
+
+    /// Stop whatever is playing and start streaming `url`, from its prefetched start if it
+    /// was the one prefetched
+    pub fn play(&self, url: &str) -> Result<(), PlayerError> {
+        self.play_interruptible(url, &|| false, &|_| {})
+    }
+
+    /// `play`, checking `interrupted` while the stream connects and buffers and telling
+    /// `starting` how far it got. Once `interrupted` says so the stream is stopped, the
+    /// connection closed, and the start fails as cancelled.
+    pub fn play_interruptible(
+        &self,
+        url: &str,
+        interrupted: &dyn Fn() -> bool,
+        starting: &dyn Fn(Starting),
+    ) -> Result<(), PlayerError> {
+        self.stop();
+        // Any other prefetch is for an episode no longer coming next
+        let prefetched = self
+            .prefetch
+            .lock()
+            .unwrap()
+            .take()
+            .filter(|prefetch| prefetch.url == url)
+            .map(|prefetch| prefetch.take());
+
+        starting(Starting::Connecting);
+
+        let sink = Arc::new(Sink::try_new(&self.stream_handle)?);
+
+        sink.set_volume(self.track_level());
+        *self.sink.lock().unwrap() = Some(Arc::clone(&sink));
+        *self.is_paused.lock().unwrap() = false;
+
+        let (tx, rx): (Sender<Chunk>, Receiver<Chunk>) = mpsc::channel();
+
+        let progress = Arc::new(StreamProgress {
+            trim_below: *self.trim_silence.lock().unwrap(),
+            max_readahead: AtomicU64::new(self.max_readahead.load(Ordering::Relaxed)),
+            ..StreamProgress::default()
+        });
+        // The download carries on after the prefetched bytes, as after a reconnect
+        if let Some((data, total)) = prefetched.filter(|(data, _)| !data.is_empty()) {
+            debug!(bytes = data.len(), "Starting from the prefetch");
+            progress
+                .received
+                .store(data.len() as u64, Ordering::Relaxed);
+            progress.total.store(total, Ordering::Relaxed);
+            let _ = tx.send((0, data));
+        }
+        *self.stream_progress.lock().unwrap() = Arc::clone(&progress);
+        let playback_progress = Arc::clone(&progress);
+
+        let cancel = CancellationToken::new();
+        *self.download_cancel.lock().unwrap() = Some(cancel.clone());
+
+        let spool_file = self.spool_dir.lock().unwrap().as_ref().map(|dir| {
+            let n = self.spools.fetch_add(1, Ordering::Relaxed);
+            dir.join(format!("{}-{}.part", std::process::id(), n))
+        });
+        *self.spool_file.lock().unwrap() = spool_file.clone();
+
+        Self::spawn_download(
+            url.to_string(),
+            tx,
+            progress,
+            self.max_reconnects.load(Ordering::Relaxed),
+            cancel.clone(),
+        );
+        let (ready_tx, ready_rx) = mpsc::channel();
+        let playback_handle = Self::spawn_playback(
+            rx,
+            spool_file,
+            self.initial_buffer.load(Ordering::Relaxed),
+            sink,
+            playback_progress,
+            Arc::clone(&self.balance),
+            cancel,
+            ready_tx,
+        );
+
+        *self.playback_thread.lock().unwrap() = Some(playback_handle);
+
+        // Until the audio starts, or as long as a silent server is waited for: a stream that
+        // is merely slow is left to the stall handling
+        let waiting = Instant::now();
+        loop {
+            match ready_rx.recv_timeout(START_POLL) {
+                Ok(StartUpdate::Buffering(percent)) => starting(Starting::Buffering(percent)),
+                Ok(StartUpdate::Playing) => return Ok(()),
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(self.take_error().unwrap_or(PlayerError::NoData))
+                }
+                Err(RecvTimeoutError::Timeout) if interrupted() => {
+                    debug!("Stream given up while starting");
+                    self.stop();
+                    return Err(PlayerError::Aborted(http::Aborted::Cancelled));
+                }
+                Err(RecvTimeoutError::Timeout) if waiting.elapsed() >= http::idle_timeout() => {
+                    return Ok(())
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+            }
+        }
+    }
+
+    /// Fetch the start of `url` in the background, for a `play` of it soon after. Replaces a
+    /// prefetch of another URL; one of the same URL carries on.
+    pub fn prefetch(&self, url: &str) {
+        let mut prefetch = self.prefetch.lock().unwrap();
+        if prefetch
+            .as_ref()
+            .is_some_and(|prefetch| prefetch.url == url)
+        {
+            return;
+        }
+        debug!(url, "Prefetching");
+        *prefetch = Some(Prefetch::start(url));
+    }
+
+    /// Drop the prefetch, for when the next episode is no longer known
+    pub fn cancel_prefetch(&self) {
+        self.prefetch.lock().unwrap().take();
+    }
+
+    /// Start the download task; why it failed, if it does, goes to `progress`
+    fn spawn_download(
+        url: String,
+        tx: Sender<Chunk>,
+        progress: Arc<StreamProgress>,
+        max_reconnects: u64,
+        cancel: CancellationToken,
+    ) {
+        http::runtime().spawn(async move {
+            let result = Self::download_stream(&url, &tx, &progress, max_reconnects, &cancel).await;
+            if let Err(e) = result {
+                warn!("Stream download failed: {:?}", e);
+                progress.fail(e);
+            }
+            // Only now, so playback finds the reason before it finds the stream closed
+            drop(tx);
+        });
+    }
+
+    /// Start the playback thread: buffer, decode and play into `sink`. `ready` hears how the
+    /// buffer fills and once the audio has started; if it closes first, why the thread failed
+    /// is in `progress`.
+    #[allow(clippy::too_many_arguments)]
+    fn spawn_playback(
+        rx: Receiver<Chunk>,
+        spool_file: Option<PathBuf>,
+        initial_buffer: u64,
+        sink: Arc<Sink>,
+        progress: Arc<StreamProgress>,
+        balance: Balance,
+        cancel: CancellationToken,
+        ready: Sender<StartUpdate>,
+    ) -> JoinHandle<()> {
+        thread::spawn(move || {
+            let spool = Spool::create(spool_file.as_deref());
+            let result = Self::play_stream(
+                rx,
+                spool,
+                initial_buffer,
+                &sink,
+                Arc::clone(&progress),
+                &balance,
+                &cancel,
+                &ready,
+            );
+            match result {
+                Err(e) if e.is_cancelled() => debug!("Stream stopped while buffering"),
+                Err(e) => {
+                    warn!("Playback failed: {:?}", e);
+                    progress.fail(e);
+                }
+                Ok(()) => {}
+            }
+            drop(ready);
+        })
+    }
+
+    /// Feed the stream to `tx`, reconnecting where it broke off, up to `max_reconnects` times
+    /// with a growing wait. Once the reconnects run out the download waits for
+    /// [`Player::reconnect`] or for the stream to be stopped. A seek far ahead moves it there
+    /// with a ranged request; the part it skipped is fetched after the end. It is only
+    /// complete once nothing is missing, and `tx` closing then tells playback so.
+    async fn download_stream(
+        url: &str,
+        tx: &Sender<Chunk>,
+        progress: &StreamProgress,
+        max_reconnects: u64,
+        cancel: &CancellationToken,
+    ) -> Result<(), PlayerError> {
+        // What has arrived, prefetched bytes included, so no part is fetched twice
+        let mut fetched = Ranges::default();
+        fetched.insert(0..progress.received.load(Ordering::Relaxed));
+        // Where the stream moved for good, once a permanent redirect says so
+        let mut url = url.to_string();
+        loop {
+            if progress.readahead_reached() {
+                Self::hold_readahead(progress, cancel).await;
+            }
+            match Self::fetch_stream(&mut url, tx, progress, &mut fetched, cancel).await {
+                Ok(Some(next)) => progress.received.store(next, Ordering::Relaxed),
+                Ok(None) => {
+                    // The end of the stream; a seek may have left a part before it out
+                    let received = progress.received.load(Ordering::Relaxed);
+                    match fetched.first_gap(0, progress.total.load(Ordering::Relaxed)) {
+                        Some(gap) if gap.start < received => {
+                            debug!(
+                                from = gap.start,
+                                to = gap.end,
+                                "Fetching the part a seek skipped"
+                            );
+                            progress.received.store(gap.start, Ordering::Relaxed);
+                        }
+                        _ => break,
+                    }
+                }
+                Err(PlayerError::Aborted(http::Aborted::Cancelled)) => {
+                    debug!("Stream stopped, stopping download");
+                    break;
+                }
+                // Nothing played yet: report it as a failed start
+                Err(e) if progress.received.load(Ordering::Relaxed) == 0 => return Err(e),
+                Err(e) => {
+                    let attempt = progress.reconnects.fetch_add(1, Ordering::Relaxed) + 1;
+                    warn!(attempt, max_reconnects, "Stream interrupted: {}", e);
+                    let automatic = attempt <= max_reconnects;
+                    progress.reconnecting.store(automatic, Ordering::Relaxed);
+                    let retry = async {
+                        if automatic {
+                            tokio::time::sleep(reconnect_delay(attempt)).await;
+                        } else {
+                            progress.retry.notified().await;
+                        }
+                    };
+                    tokio::select! {
+                        _ = retry => {}
+                        _ = cancel.cancelled() => break,
+                    }
+                }
+            }
+        }
+
+        progress.reconnecting.store(false, Ordering::Relaxed);
+        info!(
+            received = progress.received.load(Ordering::Relaxed),
+            "Stream download finished"
+        );
+        progress.complete.store(true, Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    /// Wait, with no connection open, until playback has used half of the readahead limit, a
+    /// seek has moved it, or the stream is stopped
+    async fn hold_readahead(progress: &StreamProgress, cancel: &CancellationToken) {
+        debug!(
+            received = progress.received.load(Ordering::Relaxed),
+            "Readahead limit reached, holding the download"
+        );
+        progress.held.store(true, Ordering::Relaxed);
+        let resume_at = progress.max_readahead.load(Ordering::Relaxed) / 2;
+        while progress.readahead().is_some_and(|ahead| ahead > resume_at) {
+            tokio::select! {
+                _ = tokio::time::sleep(READAHEAD_POLL) => {}
+                _ = cancel.cancelled() => break,
+            }
+        }
+        progress.held.store(false, Ordering::Relaxed);
+        debug!("Playback caught up, downloading on");
+    }
+
+    /// One connection, from `received` on. Ends with where to fetch next when a seek jumps
+    /// elsewhere, the download runs into bytes it already has or gets as far ahead of playback
+    /// as the readahead limit allows; None at the end of the body.
+    /// A permanent redirect on the way replaces `url` for the connections after.
+    async fn fetch_stream(
+        url: &mut String,
+        tx: &Sender<Chunk>,
+        progress: &StreamProgress,
+        fetched: &mut Ranges,
+        cancel: &CancellationToken,
+    ) -> Result<Option<u64>, PlayerError> {
+        let resume_from = progress.received.load(Ordering::Relaxed);
+        // A prefetch can hold all of a short stream
+        let total = progress.total.load(Ordering::Relaxed);
+        if resume_from > 0 && total > 0 && resume_from >= total {
+            return Ok(None);
+        }
+        info!(url = %url, resume_from, "Connecting to stream");
+        let idle = http::idle_timeout();
+        let http::Followed {
+            mut response,
+            moved_to,
+        } = http::get_audio_from(url, resume_from, idle, cancel).await?;
+        if let Some(moved_to) = moved_to {
+            info!(from = %url, to = %moved_to, "Stream moved permanently");
+            *url = moved_to;
+        }
+        debug!(
+            status = %response.status(),
+            content_length = ?response.content_length(),
+            "Stream response"
+        );
+
+        if !response.status().is_success() {
+            return Err(PlayerError::Status {
+                status: response.status().as_u16(),
+            });
+        }
+
+        // Servers that ignore the range send everything again; drop what was played
+        let mut skip = if response.status() == StatusCode::PARTIAL_CONTENT {
+            0
+        } else {
+            resume_from
+        };
+        // Holding the download back would cost all of that again on every resume
+        if skip > 0 && progress.max_readahead.swap(0, Ordering::Relaxed) > 0 {
+            info!("The server ignores ranges, lifting the readahead limit for this stream");
+        }
+        if resume_from == 0 {
+            progress
+                .total
+                .store(response.content_length().unwrap_or(0), Ordering::Relaxed);
+        }
+
+        let mut received = resume_from;
+
+        loop {
+            let chunk = tokio::select! {
+                chunk = http::step(response.chunk(), idle, cancel) => match chunk? {
+                    Ok(Some(chunk)) => chunk,
+                    Ok(None) => return Ok(None),
+                    Err(e) => return Err(e.into()),
+                },
+                _ = progress.jump.notified() => {
+                    let target = progress.jump_target.load(Ordering::Relaxed);
+                    let total = progress.total.load(Ordering::Relaxed);
+                    if should_jump(fetched, received, target, total) {
+                        info!(from = received, to = target, "Seek ahead of the download, jumping there");
+                        return Ok(Some(target));
+                    }
+                    continue;
+                }
+            };
+
+            let dropped = skip.min(chunk.len() as u64);
+            skip -= dropped;
+            let chunk = &chunk[dropped as usize..];
+            if chunk.is_empty() {
+                continue;
+            }
+
+            let n = chunk.len() as u64;
+            let position = received;
+            received += n;
+            fetched.insert(position..received);
+            progress.received.store(received, Ordering::Relaxed);
+            progress.reconnecting.store(false, Ordering::Relaxed);
+            if received % (4 * 1024 * 1024) < n {
+                debug!(received, "Stream download progress");
+            }
+            if tx.send((position, chunk.to_vec())).is_err() {
+                debug!("Playback side closed, stopping download");
+                return Err(PlayerError::Aborted(http::Aborted::Cancelled));
+            }
+            // Let go of the connection rather than leave it idle for minutes
+            if progress.readahead_reached() {
+                return Ok(Some(received));
+            }
+            // Past the end of a seek's jump, where the download was before
+            if fetched.end_of(received).is_some() {
+                let next = fetched.first_gap(received, progress.total.load(Ordering::Relaxed));
+                return Ok(next.map(|gap| gap.start));
+            }
+        }
+    }
+
+    /// Fill the first `initial_buffer` bytes, or less on a fast download, then decode and play
+    #[allow(clippy::too_many_arguments)]
+    fn play_stream(
+        rx: Receiver<Chunk>,
+        spool: Spool,
+        initial_buffer: u64,
+        sink: &Sink,
+        progress: Arc<StreamProgress>,
+        balance: &Balance,
+        cancel: &CancellationToken,
+        ready: &Sender<StartUpdate>,
+    ) -> Result<(), PlayerError> {
+        let buffer = StreamingBuffer::new(spool, Arc::clone(&progress.starved_since));
+        *progress.buffer.lock().unwrap() = Some(buffer.at(0));
+        // When the first chunk came and the buffer's size then, to time the download by
+        let mut first: Option<(Instant, u64)> = None;
+        let mut chunks = 0;
+        let mut audio = None;
+        let mut shown = None;
+        loop {
+            if audio.is_none() {
+                audio = audio_rate(&buffer);
+            }
+            let download = first
+                .filter(|_| chunks >= RATE_CHUNKS)
+                .and_then(|(at, len)| {
+                    let secs = at.elapsed().as_secs_f64();
+                    (secs > 0.0).then(|| ((buffer.len() - len) as f64 / secs) as u64)
+                });
+            let percent = start_progress(buffer.len(), initial_buffer, audio, download);
+            if shown != Some(percent) {
+                shown = Some(percent);
+                let _ = ready.send(StartUpdate::Buffering(percent));
+            }
+            if percent >= 100 {
+                break;
+            }
+
+            match rx.recv() {
+                Ok((position, bytes)) => {
+                    buffer.write_chunk(position, &bytes)?;
+                    chunks += 1;
+                    first.get_or_insert((Instant::now(), buffer.len()));
+                }
+                // Stopped: the download let go of the connection, nothing more is coming
+                Err(_) if cancel.is_cancelled() => {
+                    return Err(PlayerError::Aborted(http::Aborted::Cancelled));
+                }
+                Err(_) => {
+                    if buffer.len() == 0 {
+                        return Err(PlayerError::NoData);
+                    }
+                    break;
+                }
+            }
+        }
+
+        debug!(bytes = buffer.len(), "Initial buffer filled");
+
+        buffer.fill_from(rx);
+
+        std::thread::sleep(std::time::Duration::from_millis(200));
+
+        let source = metered_decoder(buffer.at(0), 0, &progress)?;
+        let open: Opener =
+            Arc::new(move |offset| Ok(Box::new(buffer.at(offset)) as Box<dyn Media>));
+        debug!(
+            sample_rate = source.sample_rate(),
+            channels = source.channels(),
+            "Decoder ready"
+        );
+
+        progress.set_format(&source, &open);
+        *progress.opener.lock().unwrap() = Some(Arc::clone(&open));
+        let level = Arc::clone(&progress.level);
+        sink.append(Leveled::new(
+            Balanced::new(
+                Counted {
+                    inner: Trimmed::new(
+                        Seekable::new(source, open, Arc::clone(&progress)),
+                        &progress,
+                    ),
+                    progress,
+                },
+                balance,
+            ),
+            level,
+        ));
+        let _ = ready.send(StartUpdate::Playing);
+        sink.sleep_until_end();
+
+        Ok(())
+    }
+
+    /// Stop playback and drop the current track
+    pub fn stop(&self) {
+        self.ramps.fetch_add(1, Ordering::SeqCst);
+        if let Some(sink) = self.sink.lock().unwrap().take() {
+            sink.stop();
+        }
+
+        let _ = self.playback_thread.lock().unwrap().take();
+        if let Some(cancel) = self.download_cancel.lock().unwrap().take() {
+            cancel.cancel();
+        }
+
+        *self.is_paused.lock().unwrap() = false;
+        // Nothing is playing: no position, no stream
+        *self.stream_progress.lock().unwrap() = Arc::default();
+        // Open handles keep reading it where the OS allows; otherwise the spool removes it later
+        if let Some(path) = self.spool_file.lock().unwrap().take() {
+            let _ = fs::remove_file(path);
+        }
+    }
+
+    /// Save the stream playing to `path` too, once all of it has arrived: what came so far at
+    /// once, the rest as the download brings it. False when no stream plays, as with a file.
+    pub fn record(&self, path: &Path) -> Result<bool, PlayerError> {
+        let progress = Arc::clone(&self.stream_progress.lock().unwrap());
+        let buffer = progress.buffer.lock().unwrap();
+        let Some(buffer) = buffer.as_ref() else {
+            return Ok(false);
+        };
+        buffer.record(path, Arc::clone(&progress.total))?;
+        Ok(true)
+    }
+
+    /// Write the stream playing to `path` from its start up to where the download has got
+    /// without a gap, for a download to carry on from; a file there already as long is left
+    /// alone. The bytes the file holds, 0 when no stream plays.
+    pub fn save_head(&self, path: &Path) -> Result<u64, PlayerError> {
+        let progress = Arc::clone(&self.stream_progress.lock().unwrap());
+        let buffer = progress.buffer.lock().unwrap();
+        let Some(buffer) = buffer.as_ref() else {
+            return Ok(0);
+        };
+        Ok(buffer.save_head(path)?)
+    }
+
+    /// Keep streams in a temporary file in `dir` instead of memory; None goes back to memory.
+    /// Applies from the next `play`.
+    pub fn set_spool_dir(&self, dir: Option<PathBuf>) {
+        *self.spool_dir.lock().unwrap() = dir;
+    }
+
+    /// Play the downloaded file at `path`, starting `from_secs` in, at the start volume like `play`
+    pub fn play_file(&self, path: &Path, from_secs: u64) -> Result<(), PlayerError> {
+        self.stop();
+
+        let file = File::open(path).map_err(StorageError::read(path))?;
+        let size = file.metadata().map_or(0, |m| m.len());
+        // Where a bookmark left off is no place to skip ahead from
+        let progress = Arc::new(StreamProgress {
+            trim_below: self.trim_silence.lock().unwrap().filter(|_| from_secs == 0),
+            ..StreamProgress::default()
+        });
+        let source = metered_decoder(file, 0, &progress)?;
+
+        progress.received.store(size, Ordering::Relaxed);
+        progress.total.store(size, Ordering::Relaxed);
+        progress.complete.store(true, Ordering::Relaxed);
+
+        let path = path.to_path_buf();
+        let open: Opener = Arc::new(move |offset| {
+            let mut file = File::open(&path)?;
+            file.seek(SeekFrom::Start(offset))?;
+            Ok(Box::new(file) as Box<dyn Media>)
+        });
+
+        progress.set_format(&source, &open);
+        let samples_per_sec = progress.samples_per_sec.load(Ordering::Relaxed);
+        progress
+            .played_samples
+            .store(from_secs * samples_per_sec, Ordering::Relaxed);
+        *self.stream_progress.lock().unwrap() = Arc::clone(&progress);
+        *progress.opener.lock().unwrap() = Some(Arc::clone(&open));
+
+        let sink = Arc::new(Sink::try_new(&self.stream_handle)?);
+        sink.set_volume(self.track_level());
+        let level = Arc::clone(&progress.level);
+        sink.append(Leveled::new(
+            Balanced::new(
+                Counted {
+                    inner: Trimmed::new(
+                        Seekable::new(
+                            source.skip_duration(Duration::from_secs(from_secs)),
+                            open,
+                            Arc::clone(&progress),
+                        ),
+                        &progress,
+                    ),
+                    progress,
+                },
+                &self.balance,
+            ),
+            level,
+        ));
+        *self.sink.lock().unwrap() = Some(Arc::clone(&sink));
+        *self.is_paused.lock().unwrap() = false;
+
+        let playback_handle = thread::spawn(move || sink.sleep_until_end());
+        *self.playback_thread.lock().unwrap() = Some(playback_handle);
+
+        Ok(())
+    }
+
+    /// Codec, format and bitrate of the track playing, as far as known yet
+    pub fn stream_info(&self) -> StreamInfo {
+        let progress = self.stream_progress.lock().unwrap();
+        let mut info = *progress.info.lock().unwrap();
+        info.kbps = progress
+            .meter
+            .lock()
+            .unwrap()
+            .kbps(progress.samples_per_sec.load(Ordering::Relaxed));
+        info
+    }
+
+    /// Why the current stream stopped playing, once: a failed request, no data, audio that
+    /// can't be decoded. Without it a failed stream would only look like silence.
+    pub fn take_error(&self) -> Option<PlayerError> {
+        self.stream_progress
+            .lock()
+            .unwrap()
+            .failure
+            .lock()
+            .unwrap()
+            .take()
+    }
+
+    /// How long playback has been waiting for stream data, if it is: starved, or paused to
+    /// refill the buffer with nothing arriving
+    pub fn stalled_for(&self) -> Option<Duration> {
+        let progress = self.stream_progress.lock().unwrap();
+        let since = *progress.starved_since.lock().unwrap();
+        let refilling = progress.rebuffering.lock().unwrap().map(|(_, since)| since);
+        since.or(refilling).map(|since| since.elapsed())
+    }
+
+    /// Reconnects made since the stream started or since the last [`reconnect`](Self::reconnect)
+    pub fn reconnects(&self) -> u64 {
+        self.stream_progress
+            .lock()
+            .unwrap()
+            .reconnects
+            .load(Ordering::Relaxed)
+    }
+
+    /// The reconnect under way and the most there will be, while a dropped stream is being
+    /// reconnected on its own
+    pub fn reconnecting(&self) -> Option<(u64, u64)> {
+        let progress = self.stream_progress.lock().unwrap();
+        progress.reconnecting.load(Ordering::Relaxed).then(|| {
+            (
+                progress.reconnects.load(Ordering::Relaxed),
+                self.max_reconnects.load(Ordering::Relaxed),
+            )
+        })
+    }
+
+    /// Reconnects a dropped stream gets before it is left to the user; applies from the next
+    /// `play`
+    pub fn set_max_reconnects(&self, max: u64) {
+        self.max_reconnects.store(max, Ordering::Relaxed);
+    }
+
+    /// Download a stream at most `mb` megabytes ahead of playback, or all of it at once with
+    /// None; applies from the next `play`
+    pub fn set_max_readahead_mb(&self, mb: Option<u64>) {
+        let bytes = mb.map_or(0, |mb| mb.max(1) * 1024 * 1024);
+        self.max_readahead.store(bytes, Ordering::Relaxed);
+    }
+
+    /// Try the stream again, with a fresh set of reconnects, from where it broke off
+    pub fn reconnect(&self) {
+        let progress = self.stream_progress.lock().unwrap();
+        progress.reconnects.store(0, Ordering::Relaxed);
+        *progress.starved_since.lock().unwrap() = None;
+        if let Some((_, since)) = progress.rebuffering.lock().unwrap().as_mut() {
+            *since = Instant::now();
+        }
+        progress.retry.notify_one();
+    }
+
+    /// Jump to `secs` into the current track. Playback goes on from the nearest MP3 frame,
+    /// waiting for the download if it hasn't got there yet; until then (or until a paused
+    /// track resumes) the position reads as `secs`. False until the decoder is ready.
+    pub fn seek_to(&self, secs: u64) -> bool {
+        if self.sink.lock().unwrap().is_none() {
+            return false;
+        }
+        let progress = self.stream_progress.lock().unwrap();
+        if progress.samples_per_sec.load(Ordering::Relaxed) == 0 {
+            return false;
+        }
+        progress.seek_target.store(secs, Ordering::Relaxed);
+        progress.seek_pending.store(true, Ordering::Relaxed);
+        true
+    }
+
+    pub fn pause(&self) {
+        if let Some(ref sink) = *self.sink.lock().unwrap() {
+            if !*self.is_paused.lock().unwrap() {
+                self.start_ramp(sink, Ramp::Down);
+                *self.is_paused.lock().unwrap() = true;
+            }
+        }
+    }
+
+    pub fn resume(&self) {
+        if let Some(ref sink) = *self.sink.lock().unwrap() {
+            if *self.is_paused.lock().unwrap() {
+                // A buffer still refilling plays on by itself once it has
+                let progress = Arc::clone(&self.stream_progress.lock().unwrap());
+                if progress.rebuffering.lock().unwrap().is_none() {
+                    self.start_ramp(sink, Ramp::Up);
+                }
+                *self.is_paused.lock().unwrap() = false;
+            }
+        }
+    }
+
+    /// Ramp the volume over a fifth of a second around pauses and resumes, against the pop
+    /// some DACs make when the sound is cut
+    pub fn set_fade_pauses(&self, fade: bool) {
+        self.fade_pauses.store(fade, Ordering::Relaxed);
+    }
+
+    /// Skip up to ten seconds of silence at the start of each track, counting as silence
+    /// what stays under `below_db` dBFS; None plays tracks as they start. Applies from the
+    /// next track that plays from its start.
+    pub fn set_trim_silence(&self, below_db: Option<f32>) {
+        *self.trim_silence.lock().unwrap() = below_db.map(silence_threshold);
+    }
+
+    /// Seconds of leading silence skipped from the track playing, if any were
+    pub fn trimmed_secs(&self) -> Option<f32> {
+        let progress = self.stream_progress.lock().unwrap();
+        let skipped = progress.trimmed_samples.load(Ordering::Relaxed);
+        let per_sec = progress.samples_per_sec.load(Ordering::Relaxed);
+        (skipped > 0 && per_sec > 0).then(|| skipped as f32 / per_sec as f32)
+    }
+
+    /// Pause or play the sink, at once or on a helper thread so the key handler doesn't wait
+    fn start_ramp(&self, sink: &Arc<Sink>, direction: Ramp) {
+        let ticket = self.ramps.fetch_add(1, Ordering::SeqCst) + 1;
+        if !self.fade_pauses.load(Ordering::Relaxed) {
+            match direction {
+                Ramp::Down => sink.pause(),
+                Ramp::Up => sink.play(),
+            }
+            return;
+        }
+
+        let sink = Arc::clone(sink);
+        let volume = Arc::clone(&self.volume);
+        let ramps = Arc::clone(&self.ramps);
+        thread::spawn(move || ramp(&sink, &volume, direction, PAUSE_FADE, &ramps, ticket));
+    }
+
+    pub fn is_paused(&self) -> bool {
+        *self.is_paused.lock().unwrap()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sink
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|s| s.empty())
+            .unwrap_or(true)
+    }
+
+    /// Current volume as the user sets it, 1.0 being unchanged; see [`amplitude`]
+    pub fn volume(&self) -> f32 {
+        *self.volume.lock().unwrap()
+    }
+
+    /// Change the volume of the current track
+    pub fn set_volume(&self, volume: f32) {
+        *self.unmuted_volume.lock().unwrap() = None;
+        self.set_sink_volume(volume);
+    }
+
+    fn set_sink_volume(&self, volume: f32) {
+        let volume = volume.clamp(0.0, 2.0);
+        *self.volume.lock().unwrap() = volume;
+        if let Some(ref sink) = *self.sink.lock().unwrap() {
+            sink.set_volume(amplitude(volume));
+        }
+    }
+
+    /// Mute, or go back to exactly the volume there was before; true when now muted. Setting
+    /// a volume also unmutes.
+    pub fn toggle_mute(&self) -> bool {
+        let mut unmuted = self.unmuted_volume.lock().unwrap();
+        match unmuted.take() {
+            Some(volume) => {
+                self.set_sink_volume(volume);
+                false
+            }
+            None => {
+                *unmuted = Some(self.volume());
+                self.set_sink_volume(0.0);
+                true
+            }
+        }
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.unmuted_volume.lock().unwrap().is_some()
+    }
+
+    /// Sink level for a new track: the start volume, or silence while muted
+    fn track_level(&self) -> f32 {
+        let volume = if self.is_muted() {
+            0.0
+        } else {
+            *self.start_volume.lock().unwrap()
+        };
+        *self.volume.lock().unwrap() = volume;
+        amplitude(volume)
+    }
+
+    /// Lean the sound from -1.0 (left only) through 0.0 (centered) to 1.0 (right only). Heard
+    /// at once and kept for the tracks after.
+    pub fn set_balance(&self, balance: f32) {
+        let balance = if balance.is_nan() {
+            0.0
+        } else {
+            balance.clamp(-1.0, 1.0)
+        };
+        self.balance.store(balance.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn balance(&self) -> f32 {
+        f32::from_bits(self.balance.load(Ordering::Relaxed))
+    }
+
+    /// RMS level of the audio playing lately, from 0.0 (silence) to 1.0 (full scale), before
+    /// the volume. It stays where it was while paused, and is 0.0 with nothing playing.
+    pub fn level(&self) -> f32 {
+        let progress = self.stream_progress.lock().unwrap();
+        f32::from_bits(progress.level.load(Ordering::Relaxed))
+    }
+
+    /// Volume the next track starts at (the current one is unaffected)
+    pub fn set_start_volume(&self, volume: f32) {
+        *self.start_volume.lock().unwrap() = volume.clamp(0.0, 2.0);
+    }
+
+    /// Whether the current track has played to its end (or stopped on an error)
+    pub fn is_finished(&self) -> bool {
+        self.playback_thread
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_none_or(|handle| handle.is_finished())
+    }
+
+    /// Block until the current track has finished
+    pub fn sleep_until_end(&self) {
+        if let Some(ref sink) = *self.sink.lock().unwrap() {
+            sink.sleep_until_end();
+        }
+    }
+
+    /// Bytes of the current stream downloaded so far
+    pub fn downloaded_bytes(&self) -> u64 {
+        self.stream_progress
+            .lock()
+            .unwrap()
+            .received
+            .load(Ordering::Relaxed)
+    }
+
+    /// Size of the current stream as announced by the server, if it was
+    pub fn total_bytes(&self) -> Option<u64> {
+        match self
+            .stream_progress
+            .lock()
+            .unwrap()
+            .total
+            .load(Ordering::Relaxed)
+        {
+            0 => None,
+            total => Some(total),
+        }
+    }
+
+    /// How far into a track of `duration_secs` the downloaded audio reaches
+    pub fn buffered_seconds(&self, duration_secs: u64) -> Option<u64> {
+        buffered_until(self.downloaded_bytes(), self.total_bytes(), duration_secs)
+    }
+
+    /// How much audio is ready past the playhead, from the bytes received beyond those the
+    /// decoder has read and the bitrate estimate. None until that estimate is in.
+    pub fn buffered_ahead_secs(&self) -> Option<BufferAhead> {
+        self.stream_progress.lock().unwrap().buffered_ahead()
+    }
+
+    /// Pause the sink when playback has run dry before the end of the download, rather than
+    /// stutter along with it, and play on once `rebuffer_secs` are buffered again. Meant for
+    /// every tick; while it waits, the seconds buffered so far.
+    pub fn poll_underrun(&self) -> Option<u64> {
+        let sink = self.sink.lock().unwrap();
+        let progress = Arc::clone(&self.stream_progress.lock().unwrap());
+        rebuffer(
+            sink.as_ref()?,
+            &progress,
+            self.rebuffer_secs.load(Ordering::Relaxed),
+            self.is_paused(),
+        )
+    }
+
+    /// Buffer at most `kb` kilobytes before a stream starts playing; a download that outruns
+    /// playback starts sooner. Applies from the next `play`.
+    pub fn set_initial_buffer_kb(&self, kb: u64) {
+        self.initial_buffer
+            .store(kb.max(1).saturating_mul(1024), Ordering::Relaxed);
+    }
+
+    /// Seconds of audio buffered again after the stream ran dry before it plays on
+    pub fn set_rebuffer_secs(&self, secs: u64) {
+        self.rebuffer_secs.store(secs, Ordering::Relaxed);
+    }
+
+    /// Seconds of audio actually played, counted from the samples handed to the output
+    /// device. None until the decoder is ready.
+    pub fn played_seconds(&self) -> Option<u64> {
+        let progress = self.stream_progress.lock().unwrap();
+        played_secs(
+            progress.played_samples.load(Ordering::Relaxed),
+            progress.samples_per_sec.load(Ordering::Relaxed),
+        )
+    }
+
+    /// Position in the current track: the audio played, so connecting, buffering, stalls and
+    /// pauses don't count. A seek that hasn't happened yet reads as its target; 0 when stopped.
+    pub fn elapsed_seconds(&self) -> u64 {
+        self.stream_progress.lock().unwrap().position_secs()
+    }
+}
+
+/// Pause `sink` once playback is starved of data the download hasn't finished, and play it
+/// again when `target` seconds are buffered (or, before there is a bitrate to tell, when data
+/// flows again). A listener's pause, `user_paused`, is left alone. While it waits, the
+/// seconds buffered so far.
+#[cfg(feature = "audio")]
+fn rebuffer(sink: &Sink, progress: &StreamProgress, target: u64, user_paused: bool) -> Option<u64> {
+    let mut rebuffering = progress.rebuffering.lock().unwrap();
+    let received = progress.received.load(Ordering::Relaxed);
+    let starved = progress.starved_since.lock().unwrap().is_some();
+    match *rebuffering {
+        None if !starved || progress.complete.load(Ordering::Relaxed) => return None,
+        None => {
+            debug!(received, "Buffer ran dry, pausing until it refills");
+            sink.pause();
+            *rebuffering = Some((received, Instant::now()));
+        }
+        Some((before, _)) if received > before => *rebuffering = Some((received, Instant::now())),
+        Some(_) => {}
+    }
+
+    let ahead = progress.buffered_ahead();
+    let refilled = match ahead {
+        Some(BufferAhead::Full) => true,
+        Some(BufferAhead::Secs(secs) | BufferAhead::Held(secs)) => secs >= target,
+        None => !starved,
+    };
+    if !refilled {
+        return Some(match ahead {
+            Some(BufferAhead::Secs(secs) | BufferAhead::Held(secs)) => secs,
+            _ => 0,
+        });
+    }
+
+    debug!(received, "Buffer refilled, playing on");
+    *rebuffering = None;
+    if !user_paused {
+        sink.play();
+    }
+    None
+}
+
+/// How far the initial buffer is toward starting playback, in percent: `buffered` bytes of
+/// `limit`, or of [`START_SECS`] of audio once the `download` (bytes per second) is seen to
+/// outrun it. `audio` is where the audio starts and its bytes per second.
+#[cfg(feature = "audio")]
+fn start_progress(
+    buffered: u64,
+    limit: u64,
+    audio: Option<(u64, u64)>,
+    download: Option<u64>,
+) -> u8 {
+    let by_size = buffered.saturating_mul(100) / limit.max(1);
+    let by_time = match (audio, download) {
+        (Some((start, rate)), Some(download)) if rate > 0 && download > rate => {
+            buffered.saturating_sub(start).saturating_mul(100) / (rate * START_SECS)
+        }
+        _ => 0,
+    };
+    by_size.max(by_time).min(100) as u8
+}
+
+/// Map downloaded bytes onto the track's timeline, assuming a constant bitrate.
+/// None when the total size or the duration is unknown.
+pub fn buffered_until(
+    downloaded: u64,
+    total_bytes: Option<u64>,
+    duration_secs: u64,
+) -> Option<u64> {
+    let total = total_bytes.filter(|&total| total > 0)?;
+    if duration_secs == 0 {
+        return None;
+    }
+
+    let secs = u128::from(duration_secs) * u128::from(downloaded.min(total)) / u128::from(total);
+    Some(secs as u64)
+}
+
+/// Sink amplitude for a volume: cubic up to 1.0, so every step of `+`/`-` sounds about as
+/// big as the last (amplitude is heard on a log scale). Boosts past 1.0 stay linear, so 2.0
+/// still doubles the amplitude.
+pub fn amplitude(volume: f32) -> f32 {
+    let volume = volume.clamp(0.0, 2.0);
+    if volume <= 1.0 {
+        volume.powi(3)
+    } else {
+        volume
+    }
+}
+
+/// Wait before reconnect number `attempt` (from 1): doubling, within `MAX_RECONNECT_DELAY`
+#[cfg(feature = "audio")]
+fn reconnect_delay(attempt: u64) -> Duration {
+    let doublings = attempt.saturating_sub(1).min(16) as u32;
+    RECONNECT_DELAY
+        .saturating_mul(1 << doublings)
+        .min(MAX_RECONNECT_DELAY)
+}
+
+/// Whether a seek reading at `target` should move a download that is at `received` there:
+/// not when that part has arrived or soon will, nor while the size (`total`, 0 while unknown)
+/// can't tell when the stream ends
+#[cfg(feature = "audio")]
+fn should_jump(fetched: &Ranges, received: u64, target: u64, total: u64) -> bool {
+    let soon = (received..received.saturating_add(JUMP_DISTANCE)).contains(&target);
+    target < total && !soon && fetched.end_of(target).is_none()
+}
+
+/// Whole seconds in `samples` interleaved samples at `samples_per_sec`
+#[cfg(feature = "audio")]
+fn played_secs(samples: u64, samples_per_sec: u64) -> Option<u64> {
+    samples.checked_div(samples_per_sec)
+}
+
+/// How far ahead of playback a stream may download
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Readahead {
+    Unlimited,
+    Mb(u64),
+}
+
+/// Parse `--readahead`: megabytes, or `unlimited`
+pub fn parse_readahead(input: &str) -> Result<Readahead, String> {
+    let input = input.trim();
+    if input.eq_ignore_ascii_case("unlimited") {
+        return Ok(Readahead::Unlimited);
+    }
+    match input.parse() {
+        Ok(mb) if mb > 0 => Ok(Readahead::Mb(mb)),
+        _ => Err(tf(Msg::InvalidReadahead, &[&input])),
+    }
+}
+
+/// Seconds in an `MM:SS` or `HH:MM:SS` string
+pub fn parse_duration(duration_str: &str) -> Option<u64> {
+    let parts: Vec<&str> = duration_str.split(':').collect();
+
+    match parts.len() {
+        2 => {
+            let minutes = parts[0].parse::<u64>().ok()?;
+            let seconds = parts[1].parse::<u64>().ok()?;
+            Some(minutes * 60 + seconds)
+        }
+        3 => {
+            let hours = parts[0].parse::<u64>().ok()?;
+            let minutes = parts[1].parse::<u64>().ok()?;
+            let seconds = parts[2].parse::<u64>().ok()?;
+            Some(hours * 3600 + minutes * 60 + seconds)
+        }
+        _ => None,
+    }
+}
+
+/// Open the default output device, with its name to notice later that it's gone
+#[cfg(feature = "audio")]
+fn open_output() -> Result<(OutputStream, OutputStreamHandle, Option<String>), PlayerError> {
+    let (stream, stream_handle) = OutputStream::try_default()?;
+    let device = cpal::default_host()
+        .default_output_device()
+        .and_then(|device| device.name().ok());
+    Ok((stream, stream_handle, device))
+}
+
+/// Which way a pause ramp goes
+#[cfg(feature = "audio")]
+#[derive(Debug, Clone, Copy)]
+enum Ramp {
+    /// To silence, then pause
+    Down,
+    /// Play, then back up from silence
+    Up,
+}
+
+/// Fade `sink` between silence and `volume` over `fade`. The volume is read again at every
+/// step, so a change made meanwhile is followed, and the sink ends at exactly that level
+/// (paused, when going down). Gives up at once when `ramps` moves on from `ticket`, leaving
+/// the sink to whatever came next.
+#[cfg(feature = "audio")]
+fn ramp(
+    sink: &Sink,
+    volume: &Mutex<f32>,
+    direction: Ramp,
+    fade: Duration,
+    ramps: &AtomicU64,
+    ticket: u64,
+) {
+    let superseded = || ramps.load(Ordering::SeqCst) != ticket;
+    if let Ramp::Up = direction {
+        sink.set_volume(0.0);
+        sink.play();
+    }
+    for step in 1..=PAUSE_FADE_STEPS {
+        thread::sleep(fade / PAUSE_FADE_STEPS);
+        if superseded() {
+            return;
+        }
+        let share = step as f32 / PAUSE_FADE_STEPS as f32;
+        let share = match direction {
+            Ramp::Down => 1.0 - share,
+            Ramp::Up => share,
+        };
+        sink.set_volume(amplitude(*volume.lock().unwrap()) * share);
+    }
+    if let Ramp::Down = direction {
+        sink.pause();
+    }
+    sink.set_volume(amplitude(*volume.lock().unwrap()));
+}
+
+/// `MM:SS`, or `HH:MM:SS` from an hour up
+pub fn format_duration(seconds: u64) -> String {
+    let hours = seconds / 3600;
+    let minutes = (seconds % 3600) / 60;
+    let secs = seconds % 60;
+
+    if hours > 0 {
+        format!("{:02}:{:02}:{:02}", hours, minutes, secs)
     } else {
         format!("{:02}:{:02}", minutes, secs)
     }
 }
 
+#[cfg(feature = "audio")]
 impl Drop for Player {
     fn drop(&mut self) {
         self.stop();
     }
 }
+
+#[cfg(all(test, feature = "audio"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buffered_maps_bytes_onto_duration() {
+        assert_eq!(buffered_until(0, Some(1000), 3600), Some(0));
+        assert_eq!(buffered_until(250, Some(1000), 3600), Some(900));
+        assert_eq!(buffered_until(1000, Some(1000), 3600), Some(3600));
+    }
+
+    #[test]
+    fn buffered_never_passes_the_end() {
+        assert_eq!(buffered_until(1500, Some(1000), 3600), Some(3600));
+    }
+
+    #[test]
+    fn buffered_is_unknown_without_sizes() {
+        assert_eq!(buffered_until(500, None, 3600), None);
+        assert_eq!(buffered_until(500, Some(0), 3600), None);
+        assert_eq!(buffered_until(500, Some(1000), 0), None);
+    }
+
+    /// Answers every request with `status` and `body`
+    fn serve(status: u16, body: &'static [u8]) -> String {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/1.mp3", server.server_addr().to_ip().unwrap());
+        thread::spawn(move || {
+            for request in server.incoming_requests() {
+                // With a length, as a podcast host sends it, rather than chunked
+                let response = tiny_http::Response::from_data(body)
+                    .with_status_code(status)
+                    .with_chunked_threshold(usize::MAX);
+                let _ = request.respond(response);
+            }
+        });
+        url
+    }
+
+    /// Start `url` the way `play` does, into a sink with no device behind it, and wait up to
+    /// two seconds for the reason it can't start
+    fn stream_failure(url: &str) -> Option<PlayerError> {
+        let (sink, _output) = Sink::new_idle();
+        let (tx, rx) = mpsc::channel();
+        let progress = Arc::new(StreamProgress::default());
+        let cancel = CancellationToken::new();
+        Player::spawn_download(
+            url.to_string(),
+            tx,
+            Arc::clone(&progress),
+            0,
+            cancel.clone(),
+        );
+        let (ready, started) = mpsc::channel();
+        Player::spawn_playback(
+            rx,
+            None,
+            BUFFER_SIZE as u64,
+            Arc::new(sink),
+            Arc::clone(&progress),
+            Arc::default(),
+            cancel.clone(),
+            ready,
+        );
+
+        // As `play` waits: the thread lets go of `ready` before it plays only once it failed
+        let failure = loop {
+            match started.recv_timeout(Duration::from_secs(2)) {
+                Ok(StartUpdate::Buffering(_)) => continue,
+                Err(RecvTimeoutError::Disconnected) => {
+                    break progress.failure.lock().unwrap().take()
+                }
+                Ok(StartUpdate::Playing) | Err(RecvTimeoutError::Timeout) => break None,
+            }
+        };
+        cancel.cancel();
+        failure
+    }
+
+    #[test]
+    fn a_missing_episode_reports_its_status() {
+        let url = serve(404, b"not found");
+        assert!(matches!(
+            stream_failure(&url),
+            Some(PlayerError::Status { status: 404 })
+        ));
+    }
+
+    #[test]
+    fn an_unreachable_server_reports_the_request() {
+        assert!(matches!(
+            stream_failure("http://127.0.0.1:1/1.mp3"),
+            Some(PlayerError::Request(_))
+        ));
+    }
+
+    #[test]
+    fn data_that_is_not_audio_reports_the_decoder() {
+        let url = serve(200, b"<html>maintenance</html>");
+        assert!(matches!(stream_failure(&url), Some(PlayerError::Decode(_))));
+    }
+
+    #[test]
+    fn amplitude_follows_the_ear() {
+        assert_eq!(amplitude(0.0), 0.0);
+        assert_eq!(amplitude(1.0), 1.0);
+        assert_eq!(amplitude(2.0), 2.0);
+        assert!((amplitude(0.5) - 0.125).abs() < 1e-6);
+        // Out of range volumes are clamped like the sink's
+        assert_eq!(amplitude(-1.0), 0.0);
+        assert_eq!(amplitude(3.0), 2.0);
+    }
+
+    #[test]
+    fn amplitude_steps_grow_with_the_volume() {
+        let levels: Vec<f32> = (0..=20).map(|step| amplitude(step as f32 / 10.0)).collect();
+        assert!(levels.windows(2).all(|pair| pair[0] < pair[1]));
+        // 10% → 20% is a small change in amplitude, 90% → 100% a large one
+        assert!(levels[2] - levels[1] < levels[10] - levels[9]);
+    }
+
+    #[test]
+    fn reconnects_back_off() {
+        let delays: Vec<u64> = (1..=6).map(|n| reconnect_delay(n).as_secs()).collect();
+        assert_eq!(delays, [2, 4, 8, 16, 30, 30]);
+        assert_eq!(reconnect_delay(u64::MAX), MAX_RECONNECT_DELAY);
+    }
+
+    #[test]
+    fn seeks_far_ahead_of_the_download_jump_there() {
+        let mut fetched = Ranges::default();
+        fetched.insert(0..1_000_000);
+        let total = 100_000_000;
+
+        assert!(should_jump(&fetched, 1_000_000, 50_000_000, total));
+        // The download gets there soon enough by itself
+        assert!(!should_jump(&fetched, 1_000_000, 1_100_000, total));
+        // Already here
+        assert!(!should_jump(&fetched, 1_000_000, 500_000, total));
+        // Back into a part an earlier jump skipped
+        fetched.insert(50_000_000..60_000_000);
+        assert!(should_jump(&fetched, 60_000_000, 20_000_000, total));
+        // Without a size, or past it
+        assert!(!should_jump(&fetched, 1_000_000, 50_000_000, 0));
+        assert!(!should_jump(&fetched, 1_000_000, total, total));
+    }
+
+    #[test]
+    fn played_counts_interleaved_samples() {
+        // 44.1 kHz stereo
+        assert_eq!(played_secs(0, 88_200), Some(0));
+        assert_eq!(played_secs(88_199, 88_200), Some(0));
+        assert_eq!(played_secs(60 * 88_200, 88_200), Some(60));
+        assert_eq!(played_secs(1000, 0), None);
+    }
+
+    /// MPEG-1 layer III, 128 kbps, 44.1 kHz, no padding: 417-byte frames
+    const FRAME_128: [u8; 4] = [0xff, 0xfb, 0x90, 0x64];
+
+    fn frames(count: usize) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for _ in 0..count {
+            bytes.extend_from_slice(&FRAME_128);
+            bytes.resize(bytes.len() + 417 - FRAME_128.len(), 0);
+        }
+        bytes
+    }
+
+    #[test]
+    fn frame_headers_give_bitrate_and_length() {
+        let header = FrameHeader::parse(&FRAME_128).unwrap();
+        assert_eq!(header.kbps, 128);
+        assert_eq!(header.sample_rate, 44_100);
+        assert_eq!(header.len, 417);
+        assert_eq!(header.bytes_per_sec(), 16_000);
+        // Layer II and a "bad" bitrate index are not layer III frames
+        assert_eq!(FrameHeader::parse(&[0xff, 0xfd, 0x90, 0x64]), None);
+        assert_eq!(FrameHeader::parse(&[0xff, 0xfb, 0xf0, 0x64]), None);
+    }
+
+    #[test]
+    fn frames_are_found_past_stray_sync_bytes() {
+        let mut bytes = vec![0x00, 0xff, 0xfb, 0x90, 0x01];
+        bytes.extend(frames(2));
+        assert_eq!(find_frame(&bytes).map(|(at, _)| at), Some(5));
+        assert_eq!(find_frame(&[0x00; 64]), None);
+    }
+
+    #[test]
+    fn id3_tags_are_skipped() {
+        assert_eq!(id3v2_len(b"ID3\x04\x00\x00\x00\x00\x02\x01"), 10 + 257);
+        assert_eq!(id3v2_len(b"ID3\x04\x00\x10\x00\x00\x00\x0a"), 10 + 10 + 10);
+        assert_eq!(id3v2_len(&FRAME_128), 0);
+    }
+
+    #[test]
+    fn seeks_land_on_a_frame_near_the_time() {
+        let mut bytes = b"ID3\x04\x00\x00\x00\x00\x00\x06".to_vec();
+        bytes.extend_from_slice(&[0; 6]);
+        bytes.extend(frames(200));
+        let bytes: Arc<[u8]> = bytes.into();
+        let open: Opener = Arc::new(move |offset| {
+            let mut reader = std::io::Cursor::new(Arc::clone(&bytes));
+            reader.set_position(offset);
+            Ok(Box::new(reader) as Box<dyn Media>)
+        });
+
+        let progress = StreamProgress::default();
+        assert_eq!(locate(&open, 0, &progress), Some(16));
+        // 16 000 bytes a second: the frame after byte 16 + 32 000 starts at 16 + 77 * 417
+        assert_eq!(locate(&open, 2, &progress), Some(16 + 77 * 417));
+        // The download is asked to go to the byte the second starts at
+        assert_eq!(
+            progress.jump_target.load(Ordering::Relaxed),
+            16 + 2 * 16_000
+        );
+        assert_eq!(locate(&open, 60, &progress), None);
+    }
+
+    /// 8 kHz mono, so one second is 8000 samples
+    fn counted(progress: &Arc<StreamProgress>) -> Counted<rodio::buffer::SamplesBuffer<i16>> {
+        progress.samples_per_sec.store(8_000, Ordering::Relaxed);
+        Counted {
+            inner: rodio::buffer::SamplesBuffer::new(1, 8_000, vec![0i16; 8_000 * 60]),
+            progress: Arc::clone(progress),
+        }
+    }
+
+    /// Play `secs` of audio the way the output device does: by pulling samples
+    fn play_for(source: &mut impl Iterator<Item = i16>, secs: usize) {
+        source.by_ref().take(8_000 * secs).for_each(drop);
+    }
+
+    #[test]
+    fn pauses_never_move_the_position() {
+        let progress = Arc::new(StreamProgress::default());
+        let mut source = counted(&progress);
+        let mut last = 0;
+
+        for _ in 0..3 {
+            play_for(&mut source, 2);
+            let playing = progress.position_secs();
+            assert!(playing >= last);
+            last = playing;
+
+            // Paused: the sink stops pulling while the wall clock goes on
+            thread::sleep(Duration::from_millis(50));
+            assert_eq!(progress.position_secs(), playing);
+        }
+        assert_eq!(last, 6);
+    }
+
+    #[test]
+    fn a_waiting_seek_reads_as_its_target() {
+        let progress = Arc::new(StreamProgress::default());
+        let mut source = counted(&progress);
+        play_for(&mut source, 5);
+
+        progress.seek_target.store(30, Ordering::Relaxed);
+        progress.seek_pending.store(true, Ordering::Relaxed);
+        assert_eq!(progress.position_secs(), 30);
+
+        progress.seek_pending.store(false, Ordering::Relaxed);
+        assert_eq!(progress.position_secs(), 5);
+    }
+
+    #[test]
+    fn buffered_handles_large_files() {
+        let total = 200 * 1024 * 1024 * 1024u64;
+        assert_eq!(buffered_until(total / 2, Some(total), 7200), Some(3600));
+    }
+
+    #[test]
+    fn leftover_spools_are_cleared() {
+        let dir = std::env::temp_dir().join(format!("mfp-spool-clear-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("4242-0.part"), b"old stream").unwrap();
+        fs::write(dir.join("notes.txt"), b"not ours").unwrap();
+
+        clear_spool(&dir);
+        assert!(!dir.join("4242-0.part").exists());
+        assert!(dir.join("notes.txt").exists());
+        // Nothing to clear is fine too
+        clear_spool(&dir.join("missing"));
+    }
+
+    #[test]
+    fn pause_ramps_end_at_the_volume_from_before() {
+        let (sink, _output) = Sink::new_idle();
+        let volume = Mutex::new(0.8);
+        sink.set_volume(amplitude(0.8));
+        let ramps = AtomicU64::new(1);
+
+        ramp(&sink, &volume, Ramp::Down, Duration::ZERO, &ramps, 1);
+        assert!(sink.is_paused());
+        assert_eq!(sink.volume(), amplitude(0.8));
+
+        ramps.store(2, Ordering::SeqCst);
+        ramp(&sink, &volume, Ramp::Up, Duration::ZERO, &ramps, 2);
+        assert!(!sink.is_paused());
+        assert_eq!(sink.volume(), amplitude(0.8));
+        assert_eq!(*volume.lock().unwrap(), 0.8);
+    }
+
+    #[test]
+    fn pause_ramps_follow_a_volume_change() {
+        let (sink, _output) = Sink::new_idle();
+        let volume = Arc::new(Mutex::new(0.8));
+        let ramps = AtomicU64::new(1);
+
+        let change = {
+            let volume = Arc::clone(&volume);
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(50));
+                *volume.lock().unwrap() = 0.5;
+            })
+        };
+        ramp(&sink, &volume, Ramp::Up, PAUSE_FADE, &ramps, 1);
+        change.join().unwrap();
+        assert_eq!(sink.volume(), amplitude(0.5));
+    }
+
+    #[test]
+    fn superseded_pause_ramps_leave_the_sink_alone() {
+        let (sink, _output) = Sink::new_idle();
+        let volume = Mutex::new(1.0);
+        // A resume came in after this pause started
+        let ramps = AtomicU64::new(2);
+
+        ramp(&sink, &volume, Ramp::Down, Duration::ZERO, &ramps, 1);
+        assert!(!sink.is_paused());
+    }
+
+    #[test]
+    fn prefetch_keeps_only_the_start() {
+        let stream = Vec::leak(vec![0xff; 2 * BUFFER_SIZE]);
+        let prefetch = Prefetch::start(&serve(200, stream));
+
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while prefetch.data.lock().unwrap().len() < BUFFER_SIZE && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        let (data, total) = prefetch.take();
+        assert_eq!(data.len(), BUFFER_SIZE);
+        assert_eq!(total, 2 * BUFFER_SIZE as u64);
+    }
+
+    #[test]
+    fn failed_prefetches_hand_over_nothing() {
+        let prefetch = Prefetch::start(&serve(404, b"not found"));
+        thread::sleep(Duration::from_millis(200));
+        assert!(prefetch.take().0.is_empty());
+    }
+
+    #[test]
+    fn balance_turns_down_the_other_side() {
+        assert_eq!(balance_gains(0.0), (1.0, 1.0));
+        assert_eq!(balance_gains(-0.5), (1.0, 0.5));
+        assert_eq!(balance_gains(1.0), (0.0, 1.0));
+    }
+
+    #[test]
+    fn balance_applies_per_channel_and_passes_through_when_centered() {
+        let stereo = || rodio::buffer::SamplesBuffer::new(2, 8_000, vec![1000i16; 8]);
+        let balance: Balance = Arc::default();
+
+        let centered: Vec<i16> = Balanced::new(stereo(), &balance).collect();
+        assert_eq!(centered, [1000; 8]);
+
+        balance.store((-0.5f32).to_bits(), Ordering::Relaxed);
+        let left: Vec<i16> = Balanced::new(stereo(), &balance).take(4).collect();
+        assert_eq!(left, [1000, 500, 1000, 500]);
+
+        let mono = rodio::buffer::SamplesBuffer::new(1, 8_000, vec![1000i16; 4]);
+        assert_eq!(Balanced::new(mono, &balance).collect::<Vec<_>>(), [1000; 4]);
+    }
+
+    #[test]
+    fn level_is_the_rms_of_each_window() {
+        // 8 kHz stereo: a 50 ms window is 800 samples
+        let half = rodio::buffer::SamplesBuffer::new(2, 8_000, vec![16_384i16; 1_600]);
+        let level = Arc::new(AtomicU32::new(0));
+        let read = || f32::from_bits(level.load(Ordering::Relaxed));
+        let mut leveled = Leveled::new(half, Arc::clone(&level));
+
+        leveled.by_ref().take(799).for_each(drop);
+        assert_eq!(read(), 0.0, "nothing published mid-window");
+        leveled.next();
+        assert!((read() - 0.5).abs() < 0.001, "level {}", read());
+
+        leveled.by_ref().for_each(drop);
+        assert_eq!(read(), 0.0, "silent once the source ends");
+    }
+
+    #[test]
+    fn downloads_hold_once_far_enough_ahead_of_the_decoder() {
+        let progress = StreamProgress {
+            max_readahead: AtomicU64::new(1_000),
+            ..StreamProgress::default()
+        };
+        progress.received.store(5_000, Ordering::Relaxed);
+        assert!(
+            !progress.readahead_reached(),
+            "not before the decoder reads"
+        );
+
+        let open: Opener = Arc::new(|_| Err(io::ErrorKind::NotFound.into()));
+        *progress.opener.lock().unwrap() = Some(open);
+        let position = |at| {
+            progress
+                .meter
+                .lock()
+                .unwrap()
+                .position
+                .store(at, Ordering::Relaxed)
+        };
+        position(4_500);
+        assert!(!progress.readahead_reached());
+        position(4_000);
+        assert!(progress.readahead_reached());
+
+        progress.max_readahead.store(0, Ordering::Relaxed);
+        assert!(!progress.readahead_reached(), "no limit");
+    }
+
+    #[test]
+    fn readahead_is_megabytes_or_unlimited() {
+        assert_eq!(parse_readahead("20"), Ok(Readahead::Mb(20)));
+        assert_eq!(parse_readahead("Unlimited"), Ok(Readahead::Unlimited));
+        assert!(parse_readahead("0").is_err());
+        assert!(parse_readahead("20mb").is_err());
+    }
+
+    #[test]
+    fn leading_silence_is_skipped_up_to_a_cap() {
+        // 8 kHz stereo: half a second of hiss under -50 dBFS, then a tone
+        let mut samples = vec![20i16; 8_000];
+        samples.extend([3, 5_000, 4_000, -4_000]);
+        let progress = StreamProgress {
+            trim_below: Some(silence_threshold(-50.0)),
+            ..StreamProgress::default()
+        };
+        let trimmed = Trimmed::new(
+            rodio::buffer::SamplesBuffer::new(2, 8_000, samples),
+            &progress,
+        );
+        // The frame with a loud right channel plays whole
+        assert_eq!(trimmed.collect::<Vec<_>>(), [3, 5_000, 4_000, -4_000]);
+        assert_eq!(progress.played_samples.load(Ordering::Relaxed), 8_000);
+        assert_eq!(progress.trimmed_samples.load(Ordering::Relaxed), 8_000);
+
+        // Twelve seconds of digital silence: ten are skipped
+        let progress = StreamProgress {
+            trim_below: Some(silence_threshold(-50.0)),
+            ..StreamProgress::default()
+        };
+        let silent = rodio::buffer::SamplesBuffer::new(1, 8_000, vec![0i16; 12 * 8_000]);
+        assert_eq!(Trimmed::new(silent, &progress).count(), 2 * 8_000);
+
+        // Off, as when resuming, nothing is skipped
+        let untouched = rodio::buffer::SamplesBuffer::new(1, 8_000, vec![0i16; 8_000]);
+        let progress = StreamProgress::default();
+        assert_eq!(Trimmed::new(untouched, &progress).count(), 8_000);
+        assert_eq!(progress.trimmed_samples.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn codecs_are_told_by_their_first_bytes() {
+        assert_eq!(codec_name(&frames(2)), Some("MP3"));
+        assert_eq!(codec_name(b"fLaC\x00\x00\x00\x22"), Some("FLAC"));
+        assert_eq!(codec_name(b"OggS\x00\x02"), Some("Ogg"));
+        assert_eq!(codec_name(b"RIFF\x24\x08\x00\x00WAVEfmt "), Some("WAV"));
+        assert_eq!(codec_name(b"\x00\x00\x00\x20ftypM4A "), Some("AAC"));
+        assert_eq!(codec_name(b"<html>"), None);
+    }
+
+    #[test]
+    fn bitrate_is_estimated_once_a_second_is_decoded() {
+        let meter = Meter::default();
+        // 8 kHz mono at 16 000 bytes a second: 128 kbps
+        meter.bytes.store(16_000 / 2, Ordering::Relaxed);
+        meter.samples.store(4_000, Ordering::Relaxed);
+        assert_eq!(meter.kbps(8_000), None);
+
+        meter.bytes.store(16_000 * 3, Ordering::Relaxed);
+        meter.samples.store(8_000 * 3, Ordering::Relaxed);
+        assert_eq!(meter.kbps(8_000), Some(128));
+        assert_eq!(meter.kbps(0), None);
+    }
+
+    #[test]
+    fn buffer_ahead_counts_from_where_the_decoder_read() {
+        let meter = Meter::default();
+        meter.bytes.store(16_000 * 3, Ordering::Relaxed);
+        meter.samples.store(8_000 * 3, Ordering::Relaxed);
+        // A seek moved the decoder 160 000 bytes (ten seconds) in
+        meter.position.store(160_000, Ordering::Relaxed);
+
+        assert_eq!(meter.ahead_secs(160_000 + 16_000 * 42, 8_000), Some(42));
+        assert_eq!(meter.ahead_secs(100_000, 8_000), Some(0));
+        assert_eq!(Meter::default().ahead_secs(1_000_000, 8_000), None);
+    }
+
+    /// A stream that has run dry: a reader on a thread takes whatever arrives, as the decoder
+    /// does, until the sink is paused. The returned closure feeds it `secs` of audio at
+    /// 16 000 bytes a second, a little at a time.
+    fn starved_stream() -> (
+        Arc<Sink>,
+        rodio::queue::SourcesQueueOutput<f32>,
+        Arc<StreamProgress>,
+        impl Fn(u64),
+    ) {
+        let (sink, output) = Sink::new_idle();
+        let sink = Arc::new(sink);
+        let progress = Arc::new(StreamProgress::default());
+        let buffer = StreamingBuffer::new(
+            Spool::Memory(Vec::new()),
+            Arc::clone(&progress.starved_since),
+        );
+        progress.samples_per_sec.store(8_000, Ordering::Relaxed);
+        let meter = Arc::clone(&progress.meter.lock().unwrap());
+        meter.bytes.store(16_000, Ordering::Relaxed);
+        meter.samples.store(8_000, Ordering::Relaxed);
+
+        let mut reader = buffer.at(0);
+        let reader_sink = Arc::clone(&sink);
+        thread::spawn(move || {
+            let mut buf = [0; 4096];
+            loop {
+                if reader_sink.is_paused() {
+                    thread::sleep(Duration::from_millis(5));
+                    continue;
+                }
+                let n = reader.read(&mut buf).unwrap();
+                meter.position.fetch_add(n as u64, Ordering::Relaxed);
+            }
+        });
+        let waited = Instant::now();
+        while progress.starved_since.lock().unwrap().is_none() {
+            assert!(
+                waited.elapsed() < Duration::from_secs(2),
+                "reader never starved"
+            );
+            thread::sleep(Duration::from_millis(5));
+        }
+
+        let feed_progress = Arc::clone(&progress);
+        let feed = move |secs: u64| {
+            for _ in 0..secs * 4 {
+                let position = feed_progress.received.fetch_add(4_000, Ordering::Relaxed);
+                buffer.write_chunk(position, &[0; 4_000]).unwrap();
+                thread::sleep(Duration::from_millis(5));
+            }
+        };
+        (sink, output, progress, feed)
+    }
+
+    #[test]
+    fn underrun_pauses_until_the_buffer_refills() {
+        let (sink, _output, progress, feed) = starved_stream();
+
+        assert_eq!(rebuffer(&sink, &progress, 10, false), Some(0));
+        assert!(sink.is_paused());
+
+        // The reader may take one more read before it sees the pause
+        feed(5);
+        let buffered = rebuffer(&sink, &progress, 10, false);
+        assert!(matches!(buffered, Some(4 | 5)), "{:?}", buffered);
+        assert!(sink.is_paused());
+
+        feed(6);
+        assert_eq!(rebuffer(&sink, &progress, 10, false), None);
+        assert!(!sink.is_paused());
+    }
+
+    #[test]
+    fn refilled_buffer_leaves_a_listeners_pause_alone() {
+        let (sink, _output, progress, feed) = starved_stream();
+
+        assert_eq!(rebuffer(&sink, &progress, 2, true), Some(0));
+        feed(3);
+        assert_eq!(rebuffer(&sink, &progress, 2, true), None);
+        assert!(sink.is_paused());
+        assert!(progress.rebuffering.lock().unwrap().is_none());
+    }
+
+    #[test]
+    fn finished_download_never_rebuffers() {
+        let (sink, _output) = Sink::new_idle();
+        let progress = StreamProgress::default();
+        *progress.starved_since.lock().unwrap() = Some(Instant::now());
+        progress.complete.store(true, Ordering::Relaxed);
+
+        assert_eq!(rebuffer(&sink, &progress, 10, false), None);
+        assert!(!sink.is_paused());
+    }
+
+    #[test]
+    fn slow_downloads_fill_the_whole_initial_buffer() {
+        // 128 kbps audio arriving at 10 KB/s
+        let audio = Some((0, 16_000));
+        assert_eq!(
+            start_progress(128 * 1024, 512 * 1024, audio, Some(10_000)),
+            25
+        );
+        assert_eq!(
+            start_progress(512 * 1024, 512 * 1024, audio, Some(10_000)),
+            100
+        );
+        assert_eq!(
+            start_progress(600 * 1024, 512 * 1024, audio, Some(10_000)),
+            100
+        );
+    }
+
+    #[test]
+    fn fast_downloads_start_on_a_few_seconds_of_audio() {
+        // Five seconds of 128 kbps audio after a 20 000-byte tag, arriving at 1 MB/s
+        let audio = Some((20_000, 16_000));
+        assert_eq!(
+            start_progress(60_000, 512 * 1024, audio, Some(1_000_000)),
+            50
+        );
+        assert_eq!(
+            start_progress(100_000, 512 * 1024, audio, Some(1_000_000)),
+            100
+        );
+        // Until the speed or the bitrate is known, only the size counts
+        assert_eq!(start_progress(100_000, 1_000_000, audio, None), 10);
+        assert_eq!(
+            start_progress(100_000, 1_000_000, None, Some(1_000_000)),
+            10
+        );
+    }
+
+    #[test]
+    fn audio_rate_is_read_past_the_tag() {
+        let buffer = StreamingBuffer::new(Spool::Memory(Vec::new()), Starved::default());
+        assert_eq!(audio_rate(&buffer), None);
+        // A 20-byte ID3v2 tag, then 128 kbps frames
+        buffer
+            .write_chunk(0, b"ID3\x04\x00\x00\x00\x00\x00\x0a")
+            .unwrap();
+        buffer.write_chunk(10, &[0; 10]).unwrap();
+        buffer.write_chunk(20, &frames(3)).unwrap();
+
+        assert_eq!(audio_rate(&buffer), Some((20, 16_000)));
+    }
+
+    #[test]
+    fn stream_info_is_unknown_before_anything_plays() {
+        let progress = StreamProgress::default();
+        assert_eq!(*progress.info.lock().unwrap(), StreamInfo::default());
+        assert_eq!(progress.meter.lock().unwrap().kbps(0), None);
+    }
+}
@@ -1,89 +1,338 @@
+//! Playback order over a list of episodes
+//! Skipping wraps around at both ends; at the end of an episode the repeat mode decides what
+//! comes next. Shuffle reorders without touching the list itself, keeping the episodes played
+//! lately for last, and queued episodes play ahead of the order without moving the cursor in
+//! it. `previous` follows what was played rather than the order.
+//! The episodes are shared (`Arc<[Episode]>`) and a playlist holds indices into them, so
+//! building one over a feed, or a part of it, copies no episodes and the cursor only moves
+//! indices around.
+
 use crate::feed::Episode;
+use crate::player;
 use rand::seq::SliceRandom;
 use rand::thread_rng;
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+/// How many of the episodes played lately a shuffled order keeps for last, unless set
+pub const SHUFFLE_MEMORY: usize = 10;
+
+/// What plays once an episode ends
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RepeatMode {
+    /// The next one, and nothing after the last
+    Off,
+    /// The same episode again
+    One,
+    /// The next one, starting over after the last
+    #[default]
+    All,
+}
+
+impl RepeatMode {
+    /// The mode after this one, in the order off, all, one
+    pub fn cycled(self) -> Self {
+        match self {
+            RepeatMode::Off => RepeatMode::All,
+            RepeatMode::All => RepeatMode::One,
+            RepeatMode::One => RepeatMode::Off,
+        }
+    }
+}
 
+/// Ordered episodes with a cursor and an optional shuffled order
 pub struct Playlist {
-    episodes: Vec<Episode>,
+    episodes: Arc<[Episode]>,
+    /// Indices into `episodes` of the ones in the playlist, in list order; a list index, as
+    /// the fields below hold, is a position in here
+    members: Vec<usize>,
     current_index: usize,
     shuffle: bool,
     shuffled_indices: Vec<usize>,
+    repeat: RepeatMode,
+    /// List indices to play before the order resumes, first one next
+    queue: VecDeque<usize>,
+    /// List index of the queued episode playing, over the one under the cursor
+    from_queue: Option<usize>,
+    /// List indices of the episodes played, the latest last, for `previous`
+    history: Vec<usize>,
+    /// The last `shuffle_memory` episodes played, the latest last, which a shuffle draws last
+    recent: VecDeque<usize>,
+    shuffle_memory: usize,
 }
 
 impl Playlist {
-    pub fn new(episodes: Vec<Episode>) -> Self {
-        let indices: Vec<usize> = (0..episodes.len()).collect();
+    /// Playlist over `episodes`, positioned on the first one. Takes a `Vec` or a list shared
+    /// with the feed ([`Feed::shared_episodes`](crate::feed::Feed::shared_episodes))
+    pub fn new(episodes: impl Into<Arc<[Episode]>>) -> Self {
+        let episodes = episodes.into();
+        let members = (0..episodes.len()).collect();
+        Self::over(episodes, members)
+    }
+
+    /// Playlist of the episodes at `members` in `episodes`, in that order
+    fn over(episodes: Arc<[Episode]>, members: Vec<usize>) -> Self {
+        let indices: Vec<usize> = (0..members.len()).collect();
         Self {
             episodes,
+            members,
             current_index: 0,
             shuffle: false,
             shuffled_indices: indices,
+            repeat: RepeatMode::default(),
+            queue: VecDeque::new(),
+            from_queue: None,
+            history: Vec::new(),
+            recent: VecDeque::new(),
+            shuffle_memory: SHUFFLE_MEMORY,
         }
     }
 
-    pub fn from_favorites(all_episodes: &[Episode], favorite_titles: &[&String]) -> Self {
-        let episodes: Vec<Episode> = all_episodes
+    /// Playlist of the episodes in `all_episodes` whose titles are in `favorite_titles`, in feed order
+    pub fn from_favorites(
+        all_episodes: impl Into<Arc<[Episode]>>,
+        favorite_titles: &[&String],
+    ) -> Self {
+        Self::filtered(all_episodes, |e| favorite_titles.contains(&&e.title))
+    }
+
+    /// Playlist of the episodes in `episodes` that `predicate` keeps, in their order
+    pub fn filtered(
+        episodes: impl Into<Arc<[Episode]>>,
+        predicate: impl FnMut(&Episode) -> bool,
+    ) -> Self {
+        Self::new(episodes).narrowed(predicate)
+    }
+
+    /// Playlist of the episodes in `all_episodes` titled `titles`, in the order of `titles`
+    /// (a named playlist); titles no longer in `all_episodes` are skipped
+    pub fn from_titles(all_episodes: impl Into<Arc<[Episode]>>, titles: &[String]) -> Self {
+        let episodes = all_episodes.into();
+        let members = titles
+            .iter()
+            .filter_map(|title| episodes.iter().position(|e| e.title == *title))
+            .collect();
+
+        Self::over(episodes, members)
+    }
+
+    /// A new playlist of the episodes in this one that `predicate` keeps, in list order,
+    /// sharing the same episodes
+    pub fn narrowed(&self, mut predicate: impl FnMut(&Episode) -> bool) -> Self {
+        let members = self
+            .members
             .iter()
-            .filter(|e| favorite_titles.contains(&&e.title))
-            .cloned()
+            .copied()
+            .filter(|&i| predicate(&self.episodes[i]))
             .collect();
 
-        Self::new(episodes)
+        Self::over(Arc::clone(&self.episodes), members)
     }
 
+    /// Switch to a fresh random order, the cursor keeping its position (the start of a new
+    /// playlist, so it starts on a random episode)
     pub fn enable_shuffle(&mut self) {
         self.shuffle = true;
         self.reshuffle();
     }
 
+    /// Go back to list order
     pub fn disable_shuffle(&mut self) {
         self.shuffle = false;
-        self.shuffled_indices = (0..self.episodes.len()).collect();
+        self.shuffled_indices = (0..self.members.len()).collect();
     }
 
+    /// Flip between list order and a fresh random order while an episode plays: either way
+    /// the order goes on from the episode under the cursor
     pub fn toggle_shuffle(&mut self) {
+        let Some(playing) = self.cursor_list_index() else {
+            self.shuffle = !self.shuffle;
+            return;
+        };
+
         if self.shuffle {
             self.disable_shuffle();
+            self.current_index = playing;
         } else {
             self.enable_shuffle();
+            self.shuffled_indices.retain(|&i| i != playing);
+            self.shuffled_indices.insert(0, playing);
+            self.current_index = 0;
         }
     }
 
+    /// Draw a random order: the episodes played lately go last, the longest ago first, so
+    /// they only come round once the rest have
     fn reshuffle(&mut self) {
         let mut rng = thread_rng();
-        self.shuffled_indices = (0..self.episodes.len()).collect();
+        self.shuffled_indices = (0..self.members.len())
+            .filter(|i| !self.recent.contains(i))
+            .collect();
         self.shuffled_indices.shuffle(&mut rng);
+        self.shuffled_indices.extend(&self.recent);
     }
 
+    /// How many of the episodes played lately a shuffle keeps for last; 0 forgets them
+    pub fn set_shuffle_memory(&mut self, episodes: usize) {
+        self.shuffle_memory = episodes;
+        self.trim_recent();
+    }
+
+    /// Episodes played before this playlist existed (in an earlier run, say), the latest
+    /// last, for the shuffle to keep for last too. Titles not in the playlist are skipped.
+    pub fn set_recently_played(&mut self, titles: &[String]) {
+        self.recent = titles
+            .iter()
+            .filter_map(|title| self.list_index_of(title))
+            .collect();
+        self.trim_recent();
+    }
+
+    /// Note the episode playing as played, once it starts: `previous` comes back to it, and
+    /// the next shuffle keeps it for last
+    pub fn mark_played(&mut self) {
+        let Some(index) = self.current_list_index() else {
+            return;
+        };
+        if self.history.last() != Some(&index) {
+            self.history.push(index);
+        }
+        self.recent.retain(|&i| i != index);
+        self.recent.push_back(index);
+        self.trim_recent();
+    }
+
+    fn trim_recent(&mut self) {
+        while self.recent.len() > self.shuffle_memory {
+            self.recent.pop_front();
+        }
+    }
+
+    /// Episode under the cursor; None for an empty playlist
     pub fn current(&self) -> Option<&Episode> {
-        if self.episodes.is_empty() {
+        self.episode(self.current_list_index()?)
+    }
+
+    /// Index of the episode playing in [`Playlist::shared_episodes`]
+    pub fn current_shared_index(&self) -> Option<usize> {
+        Some(self.members[self.current_list_index()?])
+    }
+
+    /// List index of the episode playing: a queued one, or the one under the cursor
+    pub fn current_list_index(&self) -> Option<usize> {
+        if self.members.is_empty() {
             return None;
         }
+        self.from_queue.or_else(|| self.cursor_list_index())
+    }
 
-        let index = if self.shuffle {
-            self.shuffled_indices.get(self.current_index).copied()?
-        } else {
-            self.current_index
-        };
+    /// List index of the episode under the cursor, queued or not
+    fn cursor_list_index(&self) -> Option<usize> {
+        if self.members.is_empty() {
+            return None;
+        }
 
-        self.episodes.get(index)
+        if self.shuffle {
+            self.shuffled_indices.get(self.current_index).copied()
+        } else {
+            Some(self.current_index)
+        }
     }
 
+    /// Move on as when an episode ends: to the first queued episode if there is one, then to
+    /// the same one with [`RepeatMode::One`], nowhere past the last with [`RepeatMode::Off`]
+    /// (None, the cursor staying put), otherwise to the next one, wrapping to the start
+    #[allow(clippy::should_implement_trait)]
     pub fn next(&mut self) -> Option<&Episode> {
-        if self.episodes.is_empty() {
+        if self.play_queued() || self.repeat == RepeatMode::One {
+            return self.current();
+        }
+
+        let position = self.next_position()?;
+        self.from_queue = None;
+        self.current_index = position;
+        self.current()
+    }
+
+    /// Advance as asked to: to the first queued episode, or the next one in the order,
+    /// wrapping to the start whatever the repeat mode
+    pub fn skip(&mut self) -> Option<&Episode> {
+        if self.members.is_empty() {
             return None;
         }
+        if self.play_queued() {
+            return self.current();
+        }
 
-        self.current_index = (self.current_index + 1) % self.episodes.len();
+        self.from_queue = None;
+        self.current_index = (self.current_index + 1) % self.members.len();
         self.current()
     }
 
+    /// Episode `next` would move to, without moving the cursor
+    pub fn peek_next(&self) -> Option<&Episode> {
+        if let Some(&index) = self.queue.front() {
+            return self.episode(index);
+        }
+        if self.repeat == RepeatMode::One {
+            return self.current();
+        }
+
+        let position = self.next_position()?;
+        if self.shuffle {
+            self.episode(*self.shuffled_indices.get(position)?)
+        } else {
+            self.episode(position)
+        }
+    }
+
+    /// Take the first queued episode as the one playing; false when the queue is empty
+    fn play_queued(&mut self) -> bool {
+        match self.queue.pop_front() {
+            Some(index) => {
+                self.from_queue = Some(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Play-order position `next` moves to, past the queue and [`RepeatMode::One`]
+    fn next_position(&self) -> Option<usize> {
+        if self.members.is_empty() {
+            return None;
+        }
+
+        let following = self.current_index + 1;
+        match self.repeat {
+            RepeatMode::Off if following == self.members.len() => None,
+            _ => Some(following % self.members.len()),
+        }
+    }
+
+    /// Go back to the episode played before this one (see [`Playlist::mark_played`]),
+    /// whatever the order was then. With nothing played before, from a queued episode it goes
+    /// back to the one under the cursor, the queued one gone for good; otherwise the cursor
+    /// moves back, wrapping to the end.
     pub fn previous(&mut self) -> Option<&Episode> {
-        if self.episodes.is_empty() {
+        if self.members.is_empty() {
             return None;
         }
+        let playing = self.current_list_index();
+        while playing.is_some() && self.history.last().copied() == playing {
+            self.history.pop();
+        }
+        if let Some(index) = self.history.pop() {
+            self.from_queue = None;
+            self.current_index = self.position_of(index).unwrap_or(self.current_index);
+            return self.current();
+        }
+        if self.from_queue.take().is_some() {
+            return self.current();
+        }
 
         if self.current_index == 0 {
-            self.current_index = self.episodes.len() - 1;
+            self.current_index = self.members.len() - 1;
         } else {
             self.current_index -= 1;
         }
@@ -91,18 +340,137 @@ impl Playlist {
     }
 
     pub fn len(&self) -> usize {
-        self.episodes.len()
+        self.members.len()
     }
 
     pub fn is_empty(&self) -> bool {
-        self.episodes.is_empty()
+        self.members.is_empty()
     }
 
     pub fn is_shuffled(&self) -> bool {
         self.shuffle
     }
 
-    pub fn all_episodes(&self) -> &[Episode] {
-        &self.episodes
+    pub fn repeat(&self) -> RepeatMode {
+        self.repeat
+    }
+
+    pub fn set_repeat(&mut self, mode: RepeatMode) {
+        self.repeat = mode;
+    }
+
+    /// Switch to the next repeat mode (off, all, one) and return it
+    pub fn cycle_repeat(&mut self) -> RepeatMode {
+        self.repeat = self.repeat.cycled();
+        self.repeat
+    }
+
+    /// Queue the episode at `index` in list order to play after the current one, behind any
+    /// already queued; None when there is no such episode. The same one can be queued twice
+    /// (see [`Playlist::is_queued`]).
+    pub fn queue_next(&mut self, index: usize) -> Option<&Episode> {
+        self.episode(index)?;
+        self.queue.push_back(index);
+        self.episode(index)
+    }
+
+    /// Queue the episodes titled `titles`, the next one first, as a queue saved by an earlier
+    /// run; the titles of episodes no longer in the playlist are left out and returned
+    pub fn restore_queue<'a>(&mut self, titles: &'a [String]) -> Vec<&'a str> {
+        let mut missing = Vec::new();
+        for title in titles {
+            match self.list_index_of(title) {
+                Some(index) => self.queue.push_back(index),
+                None => missing.push(title.as_str()),
+            }
+        }
+        missing
+    }
+
+    /// Whether the episode at `index` in list order is waiting in the queue
+    pub fn is_queued(&self, index: usize) -> bool {
+        self.queue.contains(&index)
+    }
+
+    /// Queued episodes, the next one first
+    pub fn queued(&self) -> impl Iterator<Item = &Episode> {
+        self.queue.iter().map(|&i| &self.episodes[self.members[i]])
+    }
+
+    /// Move the cursor to the episode at `index` in list order, shuffled or not
+    pub fn jump_to(&mut self, index: usize) -> Option<&Episode> {
+        let position = self.position_of(index)?;
+        self.from_queue = None;
+        self.current_index = position;
+        self.current()
+    }
+
+    /// Move the cursor to the episode titled `title`, shuffled or not; None when it isn't in
+    /// the playlist
+    pub fn jump_to_title(&mut self, title: &str) -> Option<&Episode> {
+        let index = self.list_index_of(title)?;
+        self.jump_to(index)
+    }
+
+    /// Move the cursor to `position` in play order
+    pub fn jump_to_position(&mut self, position: usize) -> Option<&Episode> {
+        if position >= self.members.len() {
+            return None;
+        }
+
+        self.from_queue = None;
+        self.current_index = position;
+        self.current()
+    }
+
+    /// Where the cursor is in play order
+    pub fn position(&self) -> usize {
+        self.current_index
+    }
+
+    /// Play-order position of the episode at `index` in list order
+    fn position_of(&self, index: usize) -> Option<usize> {
+        if self.shuffle {
+            self.shuffled_indices.iter().position(|&i| i == index)
+        } else {
+            (index < self.members.len()).then_some(index)
+        }
+    }
+
+    /// Episodes in the order they play: the shuffled order while shuffle is on
+    pub fn in_play_order(&self) -> impl Iterator<Item = &Episode> {
+        self.shuffled_indices
+            .iter()
+            .map(|&i| &self.episodes[self.members[i]])
+    }
+
+    /// Seconds left in the queued episodes and the ones after the current one, up to the end
+    /// of the play order; None if any of them has an unknown duration
+    pub fn remaining_after_current(&self) -> Option<u64> {
+        self.queued()
+            .chain(self.in_play_order().skip(self.current_index + 1))
+            .map(|episode| player::parse_duration(&episode.duration))
+            .sum()
+    }
+
+    /// Episode at `index` in list order
+    pub fn episode(&self, index: usize) -> Option<&Episode> {
+        self.episodes.get(*self.members.get(index)?)
+    }
+
+    /// Episodes in list order, regardless of shuffle
+    pub fn episodes(&self) -> impl Iterator<Item = &Episode> {
+        self.members.iter().map(|&i| &self.episodes[i])
+    }
+
+    /// List index of the episode titled `title`; None when it isn't in the playlist
+    pub fn list_index_of(&self, title: &str) -> Option<usize> {
+        self.episodes().position(|e| e.title == title)
+    }
+
+    /// Handle on the episodes the playlist picks from (all of the feed's, for a part of it),
+    /// that stays valid while the playlist moves on; cloning it copies no episodes
+    pub fn shared_episodes(&self) -> Arc<[Episode]> {
+        Arc::clone(&self.episodes)
     }
 }
@@ -0,0 +1,163 @@
+//! Named playlists
+//! Stored as JSON in the config directory; every change is saved immediately. Each playlist
+//! is an ordered list of episode titles, kept per station like favorites so equal titles in
+//! two feeds stay separate.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::errors::StorageError;
+use crate::paths;
+use crate::station;
+
+/// Playlists of every station
+#[derive(Debug, Serialize, Deserialize, Default)]
+#[serde(transparent)]
+pub struct Playlists {
+    /// Titles by station, then by playlist name
+    stations: BTreeMap<String, BTreeMap<String, Vec<String>>>,
+}
+
+/// What adding or removing a title came to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edit {
+    Done,
+    /// Already in the playlist, or not in it for a removal
+    Unchanged,
+    NoPlaylist,
+}
+
+impl Playlists {
+    fn config_path() -> Result<PathBuf, StorageError> {
+        let config_dir = paths::config_dir()?;
+        fs::create_dir_all(&config_dir).map_err(StorageError::write(&config_dir))?;
+
+        paths::playlists_file()
+    }
+
+    /// Read the saved playlists; none if there are none yet
+    pub fn load() -> Result<Self, StorageError> {
+        let path = Self::config_path()?;
+
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(&path).map_err(StorageError::read(&path))?;
+
+        serde_json::from_str(&content).map_err(StorageError::parse(&path))
+    }
+
+    pub fn save(&self) -> Result<(), StorageError> {
+        let path = Self::config_path()?;
+        let content = serde_json::to_string_pretty(self).map_err(StorageError::write(&path))?;
+
+        fs::write(&path, content).map_err(StorageError::write(&path))
+    }
+
+    fn active(&self) -> Option<&BTreeMap<String, Vec<String>>> {
+        self.stations.get(station::active())
+    }
+
+    fn titles_mut(&mut self, name: &str) -> Option<&mut Vec<String>> {
+        self.stations.get_mut(station::active())?.get_mut(name)
+    }
+
+    /// Playlists of the active station by name, each with its titles in order
+    pub fn list(&self) -> impl Iterator<Item = (&str, &[String])> {
+        self.active()
+            .into_iter()
+            .flatten()
+            .map(|(name, titles)| (name.as_str(), titles.as_slice()))
+    }
+
+    /// Titles of the playlist `name`, in order
+    pub fn get(&self, name: &str) -> Option<&[String]> {
+        self.active()?.get(name).map(Vec::as_slice)
+    }
+
+    /// Start an empty playlist and save; false if there is one by that name already
+    pub fn create(&mut self, name: &str) -> Result<bool, StorageError> {
+        let playlists = self
+            .stations
+            .entry(station::active().to_string())
+            .or_default();
+        if playlists.contains_key(name) {
+            return Ok(false);
+        }
+
+        playlists.insert(name.to_string(), Vec::new());
+        self.save()?;
+        Ok(true)
+    }
+
+    /// Drop the playlist `name` and save; false if there was none
+    pub fn delete(&mut self, name: &str) -> Result<bool, StorageError> {
+        let Some(playlists) = self.stations.get_mut(station::active()) else {
+            return Ok(false);
+        };
+        if playlists.remove(name).is_none() {
+            return Ok(false);
+        }
+        if playlists.is_empty() {
+            self.stations.remove(station::active());
+        }
+
+        self.save()?;
+        Ok(true)
+    }
+
+    /// Append `title` to the playlist `name` and save
+    pub fn add(&mut self, name: &str, title: &str) -> Result<Edit, StorageError> {
+        let Some(titles) = self.titles_mut(name) else {
+            return Ok(Edit::NoPlaylist);
+        };
+        if titles.iter().any(|other| other == title) {
+            return Ok(Edit::Unchanged);
+        }
+
+        titles.push(title.to_string());
+        self.save()?;
+        Ok(Edit::Done)
+    }
+
+    /// Add the playlists only `other` has, and append to the others the titles they lack;
+    /// saves and returns the number of titles added
+    pub fn merge(&mut self, other: Playlists) -> Result<usize, StorageError> {
+        let mut added = 0;
+        for (station, playlists) in other.stations {
+            let here = self.stations.entry(station).or_default();
+            for (name, titles) in playlists {
+                let list = here.entry(name).or_default();
+                for title in titles {
+                    if !list.contains(&title) {
+                        list.push(title);
+                        added += 1;
+                    }
+                }
+            }
+        }
+
+        if added > 0 {
+            self.save()?;
+        }
+        Ok(added)
+    }
+
+    /// Take `title` out of the playlist `name` and save
+    pub fn remove(&mut self, name: &str, title: &str) -> Result<Edit, StorageError> {
+        let Some(titles) = self.titles_mut(name) else {
+            return Ok(Edit::NoPlaylist);
+        };
+        let before = titles.len();
+        titles.retain(|other| other != title);
+        if titles.len() == before {
+            return Ok(Edit::Unchanged);
+        }
+
+        self.save()?;
+        Ok(Edit::Done)
+    }
+}
@@ -0,0 +1,689 @@
+//! Progress line rendering for the play loop
+//! The line is sized to the terminal width so it never wraps.
+
+use chrono::{Duration, NaiveDateTime};
+use crossterm::{
+    cursor::MoveToColumn,
+    execute,
+    terminal::{self, Clear, ClearType},
+};
+use std::io;
+
+use crate::i18n::{t, tf, Msg};
+use crate::player::{self, BufferAhead};
+use crate::style::{self, BarStyle, Role};
+
+const MAX_BAR_WIDTH: usize = 40;
+const MIN_BAR_WIDTH: usize = 5;
+/// Below this width only `[elapsed/total] percent%` is shown
+const COMPACT_WIDTH: usize = 40;
+const MAX_COMMAND_WIDTH: usize = 24;
+/// With less audio than this ready, the buffer marker warns of a stutter
+const LOW_BUFFER_SECS: u64 = 5;
+/// Cells lit on the bar of an episode of unknown length
+const MARQUEE_CELLS: usize = 4;
+/// Shown for the total and the time left when the feed has no duration
+const UNKNOWN_TIME: &str = "--:--";
+/// Cells of the level meter ahead of the bar
+const METER_CELLS: usize = 4;
+/// Quietest level that lights the meter; every cell is as many dB louder than the one before
+const METER_FLOOR_DB: f32 = -48.0;
+
+/// Current terminal width in columns (80 if it can't be queried)
+pub fn terminal_width() -> usize {
+    terminal::size()
+        .map(|(cols, _)| cols as usize)
+        .unwrap_or(80)
+}
+
+/// Clear the current terminal line and move the cursor to its start
+pub fn clear_line() {
+    let _ = execute!(io::stdout(), Clear(ClearType::CurrentLine), MoveToColumn(0));
+}
+
+/// When the episode and the rest of the playlist finish, e.g. `ends 15:42, all 18:10`, given
+/// the seconds left in each; an unknown duration shows as `—`. Nothing after the episode
+/// leaves just its own end.
+pub fn finish_times(now: NaiveDateTime, track_left: Option<u64>, list_left: Option<u64>) -> String {
+    let clock = |secs: u64| {
+        (now + Duration::seconds(secs as i64))
+            .format("%H:%M")
+            .to_string()
+    };
+    let track = track_left.map_or("—".to_string(), clock);
+
+    match (track_left, list_left) {
+        (_, Some(0)) => tf(Msg::ProgressEnds, &[&track]),
+        (Some(track_left), Some(list_left)) => tf(
+            Msg::ProgressEndsAll,
+            &[&track, &clock(track_left + list_left)],
+        ),
+        _ => tf(Msg::ProgressEndsAll, &[&track, &"—"]),
+    }
+}
+
+/// What the progress line shows of the download
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Buffer {
+    /// How far into the track the downloaded audio reaches, drawn on the bar
+    pub until: Option<u64>,
+    /// Audio ready past the playhead, shown after the times
+    pub ahead: Option<BufferAhead>,
+}
+
+/// What the level meter ahead of the bar shows
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Meter {
+    /// RMS level of what plays, 0.0 to 1.0
+    Level(f32),
+    /// Nothing to measure: paused, or playing where the level can't be read
+    Idle,
+    /// Playing, yet silent for a while: drawn as a warning
+    Silent,
+}
+
+/// Cells of the meter lit at `level`, one per 12 dB above [`METER_FLOOR_DB`]
+fn meter_cells(level: f32) -> usize {
+    if level <= 0.0 {
+        return 0;
+    }
+    let step = -METER_FLOOR_DB / METER_CELLS as f32;
+    let db = 20.0 * level.log10();
+    ((db - METER_FLOOR_DB) / step)
+        .ceil()
+        .clamp(0.0, METER_CELLS as f32) as usize
+}
+
+/// Whether `level` lights any of the meter; below that it counts as silence
+pub fn audible(level: f32) -> bool {
+    meter_cells(level) > 0
+}
+
+/// The meter, painted: lit cells in the accent color, e.g. `▂▄▁▁`; all of it dimmed when
+/// idle, and `!▁▁▁` in the error color when silent
+fn render_meter(meter: Meter, bar_style: BarStyle) -> String {
+    let (glyphs, unlit) = bar_style.meter();
+    let unlit = |cells: usize| unlit.to_string().repeat(cells);
+    match meter {
+        Meter::Level(level) => {
+            let lit = meter_cells(level);
+            let lit_glyphs: String = glyphs[..lit].iter().collect();
+            style::paint(Role::Accent, &lit_glyphs)
+                + &style::paint(Role::Dim, &unlit(METER_CELLS - lit))
+        }
+        Meter::Idle => style::paint(Role::Dim, &unlit(METER_CELLS)),
+        Meter::Silent => style::paint(Role::Error, &format!("!{}", unlit(METER_CELLS - 1))),
+    }
+}
+
+/// `[+42s]`, `[full]`, `[+300s max]` while the readahead limit holds the download or, with
+/// a stutter close, `[!3s]`; and whether it is that warning
+fn buffer_marker(ahead: Option<BufferAhead>) -> Option<(String, bool)> {
+    match ahead? {
+        BufferAhead::Full => Some((format!("[{}]", t(Msg::BufferFull)), false)),
+        BufferAhead::Secs(secs) | BufferAhead::Held(secs) if secs < LOW_BUFFER_SECS => {
+            Some((format!("[!{}s]", secs), true))
+        }
+        BufferAhead::Secs(secs) => Some((format!("[+{}s]", secs), false)),
+        BufferAhead::Held(secs) => Some((format!("[+{}s {}]", secs, t(Msg::BufferHeld)), false)),
+    }
+}
+
+/// Pieces of a progress line, measured for a given width
+struct Layout {
+    times: String,
+    /// From [`buffer_marker`], shown after the times
+    marker: Option<(String, bool)>,
+    /// None when the length is unknown
+    percent: Option<u8>,
+    suffix: String,
+    /// Command text and bar length, None for the compact line without a bar
+    bar: Option<(String, usize)>,
+}
+
+fn layout(
+    width: usize,
+    elapsed: u64,
+    total: u64,
+    ahead: Option<BufferAhead>,
+    finish: &str,
+    command: &str,
+) -> Layout {
+    let elapsed = clamp_elapsed(elapsed, total);
+    let percent = (total > 0).then(|| (elapsed as f32 / total as f32 * 100.0) as u8);
+
+    let (total_text, remaining) = match total {
+        0 => (UNKNOWN_TIME.to_string(), UNKNOWN_TIME.to_string()),
+        _ => (
+            player::format_duration(total),
+            format!("-{}", player::format_duration(total - elapsed)),
+        ),
+    };
+    let times = format!("[{}/{}]", player::format_duration(elapsed), total_text);
+    let marker = buffer_marker(ahead);
+    // Times, marker and meter, before the space ahead of the bar
+    let lead = times.len()
+        + marker
+            .as_ref()
+            .map_or(0, |(text, _)| 1 + text.chars().count())
+        + 1
+        + METER_CELLS;
+    let percent_text = percent.map_or("--".to_string(), |percent| percent.to_string());
+    let mut suffix = format!(" {}% | {} > ", percent_text, remaining);
+    let mut fixed = lead + 1 + suffix.chars().count();
+
+    // The finish times go first when the line runs short
+    let with_finish = format!(" {}% | {} | {} > ", percent_text, remaining, finish);
+    if !finish.is_empty() && width >= lead + 1 + with_finish.chars().count() + MIN_BAR_WIDTH {
+        fixed = lead + 1 + with_finish.chars().count();
+        suffix = with_finish;
+    }
+
+    let bar = (width >= COMPACT_WIDTH && width >= fixed + MIN_BAR_WIDTH).then(|| {
+        let command = truncate_start(
+            command,
+            MAX_COMMAND_WIDTH.min(width - fixed - MIN_BAR_WIDTH),
+        );
+        let bar_length = (width - fixed - command.chars().count()).min(MAX_BAR_WIDTH);
+        (command, bar_length)
+    });
+
+    Layout {
+        times,
+        marker,
+        percent,
+        suffix,
+        bar,
+    }
+}
+
+/// Render the progress line so that it fits in `width` columns.
+/// `buffer` tells how far the downloaded audio reaches and how much is ready, when known;
+/// `meter` goes ahead of the bar, and `finish` (from [`finish_times`]) is shown while there
+/// is room for it. `section` is an A-B loop's start and end, drawn on the bar as `[` and `]`.
+#[allow(clippy::too_many_arguments)]
+pub fn render(
+    width: usize,
+    elapsed: u64,
+    total: u64,
+    buffer: Buffer,
+    meter: Meter,
+    section: Option<(u64, Option<u64>)>,
+    finish: &str,
+    command: &str,
+) -> String {
+    // Keep the last column free so the cursor never wraps
+    let width = width.saturating_sub(1);
+    let Layout {
+        times,
+        marker,
+        percent,
+        suffix,
+        bar,
+    } = layout(width, elapsed, total, buffer.ahead, finish, command);
+    let (times_width, times) = match marker {
+        Some((text, low)) => (
+            times.len() + 1 + text.chars().count(),
+            format!(
+                "{} {}",
+                times,
+                style::paint(if low { Role::Error } else { Role::Dim }, &text)
+            ),
+        ),
+        None => (times.len(), times),
+    };
+
+    let Some((command, bar_length)) = bar else {
+        let line = match percent {
+            Some(percent) => format!("{} {}%", times, percent),
+            None => times,
+        };
+        let percent_width = percent.map_or(0, |percent| 1 + percent.to_string().len() + 1);
+        let room = width.saturating_sub(times_width + percent_width + 3);
+        if room == 0 {
+            return line;
+        }
+        return format!("{} > {}", line, truncate_start(command, room));
+    };
+
+    let bar_style = style::bar_style();
+    let times = format!("{} {}", times, render_meter(meter, bar_style));
+    if total == 0 {
+        let (before, lit, after) = marquee(bar_length, elapsed);
+        let empty = bar_style.empty().to_string();
+        let bar = style::paint(Role::Dim, &empty.repeat(before))
+            + &style::paint(Role::Accent, &bar_style.filled().to_string().repeat(lit))
+            + &style::paint(Role::Dim, &empty.repeat(after));
+        return format!("{} {}{}{}", times, bar, suffix, command);
+    }
+
+    let elapsed = clamp_elapsed(elapsed, total);
+    let fraction = elapsed as f64 / total as f64;
+    let mut bar = style::render_bar(bar_length, fraction, bar_style);
+    let bar = match buffer.until {
+        Some(buffered) => {
+            let (played, ahead, _) = segments(bar_length, elapsed, buffered, total);
+            // A partial block at the playhead counts as played
+            let ahead = (played + ahead).saturating_sub(bar.filled.chars().count());
+            let ahead: String = bar.empty.chars().take(ahead).collect();
+            let rest = bar.empty.chars().count() - ahead.chars().count();
+            let mut parts = [
+                bar.filled,
+                ahead,
+                bar_style.pending().to_string().repeat(rest),
+            ];
+            if let Some(section) = section {
+                mark_section(&mut parts, bar_length, total, section);
+            }
+            let [filled, ahead, rest] = parts;
+            style::paint(Role::Accent, &filled)
+                + &style::paint(Role::Buffered, &ahead)
+                + &style::paint(Role::Dim, &rest)
+        }
+        None => {
+            if let Some(section) = section {
+                let mut parts = [bar.filled, bar.empty];
+                mark_section(&mut parts, bar_length, total, section);
+                [bar.filled, bar.empty] = parts;
+            }
+            bar.paint()
+        }
+    };
+
+    format!("{} {}{}{}", times, bar, suffix, command)
+}
+
+/// Position (in seconds) under `column` of the line `render` draws with the same values;
+/// None when the column is off the bar or there is no bar. The first cell is the start
+/// and the last one the end.
+pub fn position_at(
+    column: usize,
+    width: usize,
+    elapsed: u64,
+    total: u64,
+    ahead: Option<BufferAhead>,
+    finish: &str,
+    command: &str,
+) -> Option<u64> {
+    let layout = layout(
+        width.saturating_sub(1),
+        elapsed,
+        total,
+        ahead,
+        finish,
+        command,
+    );
+    let (_, length) = layout.bar?;
+    // The bar follows the times, the buffer marker, the meter and a space
+    let marker = layout
+        .marker
+        .map_or(0, |(text, _)| 1 + text.chars().count());
+    let start = layout.times.len() + marker + 1 + METER_CELLS + 1;
+
+    if total == 0 || column < start || column >= start + length {
+        return None;
+    }
+
+    let cell = (column - start) as u128;
+    let last = (length as u128 - 1).max(1);
+    Some((cell * total as u128 / last).min(total as u128) as u64)
+}
+
+/// Draw `[` and `]` over the cells where a loop from `start` to `end` lies, on a bar of
+/// `length` cells painted in consecutive `parts`. Cells map to times as in [`position_at`].
+fn mark_section(parts: &mut [String], length: usize, total: u64, (start, end): (u64, Option<u64>)) {
+    if total == 0 || length == 0 {
+        return;
+    }
+
+    let last = length as u128 - 1;
+    let cell = |secs: u64| (secs.min(total) as u128 * last / total as u128) as usize;
+    let marks = [Some((cell(start), '[')), end.map(|end| (cell(end), ']'))];
+    let mut offset = 0;
+    for part in parts {
+        let mut cells: Vec<char> = part.chars().collect();
+        for &(at, mark) in marks.iter().flatten() {
+            if let Some(c) = at.checked_sub(offset).and_then(|at| cells.get_mut(at)) {
+                *c = mark;
+            }
+        }
+        offset += cells.len();
+        *part = cells.into_iter().collect();
+    }
+}
+
+/// Elapsed time as shown: never past the end of an episode of known length
+fn clamp_elapsed(elapsed: u64, total: u64) -> u64 {
+    match total {
+        0 => elapsed,
+        _ => elapsed.min(total),
+    }
+}
+
+/// Cells before, on and after the lit run that sweeps back and forth across a bar of
+/// `length` cells when the episode's length is unknown, one cell per second played
+fn marquee(length: usize, elapsed: u64) -> (usize, usize, usize) {
+    let lit = MARQUEE_CELLS.min(length);
+    let span = (length - lit) as u64;
+    if span == 0 {
+        return (0, lit, 0);
+    }
+
+    let step = elapsed % (2 * span);
+    let before = if step <= span { step } else { 2 * span - step };
+    let before = before as usize;
+    (before, lit, length - lit - before)
+}
+
+/// Split a bar of `length` cells into played, buffered-ahead and not-yet-downloaded cells
+fn segments(length: usize, elapsed: u64, buffered: u64, total: u64) -> (usize, usize, usize) {
+    if total == 0 {
+        return (0, 0, length);
+    }
+
+    let cells = |secs: u64| (secs.min(total) as u128 * length as u128 / total as u128) as usize;
+    let played = cells(elapsed);
+    let ahead = cells(buffered).saturating_sub(played);
+
+    (played, ahead, length - played - ahead)
+}
+
+/// Keep the end of `text` (what was typed last), marking cut text with an ellipsis
+fn truncate_start(text: &str, max: usize) -> String {
+    let len = text.chars().count();
+    if len <= max {
+        return text.to_string();
+    }
+    if max == 0 {
+        return String::new();
+    }
+
+    let tail: String = text.chars().skip(len - (max - 1)).collect();
+    format!("…{}", tail)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strip_ansi(text: &str) -> String {
+        let mut plain = String::new();
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            if c == '\u{1b}' {
+                chars.by_ref().find(|c| c.is_ascii_alphabetic());
+            } else {
+                plain.push(c);
+            }
+        }
+        plain
+    }
+
+    #[test]
+    fn segments_fill_the_bar() {
+        assert_eq!(segments(40, 900, 1800, 3600), (10, 10, 20));
+        assert_eq!(segments(40, 0, 3600, 3600), (0, 40, 0));
+        assert_eq!(segments(40, 3600, 3600, 3600), (40, 0, 0));
+    }
+
+    #[test]
+    fn buffer_behind_the_playhead_adds_nothing() {
+        assert_eq!(segments(40, 1800, 900, 3600), (20, 0, 20));
+    }
+
+    #[test]
+    fn unknown_duration_is_all_rest() {
+        assert_eq!(segments(40, 100, 200, 0), (0, 0, 40));
+    }
+
+    #[test]
+    fn clicks_map_across_the_bar() {
+        // "[10:00/01:00:00] ▁▁▁▁ " puts the bar at column 22; at 120 columns it is 40 cells long
+        let at = |column| position_at(column, 120, 600, 3600, None, "", "");
+        assert_eq!(at(21), None);
+        assert_eq!(at(22), Some(0));
+        assert_eq!(at(22 + 39), Some(3600));
+        assert_eq!(at(22 + 13), Some(1200));
+        assert_eq!(at(22 + 40), None);
+    }
+
+    #[test]
+    fn clicks_follow_the_rendered_bar() {
+        // At 60 columns the bar shrinks; every column mapped must hold a bar cell
+        let finish = "ends 15:42, all 18:10";
+        for width in [60, 100] {
+            let line = render(
+                width,
+                600,
+                3600,
+                Buffer::default(),
+                Meter::Idle,
+                None,
+                finish,
+                "vol",
+            );
+            let plain: Vec<char> = strip_ansi(&line).chars().collect();
+            for (column, cell) in plain.iter().enumerate().take(width) {
+                if position_at(column, width, 600, 3600, None, finish, "vol").is_some() {
+                    assert!(matches!(cell, '━' | '─'), "column {}", column);
+                }
+            }
+        }
+        assert_eq!(position_at(5, 30, 600, 3600, None, "", ""), None);
+    }
+
+    #[test]
+    fn buffer_marker_warns_when_running_low() {
+        let marker = |ahead| buffer_marker(Some(ahead));
+        assert_eq!(
+            marker(BufferAhead::Secs(42)),
+            Some(("[+42s]".to_string(), false))
+        );
+        assert_eq!(
+            marker(BufferAhead::Secs(3)),
+            Some(("[!3s]".to_string(), true))
+        );
+        assert_eq!(
+            marker(BufferAhead::Full),
+            Some((format!("[{}]", t(Msg::BufferFull)), false))
+        );
+        assert_eq!(
+            marker(BufferAhead::Held(300)),
+            Some((format!("[+300s {}]", t(Msg::BufferHeld)), false))
+        );
+        assert_eq!(buffer_marker(None), None);
+    }
+
+    #[test]
+    fn clicks_skip_the_buffer_marker() {
+        // "[10:00/01:00:00] [+42s] ▁▁▁▁ " puts the bar at column 29
+        let ahead = Some(BufferAhead::Secs(42));
+        let buffer = Buffer { until: None, ahead };
+        let line = strip_ansi(&render(120, 600, 3600, buffer, Meter::Idle, None, "", ""));
+        assert!(line.starts_with("[10:00/01:00:00] [+42s] "));
+        assert_eq!(position_at(28, 120, 600, 3600, ahead, "", ""), None);
+        assert_eq!(position_at(29, 120, 600, 3600, ahead, "", ""), Some(0));
+    }
+
+    #[test]
+    fn finish_times_are_dropped_before_the_bar() {
+        let finish = "ends 15:42, all 18:10";
+        assert!(strip_ansi(&render(
+            100,
+            600,
+            3600,
+            Buffer::default(),
+            Meter::Idle,
+            None,
+            finish,
+            ""
+        ))
+        .contains(finish));
+        assert!(!strip_ansi(&render(
+            50,
+            600,
+            3600,
+            Buffer::default(),
+            Meter::Idle,
+            None,
+            finish,
+            ""
+        ))
+        .contains(finish));
+    }
+
+    #[test]
+    fn finish_times_follow_the_clock() {
+        let now = chrono::NaiveDate::from_ymd_opt(2024, 5, 1)
+            .unwrap()
+            .and_hms_opt(14, 50, 0)
+            .unwrap();
+        let all = |track: &str, list: &str| tf(Msg::ProgressEndsAll, &[&track, &list]);
+
+        assert_eq!(
+            finish_times(now, Some(52 * 60), Some(3600)),
+            all("15:42", "16:42")
+        );
+        assert_eq!(
+            finish_times(now, Some(600), Some(0)),
+            tf(Msg::ProgressEnds, &[&"15:00"])
+        );
+        assert_eq!(finish_times(now, None, Some(3600)), all("—", "—"));
+        assert_eq!(finish_times(now, Some(600), None), all("15:00", "—"));
+    }
+
+    #[test]
+    fn loop_bounds_are_bracketed_on_the_bar() {
+        let whole = strip_ansi(&render(
+            100,
+            0,
+            3600,
+            Buffer::default(),
+            Meter::Idle,
+            Some((0, Some(3600))),
+            "",
+            "",
+        ));
+        assert!(whole.starts_with("[00:00/01:00:00] ▁▁▁▁ ["));
+        assert!(whole.contains("] 0% |"));
+
+        for until in [None, Some(1800)] {
+            let buffer = Buffer { until, ahead: None };
+            let start = strip_ansi(&render(
+                100,
+                0,
+                3600,
+                buffer,
+                Meter::Idle,
+                Some((1800, None)),
+                "",
+                "",
+            ));
+            assert_eq!(start.matches('[').count(), 2);
+            assert_eq!(start.matches(']').count(), 1);
+        }
+    }
+
+    #[test]
+    fn elapsed_past_the_end_is_clamped() {
+        assert_eq!(segments(40, 5000, 5000, 3600), (40, 0, 0));
+    }
+
+    #[test]
+    fn bar_runs_from_empty_to_full() {
+        let line = |elapsed| {
+            strip_ansi(&render(
+                100,
+                elapsed,
+                3600,
+                Buffer::default(),
+                Meter::Idle,
+                None,
+                "",
+                "",
+            ))
+        };
+
+        let start = line(0);
+        assert!(start.starts_with("[00:00/01:00:00] "));
+        assert!(start.contains(" 0% | -01:00:00 > "));
+        assert!(!start.contains('━'));
+
+        let end = line(3600);
+        assert!(end.contains(" 100% | -00:00 > "));
+        assert!(!end.contains('─'));
+
+        // A stall can leave the position past the feed's duration
+        assert_eq!(line(5000), end);
+    }
+
+    #[test]
+    fn unknown_duration_sweeps_the_bar() {
+        let line = strip_ansi(&render(
+            100,
+            600,
+            0,
+            Buffer::default(),
+            Meter::Idle,
+            None,
+            "",
+            "",
+        ));
+        assert!(line.starts_with("[10:00/--:--] "));
+        assert!(line.contains(" --% | --:-- > "));
+        assert_eq!(line.matches('━').count(), MARQUEE_CELLS);
+
+        let compact = strip_ansi(&render(
+            30,
+            600,
+            0,
+            Buffer::default(),
+            Meter::Idle,
+            None,
+            "",
+            "vol",
+        ));
+        assert_eq!(compact, "[10:00/--:--] > vol");
+    }
+
+    #[test]
+    fn meter_lights_a_cell_per_12_db() {
+        assert_eq!(meter_cells(0.0), 0);
+        assert_eq!(meter_cells(0.001), 0);
+        assert_eq!(meter_cells(0.01), 1);
+        assert_eq!(meter_cells(0.1), 3);
+        assert_eq!(meter_cells(1.0), 4);
+        assert!(!audible(0.002));
+        assert!(audible(0.05));
+    }
+
+    #[test]
+    fn meter_sits_between_the_times_and_the_bar() {
+        let line = |meter| {
+            strip_ansi(&render(
+                100,
+                600,
+                3600,
+                Buffer::default(),
+                meter,
+                None,
+                "",
+                "",
+            ))
+        };
+        assert!(line(Meter::Level(0.1)).starts_with("[10:00/01:00:00] ▂▄▆▁ "));
+        assert!(line(Meter::Level(1.0)).starts_with("[10:00/01:00:00] ▂▄▆█ "));
+        assert!(line(Meter::Idle).starts_with("[10:00/01:00:00] ▁▁▁▁ "));
+        // Marked without color too
+        assert!(line(Meter::Silent).starts_with("[10:00/01:00:00] !▁▁▁ "));
+    }
+
+    #[test]
+    fn marquee_bounces_between_the_ends() {
+        assert_eq!(marquee(40, 0), (0, 4, 36));
+        assert_eq!(marquee(40, 36), (36, 4, 0));
+        assert_eq!(marquee(40, 40), (32, 4, 4));
+        assert_eq!(marquee(40, 72), (0, 4, 36));
+        assert_eq!(marquee(3, 7), (0, 3, 0));
+    }
+}
@@ -0,0 +1,392 @@
+//! Where the play loop sends audio: the local player or, with `play --cast`, a Chromecast
+//! Both answer the same calls, so the loop doesn't care which one it drives.
+// Without casting there is only the local player, so its `if let`s always match
+#![cfg_attr(not(feature = "cast"), allow(irrefutable_let_patterns))]
+
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::audio::{AudioPlayer, PlaybackSource};
+#[cfg(feature = "cast")]
+use crate::cast;
+use crate::player::{BufferAhead, Starting, StreamInfo};
+
+pub enum Renderer {
+    /// The local player, or the one handed to [`play_with`](crate::play::play_with)
+    Local(Box<dyn AudioPlayer>),
+    #[cfg(feature = "cast")]
+    Cast(cast::Remote),
+}
+
+impl Renderer {
+    /// Start streaming `url`; `interrupted` can give it up and `starting` hears how far it
+    /// got while it buffers here, the cast device buffers on its own
+    pub fn play(
+        &self,
+        url: &str,
+        interrupted: &dyn Fn() -> bool,
+        starting: &dyn Fn(Starting),
+    ) -> Result<()> {
+        match self {
+            Renderer::Local(player) => {
+                player.play_interruptible(PlaybackSource::Stream(url), interrupted, starting)?
+            }
+            #[cfg(feature = "cast")]
+            Renderer::Cast(remote) => remote.play(url),
+        }
+        Ok(())
+    }
+
+    pub fn stop(&self) {
+        match self {
+            Renderer::Local(player) => player.stop(),
+            #[cfg(feature = "cast")]
+            Renderer::Cast(remote) => remote.stop(),
+        }
+    }
+
+    pub fn pause(&self) {
+        match self {
+            Renderer::Local(player) => player.pause(),
+            #[cfg(feature = "cast")]
+            Renderer::Cast(remote) => remote.pause(),
+        }
+    }
+
+    pub fn resume(&self) {
+        match self {
+            Renderer::Local(player) => player.resume(),
+            #[cfg(feature = "cast")]
+            Renderer::Cast(remote) => remote.resume(),
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        match self {
+            Renderer::Local(player) => player.is_paused(),
+            #[cfg(feature = "cast")]
+            Renderer::Cast(remote) => remote.is_paused(),
+        }
+    }
+
+    pub fn volume(&self) -> f32 {
+        match self {
+            Renderer::Local(player) => player.volume(),
+            #[cfg(feature = "cast")]
+            Renderer::Cast(remote) => remote.volume(),
+        }
+    }
+
+    pub fn set_volume(&self, volume: f32) {
+        match self {
+            Renderer::Local(player) => player.set_volume(volume),
+            #[cfg(feature = "cast")]
+            Renderer::Cast(remote) => remote.set_volume(volume),
+        }
+    }
+
+    /// Mute, or go back to the volume from before; true when now muted
+    pub fn toggle_mute(&self) -> bool {
+        match self {
+            Renderer::Local(player) => player.toggle_mute(),
+            #[cfg(feature = "cast")]
+            Renderer::Cast(remote) => remote.toggle_mute(),
+        }
+    }
+
+    pub fn is_muted(&self) -> bool {
+        match self {
+            Renderer::Local(player) => player.is_muted(),
+            #[cfg(feature = "cast")]
+            Renderer::Cast(remote) => remote.is_muted(),
+        }
+    }
+
+    /// Left/right balance; a cast device keeps its own
+    pub fn set_balance(&self, balance: f32) {
+        if let Renderer::Local(player) = self {
+            player.set_balance(balance);
+        }
+    }
+
+    pub fn balance(&self) -> f32 {
+        match self {
+            Renderer::Local(player) => player.balance(),
+            #[cfg(feature = "cast")]
+            Renderer::Cast(_) => 0.0,
+        }
+    }
+
+    /// RMS level of what plays lately, 0.0 to 1.0; None on a cast device, which plays it out
+    /// of reach
+    pub fn level(&self) -> Option<f32> {
+        match self {
+            Renderer::Local(player) => player.level(),
+            #[cfg(feature = "cast")]
+            Renderer::Cast(_) => None,
+        }
+    }
+
+    /// Seconds of leading silence skipped from the episode playing; the cast device plays
+    /// it as it is
+    pub fn trimmed_secs(&self) -> Option<f32> {
+        match self {
+            Renderer::Local(player) => player.trimmed_secs(),
+            #[cfg(feature = "cast")]
+            Renderer::Cast(_) => None,
+        }
+    }
+
+    /// Codec, format and bitrate of the audio; None on a cast device, which decodes it itself
+    pub fn stream_info(&self) -> Option<StreamInfo> {
+        match self {
+            Renderer::Local(player) => player.stream_info(),
+            #[cfg(feature = "cast")]
+            Renderer::Cast(_) => None,
+        }
+    }
+
+    pub fn set_start_volume(&self, volume: f32) {
+        match self {
+            Renderer::Local(player) => player.set_start_volume(volume),
+            #[cfg(feature = "cast")]
+            Renderer::Cast(remote) => remote.set_start_volume(volume),
+        }
+    }
+
+    /// Where streams are spooled to disk; casting devices stream for themselves
+    pub fn set_spool_dir(&self, dir: Option<PathBuf>) {
+        if let Renderer::Local(player) = self {
+            player.set_spool_dir(dir);
+        }
+    }
+
+    /// Jump to `secs`; false while the track can't seek yet
+    pub fn seek(&self, secs: u64) -> bool {
+        match self {
+            Renderer::Local(player) => player.seek(secs),
+            #[cfg(feature = "cast")]
+            Renderer::Cast(remote) => {
+                remote.seek(secs);
+                true
+            }
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        match self {
+            Renderer::Local(player) => player.is_finished(),
+            #[cfg(feature = "cast")]
+            Renderer::Cast(remote) => remote.is_finished(),
+        }
+    }
+
+    /// Position in the episode, for the progress line and the history
+    pub fn elapsed_seconds(&self) -> u64 {
+        match self {
+            Renderer::Local(player) => player.elapsed_seconds(),
+            #[cfg(feature = "cast")]
+            Renderer::Cast(remote) => remote.position_seconds(),
+        }
+    }
+
+    /// Audio actually heard, None until playback has started
+    pub fn played_seconds(&self) -> Option<u64> {
+        match self {
+            Renderer::Local(player) => player.played_seconds(),
+            #[cfg(feature = "cast")]
+            Renderer::Cast(remote) => Some(remote.position_seconds()),
+        }
+    }
+
+    /// How far the download reaches; the device doesn't say, so None when casting
+    pub fn buffered_seconds(&self, duration_secs: u64) -> Option<u64> {
+        match self {
+            Renderer::Local(player) => player.buffered_seconds(duration_secs),
+            #[cfg(feature = "cast")]
+            Renderer::Cast(_) => None,
+        }
+    }
+
+    /// Audio ready past the playhead; the device doesn't say, so None when casting
+    pub fn buffered_ahead_secs(&self) -> Option<BufferAhead> {
+        match self {
+            Renderer::Local(player) => player.buffered_ahead_secs(),
+            #[cfg(feature = "cast")]
+            Renderer::Cast(_) => None,
+        }
+    }
+
+    /// How long playback has been starved of stream data; a device buffers on its own
+    pub fn stalled_for(&self) -> Option<Duration> {
+        match self {
+            Renderer::Local(player) => player.stalled_for(),
+            #[cfg(feature = "cast")]
+            Renderer::Cast(_) => None,
+        }
+    }
+
+    /// Pause while a starved stream refills, with the seconds buffered so far; a device
+    /// buffers on its own
+    pub fn poll_underrun(&self) -> Option<u64> {
+        match self {
+            Renderer::Local(player) => player.poll_underrun(),
+            #[cfg(feature = "cast")]
+            Renderer::Cast(_) => None,
+        }
+    }
+
+    /// Most the stream buffers before it starts; a device decides for itself
+    pub fn set_initial_buffer_kb(&self, kb: u64) {
+        if let Renderer::Local(player) = self {
+            player.set_initial_buffer_kb(kb);
+        }
+    }
+
+    pub fn set_rebuffer_secs(&self, secs: u64) {
+        if let Renderer::Local(player) = self {
+            player.set_rebuffer_secs(secs);
+        }
+    }
+
+    /// Why the track stopped playing, once; a device reports its own failures as a lost device
+    pub fn take_error(&self) -> Option<anyhow::Error> {
+        match self {
+            Renderer::Local(player) => player.take_error().map(Into::into),
+            #[cfg(feature = "cast")]
+            Renderer::Cast(_) => None,
+        }
+    }
+
+    /// Reconnect under way and the most there will be; a device reconnects on its own
+    pub fn reconnecting(&self) -> Option<(u64, u64)> {
+        match self {
+            Renderer::Local(player) => player.reconnecting(),
+            #[cfg(feature = "cast")]
+            Renderer::Cast(_) => None,
+        }
+    }
+
+    /// Reconnects since the stream started or the last retry; a device reconnects on its own
+    pub fn reconnects(&self) -> u64 {
+        match self {
+            Renderer::Local(player) => player.reconnects(),
+            #[cfg(feature = "cast")]
+            Renderer::Cast(_) => 0,
+        }
+    }
+
+    pub fn set_max_reconnects(&self, max: u64) {
+        if let Renderer::Local(player) = self {
+            player.set_max_reconnects(max);
+        }
+    }
+
+    /// How far a stream downloads ahead of playback; a device decides for itself
+    pub fn set_max_readahead_mb(&self, mb: Option<u64>) {
+        if let Renderer::Local(player) = self {
+            player.set_max_readahead_mb(mb);
+        }
+    }
+
+    /// Ramp the volume around pauses; the cast device pauses on its own
+    pub fn set_fade_pauses(&self, fade: bool) {
+        if let Renderer::Local(player) = self {
+            player.set_fade_pauses(fade);
+        }
+    }
+
+    /// Skip silence at the start of the episodes played here; cast devices play them whole
+    pub fn set_trim_silence(&self, below_db: Option<f32>) {
+        if let Renderer::Local(player) = self {
+            player.set_trim_silence(below_db);
+        }
+    }
+
+    /// Fetch the start of `url` ahead of playing it; the cast device buffers on its own
+    pub fn prefetch(&self, url: &str) {
+        if let Renderer::Local(player) = self {
+            player.prefetch(url);
+        }
+    }
+
+    pub fn cancel_prefetch(&self) {
+        if let Renderer::Local(player) = self {
+            player.cancel_prefetch();
+        }
+    }
+
+    /// Try a stalled stream again
+    pub fn reconnect(&self) {
+        match self {
+            Renderer::Local(player) => player.reconnect(),
+            #[cfg(feature = "cast")]
+            Renderer::Cast(_) => {}
+        }
+    }
+
+    /// Whether `play_file` can play a download; a cast device streams the URL itself
+    pub fn plays_files(&self) -> bool {
+        matches!(self, Renderer::Local(_))
+    }
+
+    /// Play a downloaded copy, starting `from_secs` in; only the local player can
+    pub fn play_file(&self, path: &Path, from_secs: u64) -> Result<()> {
+        match self {
+            Renderer::Local(player) => player.play(PlaybackSource::File(path, from_secs))?,
+            #[cfg(feature = "cast")]
+            Renderer::Cast(_) => {}
+        }
+        Ok(())
+    }
+
+    /// The local output device went away; a cast device going away is [`lost_device`]
+    ///
+    /// [`lost_device`]: Renderer::lost_device
+    pub fn output_lost(&self) -> bool {
+        match self {
+            Renderer::Local(player) => player.output_lost(),
+            #[cfg(feature = "cast")]
+            Renderer::Cast(_) => false,
+        }
+    }
+
+    /// Carry on on the default output device; a cast device has only the one
+    pub fn reopen_output(&mut self) -> Result<()> {
+        match self {
+            Renderer::Local(player) => player.reopen_output()?,
+            #[cfg(feature = "cast")]
+            Renderer::Cast(_) => {}
+        }
+        Ok(())
+    }
+
+    /// Save the stream playing to `path` as well; false when there is none here to save
+    pub fn record(&self, path: &Path) -> Result<bool> {
+        match self {
+            Renderer::Local(player) => Ok(player.record(path)?),
+            #[cfg(feature = "cast")]
+            Renderer::Cast(_) => Ok(false),
+        }
+    }
+
+    /// Write what has arrived of the stream to `path` for a download to carry on from; the
+    /// bytes there, 0 when nothing streams here
+    pub fn save_head(&self, path: &Path) -> Result<u64> {
+        match self {
+            Renderer::Local(player) => Ok(player.save_head(path)?),
+            #[cfg(feature = "cast")]
+            Renderer::Cast(_) => Ok(0),
+        }
+    }
+
+    /// Name of the cast device that stopped answering, if that is what happened
+    pub fn lost_device(&self) -> Option<&str> {
+        match self {
+            Renderer::Local(_) => None,
+            #[cfg(feature = "cast")]
+            Renderer::Cast(remote) => remote.is_lost().then_some(remote.device.name.as_str()),
+        }
+    }
+}
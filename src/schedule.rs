@@ -0,0 +1,142 @@
+//! Scheduled start (`mfp play --at 09:00` / `--in 25m`)
+
+use chrono::{Local, NaiveDateTime, NaiveTime};
+use std::time::Duration;
+
+use crate::i18n::{tf, Msg};
+
+/// Parse a wall-clock time such as `09:00` or `9:30`
+pub fn parse_clock(input: &str) -> Result<NaiveTime, String> {
+    let (hours, minutes) = input
+        .trim()
+        .split_once(':')
+        .ok_or_else(|| tf(Msg::InvalidClock, &[&input]))?;
+
+    hours
+        .parse()
+        .ok()
+        .zip(minutes.parse().ok())
+        .filter(|_| minutes.len() == 2)
+        .and_then(|(h, m)| NaiveTime::from_hms_opt(h, m, 0))
+        .ok_or_else(|| tf(Msg::InvalidClock, &[&input]))
+}
+
+/// Parse a delay such as `25m`, `1h30m`, `90s` or `2h`. A bare number is minutes.
+pub fn parse_delay(input: &str) -> Result<Duration, String> {
+    let invalid = || tf(Msg::InvalidDelay, &[&input]);
+    let input = input.trim();
+
+    if input.is_empty() {
+        return Err(invalid());
+    }
+    if let Ok(minutes) = input.parse::<u64>() {
+        return Ok(Duration::from_secs(minutes * 60));
+    }
+
+    let mut total = 0u64;
+    let mut number = String::new();
+
+    for c in input.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+
+        let unit = match c.to_ascii_lowercase() {
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => return Err(invalid()),
+        };
+        let value: u64 = number.parse().map_err(|_| invalid())?;
+        total += value * unit;
+        number.clear();
+    }
+
+    if !number.is_empty() {
+        return Err(invalid());
+    }
+
+    Ok(Duration::from_secs(total))
+}
+
+/// Time left until the next occurrence of `time`: today, or tomorrow if it has passed
+pub fn until(time: NaiveTime, now: NaiveDateTime) -> Duration {
+    let mut target = now.date().and_time(time);
+    if target <= now {
+        target += chrono::Duration::days(1);
+    }
+
+    (target - now).to_std().unwrap_or_default()
+}
+
+/// How long the sleep timer takes to fade the volume out before it stops playback
+pub const SLEEP_FADE: Duration = Duration::from_secs(30);
+
+/// Share of the volume kept with `left` to go on the sleep timer: all of it until the
+/// last `SLEEP_FADE`, then down to silence
+pub fn sleep_fade(left: Duration) -> f32 {
+    (left.as_secs_f32() / SLEEP_FADE.as_secs_f32()).min(1.0)
+}
+
+/// Time left until `time` on the local clock
+pub fn until_local(time: NaiveTime) -> Duration {
+    until(time, Local::now().naive_local())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn at(h: u32, m: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 5, 1)
+            .unwrap()
+            .and_hms_opt(h, m, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn parses_clock_times() {
+        assert_eq!(
+            parse_clock("09:00"),
+            Ok(NaiveTime::from_hms_opt(9, 0, 0).unwrap())
+        );
+        assert_eq!(
+            parse_clock("9:30"),
+            Ok(NaiveTime::from_hms_opt(9, 30, 0).unwrap())
+        );
+        assert!(parse_clock("24:00").is_err());
+        assert!(parse_clock("9:5").is_err());
+        assert!(parse_clock("nine").is_err());
+    }
+
+    #[test]
+    fn parses_delays() {
+        assert_eq!(parse_delay("25m"), Ok(Duration::from_secs(25 * 60)));
+        assert_eq!(parse_delay("1h30m"), Ok(Duration::from_secs(90 * 60)));
+        assert_eq!(parse_delay("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_delay("2H"), Ok(Duration::from_secs(7200)));
+        assert_eq!(parse_delay("10"), Ok(Duration::from_secs(600)));
+        assert!(parse_delay("").is_err());
+        assert!(parse_delay("m").is_err());
+        assert!(parse_delay("10x").is_err());
+        assert!(parse_delay("1h30").is_err());
+    }
+
+    #[test]
+    fn waits_until_tomorrow_once_the_time_has_passed() {
+        let nine = NaiveTime::from_hms_opt(9, 0, 0).unwrap();
+        assert_eq!(until(nine, at(8, 30)), Duration::from_secs(30 * 60));
+        assert_eq!(until(nine, at(9, 0)), Duration::from_secs(24 * 3600));
+        assert_eq!(until(nine, at(10, 0)), Duration::from_secs(23 * 3600));
+    }
+
+    #[test]
+    fn sleep_fades_out_over_the_last_seconds() {
+        assert_eq!(sleep_fade(Duration::from_secs(600)), 1.0);
+        assert_eq!(sleep_fade(SLEEP_FADE), 1.0);
+        assert_eq!(sleep_fade(SLEEP_FADE / 2), 0.5);
+        assert_eq!(sleep_fade(Duration::ZERO), 0.0);
+    }
+}
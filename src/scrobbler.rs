@@ -0,0 +1,276 @@
+//! Last.fm / ListenBrainz scrobbling
+//! Requests run on short-lived threads so the play loop never waits on the network.
+//! Scrobbles that fail are queued on disk and retried on the next run.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::thread;
+
+use crate::config::{ScrobbleConfig, ScrobbleService};
+use crate::history::unix_now;
+use crate::http;
+use crate::paths;
+use crate::session::{SessionEvent, SessionListener};
+
+const ARTIST: &str = "Music For Programming";
+const ALBUM: &str = "Music For Programming";
+const LASTFM_API_URL: &str = "https://ws.audioscrobbler.com/2.0/";
+const LISTENBRAINZ_API_URL: &str = "https://api.listenbrainz.org/1";
+
+/// Listens shorter than this are never scrobbled
+const MIN_TRACK_SECS: u64 = 30;
+/// A listen counts once it reaches half the track or this many seconds
+const SCROBBLE_AFTER_SECS: u64 = 240;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Scrobble {
+    title: String,
+    /// Unix timestamp of the track start
+    timestamp: u64,
+}
+
+#[derive(Clone)]
+pub struct Scrobbler {
+    service: ScrobbleService,
+    config: ScrobbleConfig,
+    started_at: Option<u64>,
+}
+
+impl Scrobbler {
+    /// Returns None when scrobbling is not configured
+    pub fn new(config: &ScrobbleConfig) -> Option<Self> {
+        let service = config.service?;
+
+        Some(Self {
+            service,
+            config: config.clone(),
+            started_at: None,
+        })
+    }
+
+    /// Check the credentials and return the account name they belong to
+    pub fn test(&self) -> Result<String> {
+        match self.service {
+            ScrobbleService::Listenbrainz => {
+                let request = http::client()?
+                    .get(format!("{}/validate-token", LISTENBRAINZ_API_URL))
+                    .header("Authorization", format!("Token {}", self.config.token));
+                let response = http::block_on(async {
+                    let response = request
+                        .send()
+                        .await
+                        .context("No se pudo conectar a ListenBrainz")?;
+                    json_value(response).await
+                })?;
+
+                if response["valid"].as_bool() != Some(true) {
+                    bail!("Invalid ListenBrainz token");
+                }
+                Ok(response["user_name"].as_str().unwrap_or("").to_string())
+            }
+            ScrobbleService::Lastfm => {
+                let response = self.lastfm_call("user.getInfo", &[])?;
+                Ok(response["user"]["name"].as_str().unwrap_or("").to_string())
+            }
+        }
+    }
+
+    /// Retry every queued scrobble, keeping the ones that still fail
+    pub fn flush_queue(&self) -> Result<usize> {
+        let path = queue_path()?;
+        if !path.exists() {
+            return Ok(0);
+        }
+
+        let content = fs::read_to_string(&path).context("Failed to read scrobble queue")?;
+        fs::remove_file(&path).context("Failed to clear scrobble queue")?;
+
+        let mut sent = 0;
+        for scrobble in content
+            .lines()
+            .filter_map(|line| serde_json::from_str::<Scrobble>(line).ok())
+        {
+            if self.scrobble(&scrobble).is_ok() {
+                sent += 1;
+            } else {
+                enqueue(&scrobble)?;
+            }
+        }
+
+        Ok(sent)
+    }
+
+    fn now_playing(&self, title: &str) -> Result<()> {
+        match self.service {
+            ScrobbleService::Listenbrainz => self.listenbrainz_submit(json!({
+                "listen_type": "playing_now",
+                "payload": [{ "track_metadata": track_metadata(title) }],
+            })),
+            ScrobbleService::Lastfm => self
+                .lastfm_call(
+                    "track.updateNowPlaying",
+                    &[("artist", ARTIST), ("track", title), ("album", ALBUM)],
+                )
+                .map(|_| ()),
+        }
+    }
+
+    fn scrobble(&self, scrobble: &Scrobble) -> Result<()> {
+        match self.service {
+            ScrobbleService::Listenbrainz => self.listenbrainz_submit(json!({
+                "listen_type": "single",
+                "payload": [{
+                    "listened_at": scrobble.timestamp,
+                    "track_metadata": track_metadata(&scrobble.title),
+                }],
+            })),
+            ScrobbleService::Lastfm => {
+                let timestamp = scrobble.timestamp.to_string();
+                self.lastfm_call(
+                    "track.scrobble",
+                    &[
+                        ("artist", ARTIST),
+                        ("track", &scrobble.title),
+                        ("album", ALBUM),
+                        ("timestamp", &timestamp),
+                    ],
+                )
+                .map(|_| ())
+            }
+        }
+    }
+
+    fn listenbrainz_submit(&self, body: Value) -> Result<()> {
+        let request = http::client()?
+            .post(format!("{}/submit-listens", LISTENBRAINZ_API_URL))
+            .header("Authorization", format!("Token {}", self.config.token))
+            .header("Content-Type", "application/json")
+            .body(body.to_string());
+        let response =
+            http::block_on(request.send()).context("No se pudo conectar a ListenBrainz")?;
+
+        if !response.status().is_success() {
+            bail!("Error HTTP: {}", response.status());
+        }
+        Ok(())
+    }
+
+    /// Signed Last.fm API call
+    fn lastfm_call(&self, method: &str, params: &[(&str, &str)]) -> Result<Value> {
+        let mut params: Vec<(&str, &str)> = params.to_vec();
+        params.push(("method", method));
+        params.push(("api_key", &self.config.api_key));
+        params.push(("sk", &self.config.session_key));
+        params.sort();
+
+        let mut signature_base: String =
+            params.iter().map(|(k, v)| format!("{}{}", k, v)).collect();
+        signature_base.push_str(&self.config.api_secret);
+        let signature = format!("{:x}", md5::compute(signature_base));
+
+        params.push(("api_sig", &signature));
+        params.push(("format", "json"));
+
+        let request = http::client()?.post(LASTFM_API_URL).form(&params);
+        let response = http::block_on(async {
+            let response = request
+                .send()
+                .await
+                .context("No se pudo conectar a Last.fm")?;
+            json_value(response).await
+        })?;
+
+        if response.get("error").is_some() {
+            bail!(
+                "Last.fm: {}",
+                response["message"].as_str().unwrap_or("unknown error")
+            );
+        }
+        Ok(response)
+    }
+}
+
+impl SessionListener for Scrobbler {
+    fn on_event(&mut self, event: &SessionEvent) {
+        match event {
+            SessionEvent::TrackStarted { title, .. } => {
+                self.started_at = Some(unix_now());
+                let scrobbler = self.clone();
+                let title = title.clone();
+                thread::spawn(move || {
+                    let _ = scrobbler.now_playing(&title);
+                });
+            }
+            SessionEvent::TrackEnded {
+                title,
+                duration_secs,
+                listened_secs,
+            } => {
+                let started_at = self.started_at.take();
+                if !should_scrobble(*listened_secs, *duration_secs) {
+                    return;
+                }
+                let scrobble = Scrobble {
+                    title: title.clone(),
+                    timestamp: started_at.unwrap_or_else(unix_now),
+                };
+                let scrobbler = self.clone();
+                thread::spawn(move || {
+                    if scrobbler.scrobble(&scrobble).is_err() {
+                        let _ = enqueue(&scrobble);
+                    }
+                });
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Last.fm rule: the track must be longer than 30 seconds and played for half
+/// its length or 4 minutes, whichever comes first
+fn should_scrobble(listened_secs: u64, duration_secs: u64) -> bool {
+    if duration_secs > 0 && duration_secs <= MIN_TRACK_SECS {
+        return false;
+    }
+    let threshold = if duration_secs > 0 {
+        (duration_secs / 2).min(SCROBBLE_AFTER_SECS)
+    } else {
+        SCROBBLE_AFTER_SECS
+    };
+    listened_secs >= threshold
+}
+
+fn track_metadata(title: &str) -> Value {
+    json!({
+        "artist_name": ARTIST,
+        "track_name": title,
+        "release_name": ALBUM,
+    })
+}
+
+fn queue_path() -> Result<PathBuf> {
+    let data_dir = paths::data_dir()?;
+    fs::create_dir_all(&data_dir).context("Failed to create data directory")?;
+
+    Ok(paths::scrobble_queue_file()?)
+}
+
+fn enqueue(scrobble: &Scrobble) -> Result<()> {
+    let line = serde_json::to_string(scrobble).context("Failed to serialize scrobble")?;
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(queue_path()?)
+        .context("Failed to open scrobble queue")?;
+    writeln!(file, "{}", line).context("Failed to write scrobble queue")
+}
+
+async fn json_value(response: reqwest::Response) -> Result<Value> {
+    let text = response.text().await.context("Failed to read response")?;
+    serde_json::from_str(&text).context("Failed to parse response")
+}
@@ -0,0 +1,67 @@
+//! Playback session events
+//! The play loop emits events here and every registered listener reacts to them
+
+/// Fraction of an episode that must be heard for it to count as completed
+const COMPLETION_RATIO: f64 = 0.9;
+
+/// Something that happened during playback
+#[derive(Debug, Clone)]
+pub enum SessionEvent {
+    TrackStarted {
+        title: String,
+        duration_secs: u64,
+        /// Audio URL being played
+        url: String,
+    },
+    TrackEnded {
+        title: String,
+        duration_secs: u64,
+        listened_secs: u64,
+    },
+    Paused {
+        elapsed_secs: u64,
+    },
+    Resumed {
+        elapsed_secs: u64,
+    },
+    /// Volume or shuffle changed; also sent once when playback starts
+    SettingsChanged {
+        /// Volume in percent (100 leaves the audio unchanged)
+        volume_percent: u16,
+        shuffle: bool,
+    },
+    /// The user quit the player
+    Stopped,
+}
+
+/// Receiver of session events (history, integrations, ...)
+pub trait SessionListener {
+    fn on_event(&mut self, event: &SessionEvent);
+}
+
+/// Dispatches events to every registered listener
+#[derive(Default)]
+pub struct Session {
+    listeners: Vec<Box<dyn SessionListener>>,
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_listener(&mut self, listener: Box<dyn SessionListener>) {
+        self.listeners.push(listener);
+    }
+
+    pub fn emit(&mut self, event: SessionEvent) {
+        for listener in &mut self.listeners {
+            listener.on_event(&event);
+        }
+    }
+}
+
+/// Whether `listened_secs` covers enough of the episode to call it finished
+pub fn is_completed(listened_secs: u64, duration_secs: u64) -> bool {
+    duration_secs > 0 && listened_secs as f64 >= duration_secs as f64 * COMPLETION_RATIO
+}
@@ -0,0 +1,54 @@
+//! SIGTERM and SIGHUP (systemd stopping mfp, the terminal closing)
+//! While the play loop runs, a signal only raises a flag that the loop checks on every tick,
+//! so it leaves through the same path as `q` and everything is saved. Anywhere else, or on a
+//! second signal while the first is being handled, mfp exits at once as it always did.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::errors::EXIT_SIGNAL;
+
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+static WATCHED: AtomicBool = AtomicBool::new(false);
+
+/// Install the handlers; call once at startup
+#[cfg(unix)]
+pub fn install() -> std::io::Result<()> {
+    use signal_hook::consts::{SIGHUP, SIGTERM};
+
+    for signal in [SIGHUP, SIGTERM] {
+        // SAFETY: the handler only touches atomics and calls _exit, both async-signal-safe
+        unsafe {
+            signal_hook::low_level::register(signal, || {
+                if !WATCHED.load(Ordering::SeqCst) || REQUESTED.swap(true, Ordering::SeqCst) {
+                    signal_hook::low_level::exit(EXIT_SIGNAL);
+                }
+            })?;
+        }
+    }
+    Ok(())
+}
+
+/// Windows has no SIGTERM/SIGHUP to catch; closing the console ends mfp as before
+#[cfg(not(unix))]
+pub fn install() -> std::io::Result<()> {
+    Ok(())
+}
+
+/// True once a signal asked the play loop to stop
+pub fn requested() -> bool {
+    REQUESTED.load(Ordering::SeqCst)
+}
+
+/// Signals wait for the play loop until the guard is dropped
+pub fn watch() -> Watch {
+    WATCHED.store(true, Ordering::SeqCst);
+    Watch
+}
+
+pub struct Watch;
+
+impl Drop for Watch {
+    fn drop(&mut self) {
+        WATCHED.store(false, Ordering::SeqCst);
+    }
+}
@@ -0,0 +1,265 @@
+//! Player state kept between runs
+//! Stored as JSON next to the favorites; every change is saved immediately. Like favorites,
+//! entries are kept per station so equal titles in two feeds stay separate.
+
+use serde::{Deserialize, Serialize};
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::errors::StorageError;
+use crate::paths;
+use crate::station;
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct State {
+    /// Volume last chosen in the player, in percent; never the silence of a mute
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    volume: Option<u16>,
+    /// Volume offsets in percentage points, by station and episode title
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    volume_offsets: BTreeMap<String, BTreeMap<String, i32>>,
+    /// Titles of the episodes played lately by station, the latest last, so a shuffle in the
+    /// next run keeps them for last
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    recently_played: BTreeMap<String, Vec<String>>,
+    /// Titles of the episodes queued in the player by station, the next one first, for
+    /// `play --continue` to queue again
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    queue: BTreeMap<String, Vec<String>>,
+}
+
+impl State {
+    fn state_path() -> Result<PathBuf, StorageError> {
+        let config_dir = paths::config_dir()?;
+        fs::create_dir_all(&config_dir).map_err(StorageError::write(&config_dir))?;
+
+        paths::state_file()
+    }
+
+    /// Read the saved state; empty if there is none yet
+    pub fn load() -> Result<Self, StorageError> {
+        let path = Self::state_path()?;
+
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(&path).map_err(StorageError::read(&path))?;
+
+        serde_json::from_str(&content).map_err(StorageError::parse(&path))
+    }
+
+    pub fn save(&self) -> Result<(), StorageError> {
+        let path = Self::state_path()?;
+        let content = serde_json::to_string_pretty(self).map_err(StorageError::write(&path))?;
+
+        fs::write(&path, content).map_err(StorageError::write(&path))
+    }
+
+    /// Volume last chosen in the player, from 0.0 to 2.0
+    pub fn volume(&self) -> Option<f32> {
+        self.volume
+            .map(|percent| f32::from(percent.min(200)) / 100.0)
+    }
+
+    /// Remember `volume` for the next episode and the next run, saving only when it changed
+    /// by a whole percent. Pass the level from before a mute, not the silence.
+    pub fn set_volume(&mut self, volume: f32) -> Result<(), StorageError> {
+        let percent = (volume.clamp(0.0, 2.0) * 100.0).round() as u16;
+        if self.volume == Some(percent) {
+            return Ok(());
+        }
+        self.volume = Some(percent);
+        self.save()
+    }
+
+    /// Forget the volume chosen in the player, so the next start follows the config again;
+    /// false if there was none
+    pub fn clear_volume(&mut self) -> Result<bool, StorageError> {
+        if self.volume.take().is_none() {
+            return Ok(false);
+        }
+        self.save()?;
+        Ok(true)
+    }
+
+    /// Offset saved for `title` in the active station, in percentage points
+    pub fn volume_offset(&self, title: &str) -> Option<i32> {
+        self.volume_offsets
+            .get(station::active())
+            .and_then(|offsets| offsets.get(title))
+            .copied()
+    }
+
+    /// Remember `percent` for `title` and save; an offset of 0 forgets it
+    pub fn set_volume_offset(&mut self, title: &str, percent: i32) -> Result<(), StorageError> {
+        if percent == 0 {
+            self.clear_volume_offset(title)?;
+            return Ok(());
+        }
+
+        self.volume_offsets
+            .entry(station::active().to_string())
+            .or_default()
+            .insert(title.to_string(), percent);
+        self.save()
+    }
+
+    /// Forget the offset of `title` and save; false if there was none
+    pub fn clear_volume_offset(&mut self, title: &str) -> Result<bool, StorageError> {
+        let Some(offsets) = self.volume_offsets.get_mut(station::active()) else {
+            return Ok(false);
+        };
+        if offsets.remove(title).is_none() {
+            return Ok(false);
+        }
+        if offsets.is_empty() {
+            self.volume_offsets.remove(station::active());
+        }
+
+        self.save()?;
+        Ok(true)
+    }
+
+    /// Titles of the episodes played lately in the active station, the latest last
+    pub fn recently_played(&self) -> &[String] {
+        self.recently_played
+            .get(station::active())
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Note `title` as played in the active station, keeping the last `keep`, and save
+    pub fn record_played(&mut self, title: &str, keep: usize) -> Result<(), StorageError> {
+        let played = self
+            .recently_played
+            .entry(station::active().to_string())
+            .or_default();
+        remember(played, title, keep);
+        if played.is_empty() {
+            self.recently_played.remove(station::active());
+        }
+        self.save()
+    }
+
+    /// Titles queued in the active station when the player last saved its queue, the next
+    /// one first
+    pub fn queue(&self) -> &[String] {
+        self.queue
+            .get(station::active())
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Take from `other` what isn't set here: the volume, the offsets of other episodes, and
+    /// the played and queued titles of stations that have none; saves if anything was taken
+    pub fn merge(&mut self, other: State) -> Result<bool, StorageError> {
+        let mut changed = false;
+        if self.volume.is_none() && other.volume.is_some() {
+            self.volume = other.volume;
+            changed = true;
+        }
+        for (station, offsets) in other.volume_offsets {
+            let here = self.volume_offsets.entry(station).or_default();
+            for (title, percent) in offsets {
+                if let Entry::Vacant(entry) = here.entry(title) {
+                    entry.insert(percent);
+                    changed = true;
+                }
+            }
+        }
+        for (station, titles) in other.recently_played {
+            if let Entry::Vacant(entry) = self.recently_played.entry(station) {
+                entry.insert(titles);
+                changed = true;
+            }
+        }
+        for (station, titles) in other.queue {
+            if let Entry::Vacant(entry) = self.queue.entry(station) {
+                entry.insert(titles);
+                changed = true;
+            }
+        }
+
+        if changed {
+            self.save()?;
+        }
+        Ok(changed)
+    }
+
+    /// Remember `titles` as the queue of the active station, saving only when it changed
+    pub fn set_queue(&mut self, titles: Vec<String>) -> Result<(), StorageError> {
+        if self.queue() == titles.as_slice() {
+            return Ok(());
+        }
+        if titles.is_empty() {
+            self.queue.remove(station::active());
+        } else {
+            self.queue.insert(station::active().to_string(), titles);
+        }
+        self.save()
+    }
+}
+
+/// Move `title` to the end of `played`, dropping the oldest beyond `keep`
+fn remember(played: &mut Vec<String>, title: &str, keep: usize) {
+    played.retain(|other| other != title);
+    played.push(title.to_string());
+    let excess = played.len().saturating_sub(keep);
+    played.drain(..excess);
+}
+
+/// Volume an episode plays at: `session` (the volume every episode starts at, after any fade)
+/// plus its offset, within the player's 0.0 to 2.0 range
+pub fn apply_offset(session: f32, percent: i32) -> f32 {
+    (session + percent as f32 / 100.0).clamp(0.0, 2.0)
+}
+
+/// Offset that turns `session` into `current`, rounded to whole percentage points
+pub fn offset_between(session: f32, current: f32) -> i32 {
+    ((current - session) * 100.0).round() as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-6
+    }
+
+    #[test]
+    fn offsets_are_relative_to_the_session_volume() {
+        assert_eq!(offset_between(0.8, 1.1), 30);
+        assert_eq!(offset_between(1.0, 0.7), -30);
+        assert!(close(apply_offset(0.8, 30), 1.1));
+        assert!(close(apply_offset(0.5, 30), 0.8));
+    }
+
+    #[test]
+    fn offsets_stay_within_the_volume_range() {
+        assert!(close(apply_offset(1.9, 30), 2.0));
+        assert!(close(apply_offset(0.2, -50), 0.0));
+    }
+
+    #[test]
+    fn played_titles_keep_the_latest_once() {
+        let mut played = Vec::new();
+        for title in ["a", "b", "a", "c"] {
+            remember(&mut played, title, 2);
+        }
+        assert_eq!(played, ["a", "c"]);
+
+        remember(&mut played, "d", 0);
+        assert!(played.is_empty());
+    }
+
+    #[test]
+    fn a_saved_offset_round_trips() {
+        let session = 0.6;
+        let current = apply_offset(session, 25);
+        assert_eq!(offset_between(session, current), 25);
+    }
+}
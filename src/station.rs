@@ -0,0 +1,99 @@
+//! Stations: the Music For Programming feed plus other compatible RSS feeds added by name
+//! One station is active per run. Its feed cache, downloads and favorites are kept apart from
+//! those of the other stations, so episode titles from different feeds never collide.
+
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use crate::config::Config;
+use crate::feed::DEFAULT_FEED_URL;
+
+/// The musicforprogramming.net station, active unless another one is selected
+pub const DEFAULT: &str = "mfp";
+
+static ACTIVE: OnceLock<String> = OnceLock::new();
+
+/// Use the station `name` for the rest of the run. Only the first call has an effect; call it
+/// before anything fetches the feed or reads favorites or downloads.
+pub fn select(name: &str) {
+    let _ = ACTIVE.set(name.to_string());
+}
+
+/// Name of the station in use
+pub fn active() -> &'static str {
+    ACTIVE.get().map_or(DEFAULT, String::as_str)
+}
+
+/// Feed URL of the station `name`: `feed_url` (or musicforprogramming.net) for the default
+/// station, its `[stations]` entry for the others. None for a station that isn't configured.
+pub fn feed_url(config: &Config, name: &str) -> Option<String> {
+    if name == DEFAULT {
+        return Some(
+            config
+                .feed_url
+                .clone()
+                .unwrap_or_else(|| DEFAULT_FEED_URL.to_string()),
+        );
+    }
+    config.stations.get(name).cloned()
+}
+
+/// Names end up in file names: lowercase letters, digits, `-` and `_` only
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+}
+
+/// `dir` itself for the default station, a subdirectory named after the station otherwise
+pub fn scoped_dir(dir: PathBuf) -> PathBuf {
+    match active() {
+        DEFAULT => dir,
+        name => dir.join(name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_station_follows_feed_url() {
+        let mut config = Config::default();
+        assert_eq!(
+            feed_url(&config, DEFAULT).as_deref(),
+            Some(DEFAULT_FEED_URL)
+        );
+
+        config.feed_url = Some("https://example.com/mirror.xml".into());
+        assert_eq!(
+            feed_url(&config, DEFAULT).as_deref(),
+            Some("https://example.com/mirror.xml")
+        );
+    }
+
+    #[test]
+    fn other_stations_come_from_the_config() {
+        let mut config = Config::default();
+        config
+            .stations
+            .insert("lofi".into(), "https://example.com/lofi.xml".into());
+
+        assert_eq!(
+            feed_url(&config, "lofi").as_deref(),
+            Some("https://example.com/lofi.xml")
+        );
+        assert_eq!(feed_url(&config, "jazz"), None);
+    }
+
+    #[test]
+    fn names_are_safe_as_file_names() {
+        assert!(is_valid_name("lofi"));
+        assert!(is_valid_name("night-radio_2"));
+        assert!(!is_valid_name(""));
+        assert!(!is_valid_name("Lofi"));
+        assert!(!is_valid_name("../lofi"));
+        assert!(!is_valid_name("lo fi"));
+    }
+}
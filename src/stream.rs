@@ -0,0 +1,772 @@
+//! The stream as it downloads, kept whole so it can be read again from anywhere
+//! One thread writes the chunks where they go as they arrive, not always in order after a
+//! seek ahead of the download; any number of readers follow at their own positions, waiting
+//! on a condvar for the download when they reach a part it hasn't fetched. The stream can
+//! also be recorded: copied to a file as it arrives, which is renamed once all of it has.
+
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::errors::StorageError;
+use tracing::{debug, info, warn};
+
+/// Longest a read waits for the download between looks at the buffer, should a wakeup be missed
+const READ_WAIT: Duration = Duration::from_millis(500);
+/// Bytes copied at a time when a recording starts on a stream already under way
+const RECORD_COPY: u64 = 256 * 1024;
+
+/// Where the bytes of a stream are kept while it plays
+pub(crate) enum Spool {
+    Memory(Vec<u8>),
+    Disk(DiskSpool),
+}
+
+/// A temporary file holding the stream, removed when the last reader lets go
+pub(crate) struct DiskSpool {
+    file: File,
+    path: PathBuf,
+}
+
+impl Spool {
+    /// A file at `path` when there is one, memory otherwise or if the file can't be created
+    pub(crate) fn create(path: Option<&Path>) -> Self {
+        let Some(path) = path else {
+            return Spool::Memory(Vec::new());
+        };
+        let file = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|()| {
+                File::options()
+                    .read(true)
+                    .write(true)
+                    .create_new(true)
+                    .open(path)
+            });
+        match file {
+            Ok(file) => {
+                debug!(path = %path.display(), "Spooling stream to disk");
+                Spool::Disk(DiskSpool {
+                    file,
+                    path: path.to_path_buf(),
+                })
+            }
+            Err(e) => {
+                warn!(path = %path.display(), "Can't spool stream to disk, keeping it in memory: {}", e);
+                Spool::Memory(Vec::new())
+            }
+        }
+    }
+
+    /// Store `bytes` at `position`; what lies between the end and `position` reads as zeros
+    /// until it is written
+    fn write_at(&mut self, position: u64, bytes: &[u8]) -> Result<(), StorageError> {
+        match self {
+            Spool::Memory(buffer) => {
+                let start = position as usize;
+                let end = start + bytes.len();
+                if buffer.len() < end {
+                    buffer.resize(end, 0);
+                }
+                buffer[start..end].copy_from_slice(bytes);
+            }
+            Spool::Disk(disk) => {
+                disk.file
+                    .seek(SeekFrom::Start(position))
+                    .and_then(|_| disk.file.write_all(bytes))
+                    .map_err(StorageError::write(&disk.path))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Fill `buf` with the bytes from `position` on, which must all have been written
+    fn read_at(&mut self, position: u64, buf: &mut [u8]) -> io::Result<()> {
+        match self {
+            Spool::Memory(bytes) => {
+                let start = position as usize;
+                buf.copy_from_slice(&bytes[start..start + buf.len()]);
+            }
+            Spool::Disk(disk) => {
+                disk.file.seek(SeekFrom::Start(position))?;
+                disk.file.read_exact(buf)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for DiskSpool {
+    fn drop(&mut self) {
+        // Usually gone already: `stop` removes it while the stream winds down
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                warn!(path = %self.path.display(), "Can't remove stream spool: {}", e)
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Byte ranges of a stream, kept sorted with touching ones merged
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Ranges(Vec<Range<u64>>);
+
+impl Ranges {
+    pub(crate) fn insert(&mut self, range: Range<u64>) {
+        if range.is_empty() {
+            return;
+        }
+        // Every range that overlaps or touches the new one is merged into it
+        let first = self.0.partition_point(|r| r.end < range.start);
+        let last = self.0.partition_point(|r| r.start <= range.end);
+        let merged = match &self.0[first..last] {
+            [] => range,
+            touching => {
+                touching[0].start.min(range.start)..touching[touching.len() - 1].end.max(range.end)
+            }
+        };
+        self.0.splice(first..last, [merged]);
+    }
+
+    /// End of the range `position` is in, None when it's in none
+    pub(crate) fn end_of(&self, position: u64) -> Option<u64> {
+        let i = self.0.partition_point(|r| r.end <= position);
+        self.0.get(i).filter(|r| r.start <= position).map(|r| r.end)
+    }
+
+    /// The first stretch below `len` in no range, looking from `from` on and then from the
+    /// start; None when there is none
+    pub(crate) fn first_gap(&self, from: u64, len: u64) -> Option<Range<u64>> {
+        let gap_at = |position: u64| {
+            let start = self.end_of(position).unwrap_or(position);
+            let end = self
+                .0
+                .iter()
+                .map(|r| r.start)
+                .find(|&next| next > start)
+                .unwrap_or(len)
+                .min(len);
+            (start < end).then_some(start..end)
+        };
+        gap_at(from.min(len)).or_else(|| gap_at(0))
+    }
+
+    /// The stretches of these ranges that are in none of `other`, in order
+    pub(crate) fn without(&self, other: &Ranges) -> Vec<Range<u64>> {
+        let mut left = Vec::new();
+        for range in &self.0 {
+            let mut start = range.start;
+            let overlapping = other
+                .0
+                .iter()
+                .filter(|r| r.end > range.start && r.start < range.end);
+            for taken in overlapping {
+                if taken.start > start {
+                    left.push(start..taken.start);
+                }
+                start = start.max(taken.end);
+            }
+            if start < range.end {
+                left.push(start..range.end);
+            }
+        }
+        left
+    }
+}
+
+/// A copy of the stream saved to `path`. It goes to `<path>.partial` until the whole stream
+/// is there, and that file is removed if it never is: with parts missing it is of no use.
+struct Recording {
+    file: File,
+    partial: PathBuf,
+    path: PathBuf,
+    /// Size of the whole stream, 0 while unknown
+    total: Arc<AtomicU64>,
+}
+
+impl Recording {
+    fn write_at(&mut self, position: u64, bytes: &[u8]) -> io::Result<()> {
+        self.file.seek(SeekFrom::Start(position))?;
+        self.file.write_all(bytes)
+    }
+
+    /// Copy `range` of the stream, [`RECORD_COPY`] bytes at a time taken from `read`
+    fn copy(
+        &mut self,
+        range: Range<u64>,
+        mut read: impl FnMut(u64, &mut [u8]) -> io::Result<()>,
+    ) -> Result<(), StorageError> {
+        let mut position = range.start;
+        while position < range.end {
+            let mut bytes = vec![0; RECORD_COPY.min(range.end - position) as usize];
+            read(position, &mut bytes)
+                .and_then(|()| self.write_at(position, &bytes))
+                .map_err(StorageError::write(&self.partial))?;
+            position += bytes.len() as u64;
+        }
+        Ok(())
+    }
+
+    /// Whether `filled` is all of the stream
+    fn is_whole(&self, filled: &Ranges) -> bool {
+        let total = self.total.load(Ordering::Relaxed);
+        total > 0 && filled.end_of(0) == Some(total)
+    }
+
+    /// Give the file its name
+    fn finish(self) -> io::Result<()> {
+        self.file.sync_all()?;
+        fs::rename(&self.partial, &self.path)?;
+        info!(path = %self.path.display(), "Recording finished");
+        Ok(())
+    }
+}
+
+impl Drop for Recording {
+    fn drop(&mut self) {
+        // Gone already once finished
+        match fs::remove_file(&self.partial) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                warn!(path = %self.partial.display(), "Can't remove unfinished recording: {}", e)
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Bytes of the stream and where in it they go
+pub(crate) type Chunk = (u64, Vec<u8>);
+
+/// Since when a reader has been waiting for data that hasn't arrived, for whoever watches
+/// for stalls
+pub(crate) type Starved = Arc<Mutex<Option<Instant>>>;
+
+/// The parts of the stream that have arrived, and whether that is all of it
+struct BufferState {
+    spool: Spool,
+    filled: Ranges,
+    complete: bool,
+    recording: Option<Recording>,
+}
+
+impl BufferState {
+    /// Copy `bytes` at `position` to the recording, and finish it if the stream is now whole.
+    /// A recording that fails is dropped; playback goes on without it.
+    fn record(&mut self, position: u64, bytes: &[u8]) {
+        let Some(recording) = self.recording.as_mut() else {
+            return;
+        };
+        if let Err(e) = recording.write_at(position, bytes) {
+            warn!(path = %recording.partial.display(), "Can't write to the recording: {}", e);
+            self.recording = None;
+            return;
+        }
+        if recording.is_whole(&self.filled) {
+            if let Some(Err(e)) = self.recording.take().map(Recording::finish) {
+                warn!("Can't finish the recording: {}", e);
+            }
+        }
+    }
+}
+
+/// A handle on a stream being downloaded: it adds to the stream, and reads it from its own
+/// position like a file, waiting for the download where it hasn't got yet. Every handle from
+/// [`at`](Self::at) shares the one stream.
+pub(crate) struct StreamingBuffer {
+    shared: Arc<(Mutex<BufferState>, Condvar)>,
+    position: u64,
+    starved: Starved,
+}
+
+impl StreamingBuffer {
+    /// An empty stream kept in `spool`; reads that wait mark `starved`
+    pub(crate) fn new(spool: Spool, starved: Starved) -> Self {
+        Self {
+            shared: Arc::new((
+                Mutex::new(BufferState {
+                    spool,
+                    filled: Ranges::default(),
+                    complete: false,
+                    recording: None,
+                }),
+                Condvar::new(),
+            )),
+            position: 0,
+            starved,
+        }
+    }
+
+    /// Another handle on the same stream, reading from `position`
+    pub(crate) fn at(&self, position: u64) -> Self {
+        Self {
+            shared: Arc::clone(&self.shared),
+            position,
+            starved: Arc::clone(&self.starved),
+        }
+    }
+
+    /// Put `bytes` into the stream at `position` and wake the readers waiting for them
+    pub(crate) fn write_chunk(&self, position: u64, bytes: &[u8]) -> Result<(), StorageError> {
+        let (state, arrived) = &*self.shared;
+        let mut state = state.lock().unwrap();
+        state.spool.write_at(position, bytes)?;
+        state.filled.insert(position..position + bytes.len() as u64);
+        state.record(position, bytes);
+        drop(state);
+        arrived.notify_all();
+        Ok(())
+    }
+
+    /// Save the stream to `path` as well, `total` bytes long once that is known: what has
+    /// arrived at once, the rest as it does. Replaces a recording already under way.
+    pub(crate) fn record(&self, path: &Path, total: Arc<AtomicU64>) -> Result<(), StorageError> {
+        let partial = path.with_extension("partial");
+        let file = File::create(&partial).map_err(StorageError::write(&partial))?;
+        let mut recording = Recording {
+            file,
+            partial,
+            path: path.to_path_buf(),
+            total,
+        };
+
+        // The copy takes the lock a piece at a time, so the download goes on meanwhile; what
+        // arrives during it is caught up with once the lock is held for attaching the recording
+        let copied = self.shared.0.lock().unwrap().filled.clone();
+        for range in &copied.0 {
+            recording.copy(range.clone(), |position, bytes| {
+                self.shared.0.lock().unwrap().spool.read_at(position, bytes)
+            })?;
+        }
+        let mut state = self.shared.0.lock().unwrap();
+        let state = &mut *state;
+        for range in state.filled.without(&copied) {
+            recording.copy(range, |position, bytes| {
+                state.spool.read_at(position, bytes)
+            })?;
+        }
+        debug!(path = %path.display(), "Recording stream");
+        state.recording = Some(recording);
+        // Everything may be here already
+        state.record(0, &[]);
+        Ok(())
+    }
+
+    /// Write the stream from its start up to the first gap to `path`, for a download to
+    /// carry on from, unless a file there holds as much already. The bytes the file holds.
+    pub(crate) fn save_head(&self, path: &Path) -> Result<u64, StorageError> {
+        let len = self.len();
+        let existing = fs::metadata(path).map_or(0, |m| m.len());
+        if existing >= len {
+            return Ok(existing);
+        }
+        let mut file = File::create(path).map_err(StorageError::write(path))?;
+        let mut position = 0;
+        while position < len {
+            let bytes = self
+                .bytes_at(position, RECORD_COPY.min(len - position))
+                .and_then(|bytes| file.write_all(&bytes).map(|()| bytes.len() as u64))
+                .map_err(StorageError::write(path))?;
+            position += bytes;
+        }
+        debug!(path = %path.display(), bytes = len, "Saved the stream so far");
+        Ok(len)
+    }
+
+    /// Nothing more is coming: readers at the end get to it instead of waiting
+    pub(crate) fn mark_complete(&self) {
+        let (state, arrived) = &*self.shared;
+        state.lock().unwrap().complete = true;
+        arrived.notify_all();
+    }
+
+    /// Bytes of the stream arrived without a gap from its start
+    pub(crate) fn len(&self) -> u64 {
+        self.shared.0.lock().unwrap().filled.end_of(0).unwrap_or(0)
+    }
+
+    /// Up to `len` bytes from `position` on, fewer where what has arrived ends; never waits
+    pub(crate) fn bytes_at(&self, position: u64, len: u64) -> io::Result<Vec<u8>> {
+        let mut state = self.shared.0.lock().unwrap();
+        let Some(end) = state.filled.end_of(position) else {
+            return Ok(Vec::new());
+        };
+        let mut bytes = vec![0; len.min(end - position) as usize];
+        state.spool.read_at(position, &mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Write every chunk of `chunks` on a thread of its own, and mark the stream complete
+    /// when they run out. A chunk that can't be stored ends it there, incomplete: readers
+    /// stall where the stream ends, as if the connection had dropped.
+    pub(crate) fn fill_from(&self, chunks: impl IntoIterator<Item = Chunk> + Send + 'static) {
+        let buffer = self.at(0);
+        thread::spawn(move || {
+            for (position, bytes) in chunks {
+                if let Err(e) = buffer.write_chunk(position, &bytes) {
+                    warn!("Can't write to the stream spool: {}", e);
+                    return;
+                }
+            }
+            buffer.mark_complete();
+        });
+    }
+}
+
+impl Read for StreamingBuffer {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let (state, arrived) = &*self.shared;
+        let mut state = state.lock().unwrap();
+        loop {
+            if let Some(end) = state.filled.end_of(self.position) {
+                let to_read = buf.len().min((end - self.position) as usize);
+                state.spool.read_at(self.position, &mut buf[..to_read])?;
+                self.position += to_read as u64;
+                drop(state);
+                *self.starved.lock().unwrap() = None;
+
+                return Ok(to_read);
+            }
+
+            if state.complete {
+                return Ok(0);
+            }
+
+            // Playback has caught up with the download
+            self.starved
+                .lock()
+                .unwrap()
+                .get_or_insert_with(Instant::now);
+
+            state = arrived.wait_timeout(state, READ_WAIT).unwrap().0;
+        }
+    }
+}
+
+impl Seek for StreamingBuffer {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let buffer_len = self.len() as i64;
+
+        let new_pos = match pos {
+            SeekFrom::Start(offset) => offset as i64,
+            SeekFrom::Current(offset) => self.position as i64 + offset,
+            SeekFrom::End(offset) => buffer_len + offset,
+        };
+
+        if new_pos < 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Cannot seek before beginning",
+            ));
+        }
+
+        self.position = new_pos as u64;
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    fn memory() -> StreamingBuffer {
+        StreamingBuffer::new(Spool::Memory(Vec::new()), Starved::default())
+    }
+
+    fn spool_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("mfp-spool-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn disk_spool_reads_back_anywhere() {
+        let path = spool_dir("read").join("1.part");
+        let spool = Spool::create(Some(&path));
+        assert!(matches!(spool, Spool::Disk(_)));
+        let buffer = StreamingBuffer::new(spool, Starved::default());
+
+        let stream: Vec<u8> = (0..=255).cycle().take(10_000).collect();
+        for (i, chunk) in stream.chunks(3_000).enumerate() {
+            buffer.write_chunk(i as u64 * 3_000, chunk).unwrap();
+        }
+        buffer.mark_complete();
+        let mut reader = buffer.at(0);
+
+        let mut all = Vec::new();
+        reader.read_to_end(&mut all).unwrap();
+        assert_eq!(all, stream);
+
+        // Back to the start, as a seek to 0:00 does
+        reader.seek(SeekFrom::Start(100)).unwrap();
+        let mut some = [0; 10];
+        reader.read_exact(&mut some).unwrap();
+        assert_eq!(some, stream[100..110]);
+    }
+
+    #[test]
+    fn disk_spool_is_removed_with_its_last_reader() {
+        let path = spool_dir("drop").join("1.part");
+        let mut spool = Spool::create(Some(&path));
+        spool.write_at(0, b"ID3").unwrap();
+        assert!(path.exists());
+
+        drop(spool);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn reads_wait_for_data_and_say_when() {
+        let buffer = memory();
+        let starved = Arc::clone(&buffer.starved);
+        let mut reader = buffer.at(0);
+        let read = thread::spawn(move || {
+            let mut buf = [0; 8];
+            let n = reader.read(&mut buf).unwrap();
+            buf[..n].to_vec()
+        });
+
+        thread::sleep(Duration::from_millis(100));
+        assert!(!read.is_finished());
+        assert!(starved.lock().unwrap().is_some());
+
+        buffer.write_chunk(0, b"abc").unwrap();
+        assert_eq!(read.join().unwrap(), b"abc");
+        assert!(starved.lock().unwrap().is_none());
+    }
+
+    #[test]
+    fn reads_wake_as_soon_as_data_arrives() {
+        let buffer = memory();
+        let writer = buffer.at(0);
+        let (written, writes) = mpsc::channel();
+        thread::spawn(move || {
+            for i in 0..5 {
+                thread::sleep(Duration::from_millis(100));
+                written.send(Instant::now()).unwrap();
+                writer.write_chunk(u64::from(i) * 10, &[i; 10]).unwrap();
+            }
+            writer.mark_complete();
+        });
+
+        let mut reader = buffer.at(0);
+        let mut buf = [0; 64];
+        for i in 0..5 {
+            let n = reader.read(&mut buf).unwrap();
+            let woke = writes.recv().unwrap().elapsed();
+            assert_eq!(buf[..n], [i; 10]);
+            // Well under READ_WAIT, so it was the write that woke it, with room for a busy machine
+            assert!(woke < Duration::from_millis(250), "woke after {:?}", woke);
+        }
+        assert_eq!(reader.read(&mut buf).unwrap(), 0);
+    }
+
+    #[test]
+    fn reads_after_the_end_of_a_complete_stream_return_nothing() {
+        let buffer = memory();
+        buffer.fill_from(vec![(0, b"abc".to_vec()), (3, b"def".to_vec())]);
+
+        let mut all = Vec::new();
+        buffer.at(0).read_to_end(&mut all).unwrap();
+        assert_eq!(all, b"abcdef");
+        assert_eq!(buffer.at(6).read(&mut [0; 8]).unwrap(), 0);
+    }
+
+    #[test]
+    fn seeks_past_the_end_wait_for_the_download_to_get_there() {
+        let buffer = memory();
+        buffer.write_chunk(0, b"abc").unwrap();
+        let mut reader = buffer.at(0);
+        assert_eq!(reader.seek(SeekFrom::End(2)).unwrap(), 5);
+        assert!(reader.seek(SeekFrom::Current(-6)).is_err());
+
+        let read = thread::spawn(move || {
+            let mut buf = [0; 8];
+            let n = reader.read(&mut buf).unwrap();
+            buf[..n].to_vec()
+        });
+        buffer.write_chunk(3, b"de").unwrap();
+        buffer.write_chunk(5, b"fgh").unwrap();
+        buffer.mark_complete();
+        // The first bytes the read finds are the ones past the end it was put at
+        assert_eq!(read.join().unwrap(), b"fgh");
+
+        let mut beyond = buffer.at(20);
+        assert_eq!(beyond.read(&mut [0; 8]).unwrap(), 0);
+    }
+
+    #[test]
+    fn seeks_and_reads_interleave() {
+        let buffer = memory();
+        buffer.write_chunk(0, b"0123456789").unwrap();
+        buffer.mark_complete();
+        let mut reader = buffer.at(0);
+        let mut two = [0; 2];
+
+        reader.read_exact(&mut two).unwrap();
+        assert_eq!(&two, b"01");
+        reader.seek(SeekFrom::Current(3)).unwrap();
+        reader.read_exact(&mut two).unwrap();
+        assert_eq!(&two, b"56");
+        reader.seek(SeekFrom::Start(1)).unwrap();
+        reader.read_exact(&mut two).unwrap();
+        assert_eq!(&two, b"12");
+        reader.seek(SeekFrom::End(-1)).unwrap();
+        reader.read_exact(&mut two[..1]).unwrap();
+        assert_eq!(&two[..1], b"9");
+
+        // Another reader keeps its own place
+        let mut other = buffer.at(4);
+        other.read_exact(&mut two).unwrap();
+        assert_eq!(&two, b"45");
+        assert_eq!(reader.stream_position().unwrap(), 10);
+    }
+
+    #[test]
+    fn bytes_at_takes_what_is_there_without_waiting() {
+        let buffer = memory();
+        buffer.write_chunk(0, b"abcdef").unwrap();
+        assert_eq!(buffer.bytes_at(2, 3).unwrap(), b"cde");
+        assert_eq!(buffer.bytes_at(4, 10).unwrap(), b"ef");
+        assert_eq!(buffer.bytes_at(10, 10).unwrap(), b"");
+        assert_eq!(buffer.len(), 6);
+    }
+
+    #[test]
+    fn chunks_ahead_of_the_download_are_read_where_they_go() {
+        let buffer = memory();
+        buffer.write_chunk(0, b"abc").unwrap();
+        buffer.write_chunk(10, b"klm").unwrap();
+        // Only the part without a gap counts as arrived
+        assert_eq!(buffer.len(), 3);
+        assert_eq!(buffer.bytes_at(11, 10).unwrap(), b"lm");
+        assert_eq!(buffer.bytes_at(5, 10).unwrap(), b"");
+
+        let mut reader = buffer.at(2);
+        let mut buf = [0; 8];
+        // A read stops at the end of a part, not across the gap
+        assert_eq!(reader.read(&mut buf).unwrap(), 1);
+        let gap = thread::spawn(move || {
+            let n = reader.read(&mut buf).unwrap();
+            buf[..n].to_vec()
+        });
+        thread::sleep(Duration::from_millis(50));
+        assert!(!gap.is_finished());
+
+        buffer.write_chunk(3, b"defghij").unwrap();
+        assert_eq!(gap.join().unwrap(), b"defghijk");
+        assert_eq!(buffer.len(), 13);
+    }
+
+    #[test]
+    fn recordings_start_with_what_has_arrived_and_finish_when_whole() {
+        let dir = spool_dir("record");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("Episode 1.mp3");
+        let partial = dir.join("Episode 1.partial");
+        let total = Arc::new(AtomicU64::new(0));
+
+        let buffer = memory();
+        buffer.write_chunk(0, b"abc").unwrap();
+        buffer.write_chunk(6, b"ghi").unwrap();
+        buffer.record(&path, Arc::clone(&total)).unwrap();
+        assert!(partial.exists());
+
+        total.store(9, Ordering::Relaxed);
+        buffer.write_chunk(3, b"def").unwrap();
+        assert!(!partial.exists());
+        assert_eq!(fs::read(&path).unwrap(), b"abcdefghi");
+    }
+
+    #[test]
+    fn unfinished_recordings_are_removed() {
+        let dir = spool_dir("unfinished");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("Episode 2.mp3");
+
+        let buffer = memory();
+        buffer.write_chunk(0, b"abc").unwrap();
+        buffer.record(&path, Arc::new(AtomicU64::new(100))).unwrap();
+        drop(buffer);
+        assert!(!dir.join("Episode 2.partial").exists());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn downloads_are_seeded_with_the_head_of_the_stream() {
+        let dir = spool_dir("seed");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("Episode 3.tmp");
+
+        let buffer = memory();
+        buffer.write_chunk(0, b"abcd").unwrap();
+        buffer.write_chunk(8, b"ijk").unwrap();
+        assert_eq!(buffer.save_head(&path).unwrap(), 4);
+        assert_eq!(fs::read(&path).unwrap(), b"abcd");
+
+        // A longer partial download is worth more than the stream
+        fs::write(&path, b"abcdefg").unwrap();
+        assert_eq!(buffer.save_head(&path).unwrap(), 7);
+        assert_eq!(fs::read(&path).unwrap(), b"abcdefg");
+    }
+
+    #[test]
+    #[allow(clippy::single_range_in_vec_init)]
+    fn ranges_merge_when_they_meet() {
+        let mut ranges = Ranges::default();
+        ranges.insert(10..20);
+        ranges.insert(30..40);
+        ranges.insert(0..5);
+        assert_eq!(ranges, Ranges(vec![0..5, 10..20, 30..40]));
+
+        ranges.insert(5..10);
+        assert_eq!(ranges, Ranges(vec![0..20, 30..40]));
+        ranges.insert(15..35);
+        assert_eq!(ranges, Ranges(vec![0..40]));
+        ranges.insert(20..20);
+        assert_eq!(ranges, Ranges(vec![0..40]));
+
+        assert_eq!(ranges.end_of(0), Some(40));
+        assert_eq!(ranges.end_of(39), Some(40));
+        assert_eq!(ranges.end_of(40), None);
+    }
+
+    #[test]
+    fn ranges_without_others_keep_what_those_lack() {
+        let mut ranges = Ranges::default();
+        ranges.insert(0..10);
+        ranges.insert(20..40);
+        let mut copied = Ranges::default();
+        copied.insert(0..4);
+        copied.insert(25..30);
+
+        assert_eq!(ranges.without(&copied), [4..10, 20..25, 30..40]);
+        assert!(ranges.without(&ranges).is_empty());
+        assert!(Ranges::default().without(&copied).is_empty());
+    }
+
+    #[test]
+    fn gaps_are_found_after_a_position_then_from_the_start() {
+        let mut ranges = Ranges::default();
+        assert_eq!(ranges.first_gap(0, 100), Some(0..100));
+
+        ranges.insert(0..20);
+        ranges.insert(50..70);
+        assert_eq!(ranges.first_gap(55, 100), Some(70..100));
+        assert_eq!(ranges.first_gap(30, 100), Some(30..50));
+        // Nothing left after the position: back to the first gap
+        ranges.insert(70..100);
+        assert_eq!(ranges.first_gap(60, 100), Some(20..50));
+        assert_eq!(ranges.first_gap(200, 100), Some(20..50));
+
+        ranges.insert(20..50);
+        assert_eq!(ranges.first_gap(0, 100), None);
+    }
+}
@@ -0,0 +1,291 @@
+//! Terminal colors and progress bars
+//! Every styled string goes through `paint` so the palette lives in one place, and every bar
+//! through `render_bar` so they all share one look.
+//! Colors are disabled by `--color never`, `NO_COLOR`, or when output isn't a terminal.
+
+use clap::ValueEnum;
+use crossterm::style::{style, Color, Stylize};
+use serde::Deserialize;
+use std::io::{self, IsTerminal};
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+
+static STDOUT_COLOR: AtomicBool = AtomicBool::new(false);
+static STDERR_COLOR: AtomicBool = AtomicBool::new(false);
+static BAR_STYLE: AtomicU8 = AtomicU8::new(BarStyle::Unicode as u8);
+
+/// Partial blocks of the `blocks` style, one to seven eighths of a cell
+const EIGHTHS: [char; 7] = ['▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ColorMode {
+    Auto,
+    Always,
+    Never,
+}
+
+/// What a piece of text means; each role maps to one color
+#[derive(Debug, Clone, Copy)]
+pub enum Role {
+    Favorite,
+    Title,
+    Accent,
+    Success,
+    Error,
+    Dim,
+    /// Downloaded but not yet played part of the progress bar
+    Buffered,
+}
+
+impl Role {
+    fn color(self) -> Color {
+        match self {
+            Role::Favorite => Color::Yellow,
+            Role::Title => Color::Cyan,
+            Role::Accent => Color::Magenta,
+            Role::Success => Color::Green,
+            Role::Error => Color::Red,
+            Role::Dim => Color::DarkGrey,
+            Role::Buffered => Color::Grey,
+        }
+    }
+}
+
+/// Decide once, at startup, whether stdout and stderr get colors
+pub fn init(mode: ColorMode) {
+    let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    let (stdout, stderr) = match mode {
+        ColorMode::Always => (true, true),
+        ColorMode::Never => (false, false),
+        ColorMode::Auto if no_color => (false, false),
+        ColorMode::Auto => (io::stdout().is_terminal(), io::stderr().is_terminal()),
+    };
+
+    STDOUT_COLOR.store(stdout, Ordering::Relaxed);
+    STDERR_COLOR.store(stderr, Ordering::Relaxed);
+}
+
+/// Whether stderr output may contain color codes
+pub fn stderr_enabled() -> bool {
+    STDERR_COLOR.load(Ordering::Relaxed)
+}
+
+/// Style text printed to stdout
+pub fn paint(role: Role, text: &str) -> String {
+    paint_if(STDOUT_COLOR.load(Ordering::Relaxed), role, text)
+}
+
+/// Style text printed to stderr
+pub fn paint_err(role: Role, text: &str) -> String {
+    paint_if(stderr_enabled(), role, text)
+}
+
+fn paint_if(enabled: bool, role: Role, text: &str) -> String {
+    if !enabled {
+        return text.to_string();
+    }
+
+    let styled = style(text).with(role.color());
+    match role {
+        Role::Title | Role::Error => styled.bold().to_string(),
+        _ => styled.to_string(),
+    }
+}
+
+/// `progress_style` setting in config.toml: how bars are drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BarStyle {
+    /// `━━━───`
+    Unicode,
+    /// `===---`, for terminals that can't show the line glyphs
+    Ascii,
+    /// `███▌░░`, with eighths of a cell
+    Blocks,
+}
+
+impl BarStyle {
+    /// ASCII when the locale isn't UTF-8 or the terminal is a bare console, lines otherwise
+    pub fn detect() -> Self {
+        let term = std::env::var("TERM").unwrap_or_default();
+        let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|value| !value.is_empty());
+
+        Self::for_terminal(&term, locale.as_deref())
+    }
+
+    fn for_terminal(term: &str, locale: Option<&str>) -> Self {
+        if matches!(term, "dumb" | "linux" | "vt100" | "vt220") {
+            return BarStyle::Ascii;
+        }
+        let utf8 = match locale {
+            Some(locale) => {
+                let locale = locale.to_ascii_lowercase();
+                locale.contains("utf-8") || locale.contains("utf8")
+            }
+            // Windows terminals have no locale variables and show Unicode
+            None => cfg!(windows),
+        };
+
+        if utf8 {
+            BarStyle::Unicode
+        } else {
+            BarStyle::Ascii
+        }
+    }
+
+    fn from_u8(value: u8) -> Self {
+        match value {
+            v if v == BarStyle::Ascii as u8 => BarStyle::Ascii,
+            v if v == BarStyle::Blocks as u8 => BarStyle::Blocks,
+            _ => BarStyle::Unicode,
+        }
+    }
+
+    /// Glyph of a filled cell
+    pub fn filled(self) -> char {
+        match self {
+            BarStyle::Unicode => '━',
+            BarStyle::Ascii => '=',
+            BarStyle::Blocks => '█',
+        }
+    }
+
+    /// Glyph of an empty cell
+    pub fn empty(self) -> char {
+        match self {
+            BarStyle::Unicode => '─',
+            BarStyle::Ascii => '-',
+            BarStyle::Blocks => '░',
+        }
+    }
+
+    /// Glyph of a cell not downloaded yet, where a bar shows that
+    pub fn pending(self) -> char {
+        match self {
+            BarStyle::Unicode => '╌',
+            BarStyle::Ascii => '.',
+            BarStyle::Blocks => '·',
+        }
+    }
+
+    /// Glyphs of a level meter's cells when lit, quietest first, and of an unlit cell
+    pub fn meter(self) -> ([char; 4], char) {
+        match self {
+            BarStyle::Unicode | BarStyle::Blocks => (['▂', '▄', '▆', '█'], '▁'),
+            BarStyle::Ascii => (['.', ':', '|', '#'], '_'),
+        }
+    }
+}
+
+/// Use `style` for every bar drawn from now on
+pub fn set_bar_style(style: BarStyle) {
+    BAR_STYLE.store(style as u8, Ordering::Relaxed);
+}
+
+pub fn bar_style() -> BarStyle {
+    BarStyle::from_u8(BAR_STYLE.load(Ordering::Relaxed))
+}
+
+/// A bar split into its filled and empty cells, unpainted
+#[derive(Debug, PartialEq, Eq)]
+pub struct Bar {
+    pub filled: String,
+    pub empty: String,
+}
+
+impl Bar {
+    /// Filled cells in the accent color, the rest dimmed, for stdout
+    pub fn paint(&self) -> String {
+        paint(Role::Accent, &self.filled) + &paint(Role::Dim, &self.empty)
+    }
+}
+
+/// A bar `width` cells wide, filled to `fraction` (clamped to 0.0 to 1.0). Cells round down,
+/// except that the blocks style draws the last one in eighths.
+pub fn render_bar(width: usize, fraction: f64, style: BarStyle) -> Bar {
+    let fraction = if fraction.is_nan() {
+        0.0
+    } else {
+        fraction.clamp(0.0, 1.0)
+    };
+
+    let (full, partial) = match style {
+        BarStyle::Blocks => {
+            let eighths = (fraction * width as f64 * 8.0) as usize;
+            let partial = eighths % 8;
+            (eighths / 8, (partial > 0).then(|| EIGHTHS[partial - 1]))
+        }
+        _ => ((fraction * width as f64) as usize, None),
+    };
+    let full = full.min(width);
+
+    let mut filled: String = std::iter::repeat_n(style.filled(), full).collect();
+    filled.extend(partial);
+    let empty_cells = width - full - usize::from(partial.is_some());
+
+    Bar {
+        filled,
+        empty: std::iter::repeat_n(style.empty(), empty_cells).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cells(bar: &Bar) -> usize {
+        bar.filled.chars().count() + bar.empty.chars().count()
+    }
+
+    #[test]
+    fn bars_keep_their_width() {
+        for style in [BarStyle::Unicode, BarStyle::Ascii, BarStyle::Blocks] {
+            for width in [0, 1, 5, 17, 40] {
+                for fraction in [-1.0, 0.0, 0.01, 0.33, 0.5, 0.999, 1.0, 2.0, f64::NAN] {
+                    let bar = render_bar(width, fraction, style);
+                    assert_eq!(cells(&bar), width, "{:?} {} {}", style, width, fraction);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn ascii_and_unicode_round_down_to_whole_cells() {
+        let bar = render_bar(10, 0.39, BarStyle::Ascii);
+        assert_eq!(bar.filled, "===");
+        assert_eq!(bar.empty, "-------");
+
+        let bar = render_bar(4, 1.0, BarStyle::Unicode);
+        assert_eq!(bar.filled, "━━━━");
+        assert_eq!(bar.empty, "");
+    }
+
+    #[test]
+    fn blocks_show_eighths_of_a_cell() {
+        let bar = render_bar(4, 0.5 + 1.0 / 32.0 * 3.0, BarStyle::Blocks);
+        assert_eq!(bar.filled, "██▍");
+        assert_eq!(bar.empty, "░");
+
+        assert_eq!(render_bar(4, 0.0, BarStyle::Blocks).filled, "");
+        assert_eq!(render_bar(4, 1.0, BarStyle::Blocks).filled, "████");
+    }
+
+    #[test]
+    fn plain_consoles_and_non_utf8_locales_get_ascii() {
+        assert_eq!(
+            BarStyle::for_terminal("xterm-256color", Some("en_US.UTF-8")),
+            BarStyle::Unicode
+        );
+        assert_eq!(
+            BarStyle::for_terminal("xterm", Some("es_ES.utf8")),
+            BarStyle::Unicode
+        );
+        assert_eq!(BarStyle::for_terminal("xterm", Some("C")), BarStyle::Ascii);
+        assert_eq!(
+            BarStyle::for_terminal("linux", Some("en_US.UTF-8")),
+            BarStyle::Ascii
+        );
+    }
+}
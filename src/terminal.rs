@@ -0,0 +1,210 @@
+//! The terminal the play loop reads from
+//! Raw mode for single keys and a line typed at the terminal, even when stdin is a pipe.
+//! The loop goes through [`Events`], so [`Scripted`] can stand in for the keyboard in tests.
+
+use anyhow::Result;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::io::{self, BufRead, IsTerminal};
+use std::time::Duration;
+
+use crate::{logging, mouse, shutdown};
+
+/// Where the play loop's keys, clicks and typed answers come from
+pub trait Events {
+    /// Whether there are keys to read at all
+    fn interactive(&self) -> bool;
+    /// Raw mode for single-key input; log lines are held back until it is left
+    fn enter_progress_display(&self) -> Result<()>;
+    fn leave_progress_display(&self) -> Result<()>;
+    /// Whether an event is waiting, after up to `timeout`
+    fn poll(&self, timeout: Duration) -> Result<bool>;
+    fn read(&self) -> Result<Event>;
+    /// One line typed in answer to a prompt
+    fn read_line(&self) -> Result<String>;
+    /// Any key, out of the progress display
+    fn wait_for_key(&self) -> Result<()>;
+
+    /// Whether a signal came, or `q`, Esc or Ctrl+C was pressed, while a stream buffers;
+    /// other keys pressed meanwhile are dropped
+    fn start_interrupted(&self) -> bool {
+        if shutdown::requested() {
+            return true;
+        }
+        while self.interactive() && self.poll(Duration::ZERO).unwrap_or(false) {
+            if let Ok(Event::Key(KeyEvent {
+                code,
+                modifiers,
+                kind: KeyEventKind::Press,
+                ..
+            })) = self.read()
+            {
+                let ctrl_c =
+                    code == KeyCode::Char('c') && modifiers.contains(KeyModifiers::CONTROL);
+                if ctrl_c || code == KeyCode::Char('q') || code == KeyCode::Esc {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+}
+
+/// The real terminal, through crossterm
+pub struct Terminal;
+
+impl Events for Terminal {
+    // Keys come from the terminal even when stdin is a pipe (`play --stdin`)
+    fn interactive(&self) -> bool {
+        has_terminal()
+    }
+
+    fn enter_progress_display(&self) -> Result<()> {
+        enable_raw_mode()?;
+        mouse::capture();
+        logging::set_progress_active(true);
+        Ok(())
+    }
+
+    fn leave_progress_display(&self) -> Result<()> {
+        mouse::release();
+        disable_raw_mode()?;
+        logging::set_progress_active(false);
+        Ok(())
+    }
+
+    fn poll(&self, timeout: Duration) -> Result<bool> {
+        Ok(event::poll(timeout)?)
+    }
+
+    fn read(&self) -> Result<Event> {
+        Ok(event::read()?)
+    }
+
+    fn read_line(&self) -> Result<String> {
+        read_terminal_line()
+    }
+
+    fn wait_for_key(&self) -> Result<()> {
+        enable_raw_mode()?;
+        let result = loop {
+            match event::read() {
+                Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => break Ok(()),
+                Ok(_) => continue,
+                Err(e) => break Err(e.into()),
+            }
+        };
+        disable_raw_mode()?;
+        result
+    }
+}
+
+/// Keys and answers given up front, played to the loop in order. Running out is an error,
+/// so a script that forgets to quit fails instead of hanging.
+#[derive(Default)]
+pub struct Scripted {
+    events: RefCell<VecDeque<Event>>,
+    lines: RefCell<VecDeque<String>>,
+}
+
+impl Scripted {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `command` typed and sent with Enter, as at the player's prompt
+    pub fn command(self, command: &str) -> Self {
+        let keys = command.chars().map(KeyCode::Char).chain([KeyCode::Enter]);
+        self.events
+            .borrow_mut()
+            .extend(keys.map(|code| Event::Key(code.into())));
+        self
+    }
+
+    /// A single key, such as a bound one
+    pub fn key(self, code: KeyCode) -> Self {
+        self.events.borrow_mut().push_back(Event::Key(code.into()));
+        self
+    }
+
+    /// The answer to the next question the loop asks
+    pub fn answer(self, line: &str) -> Self {
+        self.lines.borrow_mut().push_back(format!("{}\n", line));
+        self
+    }
+
+    /// Whether every key and answer has been used
+    pub fn is_done(&self) -> bool {
+        self.events.borrow().is_empty() && self.lines.borrow().is_empty()
+    }
+}
+
+impl Events for Scripted {
+    fn interactive(&self) -> bool {
+        true
+    }
+
+    fn enter_progress_display(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn leave_progress_display(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn poll(&self, _timeout: Duration) -> Result<bool> {
+        Ok(true)
+    }
+
+    fn read(&self) -> Result<Event> {
+        self.events
+            .borrow_mut()
+            .pop_front()
+            .ok_or_else(|| anyhow::anyhow!("The scripted keys ran out"))
+    }
+
+    fn read_line(&self) -> Result<String> {
+        self.lines
+            .borrow_mut()
+            .pop_front()
+            .ok_or_else(|| anyhow::anyhow!("The scripted answers ran out"))
+    }
+
+    fn wait_for_key(&self) -> Result<()> {
+        self.read().map(drop)
+    }
+
+    // Buffering takes no time here, and the keys are for the loop
+    fn start_interrupted(&self) -> bool {
+        false
+    }
+}
+
+/// Whether there is a terminal to read keys from: stdin itself or, when stdin is a pipe,
+/// the controlling terminal (which is what crossterm falls back to)
+pub fn has_terminal() -> bool {
+    io::stdin().is_terminal() || open_terminal().is_ok()
+}
+
+/// One line typed at the terminal, even when stdin has been used up by a pipe
+pub fn read_terminal_line() -> Result<String> {
+    let mut line = String::new();
+    if io::stdin().is_terminal() {
+        io::stdin().read_line(&mut line)?;
+    } else {
+        io::BufReader::new(open_terminal()?).read_line(&mut line)?;
+    }
+    Ok(line)
+}
+
+#[cfg(unix)]
+fn open_terminal() -> io::Result<std::fs::File> {
+    std::fs::File::open("/dev/tty")
+}
+
+#[cfg(windows)]
+fn open_terminal() -> io::Result<std::fs::File> {
+    std::fs::File::open("CONIN$")
+}
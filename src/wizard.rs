@@ -0,0 +1,157 @@
+//! First-run setup wizard
+//! Asks a handful of questions on a fresh machine and writes `config.toml`.
+//! Only ever runs with a terminal on both stdin and stdout, so cron and pipes never see it.
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::downloader::Downloader;
+use crate::feed::Feed;
+use crate::i18n::{t, tf, Msg};
+use crate::output;
+use crate::paths;
+use crate::style::{self, Role};
+
+/// Whether a wizard may prompt at all
+pub fn is_interactive() -> bool {
+    io::stdin().is_terminal() && io::stdout().is_terminal()
+}
+
+/// Ask the questions and merge the answers into `config.toml`, keeping any other keys
+pub fn run() -> Result<()> {
+    let config = Config::load().unwrap_or_default();
+    let config_path = Config::config_path()?;
+
+    println!("{}\n", style::paint(Role::Title, t(Msg::WizardWelcome)));
+
+    let default_dir = match config.download_dir {
+        Some(dir) => dir,
+        None => Downloader::default_dir()?,
+    };
+    let download_dir = ask(&tf(
+        Msg::WizardDownloadDir,
+        &[&default_dir.display(), &free_space(&default_dir)],
+    ))?
+    .map(|answer| expand_home(&answer))
+    .unwrap_or(default_dir);
+
+    let default_volume = config.volume.unwrap_or(100);
+    let volume = match ask(&tf(Msg::WizardVolume, &[&default_volume]))? {
+        Some(answer) => answer
+            .parse::<u8>()
+            .ok()
+            .filter(|v| *v <= 100)
+            .unwrap_or_else(|| {
+                println!("{}", t(Msg::WizardInvalid));
+                default_volume
+            }),
+        None => default_volume,
+    };
+
+    let mpris = ask_yes_no(t(Msg::WizardMpris), config.mpris.unwrap_or(true))?;
+    let prefetch = ask_yes_no(t(Msg::WizardPrefetch), true)?;
+
+    let mut table: toml::Table = match fs::read_to_string(&config_path) {
+        Ok(content) => content.parse().context("Failed to parse config file")?,
+        Err(_) => toml::Table::new(),
+    };
+    table.insert(
+        "download_dir".into(),
+        download_dir.display().to_string().into(),
+    );
+    table.insert("volume".into(), i64::from(volume).into());
+    table.insert("mpris".into(), mpris.into());
+
+    if let Some(parent) = config_path.parent() {
+        fs::create_dir_all(parent).context("Failed to create config directory")?;
+    }
+    fs::write(&config_path, toml::to_string(&table)?).context("Failed to write config file")?;
+    fs::create_dir_all(&download_dir)
+        .with_context(|| format!("Failed to create {}", download_dir.display()))?;
+
+    if prefetch {
+        output::status(t(Msg::FetchingEpisodes));
+        if let Err(e) = Feed::fetch().map_err(anyhow::Error::from) {
+            eprintln!(
+                "{} {:#}",
+                style::paint_err(Role::Error, t(Msg::ErrorLabel)),
+                e
+            );
+        }
+    }
+
+    let config_dir = config_path
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+    let data_dir = paths::data_dir()?;
+    let cache_dir = paths::cache_dir()?;
+    println!(
+        "\n{}",
+        style::paint(
+            Role::Success,
+            &tf(Msg::WizardSaved, &[&config_path.display()])
+        )
+    );
+    println!(
+        "{}",
+        tf(
+            Msg::WizardPaths,
+            &[
+                &download_dir.display(),
+                &config_dir.display(),
+                &data_dir.display(),
+                &cache_dir.display()
+            ]
+        )
+    );
+    println!("{}\n", t(Msg::WizardRerun));
+
+    Ok(())
+}
+
+/// Print `prompt` and read one line; None for an empty answer
+fn ask(prompt: &str) -> Result<Option<String>> {
+    print!("{}", prompt);
+    io::stdout().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    let answer = answer.trim();
+
+    Ok((!answer.is_empty()).then(|| answer.to_string()))
+}
+
+fn ask_yes_no(prompt: &str, default: bool) -> Result<bool> {
+    Ok(match ask(prompt)? {
+        None => default,
+        Some(answer) => match answer.to_lowercase().as_str() {
+            "y" | "yes" | "s" | "si" | "sí" => true,
+            "n" | "no" => false,
+            _ => {
+                println!("{}", t(Msg::WizardInvalid));
+                default
+            }
+        },
+    })
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Free space on the filesystem that holds `path` (or would, once created)
+fn free_space(path: &Path) -> String {
+    let existing = path.ancestors().find(|p| p.exists()).unwrap_or(path);
+
+    match fs2::available_space(existing) {
+        Ok(bytes) => format!("{:.1} GB", bytes as f64 / 1_000_000_000.0),
+        Err(_) => "?".to_string(),
+    }
+}
@@ -0,0 +1,218 @@
+//! `export-state` / `import-state` carry mfp's state between directories intact.
+//! Runs the real binary against two `--config-dir` roots.
+#![cfg(unix)]
+
+mod common;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Output;
+
+use common::{succeeded, Sandbox};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+const CONFIG: &str = "volume = 40\nlanguage = \"en\"\n";
+const FAVORITES: &str = "{\n  \"episodes\": [\n    \"Episode 1: Test\"\n  ]\n}";
+const PLAYLISTS: &str = r#"{"mfp":{"focus":["Episode 1: Test","Episode 2: Other"]}}"#;
+const STATE: &str = r#"{"volume":40,"volume_offsets":{"mfp":{"Episode 1: Test":-10}},"queue":{"mfp":["Episode 2: Other"]}}"#;
+const HISTORY: &str = concat!(
+    r#"{"title":"Episode 1: Test","started_at":100,"listened_secs":60,"completed":false}"#,
+    "\n",
+    r#"{"title":"Episode 2: Other","started_at":200,"listened_secs":2700,"completed":true}"#,
+    "\n"
+);
+const JOURNAL: &str = concat!(
+    r#"{"episode":"Episode 1: Test","position_secs":34,"text":"great bit","written_at":150}"#,
+    "\n"
+);
+
+/// A sandbox with two roots: `a` to back up and `b` to restore into
+struct Roots(Sandbox);
+
+impl Roots {
+    fn new(name: &str) -> Self {
+        let sandbox = Sandbox::new(name);
+        for sub in ["a", "b"] {
+            fs::create_dir_all(sandbox.dir.join(sub)).unwrap();
+        }
+        Roots(sandbox)
+    }
+
+    fn root(&self, name: &str) -> PathBuf {
+        self.0.dir.join(name)
+    }
+
+    fn archive(&self) -> PathBuf {
+        self.0.dir.join("backup.tar.gz")
+    }
+
+    /// Run mfp on the root `name`
+    fn mfp(&self, name: &str, args: &[&str]) -> Output {
+        self.0
+            .command_on(&self.root(name))
+            .args(args)
+            .output()
+            .unwrap()
+    }
+
+    fn mfp_ok(&self, name: &str, args: &[&str]) -> Output {
+        succeeded(self.mfp(name, args), args)
+    }
+
+    fn write_state(&self, name: &str) {
+        let root = self.root(name);
+        fs::write(root.join("config.toml"), CONFIG).unwrap();
+        fs::write(root.join("favorites.json"), FAVORITES).unwrap();
+        fs::write(root.join("playlists.json"), PLAYLISTS).unwrap();
+        fs::write(root.join("state.json"), STATE).unwrap();
+        fs::write(root.join("history.jsonl"), HISTORY).unwrap();
+        fs::write(root.join("journal.jsonl"), JOURNAL).unwrap();
+    }
+}
+
+fn read(root: &Path, file: &str) -> String {
+    fs::read_to_string(root.join(file)).unwrap()
+}
+
+#[test]
+fn export_then_replace_reproduces_the_state_files() {
+    let sandbox = Roots::new("backup-roundtrip");
+    sandbox.write_state("a");
+    fs::write(
+        sandbox.root("b").join("favorites.json"),
+        "{\"episodes\":[]}",
+    )
+    .unwrap();
+
+    let archive = sandbox.archive();
+    sandbox.mfp_ok("a", &["export-state", archive.to_str().unwrap()]);
+    sandbox.mfp_ok(
+        "b",
+        &[
+            "import-state",
+            archive.to_str().unwrap(),
+            "--replace",
+            "--yes",
+        ],
+    );
+
+    for file in [
+        "config.toml",
+        "favorites.json",
+        "playlists.json",
+        "state.json",
+        "history.jsonl",
+        "journal.jsonl",
+    ] {
+        assert_eq!(
+            fs::read(sandbox.root("a").join(file)).unwrap(),
+            fs::read(sandbox.root("b").join(file)).unwrap(),
+            "{} differs",
+            file
+        );
+    }
+}
+
+#[test]
+fn merge_keeps_local_state_and_adds_the_backup() {
+    let sandbox = Roots::new("backup-merge");
+    sandbox.write_state("a");
+    let b = sandbox.root("b");
+    fs::write(b.join("config.toml"), "volume = 90\n").unwrap();
+    fs::write(
+        b.join("playlists.json"),
+        r#"{"mfp":{"focus":["Episode 2: Other"]}}"#,
+    )
+    .unwrap();
+    fs::write(b.join("state.json"), r#"{"volume":90}"#).unwrap();
+    fs::write(
+        b.join("history.jsonl"),
+        r#"{"title":"Episode 3: Local","started_at":150,"listened_secs":10,"completed":false}"#
+            .to_owned()
+            + "\n",
+    )
+    .unwrap();
+    sandbox.mfp_ok("b", &["fav", "--add", "Episode 3: Local"]);
+
+    let archive = sandbox.archive();
+    sandbox.mfp_ok("a", &["export-state", archive.to_str().unwrap()]);
+    sandbox.mfp_ok("b", &["import-state", archive.to_str().unwrap()]);
+    // A second merge adds nothing
+    sandbox.mfp_ok("b", &["import-state", archive.to_str().unwrap(), "--merge"]);
+
+    let config: toml::Table = read(&b, "config.toml").parse().unwrap();
+    assert_eq!(config["volume"].as_integer(), Some(90));
+    assert_eq!(config["language"].as_str(), Some("en"));
+
+    // Titles missing from a playlist are added after the local ones
+    let playlists: serde_json::Value = serde_json::from_str(&read(&b, "playlists.json")).unwrap();
+    assert_eq!(
+        playlists["mfp"]["focus"],
+        serde_json::json!(["Episode 2: Other", "Episode 1: Test"])
+    );
+    // The local volume wins; what only the backup has is taken
+    let state: serde_json::Value = serde_json::from_str(&read(&b, "state.json")).unwrap();
+    assert_eq!(state["volume"], 90);
+    assert_eq!(state["volume_offsets"]["mfp"]["Episode 1: Test"], -10);
+    assert_eq!(
+        state["queue"]["mfp"],
+        serde_json::json!(["Episode 2: Other"])
+    );
+
+    // The merge dropped the undo history, so the add from before it stays
+    sandbox.mfp_ok("b", &["fav", "--undo"]);
+    let favorites = String::from_utf8(sandbox.mfp_ok("b", &["fav", "--list"]).stdout).unwrap();
+    assert!(favorites.contains("Episode 1: Test"));
+    assert!(favorites.contains("Episode 3: Local"));
+
+    let started: Vec<u64> = read(&b, "history.jsonl")
+        .lines()
+        .map(|line| {
+            serde_json::from_str::<serde_json::Value>(line).unwrap()["started_at"]
+                .as_u64()
+                .unwrap()
+        })
+        .collect();
+    assert_eq!(started, [100, 150, 200]);
+    assert_eq!(read(&b, "journal.jsonl"), JOURNAL);
+}
+
+#[test]
+fn replace_needs_confirmation() {
+    let sandbox = Roots::new("backup-confirm");
+    sandbox.write_state("a");
+    let archive = sandbox.archive();
+    sandbox.mfp_ok("a", &["export-state", archive.to_str().unwrap()]);
+
+    let output = sandbox.mfp(
+        "b",
+        &["import-state", archive.to_str().unwrap(), "--replace"],
+    );
+    assert!(!output.status.success());
+    assert!(!sandbox.root("b").join("favorites.json").exists());
+}
+
+#[test]
+fn newer_archive_versions_are_refused() {
+    let sandbox = Roots::new("backup-version");
+    let archive = sandbox.archive();
+
+    let manifest = br#"{"version":99,"mfp_version":"99.0.0","created_at":0}"#;
+    let mut builder = tar::Builder::new(GzEncoder::new(
+        fs::File::create(&archive).unwrap(),
+        Compression::default(),
+    ));
+    let mut header = tar::Header::new_gnu();
+    header.set_size(manifest.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    builder
+        .append_data(&mut header, "manifest.json", &manifest[..])
+        .unwrap();
+    builder.into_inner().unwrap().finish().unwrap();
+
+    let output = sandbox.mfp("b", &["import-state", archive.to_str().unwrap()]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("format 99"));
+}
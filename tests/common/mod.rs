@@ -0,0 +1,129 @@
+//! Shared by the integration tests: the fixture feed on a local server, and sandboxes for
+//! the state mfp writes. The library calls of a test write to the sandbox's root once it is
+//! set with `paths::set_root`; runs of the real binary also get HOME and the XDG directories
+//! pointed at a decoy tree there, so nothing outside the sandbox is touched. That only holds
+//! on Unix: elsewhere those variables don't decide where files go, so the tests running the
+//! binary are Unix only.
+// Every test crate uses a different part of it
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use tiny_http::{Response, Server};
+
+/// The fixture feed, with `{base}` where the server's URL goes
+pub const FEED: &str = include_str!("../fixtures/feed.xml");
+
+/// Serve each feed at its path, with the server's URL for `{base}`, and a small blob at any
+/// other path; returns the server's URL
+pub fn serve(feeds: &[(&str, &str)]) -> String {
+    serve_counted(feeds).0
+}
+
+/// `serve`, also counting the requests for the feeds
+pub fn serve_counted(feeds: &[(&str, &str)]) -> (String, Arc<AtomicUsize>) {
+    let server = Server::http("127.0.0.1:0").unwrap();
+    let base = format!("http://{}", server.server_addr().to_ip().unwrap());
+    let requests = Arc::new(AtomicUsize::new(0));
+
+    let feeds: Vec<(String, String)> = feeds
+        .iter()
+        .map(|(path, feed)| (path.to_string(), feed.replace("{base}", &base)))
+        .collect();
+    let count = Arc::clone(&requests);
+    thread::spawn(move || {
+        for request in server.incoming_requests() {
+            let body = match feeds.iter().find(|(path, _)| path == request.url()) {
+                Some((_, feed)) => {
+                    count.fetch_add(1, Ordering::SeqCst);
+                    feed.clone().into_bytes()
+                }
+                None => vec![7u8; 4096],
+            };
+            let _ = request.respond(Response::from_data(body));
+        }
+    });
+
+    (base, requests)
+}
+
+/// A temporary directory holding the root mfp runs on and the decoy home, removed on drop
+pub struct Sandbox {
+    pub dir: PathBuf,
+}
+
+impl Sandbox {
+    pub fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("mfp-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("root")).unwrap();
+        fs::create_dir_all(dir.join("home")).unwrap();
+        Sandbox { dir }
+    }
+
+    pub fn root(&self) -> PathBuf {
+        self.dir.join("root")
+    }
+
+    pub fn home(&self) -> PathBuf {
+        self.dir.join("home")
+    }
+
+    /// mfp with the decoy home and no color, not yet told where its root is
+    pub fn bare(&self) -> Command {
+        let home = self.home();
+        let mut command = Command::new(env!("CARGO_BIN_EXE_mfp"));
+        command
+            .env_clear()
+            .env("HOME", &home)
+            .env("XDG_CONFIG_HOME", home.join(".config"))
+            .env("XDG_CACHE_HOME", home.join(".cache"))
+            .env("XDG_DATA_HOME", home.join(".local/share"))
+            .env("NO_COLOR", "1")
+            .stdin(Stdio::null());
+        command
+    }
+
+    /// mfp on the root `root`
+    pub fn command_on(&self, root: &Path) -> Command {
+        let mut command = self.bare();
+        command.arg("--config-dir").arg(root);
+        command
+    }
+
+    /// Run mfp on the sandbox's root
+    pub fn run(&self, args: &[&str]) -> Output {
+        self.command_on(&self.root()).args(args).output().unwrap()
+    }
+
+    /// `run`, failing the test if mfp fails
+    pub fn mfp(&self, args: &[&str]) -> Output {
+        succeeded(self.run(args), args)
+    }
+}
+
+impl Drop for Sandbox {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// `output`, once it is checked that mfp ran with `args` successfully
+pub fn succeeded(output: Output, args: &[&str]) -> Output {
+    assert!(
+        output.status.success(),
+        "mfp {:?} failed: {}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    output
+}
+
+pub fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
@@ -0,0 +1,68 @@
+//! `--config-dir` / `MFP_CONFIG_DIR` keep every file mfp writes inside one directory.
+//! Runs the real binary with HOME and the XDG directories pointed at a decoy tree
+//! that must stay empty.
+#![cfg(unix)]
+
+mod common;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use common::{serve, succeeded, Sandbox, FEED};
+
+/// Every file below `dir`, relative to it
+fn files(dir: &Path) -> Vec<PathBuf> {
+    let mut found = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+
+    while let Some(current) = pending.pop() {
+        for entry in fs::read_dir(&current).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                pending.push(path);
+            } else {
+                found.push(path.strip_prefix(dir).unwrap().to_path_buf());
+            }
+        }
+    }
+
+    found.sort();
+    found
+}
+
+#[test]
+fn all_state_stays_inside_the_config_dir() {
+    let base = serve(&[("/rss.xml", FEED)]);
+    let sandbox = Sandbox::new("config-dir");
+    fs::write(
+        sandbox.root().join("config.toml"),
+        format!("feed_url = \"{}/rss.xml\"\n", base),
+    )
+    .unwrap();
+
+    sandbox.mfp(&["fav", "--add", "Episode 1: Datassette"]);
+    sandbox.mfp(&["list"]);
+    sandbox.mfp(&["download", "--latest"]);
+    // The environment variable picks the same root
+    let args = ["fav", "--list"];
+    let listed = sandbox
+        .bare()
+        .env("MFP_CONFIG_DIR", sandbox.root())
+        .args(args)
+        .output()
+        .unwrap();
+    let listed = succeeded(listed, &args);
+
+    assert!(String::from_utf8_lossy(&listed.stdout).contains("Episode 1: Datassette"));
+    assert_eq!(
+        files(&sandbox.root()),
+        [
+            PathBuf::from("cache/feed.json"),
+            PathBuf::from("cache/mfp.lock"),
+            PathBuf::from("config.toml"),
+            PathBuf::from("downloads/Episode 3- Datassette.mp3"),
+            PathBuf::from("favorites.json"),
+        ]
+    );
+    assert_eq!(files(&sandbox.home()), Vec::<PathBuf>::new());
+}
@@ -0,0 +1,50 @@
+//! `mfp fav --undo` / `--redo` walk back and forth through the last favorites changes.
+//! Runs the real binary on a `--config-dir` root.
+#![cfg(unix)]
+
+mod common;
+
+use common::Sandbox;
+
+/// Run `mfp fav` and return its stdout and stderr together
+fn fav(sandbox: &Sandbox, args: &[&str]) -> String {
+    let output = sandbox.mfp(&[&["fav"], args].concat());
+    String::from_utf8_lossy(&output.stdout).into_owned() + &String::from_utf8_lossy(&output.stderr)
+}
+
+fn favorites(sandbox: &Sandbox) -> String {
+    fav(sandbox, &["--list"])
+}
+
+#[test]
+fn undo_takes_changes_back_newest_first_and_redo_makes_them_again() {
+    let sandbox = Sandbox::new("fav-undo");
+    fav(&sandbox, &["--add", "Episode 1: Test"]);
+    fav(&sandbox, &["--add", "Episode 2: Other"]);
+    fav(&sandbox, &["--remove", "Episode 1: Test"]);
+
+    fav(&sandbox, &["--undo"]);
+    assert!(favorites(&sandbox).contains("Episode 1: Test"));
+    fav(&sandbox, &["--undo"]);
+    assert!(!favorites(&sandbox).contains("Episode 2: Other"));
+
+    fav(&sandbox, &["--redo"]);
+    let listed = favorites(&sandbox);
+    assert!(listed.contains("Episode 1: Test"));
+    assert!(listed.contains("Episode 2: Other"));
+
+    // A new change ends the redo chain
+    fav(&sandbox, &["--add", "Episode 3: New"]);
+    fav(&sandbox, &["--redo"]);
+    assert!(favorites(&sandbox).contains("Episode 1: Test"));
+}
+
+#[test]
+fn undo_with_nothing_to_undo_changes_nothing() {
+    let sandbox = Sandbox::new("fav-undo-empty");
+    fav(&sandbox, &["--undo"]);
+    fav(&sandbox, &["--add", "Episode 1: Test"]);
+    fav(&sandbox, &["--undo"]);
+    fav(&sandbox, &["--undo"]);
+    assert!(!favorites(&sandbox).contains("Episode 1: Test"));
+}
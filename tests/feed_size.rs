@@ -0,0 +1,174 @@
+//! Big feeds: a generated 5,000-item feed is parsed as it is read, never held whole, and
+//! `max_items` stops reading at the cap. A counting allocator tracks the peak memory of a parse,
+//! so the tests in this file take turns.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::io::{BufReader, Read};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use mfp::feed::Feed;
+use mfp::playlist::Playlist;
+
+const ITEMS: usize = 5_000;
+const DESCRIPTION_LEN: usize = 4_096;
+
+struct Counting;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+static SERIAL: Mutex<()> = Mutex::new(());
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let now = CURRENT.fetch_add(layout.size(), Ordering::SeqCst) + layout.size();
+            PEAK.fetch_max(now, Ordering::SeqCst);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        CURRENT.fetch_sub(layout.size(), Ordering::SeqCst);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+/// The feed as a reader that writes each item only when it is asked for
+struct GeneratedFeed {
+    next_item: usize,
+    pending: Vec<u8>,
+    offset: usize,
+    finished: bool,
+}
+
+impl GeneratedFeed {
+    fn new() -> Self {
+        GeneratedFeed {
+            next_item: 0,
+            pending: concat!(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+                "<rss version=\"2.0\" xmlns:itunes=\"http://www.itunes.com/dtds/podcast-1.0.dtd\">",
+                "<channel><title>Big</title><image><url>https://example.invalid/cover.jpg</url></image>"
+            )
+            .as_bytes()
+            .to_vec(),
+            offset: 0,
+            finished: false,
+        }
+    }
+
+    /// Size of the whole document
+    fn len() -> usize {
+        let mut feed = GeneratedFeed::new();
+        let mut total = 0;
+        let mut buf = [0; 64 * 1024];
+        while let Ok(n @ 1..) = feed.read(&mut buf) {
+            total += n;
+        }
+        total
+    }
+
+    fn refill(&mut self) {
+        self.offset = 0;
+        self.pending.clear();
+        if self.next_item < ITEMS {
+            let number = ITEMS - self.next_item;
+            self.pending = format!(
+                "<item><title>Episode {n}: Curator {n}</title>\
+                 <link>https://example.invalid/{n}</link>\
+                 <description><![CDATA[<p>{text}</p>]]></description>\
+                 <pubDate>Mon, 03 Mar 2025 00:00:00 +0000</pubDate>\
+                 <enclosure url=\"https://example.invalid/{n}.mp3\" type=\"audio/mpeg\"/>\
+                 <itunes:duration>01:00:00</itunes:duration></item>",
+                n = number,
+                text = "x".repeat(DESCRIPTION_LEN)
+            )
+            .into_bytes();
+            self.next_item += 1;
+        } else if !self.finished {
+            self.pending = b"</channel></rss>".to_vec();
+            self.finished = true;
+        }
+    }
+}
+
+impl Read for GeneratedFeed {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.offset == self.pending.len() {
+            self.refill();
+        }
+        let n = buf.len().min(self.pending.len() - self.offset);
+        buf[..n].copy_from_slice(&self.pending[self.offset..self.offset + n]);
+        self.offset += n;
+        Ok(n)
+    }
+}
+
+/// Parse the generated feed; returns the feed, the time taken and the peak bytes allocated
+fn parse(max_items: Option<usize>) -> (Feed, Duration, usize) {
+    let baseline = CURRENT.load(Ordering::SeqCst);
+    PEAK.store(baseline, Ordering::SeqCst);
+    let started = Instant::now();
+
+    let feed = Feed::read_from(BufReader::new(GeneratedFeed::new()), max_items).unwrap();
+
+    let elapsed = started.elapsed();
+    (feed, elapsed, PEAK.load(Ordering::SeqCst) - baseline)
+}
+
+#[test]
+fn a_large_feed_parses_in_bounded_time_and_memory() {
+    let _turn = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let document = GeneratedFeed::len();
+
+    let (feed, elapsed, peak) = parse(None);
+
+    assert_eq!(feed.episodes().len(), ITEMS);
+    assert!(elapsed < Duration::from_secs(20), "took {:?}", elapsed);
+    // The episodes hold nearly all of the text; a second copy of the document would double it
+    assert!(
+        peak < document * 3 / 2,
+        "peak {} bytes for a {} byte feed",
+        peak,
+        document
+    );
+    assert_eq!(
+        feed.episodes()[0].image_url.as_deref(),
+        Some("https://example.invalid/cover.jpg")
+    );
+}
+
+#[test]
+fn the_item_cap_stops_reading_early() {
+    let _turn = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let document = GeneratedFeed::len();
+
+    let (feed, _, peak) = parse(Some(100));
+
+    assert_eq!(feed.episodes().len(), 100);
+    assert!(
+        peak < document / 20,
+        "peak {} bytes for a {} byte feed",
+        peak,
+        document
+    );
+
+    // The newest episodes, numbered from their titles as usual
+    assert_eq!(feed.number(0), Some(ITEMS));
+    assert_eq!(feed.number(99), Some(ITEMS - 99));
+    assert!(feed.find_by_number(ITEMS - 50).is_some());
+    assert!(feed.find_by_number(1).is_none());
+
+    let mut playlist = Playlist::new(feed.shared_episodes());
+    playlist.previous();
+    assert_eq!(
+        playlist.current().map(|ep| ep.title.as_str()),
+        Some("Episode 4901: Curator 4901")
+    );
+}
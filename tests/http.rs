@@ -0,0 +1,606 @@
+//! Feed → playlist → download against a local mock server
+//! No real network and no audio device: everything is served from 127.0.0.1.
+
+use std::fs;
+use std::io::Read;
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use mfp::downloader::{DownloadError, Downloader};
+use mfp::errors::{exit_code, EXIT_NETWORK, EXIT_NOT_FOUND};
+use mfp::feed::{Feed, FeedError};
+use mfp::http::{self, Aborted, RedirectError};
+use mfp::i18n::{t, Msg};
+use mfp::playlist::Playlist;
+use tiny_http::{Header, Request, Response, Server};
+use tokio_util::sync::CancellationToken;
+
+const FEED: &str = include_str!("fixtures/feed.xml");
+const AUDIO_LEN: usize = 100_000;
+
+/// Deterministic stand-in for an MP3, long enough to span several read chunks
+fn audio() -> Vec<u8> {
+    (0..AUDIO_LEN).map(|i| (i % 251) as u8).collect()
+}
+
+/// Serves the fixture feed and audio, honoring `Range` unless `ranges` is false.
+/// Returns the base URL and the `Range` header of every audio request.
+fn serve(ranges: bool) -> (String, Arc<Mutex<Vec<Option<String>>>>) {
+    let server = Server::http("127.0.0.1:0").unwrap();
+    let base = format!("http://{}", server.server_addr().to_ip().unwrap());
+    let seen = Arc::new(Mutex::new(Vec::new()));
+
+    let feed = FEED.replace("{base}", &base);
+    let log = Arc::clone(&seen);
+    thread::spawn(move || {
+        for request in server.incoming_requests() {
+            let range = header(&request, "Range");
+            let url = request.url().to_string();
+
+            let response = if url == "/rss.xml" {
+                Response::from_data(feed.clone().into_bytes())
+            } else if url == "/not-rss.xml" {
+                Response::from_data(b"<html>maintenance</html>".to_vec())
+            } else if url.starts_with("/audio/") && url != "/audio/404.mp3" {
+                log.lock().unwrap().push(range.clone());
+                let body = audio();
+                let start = range
+                    .filter(|_| ranges)
+                    .and_then(|r| r.strip_prefix("bytes=")?.strip_suffix('-')?.parse().ok());
+                match start {
+                    Some(start) => Response::from_data(body[start..].to_vec())
+                        .with_status_code(206)
+                        .with_header(
+                            format!(
+                                "Content-Range: bytes {}-{}/{}",
+                                start,
+                                AUDIO_LEN - 1,
+                                AUDIO_LEN
+                            )
+                            .parse::<Header>()
+                            .unwrap(),
+                        ),
+                    None => Response::from_data(body),
+                }
+            } else {
+                Response::from_data(Vec::new()).with_status_code(404)
+            };
+            let _ = request.respond(response);
+        }
+    });
+
+    (base, seen)
+}
+
+fn header(request: &Request, name: &'static str) -> Option<String> {
+    request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv(name))
+        .map(|h| h.value.to_string())
+}
+
+/// Fresh empty directory under the system temp dir
+fn temp_dir() -> PathBuf {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    let dir = std::env::temp_dir().join(format!(
+        "mfp-test-{}-{}",
+        std::process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+/// Accepts connections and reads requests but never answers
+fn silent_server() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            thread::spawn(move || {
+                let _ = stream.read(&mut [0u8; 1024]);
+                thread::sleep(Duration::from_secs(5));
+            });
+        }
+    });
+    format!("http://{}", addr)
+}
+
+/// Headers of a request as the redirecting server saw it
+#[derive(Debug, Clone)]
+struct Hop {
+    url: String,
+    range: Option<String>,
+    agent: Option<String>,
+    encoding: Option<String>,
+}
+
+/// Serves the audio under `/audio/` behind redirects: `/moved/<n>` answers 301 to the audio,
+/// `/temp/<n>` 307 to `/moved/<n>`, `/feed` 302 to `/rss.xml` and `/loop` to itself.
+/// Returns the base URL and every request made.
+fn redirecting_server() -> (String, Arc<Mutex<Vec<Hop>>>) {
+    let server = Server::http("127.0.0.1:0").unwrap();
+    let base = format!("http://{}", server.server_addr().to_ip().unwrap());
+    let seen = Arc::new(Mutex::new(Vec::new()));
+
+    let feed = FEED.replace("{base}", &base);
+    let log = Arc::clone(&seen);
+    thread::spawn(move || {
+        for request in server.incoming_requests() {
+            let url = request.url().to_string();
+            let range = header(&request, "Range");
+            log.lock().unwrap().push(Hop {
+                url: url.clone(),
+                range: range.clone(),
+                agent: header(&request, "User-Agent"),
+                encoding: header(&request, "Accept-Encoding"),
+            });
+
+            let redirect = |status: u16, to: String| {
+                Response::from_data(Vec::new())
+                    .with_status_code(status)
+                    .with_header(format!("Location: {}", to).parse::<Header>().unwrap())
+            };
+            let response = if let Some(n) = url.strip_prefix("/moved/") {
+                redirect(301, format!("/audio/{}", n))
+            } else if let Some(n) = url.strip_prefix("/temp/") {
+                redirect(307, format!("/moved/{}", n))
+            } else if url == "/feed" {
+                redirect(302, "/rss.xml".to_string())
+            } else if url == "/loop" {
+                redirect(302, "/loop".to_string())
+            } else if url == "/rss.xml" {
+                Response::from_data(feed.clone().into_bytes())
+            } else if url.starts_with("/audio/") {
+                let start = range
+                    .and_then(|r| r.strip_prefix("bytes=")?.strip_suffix('-')?.parse().ok())
+                    .unwrap_or(0);
+                Response::from_data(audio()[start..].to_vec()).with_status_code(206)
+            } else {
+                Response::from_data(Vec::new()).with_status_code(404)
+            };
+            let _ = request.respond(response);
+        }
+    });
+
+    (base, seen)
+}
+
+#[test]
+fn feed_parses_episodes_with_audio() {
+    let (base, _) = serve(true);
+    let feed = Feed::fetch_from(&format!("{}/rss.xml", base)).unwrap();
+
+    let titles: Vec<_> = feed.episodes().iter().map(|ep| ep.title.as_str()).collect();
+    assert_eq!(
+        titles,
+        [
+            "Episode 3: Datassette",
+            "Episode 2: Uberdog",
+            "Episode 1: Datassette"
+        ]
+    );
+
+    let first = &feed.episodes()[0];
+    assert_eq!(first.audio_url, format!("{}/audio/3.mp3", base));
+    assert_eq!(first.duration, "01:02:03");
+    assert_eq!(
+        first.page_url.as_deref(),
+        Some("https://musicforprogramming.net/three")
+    );
+    assert_eq!(feed.episodes()[1].page_url, None);
+    assert!(feed.find_by_title("Episode 2: Uberdog").is_some());
+}
+
+#[test]
+fn missing_feed_is_not_found() {
+    let (base, _) = serve(true);
+    let err = Feed::fetch_from(&format!("{}/missing.xml", base))
+        .err()
+        .unwrap();
+
+    assert!(matches!(err, FeedError::Status { status: 404 }));
+    assert_eq!(exit_code(&err.into()), EXIT_NOT_FOUND);
+}
+
+#[test]
+fn non_rss_response_is_a_parse_error() {
+    let (base, _) = serve(true);
+    let err = Feed::fetch_from(&format!("{}/not-rss.xml", base))
+        .err()
+        .unwrap();
+
+    assert!(matches!(err, FeedError::Parse(_)));
+}
+
+#[test]
+fn playlist_from_fetched_feed_jumps_and_shuffles() {
+    let (base, _) = serve(true);
+    let feed = Feed::fetch_from(&format!("{}/rss.xml", base)).unwrap();
+    let mut playlist = Playlist::new(feed.episodes().to_vec());
+
+    let title = |ep: Option<&mfp::feed::Episode>| ep.map(|ep| ep.title.clone());
+    assert_eq!(
+        title(playlist.jump_to(2)).as_deref(),
+        Some("Episode 1: Datassette")
+    );
+    assert_eq!(
+        title(playlist.next()).as_deref(),
+        Some("Episode 3: Datassette")
+    );
+    assert!(playlist.jump_to(3).is_none());
+
+    playlist.enable_shuffle();
+    assert_eq!(
+        title(playlist.jump_to(1)).as_deref(),
+        Some("Episode 2: Uberdog")
+    );
+
+    let mut order = vec![title(playlist.current()).unwrap()];
+    for _ in 1..playlist.len() {
+        order.push(title(playlist.next()).unwrap());
+    }
+    order.sort();
+    assert_eq!(
+        order,
+        [
+            "Episode 1: Datassette",
+            "Episode 2: Uberdog",
+            "Episode 3: Datassette"
+        ]
+    );
+}
+
+#[test]
+fn download_writes_the_whole_file() {
+    let (base, seen) = serve(true);
+    let dir = temp_dir();
+    let downloader = Downloader::with_dir(dir.clone()).unwrap();
+
+    let path = downloader
+        .download_episode("Episode 3: Datassette", &format!("{}/audio/3.mp3", base))
+        .unwrap();
+
+    assert_eq!(fs::read(&path).unwrap(), audio());
+    assert!(downloader.is_downloaded("Episode 3: Datassette"));
+    assert_eq!(downloader.list_downloaded().unwrap(), [path]);
+    assert_eq!(*seen.lock().unwrap(), [None]);
+
+    // A second call finds the file and stays off the network
+    downloader
+        .download_episode("Episode 3: Datassette", &format!("{}/audio/3.mp3", base))
+        .unwrap();
+    assert_eq!(seen.lock().unwrap().len(), 1);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn download_resumes_a_partial_file() {
+    let (base, seen) = serve(true);
+    let dir = temp_dir();
+    let downloader = Downloader::with_dir(dir.clone()).unwrap();
+    fs::write(dir.join("Episode 2.tmp"), &audio()[..40_000]).unwrap();
+
+    let path = downloader
+        .download_episode("Episode 2", &format!("{}/audio/2.mp3", base))
+        .unwrap();
+
+    assert_eq!(fs::read(&path).unwrap(), audio());
+    assert_eq!(*seen.lock().unwrap(), [Some("bytes=40000-".to_string())]);
+    assert!(!dir.join("Episode 2.tmp").exists());
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn download_restarts_when_the_server_ignores_ranges() {
+    let (base, _) = serve(false);
+    let dir = temp_dir();
+    let downloader = Downloader::with_dir(dir.clone()).unwrap();
+    fs::write(dir.join("Episode 1.tmp"), b"stale partial data").unwrap();
+
+    let path = downloader
+        .download_episode("Episode 1", &format!("{}/audio/1.mp3", base))
+        .unwrap();
+
+    assert_eq!(fs::read(&path).unwrap(), audio());
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn missing_audio_is_not_found() {
+    let (base, _) = serve(true);
+    let dir = temp_dir();
+    let downloader = Downloader::with_dir(dir.clone()).unwrap();
+
+    let err = downloader
+        .download_episode("Episode 404", &format!("{}/audio/404.mp3", base))
+        .unwrap_err();
+
+    assert!(matches!(err, DownloadError::Status { status: 404 }));
+    assert_eq!(exit_code(&err.into()), EXIT_NOT_FOUND);
+    assert!(!downloader.is_downloaded("Episode 404"));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn silent_server_times_out_as_network_error() {
+    let base = silent_server();
+    let dir = temp_dir();
+    let downloader = Downloader::with_dir(dir.clone())
+        .unwrap()
+        .with_timeout(Duration::from_millis(200));
+
+    let err = downloader
+        .download_episode("Episode 9", &format!("{}/audio/9.mp3", base))
+        .unwrap_err();
+
+    assert!(matches!(
+        &err,
+        DownloadError::Aborted(Aborted::Stalled { .. })
+    ));
+    assert_eq!(exit_code(&err.into()), EXIT_NETWORK);
+    assert!(!downloader.is_downloaded("Episode 9"));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn client_timeouts_say_the_server_did_not_answer() {
+    let base = silent_server();
+    // The timeout's timer belongs to the runtime the client is built in
+    let _runtime = http::runtime().enter();
+    let client = http::builder()
+        .unwrap()
+        .timeout(Duration::from_millis(200))
+        .build()
+        .unwrap();
+
+    let err = http::block_on(client.get(format!("{}/rss.xml", base)).send()).unwrap_err();
+    assert!(err.is_timeout());
+
+    let err = FeedError::from(err);
+    assert_eq!(err.to_string(), t(Msg::ServerNoAnswer));
+    assert_eq!(exit_code(&err.into()), EXIT_NETWORK);
+}
+
+#[test]
+fn batch_downloads_reuse_one_connection() {
+    // Records the client side of the connection and the user agent of every request
+    let server = Server::http("127.0.0.1:0").unwrap();
+    let base = format!("http://{}", server.server_addr().to_ip().unwrap());
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let log = Arc::clone(&seen);
+    thread::spawn(move || {
+        for request in server.incoming_requests() {
+            log.lock().unwrap().push((
+                request.remote_addr().copied(),
+                header(&request, "User-Agent"),
+            ));
+            let _ = request.respond(Response::from_data(audio()));
+        }
+    });
+
+    let dir = temp_dir();
+    let downloader = Downloader::with_dir(dir.clone()).unwrap();
+    for n in 1..=3 {
+        downloader
+            .download_episode(
+                &format!("Episode {}: Batch", n),
+                &format!("{}/audio/{}.mp3", base, n),
+            )
+            .unwrap();
+    }
+
+    let seen = seen.lock().unwrap();
+    assert_eq!(seen.len(), 3);
+    assert!(
+        seen.iter().all(|(addr, _)| *addr == seen[0].0),
+        "new connection per download: {:?}",
+        seen
+    );
+    assert!(seen
+        .iter()
+        .all(|(_, agent)| agent.as_deref() == Some(mfp::http::USER_AGENT)));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn deleting_a_missing_download_names_the_episode() {
+    let dir = temp_dir();
+    let downloader = Downloader::with_dir(dir.clone()).unwrap();
+
+    let err = downloader.delete_episode("Episode 7").unwrap_err();
+
+    assert!(matches!(&err, DownloadError::NotDownloaded { title } if title == "Episode 7"));
+    assert_eq!(exit_code(&err.into()), EXIT_NOT_FOUND);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn async_feed_fetch_parses_episodes() {
+    let (base, _) = serve(true);
+    let feed = Feed::fetch_from_async(&format!("{}/rss.xml", base), &CancellationToken::new())
+        .await
+        .unwrap();
+
+    assert_eq!(feed.episodes().len(), 3);
+}
+
+#[tokio::test]
+async fn cancelling_a_download_stops_waiting_at_once() {
+    let base = silent_server();
+    let dir = temp_dir();
+    let downloader = Downloader::with_dir(dir.clone()).unwrap();
+
+    let cancel = CancellationToken::new();
+    let trigger = cancel.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        trigger.cancel();
+    });
+
+    let started = std::time::Instant::now();
+    let err = downloader
+        .download_episode_async("Episode 9", &format!("{}/audio/9.mp3", base), &cancel)
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, DownloadError::Aborted(Aborted::Cancelled)));
+    assert!(started.elapsed() < Duration::from_secs(2));
+    assert!(!downloader.is_downloaded("Episode 9"));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn download_many_keeps_order_and_failures_apart() {
+    let (base, seen) = serve(true);
+    let dir = temp_dir();
+    let downloader = Downloader::with_dir(dir.clone()).unwrap();
+
+    let episodes: Vec<(String, String)> = [3, 404, 1]
+        .iter()
+        .map(|n| {
+            (
+                format!("Episode {}: Many", n),
+                format!("{}/audio/{}.mp3", base, n),
+            )
+        })
+        .collect();
+    let results = downloader
+        .download_many_async(&episodes, 2, &CancellationToken::new())
+        .await;
+
+    assert_eq!(results.len(), 3);
+    assert_eq!(fs::read(results[0].as_ref().unwrap()).unwrap(), audio());
+    assert!(matches!(
+        results[1],
+        Err(DownloadError::Status { status: 404 })
+    ));
+    assert!(results[2]
+        .as_ref()
+        .unwrap()
+        .ends_with("Episode 1- Many.mp3"));
+    assert_eq!(seen.lock().unwrap().len(), 2);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn feed_follows_redirects_with_the_user_agent() {
+    let (base, seen) = redirecting_server();
+    let feed = Feed::fetch_from(&format!("{}/feed", base)).unwrap();
+
+    assert_eq!(feed.episodes().len(), 3);
+    let seen = seen.lock().unwrap();
+    assert_eq!(
+        seen.iter().map(|hop| hop.url.as_str()).collect::<Vec<_>>(),
+        ["/feed", "/rss.xml"]
+    );
+    assert!(seen
+        .iter()
+        .all(|hop| hop.agent.as_deref() == Some(http::USER_AGENT)));
+}
+
+#[test]
+fn downloads_resume_through_redirects() {
+    let (base, seen) = redirecting_server();
+    let dir = temp_dir();
+    let downloader = Downloader::with_dir(dir.clone()).unwrap();
+    fs::write(dir.join("Episode 5.tmp"), &audio()[..30_000]).unwrap();
+
+    let path = downloader
+        .download_episode("Episode 5", &format!("{}/temp/5.mp3", base))
+        .unwrap();
+
+    assert_eq!(fs::read(&path).unwrap(), audio());
+    let seen = seen.lock().unwrap();
+    assert_eq!(seen.len(), 3);
+    assert!(seen
+        .iter()
+        .all(|hop| hop.range.as_deref() == Some("bytes=30000-")));
+    assert!(seen
+        .iter()
+        .all(|hop| hop.encoding.as_deref() == Some("identity")));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn audio_redirects_keep_the_range_and_remember_permanent_moves() {
+    let (base, seen) = redirecting_server();
+    let cancel = CancellationToken::new();
+
+    let moved = http::get_audio_from(
+        &format!("{}/moved/1.mp3", base),
+        1000,
+        http::DEFAULT_TIMEOUT,
+        &cancel,
+    )
+    .await
+    .unwrap();
+    assert_eq!(moved.response.status().as_u16(), 206);
+    assert_eq!(moved.moved_to, Some(format!("{}/audio/1.mp3", base)));
+    assert_eq!(moved.response.bytes().await.unwrap(), audio()[1000..]);
+
+    // A temporary hop on the way means the original URL stays the one to ask
+    let temporary = http::get_audio_from(
+        &format!("{}/temp/2.mp3", base),
+        500,
+        http::DEFAULT_TIMEOUT,
+        &cancel,
+    )
+    .await
+    .unwrap();
+    assert_eq!(temporary.moved_to, None);
+    assert_eq!(temporary.response.bytes().await.unwrap(), audio()[500..]);
+
+    let seen = seen.lock().unwrap();
+    assert_eq!(
+        seen.iter().map(|hop| hop.url.as_str()).collect::<Vec<_>>(),
+        [
+            "/moved/1.mp3",
+            "/audio/1.mp3",
+            "/temp/2.mp3",
+            "/moved/2.mp3",
+            "/audio/2.mp3"
+        ]
+    );
+    assert_eq!(seen[1].range.as_deref(), Some("bytes=1000-"));
+    assert!(seen[2..]
+        .iter()
+        .all(|hop| hop.range.as_deref() == Some("bytes=500-")));
+    assert!(seen
+        .iter()
+        .all(|hop| hop.agent.as_deref() == Some(http::USER_AGENT)));
+}
+
+#[tokio::test]
+async fn redirect_loops_give_up() {
+    let (base, seen) = redirecting_server();
+
+    let err = http::get_audio_from(
+        &format!("{}/loop", base),
+        0,
+        http::DEFAULT_TIMEOUT,
+        &CancellationToken::new(),
+    )
+    .await
+    .unwrap_err();
+
+    assert!(matches!(err, RedirectError::TooMany));
+    assert_eq!(err.to_string(), t(Msg::TooManyRedirects));
+    assert_eq!(seen.lock().unwrap().len(), http::MAX_REDIRECTS + 1);
+}
@@ -0,0 +1,151 @@
+//! What plays from the cached feed sends no request for it: a fresh feed is fetched only when
+//! asked for, when the cache is missing or old, or when the episode to pick up isn't in it.
+//! The feed comes from a local mock server that counts requests, and the player makes no
+//! sound.
+
+mod common;
+
+use std::fs;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, SystemTime};
+
+use common::{serve_counted, Sandbox, FEED};
+use mfp::audio::{Call, NullPlayer};
+use mfp::feed::{LazyFeed, CACHE_MAX_AGE};
+use mfp::paths;
+use mfp::play::{self, PlayOptions};
+use mfp::terminal::Scripted;
+
+/// The root is set once per process, so the tests take turns with it
+static ROOT: Mutex<()> = Mutex::new(());
+
+/// An empty sandbox whose config points at a fresh feed server, with the requests for the
+/// feed so far
+fn sandbox() -> (MutexGuard<'static, ()>, Sandbox, Arc<AtomicUsize>) {
+    let turn = ROOT.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let (base, requests) = serve_counted(&[("/rss.xml", FEED)]);
+    let sandbox = Sandbox::new("lazy-feed");
+    fs::write(
+        sandbox.root().join("config.toml"),
+        format!("feed_url = \"{}/rss.xml\"\nmpris = false\n", base),
+    )
+    .unwrap();
+    paths::set_root(sandbox.root());
+
+    (turn, sandbox, requests)
+}
+
+/// Make the cache look as if the last fetch was longer ago than it is trusted for
+fn age_cache() {
+    let path = paths::feed_cache_file().unwrap();
+    let long_ago = SystemTime::now() - CACHE_MAX_AGE - Duration::from_secs(60);
+    fs::File::options()
+        .write(true)
+        .open(path)
+        .unwrap()
+        .set_modified(long_ago)
+        .unwrap();
+}
+
+/// Play until the first episode has started, then quit; returns what was streamed
+fn play_and_quit(options: PlayOptions) -> Vec<Call> {
+    let player = NullPlayer::new();
+    let events = Scripted::new().command("q");
+    play::play_with(Box::new(player.clone()), &events, None, None, options).unwrap();
+    assert!(events.is_done());
+    player.calls()
+}
+
+fn streamed(calls: &[Call]) -> Vec<&str> {
+    calls
+        .iter()
+        .filter_map(|call| match call {
+            Call::PlayStream(url) => url.rsplit('/').next(),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn the_feed_is_fetched_only_when_asked_for_fresh_missing_or_old() {
+    let (_turn, _sandbox, requests) = sandbox();
+
+    // Nothing is loaded until something asks
+    let feeds = LazyFeed::new();
+    assert_eq!(requests.load(Ordering::SeqCst), 0);
+
+    // No cache yet: the first look fetches, once
+    let episodes = feeds.get().unwrap().episodes().len();
+    assert!(episodes > 0);
+    assert_eq!(feeds.get().unwrap().episodes().len(), episodes);
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+    // The next run plays from the cache that left behind, without a request
+    let next_run = LazyFeed::new();
+    assert_eq!(next_run.get().unwrap().episodes().len(), episodes);
+    assert!(next_run.cached().is_some());
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+    // A fresh feed is fetched, and only the first time
+    next_run.fresh().unwrap();
+    next_run.fresh().unwrap();
+    next_run.get().unwrap();
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+    // An old cache is still there, but no longer enough
+    age_cache();
+    let later = LazyFeed::new();
+    assert!(later.cached().is_some());
+    later.get().unwrap();
+    assert_eq!(requests.load(Ordering::SeqCst), 3);
+}
+
+#[test]
+fn play_fetches_only_without_a_recent_cache() {
+    let (_turn, _sandbox, requests) = sandbox();
+
+    assert_eq!(streamed(&play_and_quit(PlayOptions::default())), ["3.mp3"]);
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+    play_and_quit(PlayOptions::default());
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+    // New episodes show up once the cache is old
+    age_cache();
+    play_and_quit(PlayOptions::default());
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn continue_picks_up_from_the_cache_without_a_request() {
+    let (_turn, sandbox, requests) = sandbox();
+    LazyFeed::new().fresh().unwrap();
+    age_cache();
+    fs::write(
+        sandbox.root().join("history.jsonl"),
+        concat!(
+            r#"{"title":"Episode 1: Datassette","started_at":100,"listened_secs":60,"completed":false}"#,
+            "\n"
+        ),
+    )
+    .unwrap();
+
+    // However old the cache, it knows the episode
+    let calls = play_and_quit(play::continued(PlayOptions::default()));
+    assert_eq!(streamed(&calls), ["1.mp3"]);
+    assert!(calls.contains(&Call::Seek(60)));
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+    // One the cache doesn't know may be newer, so the feed is fetched for it
+    fs::write(
+        sandbox.root().join("history.jsonl"),
+        concat!(
+            r#"{"title":"Episode 4: Not yet cached","started_at":100,"listened_secs":60,"completed":false}"#,
+            "\n"
+        ),
+    )
+    .unwrap();
+    play_and_quit(play::continued(PlayOptions::default()));
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+}
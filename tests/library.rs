@@ -0,0 +1,347 @@
+//! Exercises the public library surface without network or audio
+
+use mfp::feed::Episode;
+use mfp::player::{format_duration, parse_duration};
+use mfp::playlist::{Playlist, RepeatMode};
+use std::sync::Arc;
+
+fn episode(title: &str) -> Episode {
+    Episode {
+        title: title.to_string(),
+        audio_url: format!("https://example.invalid/{}.mp3", title),
+        duration: "01:00:00".to_string(),
+        pub_date: String::new(),
+        description: String::new(),
+        page_url: None,
+        image_url: None,
+    }
+}
+
+fn episodes(titles: &[&str]) -> Vec<Episode> {
+    titles.iter().map(|t| episode(t)).collect()
+}
+
+fn title(ep: Option<&Episode>) -> Option<&str> {
+    ep.map(|ep| ep.title.as_str())
+}
+
+#[test]
+fn playlist_wraps_in_both_directions() {
+    let mut playlist = Playlist::new(episodes(&["a", "b", "c"]));
+
+    assert_eq!(playlist.len(), 3);
+    assert_eq!(title(playlist.current()), Some("a"));
+    assert_eq!(title(playlist.next()), Some("b"));
+    assert_eq!(title(playlist.next()), Some("c"));
+    assert_eq!(title(playlist.next()), Some("a"));
+    assert_eq!(title(playlist.previous()), Some("c"));
+}
+
+#[test]
+fn empty_playlist_has_no_current() {
+    let mut playlist = Playlist::new(Vec::new());
+
+    assert!(playlist.is_empty());
+    assert!(playlist.current().is_none());
+    assert!(playlist.next().is_none());
+    assert!(playlist.previous().is_none());
+}
+
+#[test]
+fn shuffle_visits_every_episode_once() {
+    let mut playlist = Playlist::new(episodes(&["a", "b", "c", "d", "e"]));
+    playlist.enable_shuffle();
+    assert!(playlist.is_shuffled());
+
+    let mut seen = vec![title(playlist.current()).unwrap().to_string()];
+    for _ in 1..playlist.len() {
+        seen.push(title(playlist.next()).unwrap().to_string());
+    }
+    seen.sort();
+    assert_eq!(seen, ["a", "b", "c", "d", "e"]);
+
+    playlist.disable_shuffle();
+    assert!(!playlist.is_shuffled());
+    assert_eq!(title(playlist.episode(0)), Some("a"));
+}
+
+#[test]
+fn shuffle_keeps_recently_played_episodes_for_last() {
+    let mut playlist = Playlist::new(episodes(&["a", "b", "c", "d", "e"]));
+    playlist.set_recently_played(&["d".to_string(), "z".to_string(), "b".to_string()]);
+    playlist.enable_shuffle();
+
+    let order: Vec<&str> = playlist
+        .in_play_order()
+        .map(|ep| ep.title.as_str())
+        .collect();
+    assert_eq!(&order[3..], ["d", "b"]);
+
+    // Only the last few count
+    playlist.set_shuffle_memory(1);
+    playlist.enable_shuffle();
+    assert_eq!(title(playlist.in_play_order().last()), Some("b"));
+}
+
+#[test]
+fn toggling_shuffle_goes_on_from_the_episode_playing() {
+    let mut playlist = Playlist::new(episodes(&["a", "b", "c", "d"]));
+    playlist.jump_to(2);
+    playlist.toggle_shuffle();
+    assert_eq!(title(playlist.current()), Some("c"));
+    assert_eq!(playlist.position(), 0);
+
+    playlist.next();
+    playlist.toggle_shuffle();
+    let playing = title(playlist.current()).unwrap().to_string();
+    let after = match playing.as_str() {
+        "a" => "b",
+        "b" => "c",
+        _ => "a",
+    };
+    assert_eq!(title(playlist.next()), Some(after));
+}
+
+#[test]
+fn previous_goes_back_to_what_was_played() {
+    let mut playlist = Playlist::new(episodes(&["a", "b", "c", "d"]));
+    playlist.mark_played();
+    playlist.jump_to(3);
+    playlist.mark_played();
+    playlist.toggle_shuffle();
+    playlist.next();
+    playlist.mark_played();
+
+    assert_eq!(title(playlist.previous()), Some("d"));
+    playlist.mark_played();
+    assert_eq!(title(playlist.previous()), Some("a"));
+    playlist.mark_played();
+    // Nothing played before: back along the order, where "a" went last as a recent one
+    assert_eq!(playlist.position(), 3);
+    let before = playlist.in_play_order().nth(2).map(|ep| ep.title.clone());
+    assert_eq!(title(playlist.previous()).map(str::to_string), before);
+}
+
+#[test]
+fn favorites_playlist_keeps_feed_order() {
+    let all: Arc<[Episode]> = episodes(&["a", "b", "c", "d"]).into();
+    let (d, b) = ("d".to_string(), "b".to_string());
+    let mut playlist = Playlist::from_favorites(Arc::clone(&all), &[&d, &b]);
+
+    assert_eq!(playlist.len(), 2);
+    assert_eq!(title(playlist.current()), Some("b"));
+    assert_eq!(title(playlist.next()), Some("d"));
+    // Picked out of the shared list, not copied from it
+    assert!(Arc::ptr_eq(&playlist.shared_episodes(), &all));
+    assert_eq!(playlist.current_shared_index(), Some(3));
+}
+
+#[test]
+fn named_playlist_keeps_its_own_order_and_skips_missing_titles() {
+    let all = episodes(&["a", "b", "c", "d"]);
+    let titles = ["c", "gone", "a"].map(str::to_string);
+    let mut playlist = Playlist::from_titles(all, &titles);
+
+    assert_eq!(playlist.len(), 2);
+    assert_eq!(title(playlist.current()), Some("c"));
+    assert_eq!(title(playlist.next()), Some("a"));
+}
+
+#[test]
+fn filtered_playlist_keeps_episodes_mentioning_the_keyword() {
+    let mut all = episodes(&["Ambient Dawn", "b", "c", "d"]);
+    all[2].description = "A long <b>AMBIENT</b> drift".to_string();
+    let mut playlist = Playlist::filtered(all, |episode| episode.mentions("ambient"));
+
+    assert_eq!(playlist.len(), 2);
+    assert_eq!(title(playlist.current()), Some("Ambient Dawn"));
+    assert_eq!(title(playlist.next()), Some("c"));
+    assert_eq!(playlist.list_index_of("c"), Some(1));
+    assert!(playlist
+        .narrowed(|episode| episode.mentions("drone"))
+        .is_empty());
+
+    // Narrowing again keeps to the playlist, not the whole list
+    let dawn = playlist.narrowed(|episode| episode.title.starts_with("Ambient"));
+    assert_eq!(
+        dawn.episodes()
+            .map(|ep| ep.title.as_str())
+            .collect::<Vec<_>>(),
+        ["Ambient Dawn"]
+    );
+}
+
+#[test]
+fn play_order_follows_shuffle() {
+    let mut playlist = Playlist::new(episodes(&["a", "b", "c", "d"]));
+    let order: Vec<&str> = playlist
+        .in_play_order()
+        .map(|ep| ep.title.as_str())
+        .collect();
+    assert_eq!(order, ["a", "b", "c", "d"]);
+
+    playlist.enable_shuffle();
+    let order: Vec<String> = playlist
+        .in_play_order()
+        .map(|ep| ep.title.clone())
+        .collect();
+    assert_eq!(title(playlist.jump_to_position(2)), Some(order[2].as_str()));
+    assert_eq!(playlist.position(), 2);
+    assert_eq!(title(playlist.next()), Some(order[3].as_str()));
+
+    assert!(playlist.jump_to_position(4).is_none());
+    assert_eq!(playlist.position(), 3);
+}
+
+#[test]
+fn jumping_to_a_title_works_shuffled_or_not() {
+    let mut playlist = Playlist::new(episodes(&["a", "b", "c", "d"]));
+    assert_eq!(title(playlist.jump_to_title("c")), Some("c"));
+    assert_eq!(title(playlist.next()), Some("d"));
+
+    playlist.enable_shuffle();
+    assert_eq!(title(playlist.jump_to_title("b")), Some("b"));
+    let at = playlist
+        .in_play_order()
+        .position(|ep| ep.title == "b")
+        .unwrap();
+    assert_eq!(playlist.position(), at);
+
+    assert!(playlist.jump_to_title("z").is_none());
+    assert_eq!(title(playlist.current()), Some("b"));
+}
+
+#[test]
+fn peeking_at_the_next_episode_leaves_the_cursor() {
+    let mut playlist = Playlist::new(episodes(&["a", "b", "c"]));
+    assert_eq!(title(playlist.peek_next()), Some("b"));
+    assert_eq!(title(playlist.current()), Some("a"));
+
+    playlist.jump_to_position(2);
+    assert_eq!(title(playlist.peek_next()), Some("a"));
+
+    playlist.enable_shuffle();
+    let next = title(playlist.peek_next()).map(str::to_string);
+    assert_eq!(title(playlist.next()).map(str::to_string), next);
+
+    assert!(Playlist::new(Vec::new()).peek_next().is_none());
+}
+
+#[test]
+fn repeat_modes_decide_what_follows_the_end_of_an_episode() {
+    let mut playlist = Playlist::new(episodes(&["a", "b"]));
+    assert_eq!(playlist.repeat(), RepeatMode::All);
+
+    playlist.set_repeat(RepeatMode::One);
+    assert_eq!(title(playlist.peek_next()), Some("a"));
+    assert_eq!(title(playlist.next()), Some("a"));
+    // Skipping moves on whatever the mode
+    assert_eq!(title(playlist.skip()), Some("b"));
+
+    playlist.set_repeat(RepeatMode::Off);
+    assert!(playlist.peek_next().is_none());
+    assert!(playlist.next().is_none());
+    assert_eq!(title(playlist.current()), Some("b"));
+    assert_eq!(title(playlist.skip()), Some("a"));
+    assert_eq!(title(playlist.next()), Some("b"));
+}
+
+#[test]
+fn repeat_cycles_off_all_one() {
+    let mut playlist = Playlist::new(episodes(&["a"]));
+    assert_eq!(playlist.cycle_repeat(), RepeatMode::One);
+    assert_eq!(playlist.cycle_repeat(), RepeatMode::Off);
+    assert_eq!(playlist.cycle_repeat(), RepeatMode::All);
+}
+
+#[test]
+fn queued_episodes_play_next_without_moving_the_order() {
+    let mut playlist = Playlist::new(episodes(&["a", "b", "c", "d"]));
+    assert_eq!(title(playlist.queue_next(3)), Some("d"));
+    assert!(!playlist.is_queued(2));
+    playlist.queue_next(2);
+    assert!(playlist.queue_next(9).is_none());
+
+    assert_eq!(title(playlist.peek_next()), Some("d"));
+    assert_eq!(title(playlist.next()), Some("d"));
+    assert_eq!(title(playlist.skip()), Some("c"));
+    assert_eq!(playlist.queued().count(), 0);
+    // The order picks up after the episode playing before the queue
+    assert_eq!(title(playlist.next()), Some("b"));
+}
+
+#[test]
+fn going_back_from_a_queued_episode_leaves_it_behind() {
+    let mut playlist = Playlist::new(episodes(&["a", "b", "c"]));
+    playlist.queue_next(2);
+    assert_eq!(title(playlist.next()), Some("c"));
+    assert_eq!(title(playlist.previous()), Some("a"));
+    assert_eq!(title(playlist.next()), Some("b"));
+    assert_eq!(title(playlist.previous()), Some("a"));
+}
+
+#[test]
+fn the_same_episode_can_be_queued_twice() {
+    let mut playlist = Playlist::new(episodes(&["a", "b"]));
+    playlist.queue_next(1);
+    assert!(playlist.is_queued(1));
+    playlist.queue_next(1);
+
+    let queued: Vec<&str> = playlist.queued().map(|ep| ep.title.as_str()).collect();
+    assert_eq!(queued, ["b", "b"]);
+    assert_eq!(title(playlist.next()), Some("b"));
+    assert_eq!(title(playlist.next()), Some("b"));
+    assert!(!playlist.is_queued(1));
+}
+
+#[test]
+fn the_queue_goes_first_when_shuffled() {
+    let mut playlist = Playlist::new(episodes(&["a", "b", "c", "d", "e"]));
+    playlist.enable_shuffle();
+    let current = title(playlist.current()).map(str::to_string);
+    let after = playlist.in_play_order().nth(1).map(|ep| ep.title.clone());
+
+    playlist.queue_next(4);
+    assert_eq!(title(playlist.next()), Some("e"));
+    assert_eq!(title(playlist.previous()).map(str::to_string), current);
+    assert_eq!(title(playlist.next()).map(str::to_string), after);
+}
+
+#[test]
+fn remaining_time_counts_the_episodes_after_the_current_one() {
+    let mut all = episodes(&["a", "b", "c"]);
+    all[2].duration = "30:00".to_string();
+    let mut playlist = Playlist::new(all);
+
+    assert_eq!(playlist.remaining_after_current(), Some(5400));
+    playlist.next();
+    playlist.next();
+    assert_eq!(playlist.remaining_after_current(), Some(0));
+
+    let mut unknown = episodes(&["a", "b"]);
+    unknown[1].duration = "Unknown".to_string();
+    assert_eq!(Playlist::new(unknown).remaining_after_current(), None);
+}
+
+#[test]
+fn durations_round_trip() {
+    assert_eq!(parse_duration("59:30"), Some(3570));
+    assert_eq!(parse_duration("1:02:03"), Some(3723));
+    assert_eq!(parse_duration("Unknown"), None);
+
+    assert_eq!(format_duration(3570), "59:30");
+    assert_eq!(format_duration(3723), "01:02:03");
+}
+
+#[test]
+fn playlists_share_the_episode_list() {
+    let shared: Arc<[Episode]> = episodes(&["a", "b", "c"]).into();
+    let mut playlist = Playlist::new(Arc::clone(&shared));
+    playlist.enable_shuffle();
+    playlist.next();
+
+    let handle = playlist.shared_episodes();
+    assert!(Arc::ptr_eq(&handle, &shared));
+    let index = playlist.current_shared_index().unwrap();
+    assert_eq!(title(Some(&handle[index])), title(playlist.current()));
+}
@@ -0,0 +1,33 @@
+//! Only one mfp at a time may change state: a second one is refused with the pid of the
+//! first, and goes ahead once the first is gone.
+#![cfg(unix)]
+
+mod common;
+
+use std::fs;
+
+use common::Sandbox;
+use mfp::lock::InstanceLock;
+use mfp::paths;
+
+#[test]
+fn a_second_instance_waits_for_the_first() {
+    let sandbox = Sandbox::new("lock");
+    let root = sandbox.root();
+    paths::set_root(root.clone());
+
+    let lock = InstanceLock::acquire().unwrap();
+    let refused = sandbox.run(&["fav", "--add", "Episode 1: Test"]);
+    assert!(!refused.status.success());
+    assert!(String::from_utf8_lossy(&refused.stderr).contains(&std::process::id().to_string()));
+    assert!(!root.join("favorites.json").exists());
+
+    drop(lock);
+    assert!(sandbox
+        .run(&["fav", "--add", "Episode 1: Test"])
+        .status
+        .success());
+    assert!(fs::read_to_string(root.join("favorites.json"))
+        .unwrap()
+        .contains("Episode 1: Test"));
+}
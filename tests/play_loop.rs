@@ -0,0 +1,172 @@
+//! The play loop driven by scripted keys on a player without sound: what the commands do to
+//! the playlist, the favorites and the saved queue. The feed comes from a local server and
+//! every file goes to a `--config-dir` root.
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::thread;
+
+use mfp::audio::{Call, NullPlayer};
+use mfp::favorites::Favorites;
+use mfp::paths;
+use mfp::play::{self, PlayOptions};
+use mfp::state::State;
+use mfp::terminal::Scripted;
+use tiny_http::{Response, Server};
+
+const FEED: &str = include_str!("fixtures/feed.xml");
+
+const EPISODE_3: &str = "Episode 3: Datassette";
+const EPISODE_2: &str = "Episode 2: Uberdog";
+const EPISODE_1: &str = "Episode 1: Datassette";
+
+/// The root is set once per process, so the tests take turns with it
+static ROOT: Mutex<()> = Mutex::new(());
+
+/// An empty root whose config points at a fresh feed server; returns the server's base URL
+fn fresh_root() -> String {
+    let server = Server::http("127.0.0.1:0").unwrap();
+    let base = format!("http://{}", server.server_addr().to_ip().unwrap());
+    let feed = FEED.replace("{base}", &base);
+    thread::spawn(move || {
+        for request in server.incoming_requests() {
+            let _ = request.respond(Response::from_data(feed.clone().into_bytes()));
+        }
+    });
+
+    let root = root();
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(&root).unwrap();
+    fs::write(
+        root.join("config.toml"),
+        format!("feed_url = \"{}/rss.xml\"\nmpris = false\n", base),
+    )
+    .unwrap();
+    paths::set_root(root);
+
+    base
+}
+
+fn root() -> PathBuf {
+    std::env::temp_dir().join(format!("mfp-play-loop-{}", std::process::id()))
+}
+
+/// The episodes the player was asked to stream, by number, in order
+fn streamed(player: &NullPlayer, base: &str) -> Vec<String> {
+    player
+        .calls()
+        .into_iter()
+        .filter_map(|call| match call {
+            Call::PlayStream(url) => url
+                .strip_prefix(&format!("{}/audio/", base))
+                .and_then(|file| file.strip_suffix(".mp3"))
+                .map(str::to_string),
+            _ => None,
+        })
+        .collect()
+}
+
+fn favorites() -> Vec<String> {
+    let favorites = Favorites::load().unwrap();
+    let mut titles: Vec<String> = favorites.list().into_iter().cloned().collect();
+    titles.sort();
+    titles
+}
+
+#[test]
+fn favorite_next_and_undo_change_the_favorites() {
+    let _turn = ROOT.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let base = fresh_root();
+    let player = NullPlayer::new();
+    let events = Scripted::new()
+        .command("f")
+        .command("n")
+        .command("f")
+        .command("n")
+        .command("f")
+        .command("undo")
+        .command("q");
+
+    play::play_with(
+        Box::new(player.clone()),
+        &events,
+        None,
+        None,
+        PlayOptions::default(),
+    )
+    .unwrap();
+
+    assert!(events.is_done());
+    assert_eq!(streamed(&player, &base), ["3", "2", "1"]);
+    // The third one was taken back
+    assert_eq!(favorites(), [EPISODE_2, EPISODE_3]);
+
+    let _ = fs::remove_dir_all(root());
+}
+
+#[test]
+fn favorites_mode_skips_the_rest_of_the_feed() {
+    let _turn = ROOT.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let base = fresh_root();
+    let mut saved = Favorites::load().unwrap();
+    saved.add(EPISODE_3.to_string());
+    saved.add(EPISODE_1.to_string());
+
+    // Removing the one playing keeps it playing; the list moves on as it was
+    let player = NullPlayer::new();
+    let events = Scripted::new()
+        .command("n")
+        .command("f")
+        .command("n")
+        .command("q");
+    let options = PlayOptions {
+        favorites: true,
+        ..PlayOptions::default()
+    };
+
+    play::play_with(Box::new(player.clone()), &events, None, None, options).unwrap();
+
+    assert!(events.is_done());
+    assert_eq!(streamed(&player, &base), ["3", "1", "3"]);
+    assert_eq!(favorites(), [EPISODE_3]);
+
+    let _ = fs::remove_dir_all(root());
+}
+
+#[test]
+fn the_queue_plays_next_and_waits_for_continue() {
+    let _turn = ROOT.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let base = fresh_root();
+
+    // Queued, then left before it came up
+    let player = NullPlayer::new();
+    let events = Scripted::new().command("queue 1").command("q");
+    play::play_with(
+        Box::new(player.clone()),
+        &events,
+        None,
+        None,
+        PlayOptions::default(),
+    )
+    .unwrap();
+
+    assert!(events.is_done());
+    assert_eq!(streamed(&player, &base), ["3"]);
+    assert_eq!(State::load().unwrap().queue(), [EPISODE_1]);
+
+    // Picked up again: the queued one comes before the next in order
+    let player = NullPlayer::new();
+    let events = Scripted::new().command("n").command("n").command("q");
+    let options = PlayOptions {
+        restore_queue: true,
+        ..PlayOptions::default()
+    };
+    play::play_with(Box::new(player.clone()), &events, None, None, options).unwrap();
+
+    assert!(events.is_done());
+    assert_eq!(streamed(&player, &base), ["3", "1", "2"]);
+    assert!(State::load().unwrap().queue().is_empty());
+
+    let _ = fs::remove_dir_all(root());
+}
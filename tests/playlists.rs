@@ -0,0 +1,50 @@
+//! Named playlists are saved to `playlists.json` and read back in order by the next run.
+
+mod common;
+
+use common::Sandbox;
+use mfp::paths;
+use mfp::playlists::{Edit, Playlists};
+
+#[test]
+fn playlists_keep_their_order_across_a_reload() {
+    let sandbox = Sandbox::new("playlists");
+    paths::set_root(sandbox.root());
+
+    let mut playlists = Playlists::load().unwrap();
+    assert_eq!(
+        playlists.add("deep-focus", "Episode 2").unwrap(),
+        Edit::NoPlaylist
+    );
+    assert!(playlists.create("deep-focus").unwrap());
+    assert!(!playlists.create("deep-focus").unwrap());
+    assert!(playlists.create("late-night").unwrap());
+
+    for title in ["Episode 9", "Episode 2", "Episode 5"] {
+        assert_eq!(playlists.add("deep-focus", title).unwrap(), Edit::Done);
+    }
+    assert_eq!(
+        playlists.add("deep-focus", "Episode 2").unwrap(),
+        Edit::Unchanged
+    );
+    assert_eq!(
+        playlists.remove("deep-focus", "Episode 5").unwrap(),
+        Edit::Done
+    );
+    assert_eq!(
+        playlists.remove("deep-focus", "Episode 5").unwrap(),
+        Edit::Unchanged
+    );
+
+    let reloaded = Playlists::load().unwrap();
+    assert_eq!(
+        reloaded.get("deep-focus"),
+        Some(&["Episode 9".to_string(), "Episode 2".to_string()][..])
+    );
+    let names: Vec<&str> = reloaded.list().map(|(name, _)| name).collect();
+    assert_eq!(names, ["deep-focus", "late-night"]);
+
+    assert!(playlists.delete("late-night").unwrap());
+    assert!(!playlists.delete("late-night").unwrap());
+    assert!(Playlists::load().unwrap().get("late-night").is_none());
+}
@@ -0,0 +1,73 @@
+//! The player's queue is saved to `state.json` and queued again by `play --continue`.
+
+mod common;
+
+use std::fs;
+
+use common::Sandbox;
+use mfp::feed::Episode;
+use mfp::paths;
+use mfp::playlist::Playlist;
+use mfp::state::State;
+
+fn episodes(titles: &[&str]) -> Vec<Episode> {
+    titles
+        .iter()
+        .map(|title| Episode {
+            title: title.to_string(),
+            audio_url: format!("https://example.invalid/{}.mp3", title),
+            duration: "01:00:00".to_string(),
+            pub_date: String::new(),
+            description: String::new(),
+            page_url: None,
+            image_url: None,
+        })
+        .collect()
+}
+
+fn titles(playlist: &Playlist) -> Vec<&str> {
+    playlist.queued().map(|ep| ep.title.as_str()).collect()
+}
+
+#[test]
+fn the_queue_survives_a_reload_in_its_order() {
+    let sandbox = Sandbox::new("queue");
+    let root = sandbox.root();
+    paths::set_root(root.clone());
+
+    // Queued one after the other, the same episode twice
+    let mut playlist = Playlist::new(episodes(&["a", "b", "c", "d"]));
+    for index in [3, 1, 3] {
+        playlist.queue_next(index);
+    }
+    let queued: Vec<String> = playlist.queued().map(|ep| ep.title.clone()).collect();
+    let mut state = State::load().unwrap();
+    state.set_queue(queued).unwrap();
+
+    let saved = State::load().unwrap().queue().to_vec();
+    let mut next_run = Playlist::new(episodes(&["a", "b", "c", "d"]));
+    assert!(next_run.restore_queue(&saved).is_empty());
+    assert_eq!(titles(&next_run), ["d", "b", "d"]);
+    assert_eq!(next_run.next().map(|ep| ep.title.as_str()), Some("d"));
+
+    // An empty queue leaves nothing behind
+    state.set_queue(Vec::new()).unwrap();
+    assert!(State::load().unwrap().queue().is_empty());
+    assert!(!fs::read_to_string(root.join("state.json"))
+        .unwrap()
+        .contains("queue"));
+}
+
+#[test]
+fn titles_gone_from_the_feed_are_dropped_and_reported() {
+    let saved = ["c", "gone", "a"].map(str::to_string);
+    let mut playlist = Playlist::new(episodes(&["a", "b", "c"]));
+
+    assert_eq!(playlist.restore_queue(&saved), ["gone"]);
+    assert_eq!(titles(&playlist), ["c", "a"]);
+
+    // A part of the feed only takes up what is in it
+    let mut favorites = Playlist::from_titles(episodes(&["a", "b", "c"]), &["a".to_string()]);
+    assert_eq!(favorites.restore_queue(&saved), ["c", "gone"]);
+    assert_eq!(titles(&favorites), ["a"]);
+}
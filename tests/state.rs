@@ -0,0 +1,35 @@
+//! The volume chosen in the player is saved to `state.json` and read back by the next run.
+
+mod common;
+
+use std::fs;
+
+use common::Sandbox;
+use mfp::paths;
+use mfp::state::State;
+
+#[test]
+fn volume_survives_a_reload() {
+    let sandbox = Sandbox::new("state");
+    let root = sandbox.root();
+    paths::set_root(root.clone());
+
+    let mut state = State::load().unwrap();
+    assert_eq!(state.volume(), None);
+    state.set_volume(0.4).unwrap();
+    assert_eq!(State::load().unwrap().volume(), Some(0.4));
+
+    // Out of range levels are kept within what the player plays
+    state.set_volume(3.0).unwrap();
+    assert_eq!(State::load().unwrap().volume(), Some(2.0));
+
+    // Offsets saved alongside don't disturb it
+    state.set_volume(0.4).unwrap();
+    state.set_volume_offset("Episode 1: Test", 20).unwrap();
+    let reloaded = State::load().unwrap();
+    assert_eq!(reloaded.volume(), Some(0.4));
+    assert_eq!(reloaded.volume_offset("Episode 1: Test"), Some(20));
+    assert!(fs::read_to_string(root.join("state.json"))
+        .unwrap()
+        .contains("\"volume\": 40"));
+}
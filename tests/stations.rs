@@ -0,0 +1,118 @@
+//! `mfp station` and `--station`: extra feeds with their own cache, numbering and favorites.
+//! Runs the real binary against a local server, with every file kept under `--config-dir`.
+#![cfg(unix)]
+
+mod common;
+
+use std::fs;
+
+use common::{serve, stdout, Sandbox, FEED};
+
+/// Titles without "Episode NN:", newest first
+const LOFI_FEED: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0" xmlns:itunes="http://www.itunes.com/dtds/podcast-1.0.dtd">
+  <channel>
+    <title>Lofi</title>
+    <link>https://example.invalid</link>
+    <description>Another feed</description>
+    <item>
+      <title>Late shift</title>
+      <enclosure url="{base}/audio/late.mp3" length="4096" type="audio/mpeg"/>
+      <itunes:duration>30:00</itunes:duration>
+    </item>
+    <item>
+      <title>First light</title>
+      <enclosure url="{base}/audio/first.mp3" length="4096" type="audio/mpeg"/>
+      <itunes:duration>30:00</itunes:duration>
+    </item>
+  </channel>
+</rss>"#;
+
+const EMPTY_FEED: &str = "<rss version=\"2.0\"><channel></channel></rss>";
+
+/// Serves the fixture feed at /rss.xml, the lofi one at /lofi.xml and one without episodes
+/// at /empty.xml
+fn serve_feeds() -> String {
+    serve(&[
+        ("/rss.xml", FEED),
+        ("/lofi.xml", LOFI_FEED),
+        ("/empty.xml", EMPTY_FEED),
+    ])
+}
+
+/// A sandbox whose config points at the feed server
+fn sandbox(name: &str, base: &str) -> Sandbox {
+    let sandbox = Sandbox::new(name);
+    fs::write(
+        sandbox.root().join("config.toml"),
+        format!("feed_url = \"{}/rss.xml\"\n", base),
+    )
+    .unwrap();
+    sandbox
+}
+
+#[test]
+fn stations_keep_their_own_cache_numbering_and_favorites() {
+    let base = serve_feeds();
+    let sandbox = sandbox("stations", &base);
+
+    let lofi_url = format!("{}/lofi.xml", base);
+    sandbox.mfp(&["station", "add", "lofi", &lofi_url]);
+    let config = fs::read_to_string(sandbox.root().join("config.toml")).unwrap();
+    assert!(
+        config.contains("feed_url"),
+        "other settings kept: {}",
+        config
+    );
+    assert!(config.contains(&lofi_url));
+
+    let stations = stdout(&sandbox.mfp(&["station", "list"]));
+    assert!(stations.contains("mfp"));
+    assert!(stations.contains("lofi"));
+
+    // The same title in both stations: only the lofi one is a lofi favorite
+    fs::write(
+        sandbox.root().join("favorites.json"),
+        r#"{"episodes": ["Late shift"], "stations": {"lofi": ["First light"]}}"#,
+    )
+    .unwrap();
+
+    let listed = stdout(&sandbox.mfp(&["list", "--station", "lofi"]));
+    assert!(listed.contains("*   1. First light"), "{}", listed);
+    assert!(listed.contains("    2. Late shift"), "{}", listed);
+
+    let listed = stdout(&sandbox.mfp(&["list"]));
+    assert!(listed.contains("1. Episode 1: Datassette"), "{}", listed);
+    assert!(!listed.contains("Late shift"));
+
+    assert!(sandbox.root().join("cache/feed.json").is_file());
+    assert!(sandbox.root().join("cache/feeds/lofi.json").is_file());
+
+    sandbox.mfp(&["station", "remove", "lofi"]);
+    let output = sandbox.run(&["list", "--station", "lofi"]);
+    assert_eq!(output.status.code(), Some(3));
+}
+
+#[test]
+fn bad_stations_are_refused() {
+    let base = serve_feeds();
+    let sandbox = sandbox("stations-refused", &base);
+    let url = format!("{}/lofi.xml", base);
+
+    assert!(!sandbox
+        .run(&["station", "add", "Lo Fi", &url])
+        .status
+        .success());
+    assert!(!sandbox
+        .run(&["station", "add", "mfp", &url])
+        .status
+        .success());
+    let empty = format!("{}/empty.xml", base);
+    assert!(!sandbox
+        .run(&["station", "add", "quiet", &empty])
+        .status
+        .success());
+
+    let config = fs::read_to_string(sandbox.root().join("config.toml")).unwrap();
+    assert!(!config.contains("stations"), "{}", config);
+}